@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// Audio file of an audiobook, positions in milliseconds
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RsAudiobookPart {
+    pub media: String,
+    pub name: String,
+    /// Track number of the file in the book
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub part: Option<u32>,
+    /// Position of the part start in the whole book
+    pub start: u64,
+    pub duration: u64,
+}
+
+/// Audio files of a book played as a single stream, positions in milliseconds
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RsAudiobook {
+    pub book: String,
+    pub parts: Vec<RsAudiobookPart>,
+    pub duration: u64,
+    /// Position in the whole book, from the last updated part progress
+    pub position: u64,
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
 
 use super::ElementAction;
 
@@ -24,6 +25,12 @@ pub struct Channel {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub variants: Option<Vec<ChannelVariant>>,
+
+    /// Favorite and position of the requesting user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub favorite: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -42,6 +49,53 @@ pub struct ChannelVariant {
     pub modified: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub added: Option<i64>,
+
+    /// Result of the last health check, `None` until the variant is probed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health: Option<VariantHealth>,
+    /// Time to first bytes in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// Consecutive failed checks
+    #[serde(default)]
+    pub failures: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checked: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_success: Option<i64>,
+}
+
+impl ChannelVariant {
+    pub fn is_dead(&self) -> bool {
+        self.health == Some(VariantHealth::Dead)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, EnumString, Display)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum VariantHealth {
+    Ok,
+    /// Failed its last checks but is still used
+    Failing,
+    /// Failed enough consecutive checks to be skipped when picking a variant
+    Dead,
+}
+
+/// Outcome of a variant health check
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VariantHealthUpdate {
+    pub health: Option<VariantHealth>,
+    pub latency: Option<u32>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub failures: u32,
+    pub checked: i64,
+    pub last_success: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -103,6 +157,33 @@ pub struct M3uImportResult {
     pub movies_added: usize,
     pub series_added: usize,
     pub episodes_added: usize,
+    pub medias_added: usize,
+    pub medias_removed: usize,
     pub groups_created: usize,
     pub total_parsed: usize,
+    /// XMLTV url announced by the playlist header (`url-tvg`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub epg_url: Option<String>,
+}
+
+/// Per user favorite, position and number of a channel
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelUserSettings {
+    pub channel_ref: String,
+    pub user_ref: String,
+    pub favorite: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<u32>,
+    /// Replaces the channel number for this user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelUserSettingsForUpdate {
+    pub favorite: Option<bool>,
+    pub position: Option<u32>,
+    pub number: Option<i32>,
 }
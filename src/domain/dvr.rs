@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
+use super::ElementAction;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Display, EnumString, Default)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum DvrScheduleKind {
+    /// Single recording of a fixed time window
+    #[default]
+    Once,
+    /// Same local time window on selected week days
+    Weekly,
+    /// Every EPG programme of the channel matching a title
+    Programme,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Display, EnumString, Default)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum DvrRecordingStatus {
+    #[default]
+    Scheduled,
+    /// Not enough stream slots left for this recording
+    Conflict,
+    Recording,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl DvrRecordingStatus {
+    pub fn is_pending(&self) -> bool {
+        matches!(
+            self,
+            DvrRecordingStatus::Scheduled | DvrRecordingStatus::Conflict
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DvrSchedule {
+    pub id: String,
+    pub channel: String,
+    pub kind: DvrScheduleKind,
+    /// Library receiving the recorded files
+    pub target_library: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Once: window in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<i64>,
+    /// Weekly: days from 0 (monday) to 6, local start time and duration in minutes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub days: Option<Vec<u8>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u32>,
+    /// Programme: EPG title to match (case insensitive)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Seconds recorded before start and after end
+    pub padding_before: u32,
+    pub padding_after: u32,
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub added: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DvrScheduleForAdd {
+    pub channel: String,
+    #[serde(default)]
+    pub kind: DvrScheduleKind,
+    pub target_library: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub days: Option<Vec<u8>>,
+    pub time: Option<u32>,
+    pub duration: Option<u32>,
+    pub title: Option<String>,
+    #[serde(default)]
+    pub padding_before: u32,
+    #[serde(default)]
+    pub padding_after: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DvrScheduleForUpdate {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub padding_before: Option<u32>,
+    pub padding_after: Option<u32>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DvrRecording {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<String>,
+    pub channel: String,
+    pub target_library: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Programme window in milliseconds, padding excluded
+    pub start: i64,
+    pub end: i64,
+    pub padding_before: u32,
+    pub padding_after: u32,
+    pub status: DvrRecordingStatus,
+    /// Media created in the target library
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub added: Option<i64>,
+}
+
+impl DvrRecording {
+    pub fn padded_start(&self) -> i64 {
+        self.start - self.padding_before as i64 * 1000
+    }
+
+    pub fn padded_end(&self) -> i64 {
+        self.end + self.padding_after as i64 * 1000
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DvrRecordingWithAction {
+    pub action: ElementAction,
+    pub recording: DvrRecording,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DvrRecordingMessage {
+    pub library: String,
+    pub recordings: Vec<DvrRecordingWithAction>,
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+/// XMLTV programme, keyed by the channel `tvg_id`. Times in milliseconds
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EpgProgramme {
+    pub channel: String,
+    pub start: i64,
+    pub stop: i64,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub episode_num: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EpgNowNext {
+    pub channel: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub now: Option<EpgProgramme>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<EpgProgramme>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EpgChannelGuide {
+    pub channel: String,
+    pub programmes: Vec<EpgProgramme>,
+}
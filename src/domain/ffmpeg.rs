@@ -1,9 +1,14 @@
 use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
+use super::media_chapters::RsMediaChapter;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FfprobeResult {
     pub streams: Vec<FfprobeStream>,
     pub format: FfprobeFormat,
+    #[serde(default)]
+    pub chapters: Vec<FfprobeChapter>,
 }
 
 impl FfprobeResult {
@@ -37,6 +42,14 @@ impl FfprobeResult {
             .find(|x| x.codec_type == CodecType::Video)
     }
 
+    /// Embedded cover art (`attached_pic` stream) of an audio file
+    pub fn cover_stream(&self) -> Option<&FfprobeStream> {
+        self.streams.iter().find(|x| {
+            x.codec_type == CodecType::Video
+                && x.disposition.as_ref().map(|d| d.attached_pic == 1) == Some(true)
+        })
+    }
+
     pub fn audio_stream(&self) -> Option<&FfprobeStream> {
         self.streams
             .iter()
@@ -47,6 +60,28 @@ impl FfprobeResult {
         let ivalue = self.format.duration.parse::<f64>().ok();
         ivalue
     }
+
+    pub fn hdr_format(&self) -> Option<HdrFormat> {
+        self.video_stream().and_then(|s| s.hdr_format())
+    }
+
+    /// Container chapters, skipping the ones with unparsable timestamps
+    pub fn media_chapters(&self) -> Vec<RsMediaChapter> {
+        self.chapters
+            .iter()
+            .filter_map(|c| {
+                Some(RsMediaChapter {
+                    start: c.start_time.parse::<f64>().ok()?,
+                    end: c.end_time.parse::<f64>().ok()?,
+                    title: c
+                        .tags
+                        .as_ref()
+                        .and_then(|t| t.title.clone())
+                        .filter(|t| !t.is_empty()),
+                })
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -62,6 +97,10 @@ pub struct FfprobeStream {
     pub nb_frames: Option<String>,
     pub tags: Option<StreamTags>,
     pub r_frame_rate: Option<String>,
+    pub color_transfer: Option<String>,
+    pub color_primaries: Option<String>,
+    pub side_data_list: Option<Vec<FfprobeSideData>>,
+    pub disposition: Option<FfprobeDisposition>,
 }
 
 impl FfprobeStream {
@@ -90,11 +129,92 @@ impl FfprobeStream {
         let ivalue = self.bit_rate.as_ref().and_then(|b| b.parse::<u64>().ok());
         ivalue
     }
+
+    fn has_side_data(&self, kind: &str) -> bool {
+        self.side_data_list
+            .as_ref()
+            .map(|list| {
+                list.iter()
+                    .any(|d| d.side_data_type.as_deref().unwrap_or("").contains(kind))
+            })
+            .unwrap_or(false)
+    }
+
+    /// HDR format from side data first (Dolby Vision / HDR10+ also carry an HDR10 transfer)
+    pub fn hdr_format(&self) -> Option<HdrFormat> {
+        if self.has_side_data("DOVI configuration record") {
+            return Some(HdrFormat::DolbyVision);
+        }
+        if self.has_side_data("HDR10+") {
+            return Some(HdrFormat::Hdr10Plus);
+        }
+        match self.color_transfer.as_deref() {
+            Some("smpte2084") => Some(HdrFormat::Hdr10),
+            Some("arib-std-b67") => Some(HdrFormat::Hlg),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FfprobeSideData {
+    pub side_data_type: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FfprobeDisposition {
+    #[serde(default)]
+    pub attached_pic: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Display, EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum HdrFormat {
+    Hdr10,
+    Hdr10Plus,
+    Hlg,
+    DolbyVision,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FfprobeChapter {
+    pub id: i64,
+    pub start_time: String,
+    pub end_time: String,
+    pub tags: Option<ChapterTags>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChapterTags {
+    pub title: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FfprobeFormat {
     pub duration: String,
+    pub tags: Option<FormatTags>,
+}
+
+/// Container tags. Matroska and Vorbis files use upper case keys
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct FormatTags {
+    #[serde(alias = "TITLE")]
+    pub title: Option<String>,
+    #[serde(alias = "ARTIST")]
+    pub artist: Option<String>,
+    #[serde(alias = "ALBUM_ARTIST")]
+    pub album_artist: Option<String>,
+    #[serde(alias = "ALBUM")]
+    pub album: Option<String>,
+    #[serde(alias = "TRACK")]
+    pub track: Option<String>,
+    #[serde(alias = "DATE")]
+    pub date: Option<String>,
+    #[serde(alias = "COMMENT")]
+    pub comment: Option<String>,
+    #[serde(alias = "LANGUAGE")]
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -114,3 +234,46 @@ pub struct StreamTags {
     #[serde(rename = "DURATION")]
     duration: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(json: &str) -> FfprobeStream {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn detect_hdr_formats() {
+        let sdr = stream(r#"{"index":0,"codec_type":"video","color_transfer":"bt709"}"#);
+        assert_eq!(sdr.hdr_format(), None);
+        let hdr10 = stream(
+            r#"{"index":0,"codec_type":"video","color_transfer":"smpte2084","color_primaries":"bt2020",
+            "side_data_list":[{"side_data_type":"Mastering display metadata"}]}"#,
+        );
+        assert_eq!(hdr10.hdr_format(), Some(HdrFormat::Hdr10));
+        let hlg = stream(r#"{"index":0,"codec_type":"video","color_transfer":"arib-std-b67"}"#);
+        assert_eq!(hlg.hdr_format(), Some(HdrFormat::Hlg));
+        let dovi = stream(
+            r#"{"index":0,"codec_type":"video","color_transfer":"smpte2084",
+            "side_data_list":[{"side_data_type":"DOVI configuration record","dv_profile":8}]}"#,
+        );
+        assert_eq!(dovi.hdr_format(), Some(HdrFormat::DolbyVision));
+        assert_eq!(HdrFormat::DolbyVision.to_string(), "dolbyvision");
+    }
+
+    #[test]
+    fn detect_cover_and_tags() {
+        let probe: FfprobeResult = serde_json::from_str(
+            r#"{"streams":[{"index":0,"codec_type":"audio","codec_name":"aac"},
+            {"index":1,"codec_type":"video","codec_name":"mjpeg","disposition":{"default":0,"attached_pic":1}}],
+            "format":{"duration":"3600.5","tags":{"ALBUM":"The Book","artist":"Author","track":"2/10"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(probe.cover_stream().map(|s| s.index), Some(1));
+        let tags = probe.format.tags.unwrap();
+        assert_eq!(tags.album.as_deref(), Some("The Book"));
+        assert_eq!(tags.artist.as_deref(), Some("Author"));
+        assert_eq!(tags.track.as_deref(), Some("2/10"));
+    }
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// `discover.json` of an emulated HDHomeRun tuner
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct HdhrDiscover {
+    pub friendly_name: String,
+    pub manufacturer: String,
+    pub model_number: String,
+    pub firmware_name: String,
+    pub firmware_version: String,
+    #[serde(rename = "DeviceID")]
+    pub device_id: String,
+    pub device_auth: String,
+    #[serde(rename = "BaseURL")]
+    pub base_url: String,
+    #[serde(rename = "LineupURL")]
+    pub lineup_url: String,
+    pub tuner_count: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct HdhrLineupStatus {
+    pub scan_in_progress: u8,
+    pub scan_possible: u8,
+    pub source: String,
+    pub source_list: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct HdhrLineupEntry {
+    pub guide_number: String,
+    pub guide_name: String,
+    #[serde(rename = "URL")]
+    pub url: String,
+    #[serde(rename = "HD", skip_serializing_if = "Option::is_none")]
+    pub hd: Option<u8>,
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// Reading position exchanged with KOReader (kosync protocol, snake_case fields)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct KosyncProgress {
+    /// Partial md5 of the document
+    pub document: String,
+    /// Page number for paged documents, xpointer for reflowable ones
+    pub progress: String,
+    pub percentage: f64,
+    #[serde(default)]
+    pub device: String,
+    #[serde(default)]
+    pub device_id: String,
+    /// Seconds since epoch
+    #[serde(default)]
+    pub timestamp: i64,
+}
+
+/// Credentials to enter in KOReader's progress sync settings
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KosyncCredentials {
+    pub username: String,
+    pub password: String,
+}
@@ -93,6 +93,17 @@ pub enum LibraryType {
     Other,
 }
 
+/// Where an IPTV library gets its channels: an M3U playlist at `root`,
+/// or the Xtream Codes API of the server at `root` with the library credentials
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default, EnumString, Display)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum IptvSource {
+    #[default]
+    M3u,
+    Xtream,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct UserMapping {
@@ -107,6 +118,7 @@ pub struct ServerLibrarySettings {
     pub face_threshold: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ignore_groups: Option<bool>,
+    /// Registered tagging model generating the AI tags of the library
     #[serde(skip_serializing_if = "Option::is_none")]
     pub preduction_model: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -116,9 +128,27 @@ pub struct ServerLibrarySettings {
 
     // IPTV settings
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub iptv_source: Option<IptvSource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub epg_url: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_streams: Option<u32>,
+    /// Minutes of live TV kept on disk per HLS session to pause and rewind
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeshift_minutes: Option<u32>,
+    /// Secret path segment enabling the emulated HDHomeRun tuner of the library
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hdhr_key: Option<String>,
+    /// Announce the tuner on the LAN with the SiliconDust UDP discovery protocol
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hdhr_discovery: Option<bool>,
+
+    /// Recognize the text of photos to include it in text search
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ocr: Option<bool>,
+    /// Tesseract languages used by OCR (ex `eng+fra`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ocr_languages: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
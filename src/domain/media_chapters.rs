@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// Chapter of a video, timestamps in seconds
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RsMediaChapter {
+    pub start: f64,
+    pub end: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
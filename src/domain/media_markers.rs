@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// Skippable segments of a video, timestamps in seconds
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RsMediaMarkers {
+    pub media_ref: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intro_start: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intro_end: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credits_start: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credits_end: Option<f64>,
+    /// Set by a user: automatic detection will not overwrite it
+    #[serde(default)]
+    pub manual: bool,
+    #[serde(default)]
+    pub modified: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RsMediaMarkersForUpdate {
+    pub intro_start: Option<f64>,
+    pub intro_end: Option<f64>,
+    pub credits_start: Option<f64>,
+    pub credits_end: Option<f64>,
+}
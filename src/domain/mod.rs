@@ -27,15 +27,22 @@ impl RsIdsExt for RsIds {
     }
 }
 
+pub mod audiobook;
 pub mod backup;
 pub mod book;
 pub mod channel;
 pub mod credential;
 pub mod deleted;
+pub mod dvr;
+pub mod epg;
 pub mod episode;
 pub mod ffmpeg;
+pub mod hdhomerun;
+pub mod kosync;
 pub mod library;
 pub mod media;
+pub mod media_chapters;
+pub mod media_markers;
 pub mod media_progress;
 pub mod media_rating;
 pub mod movie;
@@ -45,7 +52,10 @@ pub mod plugin_convert_queue;
 pub mod request_processing;
 pub mod rs_link;
 pub mod serie;
+pub mod serie_gaps;
+pub mod streaming_session;
 pub mod tag;
+pub mod tagging_model;
 pub mod view_progress;
 pub mod watched;
 
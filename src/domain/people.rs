@@ -21,6 +21,34 @@ pub struct PersonWithAction {
     pub person: Person,
 }
 
+/// Detector and recognizer that produced a face embedding. Embeddings of different
+/// models or versions live in different vector spaces and are never compared
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceModel {
+    pub name: String,
+    pub version: u32,
+}
+
+/// Media whose faces a face model could not all re-embed, left for review
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceReembedFailure {
+    pub media_ref: String,
+    pub error: String,
+    pub modified: i64,
+}
+
+/// Face close to a searched face, assigned to a person or not
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarFace {
+    pub id: String,
+    pub person_id: Option<String>,
+    pub media_ref: String,
+    pub similarity: f32,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct UnassignedFace {
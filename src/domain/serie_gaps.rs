@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+/// Book number compared by a gap report
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum RsGapNumbering {
+    #[default]
+    Volume,
+    Chapter,
+}
+
+/// Books of a serie sharing the same number
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RsGapDuplicate {
+    pub number: f64,
+    pub books: Vec<String>,
+}
+
+/// Volumes or chapters of a serie compared with the list of a metadata provider
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RsSerieGapReport {
+    pub serie: String,
+    pub numbering: RsGapNumbering,
+    /// Numbers listed by the metadata providers, empty when none listed the serie.
+    /// Missing numbers are then the holes between 1 and the last owned number
+    pub expected: Vec<f64>,
+    pub owned: Vec<f64>,
+    pub missing: Vec<f64>,
+    pub duplicated: Vec<RsGapDuplicate>,
+    /// Books whose number order does not match their release order
+    pub out_of_order: Vec<String>,
+    /// Owned numbers not listed by the metadata providers
+    pub unknown: Vec<f64>,
+}
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+use super::ElementAction;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum StreamingSessionKind {
+    Media,
+    Channel,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum StreamingMode {
+    Copy,
+    Transcode,
+}
+
+/// Snapshot of an active HLS stream, timestamps in milliseconds
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RsStreamingSession {
+    pub id: String,
+    pub kind: StreamingSessionKind,
+    pub library: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    pub mode: StreamingMode,
+    /// Video encoder when transcoding
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoder: Option<String>,
+    pub started: u64,
+    pub last_active: u64,
+    pub segments_served: u64,
+    pub bytes_sent: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamingSessionMessage {
+    pub action: ElementAction,
+    pub session: RsStreamingSession,
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+
+/// Format of the label CSV shipped with a tagging model
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum RsLabelFormat {
+    /// WD tagger `selected_tags.csv`: `tag_id,name,category,count` with a header line
+    #[default]
+    Wd,
+    /// One label per line, alternative names separated by `|`
+    Names,
+}
+
+/// Layout of the image tensor expected by the model
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum RsTensorLayout {
+    #[default]
+    Nhwc,
+    Nchw,
+}
+
+/// Pixel normalization applied before inference
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum RsPixelNormalization {
+    /// Raw 0-255 values
+    #[default]
+    None,
+    /// 0-1 values
+    Unit,
+    /// -1 to 1 values
+    Centered,
+    /// ImageNet mean and standard deviation
+    ImageNet,
+}
+
+/// How the output scores are read
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum RsTaggingOutput {
+    /// Independent probability per label (multi-label)
+    #[default]
+    Sigmoid,
+    /// Probabilities summing to one (single label classification)
+    Softmax,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RsTaggingModelSettings {
+    #[serde(default)]
+    pub labels: RsLabelFormat,
+    pub input_size: u32,
+    #[serde(default)]
+    pub layout: RsTensorLayout,
+    #[serde(default)]
+    pub normalization: RsPixelNormalization,
+    #[serde(default)]
+    pub bgr: bool,
+    #[serde(default)]
+    pub output: RsTaggingOutput,
+    /// Minimum probability (0-1) of generated tags
+    #[serde(default = "default_threshold")]
+    pub threshold: f32,
+}
+
+fn default_threshold() -> f32 {
+    0.35
+}
+
+impl Default for RsTaggingModelSettings {
+    fn default() -> Self {
+        Self {
+            labels: RsLabelFormat::default(),
+            input_size: 448,
+            layout: RsTensorLayout::default(),
+            normalization: RsPixelNormalization::default(),
+            bgr: false,
+            output: RsTaggingOutput::default(),
+            threshold: default_threshold(),
+        }
+    }
+}
+
+/// ONNX image tagging model registered on the server, selected per library with
+/// `ServerLibrarySettings.preduction_model`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RsTaggingModel {
+    pub id: String,
+    pub name: String,
+    pub settings: RsTaggingModelSettings,
+    pub added: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RsTaggingModelForAdd {
+    pub name: String,
+    #[serde(flatten)]
+    pub settings: RsTaggingModelSettings,
+}
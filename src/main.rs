@@ -123,6 +123,25 @@ async fn main() -> Result<()> {
         });
     }
 
+    if let Some(engine) = tools::ocr::OcrEngine::detect() {
+        log_info(
+            tools::log::LogServiceType::Register,
+            format!("OCR engine {:?}", engine),
+        );
+    } else {
+        log_info(
+            tools::log::LogServiceType::Register,
+            "No OCR engine found (install tesseract-ocr to search text in photos)".to_string(),
+        );
+    }
+
+    if !tools::comic_archive::has_unrar() {
+        log_info(
+            tools::log::LogServiceType::Register,
+            "unrar not found (install unrar to read CBR comics)".to_string(),
+        );
+    }
+
     let config = server::initialize_config().await;
 
     if !config.imagesUseIm {
@@ -256,6 +275,10 @@ async fn app() -> Result<Router> {
             "/libraries/:libraryid/channels",
             routes::channels::routes(mc.clone()),
         )
+        .nest(
+            "/libraries/:libraryid/opds",
+            routes::opds::routes(mc.clone()),
+        )
         .nest(
             "/libraries/:libraryid/plugins",
             routes::library_plugins::routes(mc.clone()),
@@ -267,7 +290,14 @@ async fn app() -> Result<Router> {
         .nest("/uploadkeys", routes::upload_keys::routes(mc.clone()))
         .nest("/backups", routes::backups::routes(mc.clone()))
         .nest("/plugins", routes::plugins::routes(mc.clone()))
+        .nest("/taggingmodels", routes::tagging_models::routes(mc.clone()))
+        .nest("/sessions", routes::sessions::routes(mc.clone()))
         .nest("/sse", routes::sse::routes(mc.clone()))
+        .nest("/kosync", routes::kosync::routes(mc.clone()))
+        .nest(
+            "/hdhr/:libraryid/:key",
+            routes::hdhomerun::routes(mc.clone()),
+        )
         .route("/socket.io/", axum::routing::any(socket_io_fallback))
         .fallback(fallback)
         .layer(middleware::from_fn(mw_range::mw_range))
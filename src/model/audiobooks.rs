@@ -0,0 +1,249 @@
+use rs_plugin_common_interfaces::ImageType;
+
+use crate::{
+    domain::{
+        audiobook::{RsAudiobook, RsAudiobookPart},
+        library::LibraryRole,
+        media::{Media, MediaForUpdate},
+        ElementAction,
+    },
+    error::{RsError, RsResult},
+    plugins::sources::error::SourcesError,
+    tools::{
+        audiobook::{
+            audiobook_metadata, global_position, is_audiobook_mime, order_parts, parse_part_number,
+            part_at,
+        },
+        log::{log_error, LogServiceType},
+        video_tools::{probe_video, thumb_video, VideoTime},
+    },
+};
+
+use super::{medias::MediaQuery, users::ConnectedUser, ModelController};
+
+/// Media param holding the part number of an audiobook file
+const PART_PARAM: &str = "part";
+
+fn media_part(media: &Media) -> Option<u32> {
+    let value = media.params.as_ref()?.get(PART_PARAM)?;
+    value
+        .as_u64()
+        .map(|part| part as u32)
+        .or_else(|| value.as_str()?.parse::<u32>().ok())
+}
+
+impl ModelController {
+    /// Duration, chapter markers and part number of an audiobook file. Links it to a matching
+    /// or new book from its tags, with the embedded cover art as book cover
+    pub async fn update_audiobook_infos(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        requesting_user: &ConnectedUser,
+        notif: bool,
+    ) -> RsResult<()> {
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Read)?;
+        self.cache_check_library_notcrypt(library_id).await?;
+        let existing = self
+            .get_media(library_id, media_id.to_string(), requesting_user)
+            .await?
+            .ok_or(SourcesError::UnableToFindMedia(
+                library_id.to_string(),
+                media_id.to_string(),
+                "update_audiobook_infos".to_string(),
+            ))?
+            .item;
+        let uri = self.get_media_uri(library_id, media_id, Some(240)).await?;
+        let probe = probe_video(&uri).await?;
+        let tags = probe.format.tags.clone().unwrap_or_default();
+        let metadata = audiobook_metadata(&tags);
+
+        let mut update = MediaForUpdate {
+            duration: probe.duration().map(|duration| duration as u64),
+            ..Default::default()
+        };
+        if existing.description.is_none() {
+            update.description = metadata.description.clone();
+        }
+        if existing.lang.is_none() {
+            update.lang = metadata.language.clone();
+        }
+        if let Some(part) = tags.track.as_deref().and_then(parse_part_number) {
+            self.store
+                .get_library_store(library_id)?
+                .set_media_param(media_id, PART_PARAM, &part.to_string())
+                .await?;
+        }
+        if existing.book.is_none() {
+            if let Some((book, created)) = self
+                .get_or_add_book_from_metadata(library_id, &metadata, requesting_user)
+                .await?
+            {
+                if created && probe.cover_stream().is_some() {
+                    let r = match thumb_video(&uri, VideoTime::Seconds(0.0)).await {
+                        Ok(cover) => {
+                            self.update_book_image(
+                                library_id,
+                                &book.id,
+                                &ImageType::Poster,
+                                Box::pin(std::io::Cursor::new(cover)),
+                                requesting_user,
+                            )
+                            .await
+                        }
+                        Err(e) => Err(e),
+                    };
+                    if let Err(r) = r {
+                        log_error(
+                            LogServiceType::Source,
+                            format!("unable to set cover of book {}: {:?}", book.id, r),
+                        );
+                    }
+                }
+                update.book = Some(book.id);
+            }
+        }
+
+        self.update_media(
+            library_id,
+            media_id.to_owned(),
+            update,
+            notif,
+            requesting_user,
+        )
+        .await?;
+        self.import_media_chapters(library_id, media_id, probe.media_chapters())
+            .await?;
+        Ok(())
+    }
+
+    /// Ordered audio files of a book and the listening position in the whole book
+    pub async fn get_audiobook(
+        &self,
+        library_id: &str,
+        book_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<RsAudiobook> {
+        let medias = self
+            .get_medias(
+                library_id,
+                MediaQuery {
+                    book: Some(book_id.to_string()),
+                    ..Default::default()
+                },
+                requesting_user,
+            )
+            .await?;
+        let parts = order_parts(
+            medias
+                .into_iter()
+                .map(|media| media.item)
+                .filter(|media| is_audiobook_mime(&media.mimetype))
+                .map(|media| RsAudiobookPart {
+                    part: media_part(&media),
+                    duration: media.duration.unwrap_or(0) * 1000,
+                    media: media.id,
+                    name: media.name,
+                    ..Default::default()
+                })
+                .collect(),
+        );
+        if parts.is_empty() {
+            return Err(RsError::NotFound(format!(
+                "No audio file for book {}",
+                book_id
+            )));
+        }
+
+        let mut last_modified = 0;
+        let mut position = 0;
+        for part in &parts {
+            let Ok(progress) = self
+                .get_media_progress(library_id, part.media.clone(), requesting_user)
+                .await
+            else {
+                continue;
+            };
+            if progress.modified > last_modified {
+                last_modified = progress.modified;
+                position = global_position(part, progress.progress);
+            }
+        }
+        Ok(RsAudiobook {
+            book: book_id.to_string(),
+            duration: parts.iter().map(|part| part.duration).sum(),
+            parts,
+            position,
+        })
+    }
+
+    /// Store a position of the whole book as the progress of the part playing at this position
+    pub async fn set_audiobook_progress(
+        &self,
+        library_id: &str,
+        book_id: &str,
+        position: u64,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<RsAudiobook> {
+        let mut audiobook = self
+            .get_audiobook(library_id, book_id, requesting_user)
+            .await?;
+        let (part, offset) = part_at(&audiobook.parts, position).ok_or(RsError::NotFound(
+            format!("No audio file for book {}", book_id),
+        ))?;
+        self.set_media_progress(library_id, part.media.clone(), offset, requesting_user)
+            .await?;
+        audiobook.position = global_position(part, offset);
+        Ok(audiobook)
+    }
+
+    /// HLS session streaming all the parts of an audiobook as one continuous audio stream
+    pub async fn get_or_create_audiobook_hls_session(
+        &self,
+        library_id: &str,
+        book_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<String> {
+        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+
+        // Crypt libraries are not supported for HLS
+        if self.cache_get_library_crypt(library_id).await {
+            return Err(crate::Error::UnavailableForCryptedLibraries);
+        }
+
+        let key = format!("{}:{}:audiobook", library_id, book_id);
+        {
+            let sessions = self.media_hls_sessions.read().await;
+            if let Some(session) = sessions.get(&key) {
+                session.touch();
+                return Ok(key);
+            }
+        }
+
+        let audiobook = self
+            .get_audiobook(library_id, book_id, requesting_user)
+            .await?;
+        let mut inputs = vec![];
+        for part in &audiobook.parts {
+            inputs.push(
+                self.get_media_uri(library_id, &part.media, Some(43200))
+                    .await?,
+            );
+        }
+
+        let session = crate::tools::media_hls_session::start_audiobook_hls_session(
+            key.clone(),
+            library_id.to_string(),
+            book_id.to_string(),
+            requesting_user.user_id().ok(),
+            &inputs,
+            self.media_hls_sessions.clone(),
+        )
+        .await?;
+        if let Some(session) = session {
+            self.send_streaming_session(ElementAction::Added, session);
+        }
+
+        Ok(key)
+    }
+}
@@ -4,9 +4,10 @@ use async_recursion::async_recursion;
 use nanoid::nanoid;
 use rs_plugin_common_interfaces::{
     domain::{
+        media::MediaItemReference,
         other_ids::OtherIds,
         rs_ids::{ApplyRsIds, RsIds},
-        ItemWithRelations,
+        ItemWithRelations, Relations,
     },
     lookup::{RsLookupBook, RsLookupMetadataResult, RsLookupQuery},
     ExternalImage, ImageType,
@@ -19,19 +20,27 @@ use crate::{
         book::{Book, BookForUpdate, BookWithAction, BooksMessage},
         deleted::RsDeleted,
         library::LibraryRole,
+        serie::{Serie, SerieWithAction, SeriesMessage},
         ElementAction, MediaElement,
     },
     error::RsResult,
-    model::{people::PersonForAdd, tags::TagForAdd},
+    model::{
+        people::{PeopleQuery, PersonForAdd},
+        tags::TagForAdd,
+    },
     plugins::sources::{error::SourcesError, AsyncReadPinBox, FileStreamResult},
     routes::sse::SseEvent,
-    tools::image_tools::{convert_image_reader, ImageSize},
+    tools::{
+        image_tools::{convert_image_reader, ImageSize},
+        opf_parser::OpfMetadata,
+    },
 };
 
 use super::{
     entity_images::EntityImageConfig,
     entity_search::merge_result_ids,
     error::{Error, Result},
+    series::SerieQuery,
     store::sql::SqlOrder,
     users::ConnectedUser,
     ModelController,
@@ -748,4 +757,139 @@ impl ModelController {
         });
         Ok(())
     }
+
+    /// Match a book on its ISBN-13 (or title and serie) or create it with its authors and serie
+    /// from EPUB or ComicInfo metadata. Returns the book and if it was created
+    pub async fn get_or_add_book_from_metadata(
+        &self,
+        library_id: &str,
+        metadata: &OpfMetadata,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Option<(Book, bool)>> {
+        let Some(name) = metadata.title.clone() else {
+            return Ok(None);
+        };
+        if let Some(isbn13) = &metadata.isbn13 {
+            let ids: RsIds = Book {
+                isbn13: Some(isbn13.clone()),
+                ..Default::default()
+            }
+            .into();
+            if let Some(existing) = self
+                .get_book_by_external_id(library_id, ids, requesting_user)
+                .await?
+            {
+                return Ok(Some((existing.item, false)));
+            }
+        }
+        requesting_user.check_library_role(library_id, LibraryRole::Write)?;
+        let store = self.store.get_library_store(library_id)?;
+
+        let serie_ref = match &metadata.series {
+            Some(serie_name) => {
+                let found = store
+                    .get_series(SerieQuery {
+                        name: Some(serie_name.clone()),
+                        ..Default::default()
+                    })
+                    .await?
+                    .into_iter()
+                    .find(|s| s.item.name.eq_ignore_ascii_case(serie_name));
+                match found {
+                    Some(serie) => Some(serie.item.id),
+                    None => {
+                        let id = nanoid!();
+                        store
+                            .add_serie(Serie {
+                                id: id.clone(),
+                                name: serie_name.clone(),
+                                ..Default::default()
+                            })
+                            .await?;
+                        if let Some(serie) = store.get_serie(&id).await? {
+                            self.send_serie(SeriesMessage {
+                                library: library_id.to_string(),
+                                series: vec![SerieWithAction {
+                                    action: ElementAction::Added,
+                                    serie: serie.item,
+                                }],
+                            });
+                        }
+                        Some(id)
+                    }
+                }
+            }
+            None => None,
+        };
+
+        if metadata.isbn13.is_none() {
+            let existing = store
+                .get_books(BookQuery {
+                    name: Some(name.clone()),
+                    serie_ref: serie_ref.clone(),
+                    ..Default::default()
+                })
+                .await?
+                .into_iter()
+                .find(|b| b.item.name.eq_ignore_ascii_case(&name) && b.item.serie_ref == serie_ref);
+            if let Some(existing) = existing {
+                return Ok(Some((existing.item, false)));
+            }
+        }
+
+        let mut people = vec![];
+        for author in &metadata.authors {
+            let found = store
+                .get_people(PeopleQuery::from_name(author))
+                .await?
+                .into_iter()
+                .find(|p| p.name.eq_ignore_ascii_case(author));
+            let id = match found {
+                Some(person) => person.id,
+                None => {
+                    self.add_pesron(
+                        library_id,
+                        PersonForAdd {
+                            name: author.clone(),
+                            ..Default::default()
+                        },
+                        requesting_user,
+                    )
+                    .await?
+                    .id
+                }
+            };
+            people.push(MediaItemReference { id, conf: None });
+        }
+
+        let book = Book {
+            name,
+            volume: serie_ref.as_ref().and(metadata.series_index),
+            chapter: serie_ref.as_ref().and(metadata.chapter),
+            serie_ref,
+            year: metadata.year,
+            overview: metadata.description.clone(),
+            lang: metadata.language.clone(),
+            isbn13: metadata.isbn13.clone(),
+            ..Default::default()
+        };
+        let relations = (!people.is_empty()).then(|| Relations {
+            people: Some(people),
+            ..Default::default()
+        });
+        let book = self
+            .add_book(
+                library_id,
+                ItemWithRelations {
+                    item: book,
+                    relations,
+                },
+                false,
+                false,
+                false,
+                requesting_user,
+            )
+            .await?;
+        Ok(Some((book, true)))
+    }
 }
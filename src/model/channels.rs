@@ -1,8 +1,10 @@
 use std::{
     collections::{HashMap, HashSet},
     io::Cursor,
+    sync::atomic::{AtomicBool, Ordering},
 };
 
+use futures::StreamExt;
 use nanoid::nanoid;
 use rs_plugin_common_interfaces::ImageType;
 use serde::{Deserialize, Serialize};
@@ -10,32 +12,90 @@ use serde::{Deserialize, Serialize};
 use crate::{
     domain::{
         channel::{
-            Channel, ChannelForUpdate, ChannelMessage, ChannelVariant, ChannelWithAction,
-            M3uImportResult,
+            Channel, ChannelForUpdate, ChannelMessage, ChannelUserSettings,
+            ChannelUserSettingsForUpdate, ChannelVariant, ChannelWithAction, M3uImportResult,
+            VariantHealth,
         },
-        library::{LibraryRole, LibraryType},
+        episode::{Episode, EpisodeWithAction, EpisodesMessage},
+        library::{IptvSource, LibraryLimits, LibraryRole, LibraryType},
+        media::{
+            FileEpisode, FileType, ItemWithRelations, Media, MediaForAdd, MediaForInsert,
+            MediaForUpdate, MediaWithAction, MediasMessage,
+        },
+        movie::{Movie, MovieWithAction, MoviesMessage},
+        serie::{Serie, SerieWithAction, SeriesMessage},
         ElementAction,
     },
     error::RsResult,
     plugins::sources::{AsyncReadPinBox, FileStreamResult},
     routes::sse::SseEvent,
     tools::{
+        auth::{sign_local, ClaimsLocal, ClaimsLocalType, ShareTokenKind},
+        file_tools::get_mime_from_filename,
+        get_time,
+        hdhomerun::guide_numbers,
         image_tools::{convert_image_reader, ImageSize},
+        iptv_export::{write_m3u, write_xmltv, ExportChannel},
+        iptv_health::{health_update, probe_variant, rank_variants},
         log::{log_error, log_info, LogServiceType},
-        m3u_parser::{self, M3uContentType, M3uEntry, QUALITY_ORDER},
+        m3u_parser::{self, M3uContentType, M3uEntry},
+        xtream::{self, XtreamClient},
     },
 };
 
 use super::{
-    entity_images::EntityImageConfig, tags::TagForAdd, users::ConnectedUser, ModelController,
+    entity_images::EntityImageConfig,
+    episodes::EpisodeQuery,
+    error::Error,
+    medias::MediaQuery,
+    movies::MovieQuery,
+    series::SerieQuery,
+    store::sql::library::SqliteLibraryStore,
+    tags::{TagForAdd, TagQuery},
+    users::ConnectedUser,
+    ModelController,
 };
 
+/// Prefix of the stream slots used while probing variants
+const HEALTH_SLOT: &str = "health";
+/// Variants probed at the same time, each one also needs a free stream slot
+const HEALTH_CONCURRENCY: usize = 4;
+/// Validity of the tokens embedded in exported playlists
+const EXPORT_TOKEN_SECONDS: u64 = 365 * 24 * 60 * 60;
+/// Guide exported before and after now
+const EXPORT_EPG_PAST: i64 = 2 * 60 * 60 * 1000;
+const EXPORT_EPG_FUTURE: i64 = 3 * 24 * 60 * 60 * 1000;
+/// Xtream VOD and series info requests running at the same time during an import
+const XTREAM_INFO_CONCURRENCY: usize = 8;
+
 #[derive(Debug, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct ChannelQuery {
     #[serde(alias = "groupTag")]
     pub tag: Option<String>,
     pub name: Option<String>,
+    pub favorite: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelExportQuery {
+    pub sharetoken: Option<String>,
+    /// Only export the favorites of the user
+    #[serde(default)]
+    pub favorites: bool,
+    /// Point players to the HLS playlist instead of the MPEG-TS stream
+    #[serde(default)]
+    pub hls: bool,
+    pub quality: Option<String>,
+}
+
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelExport {
+    pub token: String,
+    pub m3u: String,
+    pub epg: String,
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -57,9 +117,16 @@ impl ModelController {
         query: ChannelQuery,
         requesting_user: &ConnectedUser,
     ) -> RsResult<Vec<Channel>> {
-        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        let limits = requesting_user.check_library_role(library_id, LibraryRole::Read)?;
         let store = self.store.get_library_store(library_id)?;
         let mut channels = store.get_channels(query.tag, query.name).await?;
+        if let Some(user_id) = channel_settings_user(&limits, requesting_user) {
+            let settings = store.get_channel_user_settings(&user_id).await?;
+            apply_channel_user_settings(&mut channels, settings);
+        }
+        if let Some(favorite) = query.favorite {
+            channels.retain(|c| c.favorite.unwrap_or(false) == favorite);
+        }
 
         // Attach variants to each channel
         for channel in &mut channels {
@@ -78,15 +145,21 @@ impl ModelController {
         channel_id: &str,
         requesting_user: &ConnectedUser,
     ) -> RsResult<Channel> {
-        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        let limits = requesting_user.check_library_role(library_id, LibraryRole::Read)?;
         let store = self.store.get_library_store(library_id)?;
-        let mut channel = store
+        let channel = store
             .get_channel(channel_id)
             .await?
             .ok_or(crate::Error::NotFound(format!(
                 "Channel {} not found",
                 channel_id
             )))?;
+        let mut channels = vec![channel];
+        if let Some(user_id) = channel_settings_user(&limits, requesting_user) {
+            let settings = store.get_channel_user_settings(&user_id).await?;
+            apply_channel_user_settings(&mut channels, settings);
+        }
+        let mut channel = channels.remove(0);
         let variants = store.get_channel_variants(&channel.id).await?;
         if !variants.is_empty() {
             channel.variants = Some(variants);
@@ -103,6 +176,7 @@ impl ModelController {
         requesting_user.check_library_role(library_id, LibraryRole::Admin)?;
         let store = self.store.get_library_store(library_id)?;
         let existing = store.get_channel(channel_id).await?;
+        self.remove_channel_dvr(library_id, channel_id).await?;
         store.remove_channel(channel_id.to_string()).await?;
         if let Some(channel) = existing {
             self.broadcast_sse(SseEvent::Channels(ChannelMessage {
@@ -116,6 +190,221 @@ impl ModelController {
         Ok(())
     }
 
+    /// Change the favorite, position or number of a channel for the requesting user
+    pub async fn update_channel_user_settings(
+        &self,
+        library_id: &str,
+        channel_id: &str,
+        update: ChannelUserSettingsForUpdate,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Channel> {
+        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        let user_id = requesting_user.user_id()?;
+        let store = self.store.get_library_store(library_id)?;
+        if store.get_channel(channel_id).await?.is_none() {
+            return Err(crate::Error::NotFound(format!(
+                "Channel {} not found",
+                channel_id
+            )));
+        }
+        store
+            .update_channel_user_settings(channel_id, &user_id, update)
+            .await?;
+        self.get_channel(library_id, channel_id, requesting_user)
+            .await
+    }
+
+    /// Set the channel order of the requesting user, unlisted channels keep the default order after them
+    pub async fn set_channel_order(
+        &self,
+        library_id: &str,
+        channels: Vec<String>,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<Channel>> {
+        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        let user_id = requesting_user.user_id()?;
+        let store = self.store.get_library_store(library_id)?;
+        let existing: HashSet<String> = store
+            .get_channels(None, None)
+            .await?
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        let mut seen = HashSet::new();
+        let channels: Vec<String> = channels
+            .into_iter()
+            .filter(|id| existing.contains(id) && seen.insert(id.clone()))
+            .collect();
+        store.set_channel_user_positions(&user_id, channels).await?;
+        self.get_channels(library_id, ChannelQuery::default(), requesting_user)
+            .await
+    }
+
+    /// Token letting IPTV players read the channels of a library as the requesting user.
+    /// Its id is stored so it can be revoked
+    pub async fn get_channels_export_token(
+        &self,
+        library_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<String> {
+        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        let user_id = requesting_user.user_id()?;
+        let token_id = self
+            .get_share_token_id(ShareTokenKind::Channels, library_id, &user_id)
+            .await?;
+        let claims = ClaimsLocal {
+            cr: "service::channels_export".to_string(),
+            kind: ClaimsLocalType::Channels(library_id.to_string(), user_id, token_id),
+            exp: ClaimsLocal::generate_seconds(EXPORT_TOKEN_SECONDS),
+        };
+        let token = sign_local(claims)
+            .await
+            .map_err(|_| Error::UnableToSignShareToken)?;
+        Ok(token)
+    }
+
+    /// Revoke the export token of the requesting user, players using it must be set up again
+    pub async fn revoke_channels_export_token(
+        &self,
+        library_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<()> {
+        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        let user_id = requesting_user.user_id()?;
+        self.store
+            .remove_share_token_id(ShareTokenKind::Channels, library_id.to_string(), user_id)
+            .await?;
+        Ok(())
+    }
+
+    /// Playlist and guide urls of the channels of a library for `base_url`
+    pub fn get_channels_export_urls(
+        &self,
+        library_id: &str,
+        token: String,
+        base_url: &str,
+    ) -> ChannelExport {
+        let base = format!("{}/libraries/{}/channels", base_url, library_id);
+        ChannelExport {
+            m3u: format!("{}/export.m3u?sharetoken={}", base, token),
+            epg: format!("{}/epg.xml?sharetoken={}", base, token),
+            token,
+        }
+    }
+
+    /// M3U playlist of the channels with stream urls going through this server
+    pub async fn export_channels_m3u(
+        &self,
+        library_id: &str,
+        query: &ChannelExportQuery,
+        token: &str,
+        base_url: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<String> {
+        let channels = self
+            .export_channels(library_id, query, token, base_url, requesting_user)
+            .await?;
+        let mut epg_url = format!(
+            "{}/libraries/{}/channels/epg.xml?sharetoken={}",
+            base_url, library_id, token
+        );
+        if query.favorites {
+            epg_url.push_str("&favorites=true");
+        }
+        Ok(write_m3u(&channels, Some(&epg_url)))
+    }
+
+    /// XMLTV guide of the exported channels, from two hours ago to three days ahead
+    pub async fn export_channels_xmltv(
+        &self,
+        library_id: &str,
+        query: &ChannelExportQuery,
+        token: &str,
+        base_url: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<String> {
+        let mut channels = self
+            .export_channels(library_id, query, token, base_url, requesting_user)
+            .await?;
+        let mut seen = HashSet::new();
+        channels.retain(|c| seen.insert(c.tvg_id.clone()));
+        let now = get_time().as_millis() as i64;
+        let store = self.store.get_library_store(library_id)?;
+        let programmes = store
+            .get_epg_programmes(
+                Some(channels.iter().map(|c| c.tvg_id.clone()).collect()),
+                now - EXPORT_EPG_PAST,
+                now + EXPORT_EPG_FUTURE,
+            )
+            .await?;
+        Ok(write_xmltv(&channels, &programmes))
+    }
+
+    /// Channels of the requesting user, in their order, with urls authenticated by `token`
+    async fn export_channels(
+        &self,
+        library_id: &str,
+        query: &ChannelExportQuery,
+        token: &str,
+        base_url: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<ExportChannel>> {
+        let channel_query = ChannelQuery {
+            favorite: query.favorites.then_some(true),
+            ..Default::default()
+        };
+        let channels = self
+            .get_channels(library_id, channel_query, requesting_user)
+            .await?;
+        let store = self.store.get_library_store(library_id)?;
+        let tag_names: HashMap<String, String> = store
+            .get_tags(TagQuery::new_empty())
+            .await?
+            .into_iter()
+            .map(|t| (t.id, t.name))
+            .collect();
+        let numbers: HashMap<&str, u32> = guide_numbers(&channels)
+            .into_iter()
+            .map(|(number, channel)| (channel.id.as_str(), number))
+            .collect();
+        let base = format!("{}/libraries/{}/channels", base_url, library_id);
+        let stream_path = if query.hls {
+            "hls/playlist.m3u8"
+        } else {
+            "stream"
+        };
+        let quality = query
+            .quality
+            .as_deref()
+            .map(|q| format!("&quality={}", urlencoding::encode(q)))
+            .unwrap_or_default();
+        Ok(channels
+            .iter()
+            .map(|channel| ExportChannel {
+                tvg_id: channel.tvg_id.clone().unwrap_or_else(|| channel.id.clone()),
+                name: channel.name.clone(),
+                number: numbers
+                    .get(channel.id.as_str())
+                    .copied()
+                    .unwrap_or_default(),
+                logo: channel
+                    .logo
+                    .as_ref()
+                    .map(|_| format!("{}/{}/image?sharetoken={}", base, channel.id, token)),
+                group: channel
+                    .tags
+                    .iter()
+                    .flatten()
+                    .find_map(|tag| tag_names.get(tag).cloned()),
+                url: format!(
+                    "{}/{}/{}?sharetoken={}{}",
+                    base, channel.id, stream_path, token, quality
+                ),
+            })
+            .collect())
+    }
+
+    /// Stream url of the best variant of a channel that is not dead
     pub async fn get_channel_stream_url(
         &self,
         library_id: &str,
@@ -123,6 +412,20 @@ impl ModelController {
         quality: Option<String>,
         requesting_user: &ConnectedUser,
     ) -> RsResult<String> {
+        let urls = self
+            .get_channel_stream_urls(library_id, channel_id, quality, requesting_user)
+            .await?;
+        Ok(urls[0].clone())
+    }
+
+    /// Stream urls of the variants of a channel that are not dead, in the order they should be tried
+    pub async fn get_channel_stream_urls(
+        &self,
+        library_id: &str,
+        channel_id: &str,
+        quality: Option<String>,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<String>> {
         requesting_user.check_library_role(library_id, LibraryRole::Read)?;
         let store = self.store.get_library_store(library_id)?;
         let variants = store.get_channel_variants(channel_id).await?;
@@ -134,25 +437,117 @@ impl ModelController {
             )));
         }
 
-        // If quality requested, try to find that variant
-        if let Some(ref q) = quality {
-            if let Some(variant) = variants
-                .iter()
-                .find(|v| v.quality.as_deref() == Some(q.as_str()))
+        let urls: Vec<String> = rank_variants(&variants, quality.as_deref())
+            .into_iter()
+            .map(|v| v.stream_url.clone())
+            .collect();
+        if urls.is_empty() {
+            return Err(crate::Error::NotFound(format!(
+                "All variants of channel {} are dead",
+                channel_id
+            )));
+        }
+        Ok(urls)
+    }
+
+    /// Probe the variants of a library, least recently checked first, while a stream slot is free
+    pub async fn check_channel_variants(
+        &self,
+        library_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<()> {
+        requesting_user.check_library_role(library_id, LibraryRole::Admin)?;
+        let store = self.store.get_library_store(library_id)?;
+        let variants = store.get_all_channel_variants().await?;
+        let mut changed_channels: HashSet<String> = HashSet::new();
+        let (mut checked, mut dead) = (0, 0);
+        let paused = AtomicBool::new(false);
+        let paused = &paused;
+        let mut probes = futures::stream::iter(variants)
+            .map(|variant| async move {
+                // Probes open a connection to the provider like any stream
+                let slot = format!("{}-{}", HEALTH_SLOT, variant.id);
+                if paused.load(Ordering::Relaxed)
+                    || !self.try_acquire_stream_slot(library_id, &slot).await
+                {
+                    paused.store(true, Ordering::Relaxed);
+                    return None;
+                }
+                let probe = probe_variant(&variant.stream_url).await;
+                self.release_stream_slot(library_id, &slot).await;
+                Some((variant, probe))
+            })
+            .buffer_unordered(HEALTH_CONCURRENCY);
+        while let Some(result) = probes.next().await {
+            let Some((variant, probe)) = result else {
+                continue;
+            };
+            let update = health_update(&variant, &probe, get_time().as_millis() as i64);
+            if update.health != variant.health {
+                changed_channels.insert(variant.channel_ref.clone());
+            }
+            if let Some(error) = &probe.error {
+                log_info(
+                    LogServiceType::Source,
+                    format!(
+                        "IPTV variant {} ({}) failed health check: {}",
+                        variant.name.as_deref().unwrap_or(&variant.id),
+                        update.failures,
+                        error
+                    ),
+                );
+            }
+            if update.health == Some(VariantHealth::Dead) {
+                dead += 1;
+            }
+            checked += 1;
+            // Not returned early: dropping the stream would leak the slots of running probes
+            if let Err(error) = store
+                .update_channel_variant_health(&variant.id, update)
+                .await
             {
-                return Ok(variant.stream_url.clone());
+                log_error(
+                    LogServiceType::Source,
+                    format!(
+                        "Unable to store health of variant {}: {:?}",
+                        variant.id, error
+                    ),
+                );
             }
         }
+        if paused.load(Ordering::Relaxed) {
+            log_info(
+                LogServiceType::Source,
+                format!(
+                    "IPTV health check of library {} paused, no free stream slot",
+                    library_id
+                ),
+            );
+        }
 
-        // Otherwise pick best quality: 4K > FHD > HD > SD > first
-        for q in QUALITY_ORDER {
-            if let Some(variant) = variants.iter().find(|v| v.quality.as_deref() == Some(*q)) {
-                return Ok(variant.stream_url.clone());
+        let mut channel_actions: Vec<ChannelWithAction> = Vec::new();
+        for channel_id in changed_channels {
+            if let Some(channel) = store.get_channel(&channel_id).await? {
+                channel_actions.push(ChannelWithAction {
+                    action: ElementAction::Updated,
+                    channel,
+                });
             }
         }
-
-        // Fallback to first variant
-        Ok(variants[0].stream_url.clone())
+        if !channel_actions.is_empty() {
+            self.broadcast_sse(SseEvent::Channels(ChannelMessage {
+                library: library_id.to_string(),
+                channels: channel_actions,
+            }));
+        }
+        log_info(
+            LogServiceType::Source,
+            format!(
+                "IPTV health check of library {}: {} variants checked, {} dead",
+                library_id, checked, dead
+            ),
+        );
+        Ok(())
     }
 
     pub async fn import_m3u(
@@ -208,13 +603,220 @@ impl ModelController {
 
         // Parse
         let parse_result = m3u_parser::parse_m3u(&content);
-        let total_parsed = parse_result.entries.len();
 
         log_info(
             LogServiceType::Source,
-            format!("Parsed {} M3U entries", total_parsed),
+            format!("Parsed {} M3U entries", parse_result.entries.len()),
         );
 
+        self.import_iptv_entries(
+            library_id,
+            parse_result.entries,
+            parse_result.header.url_tvg,
+            requesting_user,
+        )
+        .await
+    }
+
+    /// Refresh the channels of an IPTV library from its configured source
+    pub async fn import_iptv(
+        &self,
+        library_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<M3uImportResult> {
+        let source = self
+            .cache_get_library(library_id)
+            .await
+            .and_then(|l| l.settings.iptv_source)
+            .unwrap_or_default();
+        match source {
+            IptvSource::M3u => self.import_m3u(library_id, None, requesting_user).await,
+            IptvSource::Xtream => self.import_xtream(library_id, requesting_user).await,
+        }
+    }
+
+    /// Import live streams, VOD and series from the Xtream Codes API of the server in the library
+    /// `root`, logged in with the library credentials
+    pub async fn import_xtream(
+        &self,
+        library_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<M3uImportResult> {
+        requesting_user.check_library_role(library_id, LibraryRole::Admin)?;
+
+        let library =
+            self.get_internal_library(library_id)
+                .await?
+                .ok_or(crate::Error::NotFound(format!(
+                    "Library {} not found",
+                    library_id
+                )))?;
+        if library.kind != LibraryType::Iptv {
+            return Err(crate::Error::Error(
+                "Xtream import is only available for IPTV libraries".to_string(),
+            ));
+        }
+        let server = library
+            .root
+            .clone()
+            .filter(|r| !r.is_empty())
+            .ok_or(crate::Error::Error(
+            "No Xtream server configured for this library. Set the library root to the server URL."
+                .to_string(),
+        ))?;
+        let credential_id = library.credentials.clone().ok_or(crate::Error::Error(
+            "No credentials configured for this Xtream library".to_string(),
+        ))?;
+        let credential = self
+            .get_credential(credential_id.clone(), &ConnectedUser::ServerAdmin)
+            .await?
+            .ok_or(crate::Error::NotFound(format!(
+                "Credential {} not found",
+                credential_id
+            )))?;
+        let (Some(username), Some(password)) = (credential.login, credential.password) else {
+            return Err(crate::Error::Error(
+                "Xtream credentials need a login and a password".to_string(),
+            ));
+        };
+        let client = XtreamClient::new(&server, &username, &password);
+
+        self.broadcast_sse(SseEvent::LibraryStatus(
+            crate::domain::library::LibraryStatusMessage {
+                message: "Fetching Xtream channels...".to_string(),
+                library: library_id.to_string(),
+            },
+        ));
+        log_info(
+            LogServiceType::Source,
+            format!("Fetching Xtream API from: {}", client.server),
+        );
+        client.authenticate().await?;
+
+        let mut entries: Vec<M3uEntry> = Vec::new();
+
+        let categories = xtream::category_names(client.live_categories().await?);
+        entries.extend(
+            client
+                .live_streams()
+                .await?
+                .iter()
+                .filter_map(|stream| client.live_entry(stream, &categories)),
+        );
+
+        // Release year, duration and cover are only in the VOD info. Streams already imported
+        // are skipped by the import, so the info is only fetched for new stream urls
+        let known_sources: HashSet<String> = self
+            .store
+            .get_library_store(library_id)?
+            .get_all_media_id_sources()
+            .await?
+            .into_iter()
+            .map(|(_, source)| source)
+            .collect();
+        let categories = xtream::category_names(client.vod_categories().await?);
+        let vod_streams = client.vod_streams().await?;
+        let mut new_streams = vec![];
+        for stream in &vod_streams {
+            let Some(entry) = client.vod_entry(stream, None, &categories) else {
+                continue;
+            };
+            if known_sources.contains(&entry.url) {
+                entries.push(entry);
+            } else {
+                new_streams.push(stream);
+            }
+        }
+        let total_vod = new_streams.len();
+        let client = &client;
+        let mut infos = futures::stream::iter(new_streams)
+            .map(|stream| async move {
+                let info = match stream.stream_id.as_ref() {
+                    Some(id) => Some(client.vod_info(id).await),
+                    None => None,
+                };
+                (stream, info)
+            })
+            .buffered(XTREAM_INFO_CONCURRENCY)
+            .enumerate();
+        while let Some((index, (stream, info))) = infos.next().await {
+            if index % 50 == 0 {
+                self.broadcast_sse(SseEvent::LibraryStatus(
+                    crate::domain::library::LibraryStatusMessage {
+                        message: format!("Fetching Xtream VOD {}/{}...", index, total_vod),
+                        library: library_id.to_string(),
+                    },
+                ));
+            }
+            let info = match info {
+                Some(Ok(info)) => Some(info),
+                Some(Err(error)) => {
+                    log_error(
+                        LogServiceType::Source,
+                        format!("Unable to get Xtream VOD {}: {:#}", stream.name, error),
+                    );
+                    None
+                }
+                None => None,
+            };
+            entries.extend(client.vod_entry(stream, info.as_ref(), &categories));
+        }
+
+        // Episodes are only listed per series
+        let categories = xtream::category_names(client.series_categories().await?);
+        let all_series = client.series().await?;
+        let total_series = all_series.len();
+        let mut infos = futures::stream::iter(
+            all_series
+                .iter()
+                .filter_map(|series| Some((series, series.series_id.as_ref()?))),
+        )
+        .map(|(series, series_id)| async move { (series, client.series_info(series_id).await) })
+        .buffered(XTREAM_INFO_CONCURRENCY)
+        .enumerate();
+        while let Some((index, (series, info))) = infos.next().await {
+            if index % 50 == 0 {
+                self.broadcast_sse(SseEvent::LibraryStatus(
+                    crate::domain::library::LibraryStatusMessage {
+                        message: format!("Fetching Xtream series {}/{}...", index, total_series),
+                        library: library_id.to_string(),
+                    },
+                ));
+            }
+            match info {
+                Ok(info) => entries.extend(client.episode_entries(series, &info, &categories)),
+                Err(error) => log_error(
+                    LogServiceType::Source,
+                    format!("Unable to get Xtream series {}: {:#}", series.name, error),
+                ),
+            }
+        }
+
+        log_info(
+            LogServiceType::Source,
+            format!("Fetched {} Xtream entries", entries.len()),
+        );
+
+        self.import_iptv_entries(
+            library_id,
+            entries,
+            Some(client.xmltv_url()),
+            requesting_user,
+        )
+        .await
+    }
+
+    /// Import playlist entries: live entries become channels grouped by `tvg_id` with one variant
+    /// per entry, and VOD and series entries become virtual medias
+    async fn import_iptv_entries(
+        &self,
+        library_id: &str,
+        entries: Vec<M3uEntry>,
+        epg_url: Option<String>,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<M3uImportResult> {
+        let total_parsed = entries.len();
+
         self.broadcast_sse(SseEvent::LibraryStatus(
             crate::domain::library::LibraryStatusMessage {
                 message: format!("Parsed {} entries, importing...", total_parsed),
@@ -224,18 +826,18 @@ impl ModelController {
 
         // Classify entries
         let mut live_entries: Vec<M3uEntry> = Vec::new();
-        let mut _vod_entries: Vec<M3uEntry> = Vec::new();
-        let mut _series_entries: Vec<M3uEntry> = Vec::new();
+        let mut vod_entries: Vec<M3uEntry> = Vec::new();
+        let mut series_entries: Vec<M3uEntry> = Vec::new();
 
-        for entry in parse_result.entries {
+        for entry in entries {
             match entry.content_type() {
                 M3uContentType::Live => {
                     if entry.tvg_id.is_some() {
                         live_entries.push(entry);
                     }
                 }
-                M3uContentType::Vod => _vod_entries.push(entry),
-                M3uContentType::Series => _series_entries.push(entry),
+                M3uContentType::Vod => vod_entries.push(entry),
+                M3uContentType::Series => series_entries.push(entry),
             }
         }
 
@@ -244,13 +846,14 @@ impl ModelController {
             format!(
                 "Classified: {} live, {} VOD, {} series",
                 live_entries.len(),
-                _vod_entries.len(),
-                _series_entries.len()
+                vod_entries.len(),
+                series_entries.len()
             ),
         );
 
         let mut result = M3uImportResult {
             total_parsed,
+            epg_url,
             ..Default::default()
         };
 
@@ -343,6 +946,8 @@ impl ModelController {
                         modified: None,
                         added: None,
                         variants: None,
+                        favorite: None,
+                        position: None,
                     })
                     .await?;
                 result.channels_added += 1;
@@ -413,8 +1018,7 @@ impl ModelController {
                             stream_url: entry.url.clone(),
                             name: Some(variant_name),
                             tvg_name: Some(tvg_name.clone()),
-                            modified: None,
-                            added: None,
+                            ..Default::default()
                         })
                         .await?;
                 }
@@ -454,6 +1058,7 @@ impl ModelController {
                         channel: removed,
                     });
                 }
+                self.remove_channel_dvr(library_id, old_id).await?;
                 store.remove_channel(old_id.clone()).await?;
                 result.channels_removed += 1;
             }
@@ -467,21 +1072,28 @@ impl ModelController {
             }));
         }
 
-        // --- Import VOD Movies ---
-        // TODO: Import VOD movies using existing movies + medias tables
-
-        // --- Import Series ---
-        // TODO: Import series using existing series + episodes + medias tables
+        // --- Import VOD movies and series episodes ---
+        self.import_m3u_vod(
+            library_id,
+            vod_entries,
+            series_entries,
+            &mut result,
+            requesting_user,
+        )
+        .await?;
 
         self.broadcast_sse(SseEvent::LibraryStatus(
             crate::domain::library::LibraryStatusMessage {
                 message: format!(
-                    "Import complete: {} channels ({} new, {} updated, {} removed), {} groups",
+                    "Import complete: {} channels ({} new, {} updated, {} removed), {} groups, {} movies, {} series, {} episodes",
                     seen_channel_ids.len(),
                     result.channels_added,
                     result.channels_updated,
                     result.channels_removed,
-                    result.groups_created
+                    result.groups_created,
+                    result.movies_added,
+                    result.series_added,
+                    result.episodes_added
                 ),
                 library: library_id.to_string(),
             },
@@ -495,6 +1107,296 @@ impl ModelController {
         Ok(result)
     }
 
+    /// Import VOD and series entries as virtual medias whose source is the stream url.
+    /// Medias no longer in the playlist are removed, with the movies and series they leave empty
+    async fn import_m3u_vod(
+        &self,
+        library_id: &str,
+        vod_entries: Vec<M3uEntry>,
+        series_entries: Vec<M3uEntry>,
+        result: &mut M3uImportResult,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<()> {
+        let store = self.store.get_library_store(library_id)?;
+        let existing_sources: HashMap<String, String> = store
+            .get_all_media_id_sources()
+            .await?
+            .into_iter()
+            .filter(|(_, source)| M3uContentType::from_url(source) != M3uContentType::Live)
+            .map(|(id, source)| (source, id))
+            .collect();
+        let mut seen_sources: HashSet<String> = HashSet::new();
+        let mut media_actions: Vec<MediaWithAction> = Vec::new();
+
+        let total = vod_entries.len() + series_entries.len();
+        if total > 0 {
+            self.broadcast_sse(SseEvent::LibraryStatus(
+                crate::domain::library::LibraryStatusMessage {
+                    message: format!("Importing {} VOD and series entries...", total),
+                    library: library_id.to_string(),
+                },
+            ));
+        }
+
+        // Movies are matched by name and year
+        let mut movies: HashMap<(String, Option<u32>), String> = store
+            .get_movies(MovieQuery::new_empty())
+            .await?
+            .into_iter()
+            .map(|m| ((m.name.to_lowercase(), m.year), m.id))
+            .collect();
+        let mut movie_actions: Vec<MovieWithAction> = Vec::new();
+        for entry in vod_entries {
+            let is_new = seen_sources.insert(entry.url.clone());
+            if !is_new || existing_sources.contains_key(&entry.url) {
+                continue;
+            }
+            let (name, year) = entry.parse_name_and_year();
+            if name.is_empty() {
+                continue;
+            }
+            let key = (name.to_lowercase(), year);
+            let movie_id = match movies.get(&key) {
+                Some(id) => id.clone(),
+                None => {
+                    let id = nanoid!();
+                    store
+                        .add_movie(Movie {
+                            id: id.clone(),
+                            name,
+                            year,
+                            ..Default::default()
+                        })
+                        .await?;
+                    if let Some(movie) = store.get_movie(&id).await? {
+                        movie_actions.push(MovieWithAction {
+                            action: ElementAction::Added,
+                            movie,
+                        });
+                    }
+                    movies.insert(key, id.clone());
+                    result.movies_added += 1;
+                    id
+                }
+            };
+            let media = self
+                .add_m3u_media(&store, &entry, Some(movie_id), None)
+                .await?;
+            media_actions.push(MediaWithAction {
+                action: ElementAction::Added,
+                media,
+            });
+            result.medias_added += 1;
+        }
+
+        // Series are matched by name, episodes by season and number
+        let mut series: HashMap<String, String> = store
+            .get_series(SerieQuery::new_empty())
+            .await?
+            .into_iter()
+            .map(|s| (s.item.name.to_lowercase(), s.item.id))
+            .collect();
+        let mut episodes: HashSet<(String, u32, u32)> = HashSet::new();
+        let mut loaded_series: HashSet<String> = HashSet::new();
+        let mut serie_actions: Vec<SerieWithAction> = Vec::new();
+        let mut episode_actions: Vec<EpisodeWithAction> = Vec::new();
+        for entry in series_entries {
+            let is_new = seen_sources.insert(entry.url.clone());
+            if !is_new || existing_sources.contains_key(&entry.url) {
+                continue;
+            }
+            let Some((season, number)) = entry.parse_season_episode() else {
+                continue;
+            };
+            let name = entry.parse_series_name();
+            if name.is_empty() {
+                continue;
+            }
+            let serie_id = match series.get(&name.to_lowercase()) {
+                Some(id) => id.clone(),
+                None => {
+                    let id = nanoid!();
+                    store
+                        .add_serie(Serie {
+                            id: id.clone(),
+                            name: name.clone(),
+                            ..Default::default()
+                        })
+                        .await?;
+                    if let Some(serie) = store.get_serie(&id).await? {
+                        serie_actions.push(SerieWithAction {
+                            action: ElementAction::Added,
+                            serie: serie.item,
+                        });
+                    }
+                    series.insert(name.to_lowercase(), id.clone());
+                    result.series_added += 1;
+                    id
+                }
+            };
+            if loaded_series.insert(serie_id.clone()) {
+                let existing_episodes = store
+                    .get_episodes(EpisodeQuery {
+                        serie_ref: Some(serie_id.clone()),
+                        ..Default::default()
+                    })
+                    .await?;
+                episodes.extend(
+                    existing_episodes
+                        .into_iter()
+                        .map(|e| (e.serie, e.season, e.number)),
+                );
+            }
+            if episodes.insert((serie_id.clone(), season, number)) {
+                let episode = Episode {
+                    serie: serie_id.clone(),
+                    season,
+                    number,
+                    ..Default::default()
+                };
+                store.add_episode(episode.clone()).await?;
+                episode_actions.push(EpisodeWithAction {
+                    action: ElementAction::Added,
+                    episode,
+                });
+                result.episodes_added += 1;
+            }
+            let file_episode = FileEpisode {
+                id: serie_id,
+                season: Some(season),
+                episode: Some(number),
+                episode_to: None,
+            };
+            let media = self
+                .add_m3u_media(&store, &entry, None, Some(file_episode))
+                .await?;
+            media_actions.push(MediaWithAction {
+                action: ElementAction::Added,
+                media,
+            });
+            result.medias_added += 1;
+        }
+
+        // Remove medias no longer in the playlist
+        let mut emptied_movies: HashSet<String> = HashSet::new();
+        let mut emptied_series: HashSet<String> = HashSet::new();
+        for (source, id) in &existing_sources {
+            if seen_sources.contains(source) {
+                continue;
+            }
+            if let Some(media) = store.get_media(id, None).await? {
+                if let Some(relations) = &media.relations {
+                    emptied_movies.extend(relations.movies.iter().flatten().cloned());
+                    emptied_series.extend(relations.series.iter().flatten().map(|s| s.id.clone()));
+                }
+            }
+            // Also clears faces, images and caches, records the deletion and notifies clients
+            self.remove_media(library_id, id, requesting_user).await?;
+            result.medias_removed += 1;
+        }
+
+        if !movie_actions.is_empty() {
+            self.send_movie(MoviesMessage {
+                library: library_id.to_string(),
+                movies: movie_actions,
+            });
+        }
+        if !serie_actions.is_empty() {
+            self.send_serie(SeriesMessage {
+                library: library_id.to_string(),
+                series: serie_actions,
+            });
+        }
+        if !episode_actions.is_empty() {
+            self.send_episode(EpisodesMessage {
+                library: library_id.to_string(),
+                episodes: episode_actions,
+            });
+        }
+        if !media_actions.is_empty() {
+            self.send_media(MediasMessage {
+                library: library_id.to_string(),
+                medias: media_actions,
+            });
+        }
+
+        for movie_id in emptied_movies {
+            let remaining = store
+                .count_medias(
+                    MediaQuery {
+                        movie: Some(movie_id.clone()),
+                        ..Default::default()
+                    },
+                    LibraryLimits::default(),
+                )
+                .await?;
+            if remaining == 0 {
+                self.remove_movie(library_id, &movie_id, requesting_user)
+                    .await?;
+            }
+        }
+        for serie_id in emptied_series {
+            let remaining = store
+                .count_medias(
+                    MediaQuery {
+                        series: vec![serie_id.clone()],
+                        ..Default::default()
+                    },
+                    LibraryLimits::default(),
+                )
+                .await?;
+            if remaining == 0 {
+                self.remove_serie(library_id, &serie_id, false, requesting_user)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Virtual media streaming an IPTV VOD url
+    async fn add_m3u_media(
+        &self,
+        store: &SqliteLibraryStore,
+        entry: &M3uEntry,
+        movie: Option<String>,
+        episode: Option<FileEpisode>,
+    ) -> RsResult<ItemWithRelations<Media>> {
+        let path = entry.url.split('?').next().unwrap_or(&entry.url);
+        let mimetype = get_mime_from_filename(path)
+            .filter(|m| m.starts_with("video"))
+            .unwrap_or_else(|| "video/mp4".to_string());
+        let id = nanoid!();
+        store
+            .add_media(MediaForInsert {
+                id: id.clone(),
+                media: MediaForAdd {
+                    source: Some(entry.url.clone()),
+                    name: entry.variant_name(),
+                    kind: FileType::Video,
+                    mimetype,
+                    movie,
+                    ..Default::default()
+                },
+            })
+            .await?;
+        if let Some(episode) = episode {
+            store
+                .update_media(
+                    &id,
+                    MediaForUpdate {
+                        add_series: Some(vec![episode]),
+                        ..Default::default()
+                    },
+                    None,
+                )
+                .await?;
+        }
+        Ok(store
+            .get_media(&id, None)
+            .await?
+            .ok_or(crate::Error::MediaNotFound(id))?)
+    }
+
     // -- Channel tag management --
 
     pub async fn add_channel_tag(
@@ -738,6 +1640,20 @@ impl ModelController {
         Ok(())
     }
 
+    /// Take a slot only if one is free, without evicting sessions
+    pub async fn try_acquire_stream_slot(&self, library_id: &str, slot: &str) -> bool {
+        let Some(library) = self.cache_get_library(library_id).await else {
+            return false;
+        };
+        let max_streams = library.settings.max_streams.unwrap_or(1) as usize;
+        let mut streams = self.active_streams.write().await;
+        let channels = streams.entry(library_id.to_string()).or_default();
+        if channels.len() >= max_streams {
+            return false;
+        }
+        channels.insert(slot.to_string())
+    }
+
     pub async fn release_stream_slot(&self, library_id: &str, channel_id: &str) {
         let mut streams = self.active_streams.write().await;
         if let Some(channels) = streams.get_mut(library_id) {
@@ -764,9 +1680,14 @@ impl ModelController {
         // Note: we intentionally do NOT touch() here — only segment requests
         // should keep the session alive. Otherwise, a client polling the playlist
         // while FFmpeg is stalled (reconnecting) would prevent cleanup forever.
+        // Timeshift sessions are the exception: a paused player only polls the playlist,
+        // and the stall detector still ends sessions whose FFmpeg stopped producing.
         {
             let sessions = self.hls_sessions.read().await;
             if let Some(session) = sessions.get(&key) {
+                if session.timeshift_minutes > 0 {
+                    session.touch();
+                }
                 return Ok((session.output_dir.clone(), session.playlist_path.clone()));
             }
         }
@@ -774,17 +1695,26 @@ impl ModelController {
         // Acquire stream slot (enforces max_streams)
         self.acquire_stream_slot(library_id, channel_id).await?;
 
-        // Resolve the stream URL
-        let stream_url = self
-            .get_channel_stream_url(library_id, channel_id, quality, requesting_user)
-            .await?;
+        // Resolve the stream URLs, later ones are failovers
+        let stream_urls = match self
+            .get_channel_stream_urls(library_id, channel_id, quality, requesting_user)
+            .await
+        {
+            Ok(urls) => urls,
+            Err(e) => {
+                self.release_stream_slot(library_id, channel_id).await;
+                return Err(e);
+            }
+        };
 
         // Create the session
         match crate::tools::hls_session::create_session(
             key.clone(),
             library_id.to_string(),
             channel_id.to_string(),
-            stream_url,
+            stream_urls,
+            self.get_timeshift_minutes(library_id).await,
+            requesting_user.user_id().ok(),
             self.hls_sessions.clone(),
             self.clone(),
         )
@@ -799,6 +1729,15 @@ impl ModelController {
         }
     }
 
+    /// Minutes of live TV buffered by the HLS sessions of a library
+    pub async fn get_timeshift_minutes(&self, library_id: &str) -> u32 {
+        self.cache_get_library(library_id)
+            .await
+            .and_then(|l| l.settings.timeshift_minutes)
+            .unwrap_or(0)
+            .min(crate::tools::hls_session::MAX_TIMESHIFT_MINUTES)
+    }
+
     pub async fn stop_hls_session(&self, library_id: &str, channel_id: &str) -> RsResult<()> {
         let keys_to_stop: Vec<String> = {
             let sessions = self.hls_sessions.read().await;
@@ -820,3 +1759,32 @@ impl ModelController {
         Ok(())
     }
 }
+
+/// User whose favorites and order apply: the owner of a channels token or the connected user
+fn channel_settings_user(
+    limits: &LibraryLimits,
+    requesting_user: &ConnectedUser,
+) -> Option<String> {
+    limits
+        .user_id
+        .clone()
+        .or_else(|| requesting_user.user_id().ok())
+}
+
+/// Set the favorite, position and number of the user on the channels, sorted by position
+fn apply_channel_user_settings(channels: &mut [Channel], settings: Vec<ChannelUserSettings>) {
+    let settings: HashMap<String, ChannelUserSettings> = settings
+        .into_iter()
+        .map(|s| (s.channel_ref.clone(), s))
+        .collect();
+    for channel in channels.iter_mut() {
+        if let Some(setting) = settings.get(&channel.id) {
+            channel.favorite = Some(setting.favorite);
+            channel.position = setting.position;
+            if setting.number.is_some() {
+                channel.channel_number = setting.number;
+            }
+        }
+    }
+    channels.sort_by_key(|c| (c.position.is_none(), c.position));
+}
@@ -0,0 +1,693 @@
+use std::collections::HashMap;
+
+use chrono::{Local, TimeZone};
+use nanoid::nanoid;
+use serde::Deserialize;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    domain::{
+        dvr::{
+            DvrRecording, DvrRecordingMessage, DvrRecordingStatus, DvrRecordingWithAction,
+            DvrSchedule, DvrScheduleForAdd, DvrScheduleForUpdate, DvrScheduleKind,
+        },
+        library::{LibraryRole, LibraryType},
+        media::{FileType, Media, MediaForUpdate},
+        ElementAction,
+    },
+    error::{RsError, RsResult},
+    routes::sse::SseEvent,
+    server::get_server_folder_path_array,
+    tools::{
+        clock::now,
+        dvr::{find_conflicts, record_stream, schedule_occurrences},
+        log::{log_error, log_info, LogServiceType},
+    },
+};
+
+use super::{users::ConnectedUser, ModelController};
+
+/// Recordings are planned one day ahead
+const DVR_PLANNING_WINDOW: i64 = 24 * 60 * 60 * 1000;
+const DVR_MIMETYPE: &str = "video/x-matroska";
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DvrRecordingQuery {
+    /// Comma separated statuses
+    pub status: Option<String>,
+}
+
+fn dvr_slot(recording_id: &str) -> String {
+    format!("dvr:{}", recording_id)
+}
+
+fn recording_filename(recording: &DvrRecording) -> String {
+    let date = Local
+        .timestamp_millis_opt(recording.start)
+        .earliest()
+        .map(|d| d.format("%Y-%m-%d %Hh%M").to_string())
+        .unwrap_or_default();
+    let name: String = recording
+        .name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect();
+    format!("{} - {}.mkv", name.trim(), date)
+}
+
+impl ModelController {
+    pub fn send_dvr_recordings(&self, message: DvrRecordingMessage) {
+        self.broadcast_sse(SseEvent::DvrRecordings(message));
+    }
+
+    fn send_dvr_recording_actions(
+        &self,
+        library_id: &str,
+        recordings: Vec<DvrRecording>,
+        action: ElementAction,
+    ) {
+        if recordings.is_empty() {
+            return;
+        }
+        self.send_dvr_recordings(DvrRecordingMessage {
+            library: library_id.to_string(),
+            recordings: recordings
+                .into_iter()
+                .map(|recording| DvrRecordingWithAction {
+                    action: action.clone(),
+                    recording,
+                })
+                .collect(),
+        });
+    }
+
+    async fn check_dvr_library(&self, library_id: &str) -> RsResult<()> {
+        let library = self
+            .cache_get_library(library_id)
+            .await
+            .ok_or(RsError::NotFound(format!(
+                "Library {} not found",
+                library_id
+            )))?;
+        if library.kind != LibraryType::Iptv {
+            return Err(RsError::Error(
+                "DVR is only available for IPTV libraries".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub async fn get_dvr_schedules(
+        &self,
+        library_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<DvrSchedule>> {
+        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        let store = self.store.get_library_store(library_id)?;
+        Ok(store.get_dvr_schedules().await?)
+    }
+
+    pub async fn add_dvr_schedule(
+        &self,
+        library_id: &str,
+        schedule: DvrScheduleForAdd,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<DvrSchedule> {
+        requesting_user.check_library_role(library_id, LibraryRole::Write)?;
+        requesting_user.check_library_role(&schedule.target_library, LibraryRole::Write)?;
+        self.check_dvr_library(library_id).await?;
+        let target = self
+            .cache_get_library(&schedule.target_library)
+            .await
+            .ok_or(RsError::NotFound(format!(
+                "Library {} not found",
+                schedule.target_library
+            )))?;
+        if target.kind == LibraryType::Iptv {
+            return Err(RsError::Error(
+                "Recordings can't be stored in an IPTV library".to_string(),
+            ));
+        }
+        let store = self.store.get_library_store(library_id)?;
+        let channel = store
+            .get_channel(&schedule.channel)
+            .await?
+            .ok_or(RsError::NotFound(format!(
+                "Channel {} not found",
+                schedule.channel
+            )))?;
+
+        match schedule.kind {
+            DvrScheduleKind::Once => match (schedule.start, schedule.end) {
+                (Some(start), Some(end)) if end > start => {}
+                _ => {
+                    return Err(RsError::Error(
+                        "One-off recordings need a start before their end".to_string(),
+                    ))
+                }
+            },
+            DvrScheduleKind::Weekly => {
+                let days_valid = schedule
+                    .days
+                    .as_ref()
+                    .map(|days| !days.is_empty() && days.iter().all(|d| *d < 7))
+                    .unwrap_or(false);
+                let time_valid = schedule.time.map(|t| t < 24 * 60).unwrap_or(false);
+                let duration_valid = schedule.duration.map(|d| d > 0).unwrap_or(false);
+                if !days_valid || !time_valid || !duration_valid {
+                    return Err(RsError::Error(
+                        "Weekly recordings need days (0-6), a time and a duration".to_string(),
+                    ));
+                }
+            }
+            DvrScheduleKind::Programme => {
+                if schedule
+                    .title
+                    .as_ref()
+                    .map(|t| t.trim().is_empty())
+                    .unwrap_or(true)
+                {
+                    return Err(RsError::Error(
+                        "Programme recordings need a title".to_string(),
+                    ));
+                }
+                if channel.tvg_id.is_none() {
+                    return Err(RsError::Error(format!(
+                        "Channel {} has no EPG id",
+                        channel.name
+                    )));
+                }
+            }
+        }
+
+        let schedule = DvrSchedule {
+            id: nanoid!(),
+            channel: schedule.channel,
+            kind: schedule.kind,
+            target_library: schedule.target_library,
+            name: schedule.name,
+            description: schedule.description,
+            start: schedule.start,
+            end: schedule.end,
+            days: schedule.days,
+            time: schedule.time,
+            duration: schedule.duration,
+            title: schedule.title,
+            padding_before: schedule.padding_before,
+            padding_after: schedule.padding_after,
+            enabled: true,
+            ..Default::default()
+        };
+        store.add_dvr_schedule(schedule.clone()).await?;
+        self.plan_dvr_recordings(library_id).await?;
+        Ok(store
+            .get_dvr_schedule(&schedule.id)
+            .await?
+            .unwrap_or(schedule))
+    }
+
+    pub async fn update_dvr_schedule(
+        &self,
+        library_id: &str,
+        schedule_id: &str,
+        update: DvrScheduleForUpdate,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<DvrSchedule> {
+        requesting_user.check_library_role(library_id, LibraryRole::Write)?;
+        let store = self.store.get_library_store(library_id)?;
+        store.update_dvr_schedule(schedule_id, update).await?;
+        // Pending recordings are planned again with the new settings
+        let removed = store.remove_pending_dvr_recordings(schedule_id).await?;
+        self.send_dvr_recording_actions(library_id, removed, ElementAction::Deleted);
+        self.plan_dvr_recordings(library_id).await?;
+        store
+            .get_dvr_schedule(schedule_id)
+            .await?
+            .ok_or(RsError::NotFound(format!(
+                "DVR schedule {} not found",
+                schedule_id
+            )))
+    }
+
+    /// Remove a schedule and its pending recordings. Running and past recordings are kept
+    pub async fn remove_dvr_schedule(
+        &self,
+        library_id: &str,
+        schedule_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<DvrSchedule> {
+        requesting_user.check_library_role(library_id, LibraryRole::Write)?;
+        let store = self.store.get_library_store(library_id)?;
+        let schedule = store
+            .get_dvr_schedule(schedule_id)
+            .await?
+            .ok_or(RsError::NotFound(format!(
+                "DVR schedule {} not found",
+                schedule_id
+            )))?;
+        let removed = store.remove_pending_dvr_recordings(schedule_id).await?;
+        store.remove_dvr_schedule(schedule_id).await?;
+        self.send_dvr_recording_actions(library_id, removed, ElementAction::Deleted);
+        Ok(schedule)
+    }
+
+    /// Forget the schedules and pending recordings of a channel being removed.
+    /// Running and past recordings are kept
+    pub(crate) async fn remove_channel_dvr(
+        &self,
+        library_id: &str,
+        channel_id: &str,
+    ) -> RsResult<()> {
+        let store = self.store.get_library_store(library_id)?;
+        let removed = store.remove_channel_dvr(channel_id).await?;
+        self.send_dvr_recording_actions(library_id, removed, ElementAction::Deleted);
+        Ok(())
+    }
+
+    pub async fn get_dvr_recordings(
+        &self,
+        library_id: &str,
+        query: DvrRecordingQuery,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<DvrRecording>> {
+        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        let statuses = query
+            .status
+            .map(|s| {
+                s.split(',')
+                    .map(|status| status.trim().parse::<DvrRecordingStatus>())
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()
+            .map_err(|_| RsError::Error("Invalid recording status".to_string()))?;
+        let store = self.store.get_library_store(library_id)?;
+        Ok(store.get_dvr_recordings(statuses).await?)
+    }
+
+    /// Stop a running recording, keeping what was captured as a completed recording, cancel a
+    /// pending one or delete a finished one from the list
+    pub async fn remove_dvr_recording(
+        &self,
+        library_id: &str,
+        recording_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<DvrRecording> {
+        requesting_user.check_library_role(library_id, LibraryRole::Write)?;
+        let store = self.store.get_library_store(library_id)?;
+        let mut recording =
+            store
+                .get_dvr_recording(recording_id)
+                .await?
+                .ok_or(RsError::NotFound(format!(
+                    "DVR recording {} not found",
+                    recording_id
+                )))?;
+        if recording.status == DvrRecordingStatus::Recording {
+            if let Some(token) = self.dvr_recordings.read().await.get(recording_id) {
+                token.cancel();
+            }
+        } else if recording.status.is_pending() {
+            // Kept as cancelled so the planner does not schedule it again
+            store
+                .update_dvr_recording_status(
+                    recording_id,
+                    DvrRecordingStatus::Cancelled,
+                    None,
+                    None,
+                )
+                .await?;
+            recording.status = DvrRecordingStatus::Cancelled;
+            self.send_dvr_recording_actions(
+                library_id,
+                vec![recording.clone()],
+                ElementAction::Updated,
+            );
+        } else {
+            store.remove_dvr_recording(recording_id).await?;
+            self.send_dvr_recording_actions(
+                library_id,
+                vec![recording.clone()],
+                ElementAction::Deleted,
+            );
+        }
+        Ok(recording)
+    }
+
+    async fn set_dvr_recording_status(
+        &self,
+        library_id: &str,
+        recording: &mut DvrRecording,
+        status: DvrRecordingStatus,
+        media: Option<String>,
+        error: Option<String>,
+    ) -> RsResult<()> {
+        let store = self.store.get_library_store(library_id)?;
+        store
+            .update_dvr_recording_status(
+                &recording.id,
+                status.clone(),
+                media.clone(),
+                error.clone(),
+            )
+            .await?;
+        recording.status = status;
+        recording.media = media.or(recording.media.take());
+        recording.error = error;
+        self.send_dvr_recording_actions(
+            library_id,
+            vec![recording.clone()],
+            ElementAction::Updated,
+        );
+        Ok(())
+    }
+
+    /// Create recordings of enabled schedules for the planning window and flag the ones
+    /// exceeding the library `max_streams`
+    async fn plan_dvr_recordings(&self, library_id: &str) -> RsResult<()> {
+        let library = self
+            .cache_get_library(library_id)
+            .await
+            .ok_or(RsError::NotFound(format!(
+                "Library {} not found",
+                library_id
+            )))?;
+        let store = self.store.get_library_store(library_id)?;
+        let schedules: Vec<DvrSchedule> = store
+            .get_dvr_schedules()
+            .await?
+            .into_iter()
+            .filter(|s| s.enabled)
+            .collect();
+        let now = now().timestamp_millis();
+        let to = now + DVR_PLANNING_WINDOW;
+
+        let channels: HashMap<String, (String, Option<String>)> = store
+            .get_channels(None, None)
+            .await?
+            .into_iter()
+            .map(|c| (c.id, (c.name, c.tvg_id)))
+            .collect();
+        let mut programmes = HashMap::new();
+        if schedules
+            .iter()
+            .any(|s| s.kind == DvrScheduleKind::Programme)
+        {
+            for programme in store.get_epg_programmes(None, now, to).await? {
+                programmes
+                    .entry(programme.channel.clone())
+                    .or_insert_with(Vec::new)
+                    .push(programme);
+            }
+        }
+
+        let mut added = vec![];
+        for schedule in schedules {
+            let Some((channel_name, tvg_id)) = channels.get(&schedule.channel) else {
+                continue;
+            };
+            let channel_programmes = tvg_id
+                .as_ref()
+                .and_then(|tvg_id| programmes.get(tvg_id))
+                .map(|p| p.as_slice())
+                .unwrap_or_default();
+            for occurrence in
+                schedule_occurrences(&schedule, &Local, channel_programmes, channel_name, now, to)
+            {
+                let recording = DvrRecording {
+                    id: nanoid!(),
+                    schedule: Some(schedule.id.clone()),
+                    channel: schedule.channel.clone(),
+                    target_library: schedule.target_library.clone(),
+                    name: occurrence.name,
+                    description: occurrence.description,
+                    start: occurrence.start,
+                    end: occurrence.end,
+                    padding_before: schedule.padding_before,
+                    padding_after: schedule.padding_after,
+                    status: DvrRecordingStatus::Scheduled,
+                    ..Default::default()
+                };
+                if store.add_dvr_recording(recording.clone()).await? {
+                    added.push(recording);
+                }
+            }
+        }
+        self.send_dvr_recording_actions(library_id, added, ElementAction::Added);
+
+        let active = store
+            .get_dvr_recordings(Some(vec![
+                DvrRecordingStatus::Scheduled,
+                DvrRecordingStatus::Conflict,
+                DvrRecordingStatus::Recording,
+            ]))
+            .await?;
+        let max_streams = library.settings.max_streams.unwrap_or(1) as usize;
+        let conflicts = find_conflicts(&active, max_streams);
+        // Due recordings keep the status set when they tried to get a slot
+        for mut recording in active
+            .into_iter()
+            .filter(|r| r.status.is_pending() && r.padded_start() > now)
+        {
+            let status = if conflicts.contains(&recording.id) {
+                DvrRecordingStatus::Conflict
+            } else {
+                DvrRecordingStatus::Scheduled
+            };
+            if status != recording.status {
+                let error = (status == DvrRecordingStatus::Conflict).then(|| {
+                    format!(
+                        "All {} stream slots are used by other recordings",
+                        max_streams
+                    )
+                });
+                self.set_dvr_recording_status(library_id, &mut recording, status, None, error)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Plan recordings of an IPTV library, start the due ones and fail the missed ones
+    pub async fn dvr_tick(&self, library_id: &str) -> RsResult<()> {
+        self.check_dvr_library(library_id).await?;
+        self.plan_dvr_recordings(library_id).await?;
+        let store = self.store.get_library_store(library_id)?;
+        let now = now().timestamp_millis();
+        let active = store
+            .get_dvr_recordings(Some(vec![
+                DvrRecordingStatus::Scheduled,
+                DvrRecordingStatus::Conflict,
+                DvrRecordingStatus::Recording,
+            ]))
+            .await?;
+        for mut recording in active {
+            if recording.status == DvrRecordingStatus::Recording {
+                if !self.dvr_recordings.read().await.contains_key(&recording.id) {
+                    self.set_dvr_recording_status(
+                        library_id,
+                        &mut recording,
+                        DvrRecordingStatus::Failed,
+                        None,
+                        Some("Recording interrupted by a server restart".to_string()),
+                    )
+                    .await?;
+                }
+            } else if recording.padded_end() <= now {
+                let error = recording
+                    .error
+                    .clone()
+                    .unwrap_or_else(|| "Recording window missed".to_string());
+                self.set_dvr_recording_status(
+                    library_id,
+                    &mut recording,
+                    DvrRecordingStatus::Failed,
+                    None,
+                    Some(error),
+                )
+                .await?;
+            } else if recording.padded_start() <= now {
+                self.start_dvr_recording(library_id, recording).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn start_dvr_recording(
+        &self,
+        library_id: &str,
+        mut recording: DvrRecording,
+    ) -> RsResult<()> {
+        if let Err(error) = self
+            .acquire_stream_slot(library_id, &dvr_slot(&recording.id))
+            .await
+        {
+            // Retried on next tick until the window is over
+            if recording.status != DvrRecordingStatus::Conflict {
+                self.set_dvr_recording_status(
+                    library_id,
+                    &mut recording,
+                    DvrRecordingStatus::Conflict,
+                    None,
+                    Some(error.to_string()),
+                )
+                .await?;
+            }
+            return Ok(());
+        }
+        let url = match self
+            .get_channel_stream_url(
+                library_id,
+                &recording.channel,
+                None,
+                &ConnectedUser::ServerAdmin,
+            )
+            .await
+        {
+            Ok(url) => url,
+            Err(error) => {
+                self.release_stream_slot(library_id, &dvr_slot(&recording.id))
+                    .await;
+                return self
+                    .set_dvr_recording_status(
+                        library_id,
+                        &mut recording,
+                        DvrRecordingStatus::Failed,
+                        None,
+                        Some(error.to_string()),
+                    )
+                    .await;
+            }
+        };
+
+        let token = CancellationToken::new();
+        self.dvr_recordings
+            .write()
+            .await
+            .insert(recording.id.clone(), token.clone());
+        self.set_dvr_recording_status(
+            library_id,
+            &mut recording,
+            DvrRecordingStatus::Recording,
+            None,
+            None,
+        )
+        .await?;
+        log_info(
+            LogServiceType::Source,
+            format!(
+                "Starting DVR recording {} ({})",
+                recording.name, recording.id
+            ),
+        );
+
+        let mc = self.clone();
+        let library_id = library_id.to_string();
+        tokio::spawn(async move {
+            let result = mc.record_dvr_media(&recording, &url, token.clone()).await;
+            let (status, media, error) = match result {
+                Ok((media, error)) => (DvrRecordingStatus::Completed, Some(media.id), error),
+                Err(_) if token.is_cancelled() => (DvrRecordingStatus::Cancelled, None, None),
+                Err(error) => {
+                    log_error(
+                        LogServiceType::Source,
+                        format!("DVR recording {} failed: {:?}", recording.id, error),
+                    );
+                    (DvrRecordingStatus::Failed, None, Some(error.to_string()))
+                }
+            };
+            let mut recording = recording;
+            if let Err(error) = mc
+                .set_dvr_recording_status(&library_id, &mut recording, status, media, error)
+                .await
+            {
+                log_error(
+                    LogServiceType::Source,
+                    format!(
+                        "Unable to update DVR recording {}: {:?}",
+                        recording.id, error
+                    ),
+                );
+            }
+            mc.dvr_recordings.write().await.remove(&recording.id);
+            mc.release_stream_slot(&library_id, &dvr_slot(&recording.id))
+                .await;
+        });
+        Ok(())
+    }
+
+    /// Record until the padded end or until stopped and import the file in the target library.
+    /// Interrupted streams still import what was captured, with the error returned alongside
+    async fn record_dvr_media(
+        &self,
+        recording: &DvrRecording,
+        url: &str,
+        token: CancellationToken,
+    ) -> RsResult<(Media, Option<String>)> {
+        let folder = get_server_folder_path_array(vec![".cache", ".dvr"]).await?;
+        let path = folder.join(format!("{}.mkv", recording.id));
+        let duration = ((recording.padded_end() - now().timestamp_millis()) / 1000).max(1) as u64;
+        let recorded = record_stream(url, &path, duration, token).await;
+
+        let size = tokio::fs::metadata(&path)
+            .await
+            .map(|m| m.len())
+            .unwrap_or(0);
+        let error = match recorded {
+            Ok(()) if size == 0 => {
+                let _ = tokio::fs::remove_file(&path).await;
+                return Err(RsError::Error("Nothing was recorded".to_string()));
+            }
+            Ok(()) => None,
+            Err(error) if size == 0 => {
+                let _ = tokio::fs::remove_file(&path).await;
+                return Err(error);
+            }
+            Err(error) => Some(error.to_string()),
+        };
+
+        let file = tokio::fs::File::open(&path).await?;
+        let media = self
+            .add_library_file(
+                &recording.target_library,
+                &recording_filename(recording),
+                Some(MediaForUpdate {
+                    name: Some(recording_filename(recording)),
+                    description: recording.description.clone(),
+                    mimetype: Some(DVR_MIMETYPE.to_string()),
+                    kind: Some(FileType::Video),
+                    size: Some(size),
+                    ..Default::default()
+                }),
+                file,
+                &ConnectedUser::ServerAdmin,
+            )
+            .await;
+        let _ = tokio::fs::remove_file(&path).await;
+        Ok((media?, error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recording_filenames_are_safe() {
+        let recording = DvrRecording {
+            name: "News: 20/20 ".to_string(),
+            start: Local
+                .with_ymd_and_hms(2024, 1, 1, 20, 30, 0)
+                .unwrap()
+                .timestamp_millis(),
+            ..Default::default()
+        };
+        assert_eq!(
+            recording_filename(&recording),
+            "News_ 20_20 - 2024-01-01 20h30.mkv"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+
+use crate::{
+    domain::{
+        epg::{EpgChannelGuide, EpgNowNext, EpgProgramme},
+        library::{LibraryRole, LibraryStatusMessage, LibraryType},
+    },
+    error::{RsError, RsResult},
+    tools::{
+        clock::now,
+        log::{log_info, LogServiceType},
+        xmltv_parser::{decode_xmltv, parse_xmltv},
+    },
+};
+
+use super::{users::ConnectedUser, ModelController};
+
+/// Ended programmes are kept one day so the guide can still show what just aired
+const EPG_RETENTION: i64 = 24 * 60 * 60 * 1000;
+/// Window searched for the next programme of each channel
+const EPG_NEXT_WINDOW: i64 = 12 * 60 * 60 * 1000;
+const EPG_GRID_DEFAULT: i64 = 6 * 60 * 60 * 1000;
+const EPG_GRID_MAX: i64 = 48 * 60 * 60 * 1000;
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EpgNowQuery {
+    /// Comma separated channel ids
+    pub channels: Option<String>,
+    #[serde(alias = "groupTag")]
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EpgGridQuery {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// Comma separated channel ids
+    pub channels: Option<String>,
+    #[serde(alias = "groupTag")]
+    pub tag: Option<String>,
+}
+
+/// Url without its query string, safe to log
+fn redact_url(url: &str) -> &str {
+    url.split_once('?').map(|(base, _)| base).unwrap_or(url)
+}
+
+impl ModelController {
+    /// Download the XMLTV guide of an IPTV library and store programmes of its channels.
+    /// `fallback_url` is used when the library has no `epg_url` (e.g. the M3U `url-tvg`)
+    pub async fn import_epg(
+        &self,
+        library_id: &str,
+        fallback_url: Option<String>,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<usize> {
+        requesting_user.check_library_role(library_id, LibraryRole::Admin)?;
+        let library = self
+            .get_internal_library(library_id)
+            .await?
+            .ok_or(RsError::NotFound(format!(
+                "Library {} not found",
+                library_id
+            )))?;
+        if library.kind != LibraryType::Iptv {
+            return Err(RsError::Error(
+                "EPG import is only available for IPTV libraries".to_string(),
+            ));
+        }
+        let url = library
+            .settings
+            .epg_url
+            .filter(|u| !u.is_empty())
+            .or(fallback_url.filter(|u| !u.is_empty()))
+            .ok_or(RsError::Error(format!(
+                "No EPG url configured for library {}",
+                library.name
+            )))?;
+
+        self.send_library_status(LibraryStatusMessage {
+            message: "Fetching EPG...".to_string(),
+            library: library_id.to_string(),
+        });
+        log_info(
+            LogServiceType::Source,
+            format!("Fetching EPG from: {}", redact_url(&url)),
+        );
+        // Guide urls can carry credentials (Xtream `xmltv.php?username=..&password=..`)
+        let bytes = reqwest::get(&url)
+            .await
+            .map_err(|e| RsError::Error(format!("Failed to fetch EPG: {}", e.without_url())))?
+            .bytes()
+            .await
+            .map_err(|e| {
+                RsError::Error(format!("Failed to read EPG content: {}", e.without_url()))
+            })?;
+
+        let programmes = tokio::task::spawn_blocking(move || -> RsResult<Vec<EpgProgramme>> {
+            Ok(parse_xmltv(&decode_xmltv(&bytes)?))
+        })
+        .await
+        .map_err(|e| RsError::Error(format!("EPG parsing task failed: {:?}", e)))??;
+        let total_parsed = programmes.len();
+
+        let store = self.store.get_library_store(library_id)?;
+        let tvg_ids: HashSet<String> = store
+            .get_channels(None, None)
+            .await?
+            .into_iter()
+            .filter_map(|c| c.tvg_id)
+            .collect();
+        let programmes: Vec<EpgProgramme> = programmes
+            .into_iter()
+            .filter(|p| tvg_ids.contains(&p.channel))
+            .collect();
+        let imported = programmes.len();
+        store
+            .replace_epg_programmes(programmes, now().timestamp_millis() - EPG_RETENTION)
+            .await?;
+
+        log_info(
+            LogServiceType::Source,
+            format!(
+                "Imported {} EPG programmes ({} parsed) for library {}",
+                imported, total_parsed, library.name
+            ),
+        );
+        self.send_library_status(LibraryStatusMessage {
+            message: format!("EPG updated: {} programmes", imported),
+            library: library_id.to_string(),
+        });
+        Ok(imported)
+    }
+
+    /// Channel ids with their tvg id, restricted to the requested channels and tag
+    async fn epg_channels(
+        &self,
+        library_id: &str,
+        channels: Option<String>,
+        tag: Option<String>,
+    ) -> RsResult<Vec<(String, String)>> {
+        let store = self.store.get_library_store(library_id)?;
+        let requested: Option<Vec<String>> = channels.map(|c| {
+            c.split(',')
+                .map(|id| id.trim().to_string())
+                .filter(|id| !id.is_empty())
+                .collect()
+        });
+        Ok(store
+            .get_channels(tag, None)
+            .await?
+            .into_iter()
+            .filter(|c| {
+                requested
+                    .as_ref()
+                    .map(|ids| ids.contains(&c.id))
+                    .unwrap_or(true)
+            })
+            .filter_map(|c| c.tvg_id.map(|tvg_id| (c.id, tvg_id)))
+            .collect())
+    }
+
+    /// Programmes of the given channels grouped by tvg id
+    async fn epg_programmes_by_channel(
+        &self,
+        library_id: &str,
+        channels: &[(String, String)],
+        from: i64,
+        to: i64,
+    ) -> RsResult<HashMap<String, Vec<EpgProgramme>>> {
+        let store = self.store.get_library_store(library_id)?;
+        let tvg_ids = channels.iter().map(|(_, tvg_id)| tvg_id.clone()).collect();
+        let mut grouped: HashMap<String, Vec<EpgProgramme>> = HashMap::new();
+        for programme in store.get_epg_programmes(Some(tvg_ids), from, to).await? {
+            grouped
+                .entry(programme.channel.clone())
+                .or_default()
+                .push(programme);
+        }
+        Ok(grouped)
+    }
+
+    /// Current and next programme of each channel
+    pub async fn get_epg_now_next(
+        &self,
+        library_id: &str,
+        query: EpgNowQuery,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<EpgNowNext>> {
+        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        let channels = self
+            .epg_channels(library_id, query.channels, query.tag)
+            .await?;
+        let now = now().timestamp_millis();
+        let mut grouped = self
+            .epg_programmes_by_channel(library_id, &channels, now, now + EPG_NEXT_WINDOW)
+            .await?;
+
+        Ok(channels
+            .into_iter()
+            .map(|(channel, tvg_id)| {
+                let mut current = None;
+                let mut next = None;
+                for programme in grouped.remove(&tvg_id).unwrap_or_default() {
+                    if programme.start <= now {
+                        current = Some(programme);
+                    } else {
+                        next = Some(programme);
+                        break;
+                    }
+                }
+                EpgNowNext {
+                    channel,
+                    now: current,
+                    next,
+                }
+            })
+            .collect())
+    }
+
+    /// Start of the programme airing now on a channel, to restart it from the timeshift buffer
+    pub async fn get_epg_current_start(
+        &self,
+        library_id: &str,
+        channel_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Option<i64>> {
+        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        let store = self.store.get_library_store(library_id)?;
+        let Some(tvg_id) = store.get_channel(channel_id).await?.and_then(|c| c.tvg_id) else {
+            return Ok(None);
+        };
+        let now = now().timestamp_millis();
+        Ok(store
+            .get_epg_programmes(Some(vec![tvg_id]), now, now + 1)
+            .await?
+            .into_iter()
+            .find(|p| p.start <= now)
+            .map(|p| p.start))
+    }
+
+    /// Programmes of each channel overlapping a time window (default: next 6 hours)
+    pub async fn get_epg_grid(
+        &self,
+        library_id: &str,
+        query: EpgGridQuery,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<EpgChannelGuide>> {
+        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        let from = query.from.unwrap_or_else(|| now().timestamp_millis());
+        let to = query.to.unwrap_or(from + EPG_GRID_DEFAULT);
+        if to <= from {
+            return Err(RsError::Error(format!(
+                "Invalid EPG window {} - {}",
+                from, to
+            )));
+        }
+        let to = to.min(from + EPG_GRID_MAX);
+
+        let channels = self
+            .epg_channels(library_id, query.channels, query.tag)
+            .await?;
+        let mut grouped = self
+            .epg_programmes_by_channel(library_id, &channels, from, to)
+            .await?;
+        Ok(channels
+            .into_iter()
+            .map(|(channel, tvg_id)| EpgChannelGuide {
+                channel,
+                programmes: grouped.remove(&tvg_id).unwrap_or_default(),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    domain::{library::LibraryRole, people::SimilarFace},
+    error::{RsError, RsResult},
+    server::get_server_file_path_array,
+    tools::{
+        face_index::LibraryFaceIndex,
+        log::{log_error, log_info, LogServiceType},
+        recognition::current_face_model,
+    },
+};
+
+use super::{users::ConnectedUser, ModelController};
+
+pub type SharedFaceIndex = Arc<RwLock<LibraryFaceIndex>>;
+
+/// Number of similar faces returned without explicit limit
+pub const DEFAULT_SIMILAR_FACES_LIMIT: usize = 50;
+
+async fn face_index_path(library_id: &str) -> RsResult<PathBuf> {
+    Ok(get_server_file_path_array(vec!["dbs", &format!("faces-{}.idx", library_id)]).await?)
+}
+
+impl ModelController {
+    /// Face index of a library, loaded from disk or built from the database. Faces changed
+    /// without incremental update (deleted medias or people) are reconciled on load and
+    /// when `sync` is requested
+    pub async fn get_face_index(&self, library_id: &str, sync: bool) -> RsResult<SharedFaceIndex> {
+        let cached = self.face_indexes.read().await.get(library_id).cloned();
+        let index = match cached {
+            Some(index) if !sync => return Ok(index),
+            Some(index) => index,
+            None => {
+                let path = face_index_path(library_id).await?;
+                let loaded = tokio::task::spawn_blocking(move || {
+                    if !path.exists() {
+                        return LibraryFaceIndex::default();
+                    }
+                    match File::open(&path)
+                        .and_then(|file| LibraryFaceIndex::read_from(&mut BufReader::new(file)))
+                    {
+                        Ok(index) => index,
+                        Err(error) => {
+                            log_error(
+                                LogServiceType::Other,
+                                format!("Rebuilding invalid face index {:?}: {}", path, error),
+                            );
+                            LibraryFaceIndex::default()
+                        }
+                    }
+                })
+                .await?;
+                self.face_indexes
+                    .write()
+                    .await
+                    .entry(library_id.to_string())
+                    .or_insert_with(|| Arc::new(RwLock::new(loaded)))
+                    .clone()
+            }
+        };
+        self.sync_face_index(library_id, &index).await?;
+        Ok(index)
+    }
+
+    async fn sync_face_index(&self, library_id: &str, index: &SharedFaceIndex) -> RsResult<()> {
+        let store = self.store.get_library_store(library_id)?;
+        let (people, unassigned) = store.get_face_ids(current_face_model()).await?;
+        let shared = index.clone();
+        let missing =
+            tokio::task::spawn_blocking(move || shared.write().unwrap().sync(&people, &unassigned))
+                .await?;
+
+        for chunk in missing.chunks(5000) {
+            let faces = store.get_face_embeddings_by_ids(chunk.to_vec()).await?;
+            let shared = index.clone();
+            tokio::task::spawn_blocking(move || {
+                let mut index = shared.write().unwrap();
+                for (face_id, person_id, embedding) in faces {
+                    match person_id {
+                        Some(person_id) => index.add_person_face(&face_id, &person_id, embedding),
+                        None => index.add_unassigned_face(&face_id, embedding),
+                    }
+                }
+            })
+            .await?;
+        }
+        if !missing.is_empty() {
+            log_info(
+                LogServiceType::Other,
+                format!("Indexed {} faces of library {}", missing.len(), library_id),
+            );
+        }
+        Ok(())
+    }
+
+    /// Apply a change to the face index of a library if it is loaded. Indexes not loaded
+    /// are reconciled with the database when they are
+    pub async fn update_face_index<F>(&self, library_id: &str, update: F) -> RsResult<()>
+    where
+        F: FnOnce(&mut LibraryFaceIndex) + Send + 'static,
+    {
+        let cached = self.face_indexes.read().await.get(library_id).cloned();
+        if let Some(index) = cached {
+            tokio::task::spawn_blocking(move || update(&mut index.write().unwrap())).await?;
+        }
+        Ok(())
+    }
+
+    /// Write the face index of a library to disk if it changed
+    pub async fn save_face_index(&self, library_id: &str) -> RsResult<()> {
+        let cached = self.face_indexes.read().await.get(library_id).cloned();
+        let Some(index) = cached else {
+            return Ok(());
+        };
+        let path = face_index_path(library_id).await?;
+        tokio::task::spawn_blocking(move || -> RsResult<()> {
+            let mut index = index.write().unwrap();
+            if !index.dirty {
+                return Ok(());
+            }
+            let temp = path.with_extension("tmp");
+            let mut writer = BufWriter::new(File::create(&temp)?);
+            index.write_to(&mut writer)?;
+            writer.flush()?;
+            drop(writer);
+            std::fs::rename(&temp, &path)?;
+            index.dirty = false;
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Forget the face index of a deleted library
+    pub async fn remove_face_index(&self, library_id: &str) -> RsResult<()> {
+        self.face_indexes.write().await.remove(library_id);
+        let path = face_index_path(library_id).await?;
+        if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            tokio::fs::remove_file(&path).await?;
+        }
+        Ok(())
+    }
+
+    /// Faces closest to a face, assigned to people or not. Faces embedded by an older face
+    /// model are not indexed and cannot be searched until re-embedded
+    pub async fn get_similar_faces(
+        &self,
+        library_id: &str,
+        face_id: &str,
+        limit: Option<usize>,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<SimilarFace>> {
+        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        let limit = limit.unwrap_or(DEFAULT_SIMILAR_FACES_LIMIT);
+        let store = self.store.get_library_store(library_id)?;
+        let index = self.get_face_index(library_id, false).await?;
+        let searched = face_id.to_string();
+        let mut found = tokio::task::spawn_blocking(move || {
+            let index = index.read().unwrap();
+            let embedding = index
+                .people
+                .vector(&searched)
+                .or_else(|| index.unassigned.vector(&searched))?
+                .to_vec();
+            let mut found: Vec<(String, Option<String>, f32)> = index
+                .people
+                .search(&embedding, limit + 1)
+                .into_iter()
+                .map(|m| (m.id, Some(m.label), m.similarity))
+                .chain(
+                    index
+                        .unassigned
+                        .search(&embedding, limit + 1)
+                        .into_iter()
+                        .map(|m| (m.id, None, m.similarity)),
+                )
+                .filter(|(id, _, _)| id != &searched)
+                .collect();
+            found.sort_by(|a, b| b.2.total_cmp(&a.2));
+            found.truncate(limit);
+            Some(found)
+        })
+        .await?
+        .ok_or_else(|| RsError::NotFound(format!("Face not indexed: {}", face_id)))?;
+
+        let mut faces = vec![];
+        for (id, person_id, similarity) in found.drain(..) {
+            // The index may still reference faces of deleted medias until its next sync
+            if let Some((media_ref, _)) = store.get_face_by_id(&id).await? {
+                faces.push(SimilarFace {
+                    id,
+                    person_id,
+                    media_ref,
+                    similarity,
+                });
+            }
+        }
+        Ok(faces)
+    }
+}
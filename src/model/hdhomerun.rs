@@ -0,0 +1,245 @@
+use std::net::{IpAddr, SocketAddr};
+
+use tokio::net::UdpSocket;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    domain::{
+        hdhomerun::{HdhrDiscover, HdhrLineupEntry, HdhrLineupStatus},
+        library::{LibraryType, ServerLibrary},
+    },
+    error::{RsError, RsResult},
+    server::get_server_port,
+    tools::{
+        auth::constant_time_eq,
+        hdhomerun::{
+            device_id, discover_matches, discover_reply, guide_numbers, parse_discover_request,
+            HdhrDiscoverDevice, HDHR_DISCOVER_PORT,
+        },
+        log::{log_error, log_info, LogServiceType},
+    },
+};
+
+use super::ModelController;
+
+/// Identity of a network tuner, as clients only accept known HDHomeRun models
+const HDHR_MANUFACTURER: &str = "Silicondust";
+const HDHR_MODEL: &str = "HDTC-2US";
+const HDHR_FIRMWARE_NAME: &str = "hdhomeruntc_atsc";
+const HDHR_FIRMWARE_VERSION: &str = "20200101";
+const HD_QUALITIES: [&str; 3] = ["4K", "FHD", "HD"];
+
+/// IPTV library announced on the LAN by discovery
+fn is_hdhr_discoverable(library: &ServerLibrary) -> bool {
+    library.kind == LibraryType::Iptv
+        && library.settings.hdhr_discovery.unwrap_or(false)
+        && library
+            .settings
+            .hdhr_key
+            .as_ref()
+            .map(|k| !k.is_empty())
+            .unwrap_or(false)
+}
+
+impl ModelController {
+    /// IPTV library exposed as a tuner with the given key
+    pub async fn get_hdhr_library(&self, library_id: &str, key: &str) -> RsResult<ServerLibrary> {
+        self.cache_get_library(library_id)
+            .await
+            .filter(|library| library.kind == LibraryType::Iptv)
+            .filter(|library| {
+                library
+                    .settings
+                    .hdhr_key
+                    .as_deref()
+                    .map(|k| !k.is_empty() && constant_time_eq(k, key))
+                    .unwrap_or(false)
+            })
+            .ok_or(RsError::NotFound(format!(
+                "HDHomeRun device {} not found",
+                library_id
+            )))
+    }
+
+    pub async fn get_hdhr_discover(
+        &self,
+        library_id: &str,
+        key: &str,
+        base_url: &str,
+    ) -> RsResult<HdhrDiscover> {
+        let library = self.get_hdhr_library(library_id, key).await?;
+        let device_id = format!("{:08X}", device_id(&library.id));
+        Ok(HdhrDiscover {
+            friendly_name: format!("Redseat {}", library.name),
+            manufacturer: HDHR_MANUFACTURER.to_string(),
+            model_number: HDHR_MODEL.to_string(),
+            firmware_name: HDHR_FIRMWARE_NAME.to_string(),
+            firmware_version: HDHR_FIRMWARE_VERSION.to_string(),
+            device_auth: device_id.clone(),
+            device_id,
+            base_url: base_url.to_string(),
+            lineup_url: format!("{}/lineup.json", base_url),
+            tuner_count: library.settings.max_streams.unwrap_or(1),
+        })
+    }
+
+    pub async fn get_hdhr_lineup_status(
+        &self,
+        library_id: &str,
+        key: &str,
+    ) -> RsResult<HdhrLineupStatus> {
+        self.get_hdhr_library(library_id, key).await?;
+        Ok(HdhrLineupStatus {
+            scan_in_progress: 0,
+            scan_possible: 1,
+            source: "Cable".to_string(),
+            source_list: vec!["Cable".to_string()],
+        })
+    }
+
+    pub async fn get_hdhr_lineup(
+        &self,
+        library_id: &str,
+        key: &str,
+        base_url: &str,
+    ) -> RsResult<Vec<HdhrLineupEntry>> {
+        self.get_hdhr_library(library_id, key).await?;
+        let store = self.store.get_library_store(library_id)?;
+        let channels = store.get_channels(None, None).await?;
+        let mut lineup = vec![];
+        for (number, channel) in guide_numbers(&channels) {
+            let variants = store.get_channel_variants(&channel.id).await?;
+            let hd = variants.iter().any(|v| {
+                v.quality
+                    .as_deref()
+                    .map(|q| HD_QUALITIES.contains(&q))
+                    .unwrap_or(false)
+            });
+            lineup.push(HdhrLineupEntry {
+                guide_number: number.to_string(),
+                guide_name: channel.name.clone(),
+                url: format!("{}/auto/v{}", base_url, number),
+                hd: hd.then_some(1),
+            });
+        }
+        Ok(lineup)
+    }
+
+    /// Channel id behind a guide number of the lineup
+    pub async fn get_hdhr_channel(
+        &self,
+        library_id: &str,
+        key: &str,
+        guide_number: u32,
+    ) -> RsResult<String> {
+        self.get_hdhr_library(library_id, key).await?;
+        let store = self.store.get_library_store(library_id)?;
+        let channels = store.get_channels(None, None).await?;
+        guide_numbers(&channels)
+            .into_iter()
+            .find(|(number, _)| *number == guide_number)
+            .map(|(_, channel)| channel.id.clone())
+            .ok_or(RsError::NotFound(format!(
+                "Channel {} not found",
+                guide_number
+            )))
+    }
+
+    /// Tuners announced on the LAN, with urls reachable on `local_ip`
+    async fn hdhr_discover_devices(&self, local_ip: IpAddr) -> Vec<HdhrDiscoverDevice> {
+        let port = get_server_port().await;
+        let host = SocketAddr::new(local_ip, port);
+        let cache = self.chache_libraries.read().await;
+        cache
+            .values()
+            .filter(|library| is_hdhr_discoverable(library))
+            .filter_map(|library| {
+                let key = library.settings.hdhr_key.as_ref()?;
+                let base_url = format!("http://{}/hdhr/{}/{}", host, library.id, key);
+                let device_id = device_id(&library.id);
+                Some(HdhrDiscoverDevice {
+                    device_id,
+                    tuner_count: library.settings.max_streams.unwrap_or(1).min(255) as u8,
+                    lineup_url: format!("{}/lineup.json", base_url),
+                    base_url,
+                    device_auth: format!("{:08X}", device_id),
+                })
+            })
+            .collect()
+    }
+
+    /// Listen for discovery requests only while a library has `hdhr_discovery` enabled,
+    /// the discovery port stays free otherwise
+    pub async fn update_hdhr_discovery(&self) {
+        let enabled = self
+            .chache_libraries
+            .read()
+            .await
+            .values()
+            .any(is_hdhr_discoverable);
+        let mut running = self.hdhr_discovery.write().await;
+        match (enabled, running.as_ref()) {
+            (true, None) => {
+                let token = CancellationToken::new();
+                self.start_hdhr_discovery(token.clone());
+                *running = Some(token);
+            }
+            (false, Some(token)) => {
+                token.cancel();
+                *running = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Answer SiliconDust discovery requests for libraries with `hdhr_discovery` enabled
+    fn start_hdhr_discovery(&self, token: CancellationToken) {
+        let mc = self.clone();
+        tokio::spawn(async move {
+            let socket =
+                match UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], HDHR_DISCOVER_PORT))).await {
+                    Ok(socket) => socket,
+                    Err(error) => {
+                        log_info(
+                            LogServiceType::Other,
+                            format!("HDHomeRun discovery disabled: {}", error),
+                        );
+                        return;
+                    }
+                };
+            let mut buffer = [0u8; 1500];
+            loop {
+                let received = tokio::select! {
+                    received = socket.recv_from(&mut buffer) => received,
+                    _ = token.cancelled() => break,
+                };
+                let (len, peer) = match received {
+                    Ok(received) => received,
+                    Err(error) => {
+                        log_error(
+                            LogServiceType::Other,
+                            format!("HDHomeRun discovery error: {}", error),
+                        );
+                        continue;
+                    }
+                };
+                let Some(request) = parse_discover_request(&buffer[..len]) else {
+                    continue;
+                };
+                // Local address of the interface routing to the client
+                let local_ip = match UdpSocket::bind("0.0.0.0:0").await {
+                    Ok(probe) => match probe.connect(peer).await.and(probe.local_addr()) {
+                        Ok(addr) => addr.ip(),
+                        Err(_) => continue,
+                    },
+                    Err(_) => continue,
+                };
+                for device in mc.hdhr_discover_devices(local_ip).await {
+                    if discover_matches(request, device.device_id) {
+                        let _ = socket.send_to(&discover_reply(&device), peer).await;
+                    }
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,205 @@
+use nanoid::nanoid;
+
+use crate::{
+    domain::{
+        kosync::{KosyncCredentials, KosyncProgress},
+        library::LibraryType,
+        media::Media,
+    },
+    error::{RsError, RsResult},
+    plugins::sources::error::SourcesError,
+    tools::{
+        clock::now,
+        kosync::{kosync_from_progress, kosync_key, partial_md5, progress_from_kosync},
+        log::{log_error, LogServiceType},
+    },
+};
+
+use super::{users::ConnectedUser, ModelController};
+
+/// Device name reported to KOReader for positions set in Redseat apps
+const REDSEAT_DEVICE: &str = "Redseat";
+
+impl ModelController {
+    /// New KOReader credentials for the user. Only the md5 of the password is kept
+    pub async fn create_kosync_credentials(
+        &self,
+        username: Option<String>,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<KosyncCredentials> {
+        let user = requesting_user.check_registered()?;
+        let username = username
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or(user.name);
+        if let Some((user_ref, _)) = self.store.get_kosync_user(username.clone()).await? {
+            if user_ref != user.id {
+                return Err(RsError::Error(format!(
+                    "KOReader username {} is already used",
+                    username
+                )));
+            }
+        }
+        let password = nanoid!();
+        self.store
+            .set_kosync_user(username.clone(), user.id, kosync_key(&password))
+            .await?;
+        Ok(KosyncCredentials { username, password })
+    }
+
+    pub async fn get_kosync_username(
+        &self,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Option<String>> {
+        let user = requesting_user.check_registered()?;
+        Ok(self.store.get_kosync_username(user.id).await?)
+    }
+
+    /// User of the `x-auth-user` and `x-auth-key` headers sent by KOReader
+    pub async fn kosync_authenticate(&self, username: &str, key: &str) -> RsResult<ConnectedUser> {
+        let (user_ref, stored_key) = self
+            .store
+            .get_kosync_user(username.to_string())
+            .await?
+            .ok_or(RsError::AuthFail)?;
+        if !stored_key.eq_ignore_ascii_case(key) {
+            return Err(RsError::AuthFail);
+        }
+        let user = self.get_user_unchecked(&user_ref).await?;
+        Ok(ConnectedUser::Server(user))
+    }
+
+    /// Medias with this KOReader document hash in the libraries readable by the user
+    async fn kosync_medias(
+        &self,
+        document: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<(String, Media)>> {
+        let mut medias = vec![];
+        for library in self.get_libraries(requesting_user).await? {
+            if library.kind == LibraryType::Iptv {
+                continue;
+            }
+            let Ok(store) = self.store.get_library_store(&library.id) else {
+                continue;
+            };
+            for media_id in store.get_media_ids_by_kohash(document).await? {
+                if let Some(media) = self
+                    .get_media(&library.id, media_id, requesting_user)
+                    .await?
+                {
+                    medias.push((library.id.clone(), media.item));
+                }
+            }
+        }
+        Ok(medias)
+    }
+
+    /// Last position of a document. Progress made in Redseat apps on a paged document
+    /// is returned when it is newer than the last KOReader update
+    pub async fn get_kosync_progress(
+        &self,
+        document: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Option<KosyncProgress>> {
+        let user = requesting_user.check_registered()?;
+        let stored = self
+            .store
+            .get_kosync_progress(document.to_string(), user.id)
+            .await?;
+        for (library_id, media) in self.kosync_medias(document, requesting_user).await? {
+            let Ok(progress) = self
+                .get_media_progress(&library_id, media.id.clone(), requesting_user)
+                .await
+            else {
+                continue;
+            };
+            let timestamp = progress.modified / 1000;
+            if stored.as_ref().map(|s| s.timestamp).unwrap_or(0) >= timestamp {
+                continue;
+            }
+            if let Some((page, percentage)) = kosync_from_progress(progress.progress, media.pages) {
+                return Ok(Some(KosyncProgress {
+                    document: document.to_string(),
+                    progress: page,
+                    percentage,
+                    device: REDSEAT_DEVICE.to_string(),
+                    device_id: REDSEAT_DEVICE.to_lowercase(),
+                    timestamp,
+                }));
+            }
+        }
+        Ok(stored)
+    }
+
+    /// Store a KOReader position and report it on the matching medias progress
+    pub async fn set_kosync_progress(
+        &self,
+        mut progress: KosyncProgress,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<KosyncProgress> {
+        let user = requesting_user.check_registered()?;
+        progress.timestamp = now().timestamp();
+        self.store
+            .set_kosync_progress(progress.clone(), user.id)
+            .await?;
+        for (library_id, media) in self
+            .kosync_medias(&progress.document, requesting_user)
+            .await?
+        {
+            let value = progress_from_kosync(&progress.progress, progress.percentage, media.pages);
+            if let Err(e) = self
+                .set_media_progress(&library_id, media.id.clone(), value, requesting_user)
+                .await
+            {
+                log_error(
+                    LogServiceType::Source,
+                    format!(
+                        "Unable to set progress of {} from KOReader: {:?}",
+                        media.id, e
+                    ),
+                );
+            }
+        }
+        Ok(progress)
+    }
+
+    /// Compute the KOReader document hash of a book or comic
+    pub async fn update_media_kohash(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<()> {
+        self.cache_check_library_notcrypt(library_id).await?;
+        let store = self.store.get_library_store(library_id)?;
+        let existing =
+            store
+                .get_media_source(media_id)
+                .await?
+                .ok_or(SourcesError::UnableToFindMedia(
+                    library_id.to_string(),
+                    media_id.to_string(),
+                    "update_media_kohash".to_string(),
+                ))?;
+        let (path, temporary) = self
+            .local_media_path(library_id, &existing.source, requesting_user)
+            .await?;
+        let hash_path = path.clone();
+        let hash = tokio::task::spawn_blocking(move || {
+            partial_md5(std::io::BufReader::new(std::fs::File::open(hash_path)?))
+        })
+        .await?;
+        if temporary {
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+        store.set_media_kohash(media_id, &hash?).await?;
+        Ok(())
+    }
+
+    /// Ids of the books and comics of a library without KOReader document hash
+    pub async fn get_medias_without_kohash(&self, library_id: &str) -> RsResult<Vec<String>> {
+        let store = self.store.get_library_store(library_id)?;
+        Ok(store.get_media_ids_without_kohash().await?)
+    }
+}
@@ -24,6 +24,7 @@ use crate::{
     server::get_server_file_path_array,
     tools::{
         auth::{sign_local, ClaimsLocal, ClaimsLocalType},
+        hls_session::MAX_TIMESHIFT_MINUTES,
         log::{log_error, log_info, LogServiceType},
     },
 };
@@ -237,6 +238,17 @@ impl LibraryMessage {
     }
 }
 
+/// Reject settings the server cannot honor
+fn check_library_settings(settings: &ServerLibrarySettings) -> Result<()> {
+    if settings.timeshift_minutes.unwrap_or(0) > MAX_TIMESHIFT_MINUTES {
+        return Err(Error::Other(format!(
+            "Timeshift is limited to {} minutes",
+            MAX_TIMESHIFT_MINUTES
+        )));
+    }
+    Ok(())
+}
+
 impl ModelController {
     pub async fn get_library(
         &self,
@@ -421,6 +433,9 @@ impl ModelController {
         requesting_user: &ConnectedUser,
     ) -> Result<Option<super::libraries::ServerLibraryForRead>> {
         requesting_user.check_library_role(&library_id, LibraryRole::Admin)?;
+        if let Some(settings) = &update.settings {
+            check_library_settings(settings)?;
+        }
 
         // Check if password is being changed to trigger encryption migration
         let old_library = self.cache_get_library(library_id).await;
@@ -486,6 +501,7 @@ impl ModelController {
         requesting_user: &ConnectedUser,
     ) -> RsResult<Option<super::libraries::ServerLibraryForRead>> {
         requesting_user.check_role(&UserRole::Admin)?;
+        check_library_settings(&library_for_add.settings)?;
         let library_id = nanoid!();
         let source = if library_for_add.kind == LibraryType::Iptv {
             "virtual".to_string()
@@ -733,6 +749,8 @@ impl ModelController {
         Self::remove_file_if_exists(&db_path).await?;
         Self::remove_file_if_exists(&db_wal_path).await?;
         Self::remove_file_if_exists(&db_shm_path).await?;
+        self.remove_face_index(library_id).await?;
+        self.remove_media_embeddings_cache(library_id).await;
         Ok(())
     }
 
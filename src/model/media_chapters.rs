@@ -0,0 +1,138 @@
+use std::path::PathBuf;
+
+use crate::{
+    domain::{library::LibraryRole, media_chapters::RsMediaChapter},
+    error::{RsError, RsResult},
+    tools::video_tools::VideoTime,
+};
+
+use super::{users::ConnectedUser, ModelController};
+
+/// Thumbnails are taken slightly after the chapter start to skip fades to black
+const CHAPTER_THUMB_OFFSET: f64 = 3.0;
+
+impl ModelController {
+    /// Folder holding the chapter thumbnails of a media. Hidden so `clean_temp` keeps it
+    async fn chapters_folder(&self, library_id: &str, media_id: &str) -> RsResult<PathBuf> {
+        let local = self.library_source_for_library(library_id).await?;
+        Ok(local.get_full_path(&format!(".cache/.chapters/{}", media_id)))
+    }
+
+    pub async fn get_media_chapters(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<RsMediaChapter>> {
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Read)?;
+        let store = self.store.get_library_store(library_id)?;
+        Ok(store.get_media_chapters(media_id.to_string()).await?)
+    }
+
+    pub async fn set_media_chapters(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        mut chapters: Vec<RsMediaChapter>,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<RsMediaChapter>> {
+        requesting_user.check_library_role(library_id, LibraryRole::Write)?;
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Write)?;
+        self.get_media(library_id, media_id.to_string(), requesting_user)
+            .await?
+            .ok_or(RsError::NotFound(format!("Media {}", media_id)))?;
+        for chapter in &chapters {
+            if chapter.start < 0.0 || chapter.end <= chapter.start {
+                return Err(RsError::Error(format!(
+                    "Invalid chapter range {} - {}",
+                    chapter.start, chapter.end
+                )));
+            }
+        }
+        chapters.sort_by(|a, b| a.start.total_cmp(&b.start));
+        let store = self.store.get_library_store(library_id)?;
+        store
+            .set_media_chapters(media_id.to_string(), chapters, true)
+            .await?;
+        self.remove_media_chapter_images(library_id, media_id)
+            .await?;
+        Ok(store.get_media_chapters(media_id.to_string()).await?)
+    }
+
+    /// Replace the chapters with the ones found in the container unless a user edited them
+    pub async fn import_media_chapters(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        chapters: Vec<RsMediaChapter>,
+    ) -> RsResult<()> {
+        let store = self.store.get_library_store(library_id)?;
+        if store
+            .get_media_chapters_manual(media_id.to_string())
+            .await?
+            || store.get_media_chapters(media_id.to_string()).await? == chapters
+        {
+            return Ok(());
+        }
+        store
+            .set_media_chapters(media_id.to_string(), chapters, false)
+            .await?;
+        self.remove_media_chapter_images(library_id, media_id)
+            .await?;
+        Ok(())
+    }
+
+    /// Jpeg thumbnail of a chapter, extracted on first request then cached
+    pub async fn get_media_chapter_image(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        position: usize,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<u8>> {
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Read)?;
+        let folder = self.chapters_folder(library_id, media_id).await?;
+        let path = folder.join(format!("{}.jpg", position));
+        if let Ok(image) = tokio::fs::read(&path).await {
+            return Ok(image);
+        }
+
+        self.cache_check_library_notcrypt(library_id).await?;
+        if self.get_library_encryption_key(library_id).await.is_some() {
+            return Err(RsError::UnavailableForCryptedLibraries);
+        }
+        let chapters = self
+            .get_media_chapters(library_id, media_id, requesting_user)
+            .await?;
+        let chapter = chapters.get(position).ok_or(RsError::NotFound(format!(
+            "Chapter {} of media {}",
+            position, media_id
+        )))?;
+        let time = chapter.start + CHAPTER_THUMB_OFFSET.min((chapter.end - chapter.start) / 2.0);
+        let image = self
+            .get_video_thumb(
+                library_id,
+                media_id,
+                VideoTime::Seconds(time),
+                image::ImageFormat::Jpeg,
+                Some(80),
+                requesting_user,
+            )
+            .await?;
+        tokio::fs::create_dir_all(&folder).await?;
+        tokio::fs::write(&path, &image).await?;
+        Ok(image)
+    }
+
+    pub async fn remove_media_chapter_images(
+        &self,
+        library_id: &str,
+        media_id: &str,
+    ) -> RsResult<()> {
+        let folder = self.chapters_folder(library_id, media_id).await?;
+        if tokio::fs::try_exists(&folder).await.unwrap_or(false) {
+            tokio::fs::remove_dir_all(&folder).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+
+use crate::{
+    domain::{
+        library::LibraryRole,
+        media::FileType,
+        media_markers::{RsMediaMarkers, RsMediaMarkersForUpdate},
+    },
+    error::{RsError, RsResult},
+    tools::{
+        audio_fingerprint::{decode_audio, find_shared_segment, fingerprint, point_duration},
+        log::{log_error, log_info, LogServiceType},
+        video_tools::get_duration,
+    },
+};
+
+use super::{medias::MediaQuery, users::ConnectedUser, ModelController};
+
+/// Beginning of the episode searched for an intro
+const INTRO_ANALYSIS_SECONDS: f64 = 600.0;
+/// End of the episode searched for credits
+const CREDITS_ANALYSIS_SECONDS: f64 = 300.0;
+const MIN_INTRO_SECONDS: f64 = 15.0;
+const MAX_INTRO_SECONDS: f64 = 150.0;
+const MIN_CREDITS_SECONDS: f64 = 15.0;
+/// Unmatched audio tolerated inside a shared segment
+const MAX_GAP_SECONDS: f64 = 3.5;
+
+struct EpisodeFingerprint {
+    media_id: String,
+    intro: Vec<u32>,
+    credits: Vec<u32>,
+    credits_offset: f64,
+}
+
+/// Keep the longest candidate segment found for a media
+fn keep_longest(found: &mut HashMap<String, (f64, f64)>, media_id: &str, segment: (f64, f64)) {
+    let longer = found
+        .get(media_id)
+        .map(|(start, end)| segment.1 - segment.0 > end - start)
+        .unwrap_or(true);
+    if longer {
+        found.insert(media_id.to_string(), segment);
+    }
+}
+
+impl ModelController {
+    pub async fn get_media_markers(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Option<RsMediaMarkers>> {
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Read)?;
+        let store = self.store.get_library_store(library_id)?;
+        Ok(store.get_media_markers(media_id.to_string()).await?)
+    }
+
+    /// Markers of several medias of a library in one query
+    pub async fn get_medias_markers(
+        &self,
+        library_id: &str,
+        media_ids: Vec<String>,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<HashMap<String, RsMediaMarkers>> {
+        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        let store = self.store.get_library_store(library_id)?;
+        Ok(store
+            .get_medias_markers(media_ids)
+            .await?
+            .into_iter()
+            .map(|markers| (markers.media_ref.clone(), markers))
+            .collect())
+    }
+
+    /// Manual override: stored markers won't be replaced by automatic detection
+    pub async fn set_media_markers(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        update: RsMediaMarkersForUpdate,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<RsMediaMarkers> {
+        requesting_user.check_library_role(library_id, LibraryRole::Write)?;
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Write)?;
+        self.get_media(library_id, media_id.to_string(), requesting_user)
+            .await?
+            .ok_or(RsError::NotFound(format!("Media {}", media_id)))?;
+        for (start, end) in [
+            (update.intro_start, update.intro_end),
+            (update.credits_start, update.credits_end),
+        ] {
+            if let (Some(start), Some(end)) = (start, end) {
+                if start < 0.0 || end <= start {
+                    return Err(RsError::Error(format!(
+                        "Invalid marker range {} - {}",
+                        start, end
+                    )));
+                }
+            }
+        }
+        let store = self.store.get_library_store(library_id)?;
+        store
+            .set_media_markers(RsMediaMarkers {
+                media_ref: media_id.to_string(),
+                intro_start: update.intro_start,
+                intro_end: update.intro_end,
+                credits_start: update.credits_start,
+                credits_end: update.credits_end,
+                manual: true,
+                modified: 0,
+            })
+            .await?;
+        store
+            .get_media_markers(media_id.to_string())
+            .await?
+            .ok_or(RsError::NotFound(format!("Markers for {}", media_id)))
+    }
+
+    /// Remove markers (manual or detected) so the media is analyzed again
+    pub async fn remove_media_markers(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<()> {
+        requesting_user.check_library_role(library_id, LibraryRole::Write)?;
+        let store = self.store.get_library_store(library_id)?;
+        store.remove_media_markers(media_id.to_string()).await?;
+        Ok(())
+    }
+
+    pub async fn get_seasons_without_markers(
+        &self,
+        library_id: &str,
+    ) -> RsResult<Vec<(String, u32)>> {
+        let store = self.store.get_library_store(library_id)?;
+        Ok(store.get_seasons_without_markers().await?)
+    }
+
+    async fn fingerprint_episode(
+        &self,
+        library_id: &str,
+        media_id: &str,
+    ) -> RsResult<EpisodeFingerprint> {
+        let uri = self.get_media_uri(library_id, media_id, Some(600)).await?;
+        let duration = get_duration(&uri)
+            .await?
+            .ok_or(RsError::Error("Unable to get video duration".to_string()))?;
+
+        let intro_length = INTRO_ANALYSIS_SECONDS.min(duration / 2.0);
+        let intro_samples = decode_audio(&uri, 0.0, intro_length).await?;
+        let credits_length = CREDITS_ANALYSIS_SECONDS.min(duration / 2.0);
+        let credits_offset = duration - credits_length;
+        let credits_samples = decode_audio(&uri, credits_offset, credits_length).await?;
+
+        let (intro, credits) = tokio::task::spawn_blocking(move || {
+            (fingerprint(&intro_samples), fingerprint(&credits_samples))
+        })
+        .await
+        .map_err(|e| RsError::Error(format!("Fingerprint task failed: {:?}", e)))?;
+
+        Ok(EpisodeFingerprint {
+            media_id: media_id.to_string(),
+            intro,
+            credits,
+            credits_offset,
+        })
+    }
+
+    /// Detect intro and credits shared by the episodes of a season
+    pub async fn detect_season_markers(
+        &self,
+        library_id: &str,
+        serie_id: &str,
+        season: u32,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<RsMediaMarkers>> {
+        requesting_user.check_library_role(library_id, LibraryRole::Write)?;
+        self.cache_check_library_notcrypt(library_id).await?;
+        if self.get_library_encryption_key(library_id).await.is_some() {
+            return Err(RsError::UnavailableForCryptedLibraries);
+        }
+
+        let medias = self
+            .get_medias(
+                library_id,
+                MediaQuery {
+                    series: vec![format!("{}|{:04}|", serie_id, season)],
+                    types: vec![FileType::Video],
+                    ..Default::default()
+                },
+                requesting_user,
+            )
+            .await?;
+        let mut episodes: Vec<(u32, String)> = medias
+            .into_iter()
+            .filter_map(|m| {
+                let number = m
+                    .relations
+                    .as_ref()
+                    .and_then(|r| r.series.as_ref())
+                    .and_then(|series| {
+                        series
+                            .iter()
+                            .find(|s| s.id == serie_id && s.season == Some(season))
+                    })
+                    .and_then(|s| s.episode)?;
+                Some((number, m.item.id))
+            })
+            .collect();
+        episodes.sort();
+        let store = self.store.get_library_store(library_id)?;
+        // Seasons that can't produce markers are only analyzed again when their episodes change
+        store
+            .set_season_markers_analyzed(serie_id.to_string(), season, episodes.len() as u32)
+            .await?;
+        if episodes.len() < 2 {
+            return Err(RsError::Error(format!(
+                "At least two episodes are needed to detect markers for {} season {}",
+                serie_id, season
+            )));
+        }
+
+        let mut fingerprints = vec![];
+        for (_, media_id) in &episodes {
+            match self.fingerprint_episode(library_id, media_id).await {
+                Ok(fingerprint) => fingerprints.push(fingerprint),
+                Err(e) => log_error(
+                    LogServiceType::Source,
+                    format!("Unable to fingerprint audio of {}: {:?}", media_id, e),
+                ),
+            }
+        }
+
+        let point = point_duration();
+        let max_gap = (MAX_GAP_SECONDS / point) as usize;
+        let mut intros: HashMap<String, (f64, f64)> = HashMap::new();
+        let mut credits: HashMap<String, (f64, f64)> = HashMap::new();
+        for pair in fingerprints.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            if let Some(segment) = find_shared_segment(
+                &a.intro,
+                &b.intro,
+                (MIN_INTRO_SECONDS / point) as usize,
+                max_gap,
+            ) {
+                if segment.len() as f64 * point <= MAX_INTRO_SECONDS {
+                    let a_range = (segment.a_start as f64 * point, segment.a_end as f64 * point);
+                    let b_range = (segment.b_start as f64 * point, segment.b_end as f64 * point);
+                    keep_longest(&mut intros, &a.media_id, a_range);
+                    keep_longest(&mut intros, &b.media_id, b_range);
+                }
+            }
+            if let Some(segment) = find_shared_segment(
+                &a.credits,
+                &b.credits,
+                (MIN_CREDITS_SECONDS / point) as usize,
+                max_gap,
+            ) {
+                let a_range = (
+                    a.credits_offset + segment.a_start as f64 * point,
+                    a.credits_offset + segment.a_end as f64 * point,
+                );
+                let b_range = (
+                    b.credits_offset + segment.b_start as f64 * point,
+                    b.credits_offset + segment.b_end as f64 * point,
+                );
+                keep_longest(&mut credits, &a.media_id, a_range);
+                keep_longest(&mut credits, &b.media_id, b_range);
+            }
+        }
+
+        let mut results = vec![];
+        for fingerprint in fingerprints {
+            let existing = store
+                .get_media_markers(fingerprint.media_id.clone())
+                .await?;
+            if let Some(existing) = existing.filter(|m| m.manual) {
+                results.push(existing);
+                continue;
+            }
+            let intro = intros.get(&fingerprint.media_id);
+            let credit = credits.get(&fingerprint.media_id);
+            // Stored even when nothing is found so the media is not analyzed again
+            let markers = RsMediaMarkers {
+                media_ref: fingerprint.media_id.clone(),
+                intro_start: intro.map(|r| r.0),
+                intro_end: intro.map(|r| r.1),
+                credits_start: credit.map(|r| r.0),
+                credits_end: credit.map(|r| r.1),
+                manual: false,
+                modified: 0,
+            };
+            store.set_media_markers(markers).await?;
+            if let Some(markers) = store.get_media_markers(fingerprint.media_id).await? {
+                results.push(markers);
+            }
+        }
+
+        log_info(
+            LogServiceType::Source,
+            format!(
+                "Detected markers for {} season {}: {} intros, {} credits",
+                serie_id,
+                season,
+                intros.len(),
+                credits.len()
+            ),
+        );
+        Ok(results)
+    }
+}
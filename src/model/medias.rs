@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     io::{self, Cursor, Read},
     path::PathBuf,
     pin::Pin,
@@ -22,7 +23,7 @@ use rs_plugin_common_interfaces::{
         RsVideoTranscodeJob, RsVideoTranscodeJobPluginRequest, RsVideoTranscodeStatus,
         VideoConvertRequest, VideoOverlayType,
     },
-    ElementType, PluginType, RsCookie,
+    ElementType, ImageType, PluginType, RsCookie,
 };
 use rusqlite::{
     types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
@@ -64,7 +65,7 @@ use crate::{
     routes::infos,
     server::get_config,
     tools::{
-        file_tools::{filename_from_path, remove_extension},
+        file_tools::{filename_from_path, is_imported_name, remove_extension},
         image_tools::{convert_image_reader, image_infos, IMAGES_MIME_FULL_BROWSER_SUPPORT},
         recognition,
         video_tools::VideoCommandBuilder,
@@ -73,6 +74,7 @@ use crate::{
 
 use crate::{
     domain::{
+        ffmpeg::HdrFormat,
         library::{LibraryLimits, LibraryRole},
         media::{
             FileType, Media, MediaForAdd, MediaForInsert, MediaForUpdate, MediaItemReference,
@@ -90,13 +92,20 @@ use crate::{
         },
     },
     routes::mw_range::RangeDefinition,
-    server::get_server_port,
+    server::{get_server_port, get_server_temp_file_path},
     tools::{
+        audiobook::is_audiobook_mime,
         auth::{sign_local, ClaimsLocal},
+        comic_archive::{
+            comic_entries, comic_entry, is_comic_info, sort_pages, CachedComicArchive, ComicArchive,
+        },
+        comicinfo_parser::parse_comic_info,
         encryption::{CtrDecryptReader, CtrEncryptWriter, CTR_NONCE_SIZE},
         file_tools::{file_type_from_mime, get_extension_from_mime},
         image_tools::{self, resize_image_reader, ImageSize},
         log::{log_error, log_info, log_warn, LogServiceType},
+        opf_parser::{read_epub_metadata, EPUB_MIME},
+        pdf_tools::PDF_MIME,
         prediction::{predict_net, preload_model, PredictionTagResult},
         video_tools::{self, concat_videos, probe_video, VideoTime},
         zip_range::extract_zip_page_from_request,
@@ -115,6 +124,8 @@ use crate::routes::sse::SseEvent;
 
 pub const CRYPTO_HEADER_SIZE: u64 = 16 + 4 + 4 + 32 + 256;
 const PLUGIN_CONVERT_CONCURRENCY_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+/// Time a comic archive stays cached after its last page was read
+const COMIC_ARCHIVE_TTL: Duration = Duration::from_secs(10 * 60);
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
@@ -150,6 +161,11 @@ pub struct MediaQuery {
     pub types: Vec<FileType>,
 
     pub text: Option<String>,
+    /// Free text description of the image content, ranked by similarity.
+    /// `page_key` is then the number of results already received
+    pub semantic: Option<String>,
+    #[serde(default)]
+    pub ids: Vec<String>,
 
     pub long: Option<f64>,
     pub lat: Option<f64>,
@@ -167,6 +183,8 @@ pub struct MediaQuery {
     pub max_size: Option<u64>,
 
     pub vcodec: Option<String>,
+    /// Only HDR (true) or SDR (false) videos
+    pub hdr: Option<bool>,
 
     pub uploadkey: Option<String>,
 
@@ -202,6 +220,8 @@ pub struct MediaFileQuery {
     pub unsupported_mime: Vec<String>,
 
     pub page: Option<u32>,
+    /// Width of rendered pages (PDF)
+    pub size: Option<ImageSize>,
 
     #[serde(default)]
     pub raw: bool,
@@ -469,23 +489,26 @@ impl ModelController {
         mut query: MediaQuery,
         requesting_user: &ConnectedUser,
     ) -> RsResult<Vec<ItemWithRelations<Media>>> {
-        if let Ok(key) = requesting_user.check_upload_key(library_id) {
+        let limits = if let Ok(key) = requesting_user.check_upload_key(library_id) {
             // UploadKey: can only see medias uploaded with this key
             query.uploadkey = Some(key.id.clone());
-            let limits = LibraryLimits::default();
-            let store = self.store.get_library_store(library_id)?;
-            let medias = store.get_medias(query, limits).await?;
-            return Ok(medias);
+            LibraryLimits::default()
+        } else {
+            let progress_user = self
+                .get_library_mapped_user(library_id, requesting_user.user_id()?)
+                .await
+                .ok();
+            let mut limits = requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+            limits.user_id = progress_user;
+            limits
+        };
+        if let Some(text) = query.semantic.take() {
+            return self
+                .get_medias_by_text(library_id, &text, query, limits)
+                .await;
         }
-        let progress_user = self
-            .get_library_mapped_user(library_id, requesting_user.user_id()?)
-            .await
-            .ok();
-        let mut limits = requesting_user.check_library_role(library_id, LibraryRole::Read)?;
-        limits.user_id = progress_user;
         let store = self.store.get_library_store(library_id)?;
-        let medias = store.get_medias(query, limits).await?;
-        Ok(medias)
+        Ok(store.get_medias(query, limits).await?)
     }
 
     pub async fn count_medias(
@@ -690,6 +713,7 @@ impl ModelController {
         if let Some(existing) = existing {
             self.remove_library_file(library_id, media_id, requesting_user)
                 .await?;
+            self.remove_media_embeddings_cache(library_id).await;
             self.add_deleted(
                 library_id,
                 RsDeleted::media(media_id.to_owned()),
@@ -1046,7 +1070,44 @@ impl ModelController {
         }
         let mut reader_response = m.get_file(&existing.source, range.clone()).await?;
 
+        if existing.mime == PDF_MIME && !query.raw {
+            if let Some(page) = query.page {
+                let image = self
+                    .get_pdf_page(
+                        library_id,
+                        media_id,
+                        page as usize,
+                        &query.size.unwrap_or(ImageSize::Large),
+                        requesting_user,
+                    )
+                    .await?;
+                let size = image.len() as u64;
+                let async_reader: AsyncReadPinBox = Box::pin(std::io::Cursor::new(image));
+                return Ok(SourceRead::Stream(FileStreamResult {
+                    stream: async_reader,
+                    size: Some(size),
+                    accept_range: false,
+                    range: None,
+                    mime: Some("image/jpeg".to_string()),
+                    name: Some(format!("{}.jpg", page)),
+                    cleanup: None,
+                }));
+            }
+        }
+
         if existing.kind == FileType::Album && !query.raw && query.page.is_some() {
+            let archive = ComicArchive::from_mime(&existing.mime).unwrap_or(ComicArchive::Zip);
+            if archive != ComicArchive::Zip {
+                return self
+                    .comic_page(
+                        library_id,
+                        &existing.source,
+                        archive,
+                        query.page.unwrap_or(1) as usize,
+                        requesting_user,
+                    )
+                    .await;
+            }
             // For password-encrypted zips, we need to decrypt the full file first
             // since zip requires random access (seeking).
             if let Some(ref key) = encryption_key {
@@ -1297,11 +1358,16 @@ impl ModelController {
             let r = self
                 .update_video_infos(library_id, media_id, requesting_user, false)
                 .await;
-            if let Err(r) = r {
-                log_error(
+            match r {
+                Ok(_) => self.generate_media_trickplay_spawn(
+                    library_id.to_string(),
+                    media_id.to_string(),
+                    requesting_user.clone(),
+                ),
+                Err(r) => log_error(
                     LogServiceType::Source,
                     format!("unable to get video infos for {}: {:?}", media_id, r),
-                );
+                ),
             }
         } else if existing.kind == FileType::Photo {
             let r = self
@@ -1323,11 +1389,80 @@ impl ModelController {
                     format!("unable to get album infos for {}: {:?}", media_id, r),
                 );
             }
+        } else if existing.kind == FileType::Book && existing.mimetype == EPUB_MIME {
+            let r = self
+                .update_epub_infos(library_id, media_id, requesting_user, false)
+                .await;
+            if let Err(r) = r {
+                log_error(
+                    LogServiceType::Source,
+                    format!("unable to get epub infos for {}: {:?}", media_id, r),
+                );
+            }
+        } else if existing.mimetype == PDF_MIME {
+            let r = self
+                .update_pdf_infos(library_id, media_id, requesting_user, false)
+                .await;
+            if let Err(r) = r {
+                log_error(
+                    LogServiceType::Source,
+                    format!("unable to get pdf infos for {}: {:?}", media_id, r),
+                );
+            }
+        } else if is_audiobook_mime(&existing.mimetype)
+            && self.cache_get_library(library_id).await.map(|l| l.kind) == Some(LibraryType::Books)
+        {
+            let r = self
+                .update_audiobook_infos(library_id, media_id, requesting_user, false)
+                .await;
+            if let Err(r) = r {
+                log_error(
+                    LogServiceType::Source,
+                    format!("unable to get audiobook infos for {}: {:?}", media_id, r),
+                );
+            }
+        }
+        if existing.kind == FileType::Photo && self.is_ocr_enabled(library_id).await {
+            let r = self
+                .update_media_ocr(library_id, media_id, requesting_user)
+                .await;
+            if let Err(r) = r {
+                log_error(
+                    LogServiceType::Source,
+                    format!("unable to recognize text of {}: {:?}", media_id, r),
+                );
+            }
+        }
+        if matches!(existing.kind, FileType::Photo | FileType::Video) {
+            match self
+                .update_media_embedding(library_id, media_id, requesting_user)
+                .await
+            {
+                Ok(_) | Err(crate::Error::NoModelFound) => {}
+                Err(r) => log_error(
+                    LogServiceType::Source,
+                    format!(
+                        "unable to compute search embedding for {}: {:?}",
+                        media_id, r
+                    ),
+                ),
+            }
+        }
+        if matches!(existing.kind, FileType::Book | FileType::Album) {
+            let r = self
+                .update_media_kohash(library_id, media_id, requesting_user)
+                .await;
+            if let Err(r) = r {
+                log_error(
+                    LogServiceType::Source,
+                    format!("unable to get KOReader hash for {}: {:?}", media_id, r),
+                );
+            }
         }
 
         if predict {
             let prediction_result = self
-                .prediction(library_id, media_id, true, requesting_user, false)
+                .prediction(library_id, media_id, None, true, requesting_user, false)
                 .await;
             match prediction_result {
                 Ok(_) => Ok(()),
@@ -2258,8 +2393,7 @@ impl ModelController {
                         None,
                         MediaFileQuery {
                             page: Some(1),
-                            unsupported_mime: vec![],
-                            raw: false,
+                            ..Default::default()
                         },
                         requesting_user,
                     )
@@ -2296,30 +2430,26 @@ impl ModelController {
                     .await?;
                 Ok(th)
             }
-            FileType::Book => {
-                let media_source: MediaSource = media.try_into()?;
-                let reader = m.get_file(&media_source.source, None).await?;
-                let mut reader = reader
-                    .into_reader(
-                        Some(library_id),
-                        None,
-                        None,
-                        Some((self.clone(), &requesting_user)),
-                        None,
-                    )
+            FileType::Book if media.mimetype == PDF_MIME => {
+                let page = self
+                    .get_pdf_page(library_id, media_id, 1, &ImageSize::Small, requesting_user)
                     .await?;
-                // Decrypt after resolving to stream (plugin sources return Request, not Stream)
-                let stream: AsyncReadPinBox = if let Some(ref key) = encryption_key {
-                    Box::pin(CtrDecryptReader::new(reader.stream, key))
-                } else {
-                    reader.stream
-                };
-                let mut epub_bytes = Vec::new();
-                tokio::io::AsyncReadExt::read_to_end(
-                    &mut tokio::io::BufReader::new(stream),
-                    &mut epub_bytes,
+                let image_reader: AsyncReadPinBox = Box::pin(std::io::Cursor::new(page));
+                let image = resize_image_reader(
+                    image_reader,
+                    512,
+                    image::ImageFormat::Avif,
+                    Some(50),
+                    false,
                 )
                 .await?;
+                Ok(image)
+            }
+            FileType::Book => {
+                let media_source: MediaSource = media.try_into()?;
+                let epub_bytes = self
+                    .read_media_bytes(library_id, &media_source.source, requesting_user)
+                    .await?;
                 let cover_bytes = tokio::task::spawn_blocking(move || {
                     Self::extract_epub_cover_from_bytes(epub_bytes)
                 })
@@ -2336,6 +2466,19 @@ impl ModelController {
                 .await?;
                 Ok(image)
             }
+            _ if is_audiobook_mime(&media.mimetype) => {
+                let th = self
+                    .get_video_thumb(
+                        library_id,
+                        media_id,
+                        VideoTime::Seconds(0.0),
+                        image::ImageFormat::Avif,
+                        Some(50),
+                        requesting_user,
+                    )
+                    .await?;
+                Ok(th)
+            }
             _ => Err(crate::model::error::Error::UnsupportedTypeForThumb),
         }?;
         self.update_library_image(
@@ -2352,6 +2495,164 @@ impl ModelController {
         Ok(())
     }
 
+    /// Whole content of a media file, decrypted
+    async fn read_media_bytes(
+        &self,
+        library_id: &str,
+        source: &str,
+        requesting_user: &ConnectedUser,
+    ) -> crate::error::Result<Vec<u8>> {
+        let encryption_key = self.get_library_encryption_key(library_id).await;
+        let m = self.source_for_library(library_id).await?;
+        let reader = m.get_file(source, None).await?;
+        let reader = reader
+            .into_reader(
+                Some(library_id),
+                None,
+                None,
+                Some((self.clone(), requesting_user)),
+                None,
+            )
+            .await?;
+        // Decrypt after resolving to stream (plugin sources return Request, not Stream)
+        let stream: AsyncReadPinBox = if let Some(ref key) = encryption_key {
+            Box::pin(CtrDecryptReader::new(reader.stream, key))
+        } else {
+            reader.stream
+        };
+        let mut bytes = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut tokio::io::BufReader::new(stream), &mut bytes)
+            .await?;
+        Ok(bytes)
+    }
+
+    /// Local path of a media file, copied decrypted to a temporary file (flagged `true`)
+    /// when the source is encrypted or not local
+    pub(super) async fn local_media_path(
+        &self,
+        library_id: &str,
+        source: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<(PathBuf, bool)> {
+        let m = self.source_for_library(library_id).await?;
+        if self.get_library_encryption_key(library_id).await.is_none() {
+            if let Some(local_path) = m.local_path(source) {
+                return Ok((local_path, false));
+            }
+        }
+        let bytes = self
+            .read_media_bytes(library_id, source, requesting_user)
+            .await?;
+        let path = get_server_temp_file_path().await?;
+        tokio::fs::write(&path, bytes).await?;
+        Ok((path, true))
+    }
+
+    /// Archive and pages of a RAR or 7z comic, from the cache while it is being read
+    async fn comic_archive(
+        &self,
+        library_id: &str,
+        source: &str,
+        kind: ComicArchive,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<CachedComicArchive> {
+        let key = format!("{}:{}", library_id, source);
+        let (expired, cached): (Vec<CachedComicArchive>, _) = {
+            let mut archives = self.comic_archives.write().await;
+            let expired_keys: Vec<String> = archives
+                .iter()
+                .filter(|(k, a)| **k != key && a.last_used.elapsed() > COMIC_ARCHIVE_TTL)
+                .map(|(k, _)| k.clone())
+                .collect();
+            let expired = expired_keys
+                .iter()
+                .filter_map(|k| archives.remove(k))
+                .collect();
+            let cached = archives.get_mut(&key).map(|archive| {
+                archive.last_used = std::time::Instant::now();
+                archive.clone()
+            });
+            (expired, cached)
+        };
+        for archive in expired.into_iter().filter(|a| a.temporary) {
+            let _ = tokio::fs::remove_file(&archive.path).await;
+        }
+        if let Some(archive) = cached {
+            return Ok(archive);
+        }
+
+        let (path, temporary) = self
+            .local_media_path(library_id, source, requesting_user)
+            .await?;
+        let listed = async {
+            let kind = ComicArchive::detect_file(&path).await.unwrap_or(kind);
+            let pages = sort_pages(comic_entries(kind, &path).await?);
+            Ok::<_, RsError>((kind, pages))
+        }
+        .await;
+        let (kind, pages) = match listed {
+            Ok(listed) => listed,
+            Err(e) => {
+                if temporary {
+                    let _ = tokio::fs::remove_file(&path).await;
+                }
+                return Err(e);
+            }
+        };
+        let archive = CachedComicArchive {
+            path,
+            temporary,
+            kind,
+            pages,
+            last_used: std::time::Instant::now(),
+        };
+        let previous = self
+            .comic_archives
+            .write()
+            .await
+            .insert(key, archive.clone());
+        // Another request copied the same archive at the same time
+        if let Some(previous) = previous.filter(|p| p.temporary && p.path != archive.path) {
+            let _ = tokio::fs::remove_file(&previous.path).await;
+        }
+        Ok(archive)
+    }
+
+    /// Page (starting at 1) of a RAR or 7z comic, pages are in natural order of their names
+    async fn comic_page(
+        &self,
+        library_id: &str,
+        source: &str,
+        kind: ComicArchive,
+        page: usize,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<SourceRead> {
+        let archive = self
+            .comic_archive(library_id, source, kind, requesting_user)
+            .await?;
+        let name = archive
+            .pages
+            .get(page.saturating_sub(1))
+            .ok_or(RsError::Error(format!(
+                "Unable to get file at page {}. Pages in archive: {}",
+                page,
+                archive.pages.len()
+            )))?
+            .clone();
+        let data = comic_entry(archive.kind, &archive.path, &name).await?;
+        let size = data.len() as u64;
+        let async_reader: AsyncReadPinBox = Box::pin(std::io::Cursor::new(data));
+        Ok(SourceRead::Stream(FileStreamResult {
+            stream: async_reader,
+            size: Some(size),
+            accept_range: false,
+            range: None,
+            mime: None,
+            name: Some(filename_from_path(&name).unwrap_or(name)),
+            cleanup: None,
+        }))
+    }
+
     fn extract_epub_cover_from_bytes(bytes: Vec<u8>) -> crate::error::Result<Vec<u8>> {
         use std::io::Read as _;
         let cursor = std::io::Cursor::new(bytes);
@@ -2554,15 +2855,19 @@ impl ModelController {
             .await?;
 
         // Create and start the session
-        crate::tools::media_hls_session::start_media_hls_session(
+        let session = crate::tools::media_hls_session::start_media_hls_session(
             key.clone(),
             library_id.to_string(),
             media_id.to_string(),
+            requesting_user.user_id().ok(),
             &uri,
             convert_request,
             self.media_hls_sessions.clone(),
         )
         .await?;
+        if let Some(session) = session {
+            self.send_streaming_session(ElementAction::Added, session);
+        }
 
         Ok(key)
     }
@@ -2578,7 +2883,11 @@ impl ModelController {
         };
 
         for key in &keys_to_stop {
-            crate::tools::media_hls_session::stop_session(key, &self.media_hls_sessions).await;
+            if let Some(session) =
+                crate::tools::media_hls_session::stop_session(key, &self.media_hls_sessions).await
+            {
+                self.send_streaming_session(ElementAction::Deleted, session);
+            }
         }
         Ok(())
     }
@@ -2606,16 +2915,67 @@ impl ModelController {
         Ok(token)
     }
 
+    /// Images used to tag a media: the media image and, for videos, frames every 5%
+    async fn prediction_images(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> crate::Result<Vec<Vec<u8>>> {
+        let media = self
+            .get_media(library_id, media_id.to_string(), requesting_user)
+            .await?
+            .ok_or(SourcesError::UnableToFindMedia(
+                library_id.to_string(),
+                media_id.to_string(),
+                "prediction".to_string(),
+            ))?
+            .item;
+
+        let reader_response = self
+            .media_image(&library_id, &media_id, None, &requesting_user)
+            .await?;
+        let buffer =
+            convert_image_reader(reader_response.stream, image::ImageFormat::Png, None, true)
+                .await?;
+
+        let mut images = vec![buffer];
+        if media.kind == FileType::Video {
+            let percents: Vec<u32> = (5..=95).step_by(5).collect();
+            //let percents = vec![15];
+            for percent in percents {
+                let thumb = self
+                    .get_video_thumb(
+                        library_id,
+                        media_id,
+                        VideoTime::Percent(percent),
+                        image::ImageFormat::Png,
+                        Some(70),
+                        requesting_user,
+                    )
+                    .await?;
+                images.push(thumb);
+            }
+        }
+        Ok(images)
+    }
+
+    /// Tag a media with the image classification plugins and the tagging model of the
+    /// library. An explicit `model` only runs this registered tagging model.
+    /// Inserting tags replaces the AI tags previously generated for the media
     pub async fn prediction(
         &self,
         library_id: &str,
         media_id: &str,
+        model: Option<String>,
         insert_tags: bool,
         requesting_user: &ConnectedUser,
         notif: bool,
     ) -> crate::Result<Vec<PredictionTagResult>> {
-        let plugins = self
-            .get_plugins(
+        let plugins = if model.is_some() {
+            vec![]
+        } else {
+            self.get_plugins(
                 PluginQuery {
                     kind: Some(PluginType::ImageClassification),
                     library: Some(library_id.to_string()),
@@ -2623,87 +2983,73 @@ impl ModelController {
                 },
                 &ConnectedUser::ServerAdmin,
             )
-            .await?;
-        if !plugins.is_empty() {
-            let mut all_predictions: Vec<PredictionTagResult> = vec![];
-            let media = self
-                .get_media(library_id, media_id.to_string(), requesting_user)
-                .await?
-                .ok_or(SourcesError::UnableToFindMedia(
-                    library_id.to_string(),
-                    media_id.to_string(),
-                    "prediction".to_string(),
-                ))?
-                .item;
+            .await?
+        };
+        let tagging_model = self.library_tagging_model(library_id, model).await?;
+        if plugins.is_empty() && tagging_model.is_none() {
+            return Err(crate::Error::NoModelFound);
+        }
 
-            let mut reader_response = self
-                .media_image(&library_id, &media_id, None, &requesting_user)
+        let images = self
+            .prediction_images(library_id, media_id, requesting_user)
+            .await?;
+        let mut all_predictions: Vec<PredictionTagResult> = vec![];
+        for plugin in plugins.clone() {
+            let mut path = get_plugin_fodler().await?;
+            path.push(&plugin.path);
+            let mut model: ort::session::Session = preload_model(&path).await?;
+            for buffer in &images {
+                let mut prediction = predict_net(
+                    path.clone(),
+                    plugin.settings.bgr.unwrap_or(false),
+                    plugin.settings.normalize.unwrap_or(false),
+                    buffer.clone(),
+                    Some(&mut model),
+                )
                 .await?;
-            let buffer =
-                convert_image_reader(reader_response.stream, image::ImageFormat::Png, None, true)
-                    .await?;
+                all_predictions.append(&mut prediction);
+            }
+        }
+        if let Some(tagging_model) = &tagging_model {
+            let mut prediction = self
+                .predict_with_tagging_model(tagging_model, images)
+                .await?;
+            all_predictions.append(&mut prediction);
+        }
+        all_predictions.sort_by(|a, b| b.probability.total_cmp(&a.probability));
 
-            let mut images = vec![buffer];
-            if media.kind == FileType::Video {
-                let percents: Vec<u32> = (5..=95).step_by(5).collect();
-                //let percents = vec![15];
-                for percent in percents {
-                    let thumb = self
-                        .get_video_thumb(
-                            library_id,
-                            media_id,
-                            VideoTime::Percent(percent),
-                            image::ImageFormat::Png,
-                            Some(70),
-                            requesting_user,
-                        )
-                        .await?;
-                    images.push(thumb);
+        if insert_tags {
+            let store = self.store.get_library_store(library_id)?;
+            store
+                .remove_media_generated_tags(media_id.to_string())
+                .await?;
+            let mut inserted: Vec<String> = vec![];
+            for tag in &all_predictions {
+                if inserted.contains(&tag.tag.id) {
+                    continue;
                 }
-            }
-            for plugin in plugins.clone() {
-                let mut path = get_plugin_fodler().await?;
-                path.push(&plugin.path);
-                let mut model: ort::session::Session = preload_model(&path).await?;
-                for buffer in &images {
-                    let mut prediction = predict_net(
-                        path.clone(),
-                        plugin.settings.bgr.unwrap_or(false),
-                        plugin.settings.normalize.unwrap_or(false),
-                        buffer.clone(),
-                        Some(&mut model),
-                    )
+                inserted.push(tag.tag.id.clone());
+                let db_tag = self
+                    .get_ai_tag(&library_id, tag.tag.clone(), &requesting_user)
                     .await?;
-                    prediction.sort_by(|a, b| b.probability.partial_cmp(&a.probability).unwrap());
-                    if insert_tags {
-                        for tag in &prediction {
-                            let db_tag = self
-                                .get_ai_tag(&library_id, tag.tag.clone(), &requesting_user)
-                                .await?;
-                            self.update_media(
-                                &library_id,
-                                media_id.to_string(),
-                                MediaForUpdate {
-                                    add_tags: Some(vec![MediaItemReference {
-                                        id: db_tag.id,
-                                        conf: Some(tag.probability as u16),
-                                    }]),
-                                    ..Default::default()
-                                },
-                                notif,
-                                &requesting_user,
-                            )
-                            .await?;
-                        }
-                    }
-                    all_predictions.append(&mut prediction);
-                }
+                self.update_media(
+                    &library_id,
+                    media_id.to_string(),
+                    MediaForUpdate {
+                        add_tags: Some(vec![MediaItemReference {
+                            id: db_tag.id,
+                            conf: Some(tag.probability as u16),
+                        }]),
+                        ..Default::default()
+                    },
+                    notif,
+                    &requesting_user,
+                )
+                .await?;
             }
-
-            Ok(all_predictions)
-        } else {
-            Err(crate::Error::NoModelFound)
         }
+
+        Ok(all_predictions)
     }
 
     pub async fn convert(
@@ -3394,6 +3740,10 @@ impl ModelController {
         }
         let progress_id = element.request.id.clone();
         video_builder.set_request(element.request.clone()).await?;
+        let chapters = store.get_media_chapters(element.media.clone()).await?;
+        if !chapters.is_empty() {
+            video_builder.set_chapters(&chapters).await?;
+        }
         video_builder.run_file(dest.to_str().unwrap()).await?;
         let message = ConvertMessage {
             library: element.library.to_string(),
@@ -3790,10 +4140,65 @@ impl ModelController {
         )
         .await?;
 
+        self.import_media_chapters(library_id, media_id, videos_infos.media_chapters())
+            .await?;
+        let store = self.store.get_library_store(library_id)?;
+        store
+            .set_media_hdr(media_id.to_string(), videos_infos.hdr_format())
+            .await?;
+
         //println!("videos infos {:?}", videos_infos);
         Ok(())
     }
 
+    pub async fn get_media_hdr(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> crate::Result<Option<HdrFormat>> {
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Read)?;
+        let store = self.store.get_library_store(library_id)?;
+        Ok(store.get_media_hdr(media_id.to_string()).await?)
+    }
+
+    /// HDR format of the listed medias, keyed by media id
+    pub async fn get_medias_hdr(
+        &self,
+        library_id: &str,
+        media_ids: Vec<String>,
+        requesting_user: &ConnectedUser,
+    ) -> crate::Result<HashMap<String, HdrFormat>> {
+        if requesting_user.check_upload_key(library_id).is_err() {
+            requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        }
+        let store = self.store.get_library_store(library_id)?;
+        Ok(store.get_medias_hdr(media_ids).await?)
+    }
+
+    /// Probe a video again only to detect its HDR format
+    pub async fn update_media_hdr(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> crate::Result<()> {
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Write)?;
+        let uri = self.get_media_uri(library_id, media_id, Some(240)).await?;
+        let videos_infos = probe_video(&uri).await?;
+        let store = self.store.get_library_store(library_id)?;
+        store
+            .set_media_hdr(media_id.to_string(), videos_infos.hdr_format())
+            .await?;
+        Ok(())
+    }
+
+    /// Ids of the videos of a library never probed for HDR
+    pub async fn get_medias_without_hdr_probe(&self, library_id: &str) -> RsResult<Vec<String>> {
+        let store = self.store.get_library_store(library_id)?;
+        Ok(store.get_media_ids_without_hdr_probe().await?)
+    }
+
     pub async fn update_file_infos(
         &self,
         library_id: &str,
@@ -3896,26 +4301,200 @@ impl ModelController {
                 existing.source.clone().unwrap_or("unknown".to_string()),
                 "update_album_infos".to_string(),
             ))?;
-        let local_path = m.local_path(&source);
-        if let Some(local_path) = local_path {
-            let mut update = MediaForUpdate::default();
-            let archive = std::fs::File::open(local_path)?;
-            let buffreader = std::io::BufReader::new(archive);
+        let kind = ComicArchive::from_mime(&existing.mimetype).unwrap_or(ComicArchive::Zip);
+        if kind == ComicArchive::Zip && m.local_path(&source).is_none() {
+            return Ok(());
+        }
+        let (path, temporary) = self
+            .local_media_path(library_id, &source, requesting_user)
+            .await?;
+        let result = self
+            .update_comic_infos(library_id, &existing, &path, kind, requesting_user, notif)
+            .await;
+        if temporary {
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+        result
+    }
 
-            let mut archive = zip::ZipArchive::new(buffreader)?;
+    /// Page count, `ComicInfo.xml` metadata and book link of a comic archive
+    async fn update_comic_infos(
+        &self,
+        library_id: &str,
+        existing: &Media,
+        path: &std::path::Path,
+        kind: ComicArchive,
+        requesting_user: &ConnectedUser,
+        notif: bool,
+    ) -> crate::Result<()> {
+        let kind = ComicArchive::detect_file(path).await.unwrap_or(kind);
+        let entries = comic_entries(kind, path).await?;
+        let comic_info = entries.iter().find(|name| is_comic_info(name)).cloned();
+        // Zip pages are read by entry index
+        let pages = match kind {
+            ComicArchive::Zip => entries,
+            _ => sort_pages(entries),
+        };
+        let mut update = MediaForUpdate {
+            pages: Some(pages.len()),
+            ..Default::default()
+        };
 
-            update.pages = Some(archive.len());
+        if let Some(comic_info) = comic_info {
+            let content = comic_entry(kind, path, &comic_info).await?;
+            let info = parse_comic_info(&String::from_utf8_lossy(&content));
+            if existing.description.is_none() {
+                update.description = info.summary.clone();
+            }
+            if existing.lang.is_none() {
+                update.lang = info.language.clone();
+            }
+            if let Some(direction) = info.direction {
+                self.store
+                    .get_library_store(library_id)?
+                    .set_media_param(&existing.id, "direction", direction.as_str())
+                    .await?;
+            }
+            if existing.book.is_none() {
+                if let Some((book, created)) = self
+                    .get_or_add_book_from_metadata(
+                        library_id,
+                        &info.book_metadata(),
+                        requesting_user,
+                    )
+                    .await?
+                {
+                    if created {
+                        let cover = match sort_pages(pages).first() {
+                            Some(first) => comic_entry(kind, path, first).await,
+                            None => Err(RsError::Error("No page in comic".to_string())),
+                        };
+                        let r = match cover {
+                            Ok(cover) => {
+                                self.update_book_image(
+                                    library_id,
+                                    &book.id,
+                                    &ImageType::Poster,
+                                    Box::pin(std::io::Cursor::new(cover)),
+                                    requesting_user,
+                                )
+                                .await
+                            }
+                            Err(e) => Err(e),
+                        };
+                        if let Err(r) = r {
+                            log_error(
+                                LogServiceType::Source,
+                                format!("unable to set cover of book {}: {:?}", book.id, r),
+                            );
+                        }
+                    }
+                    update.book = Some(book.id);
+                }
+            }
+        }
 
-            self.update_media(
-                library_id,
-                media_id.to_owned(),
-                update,
-                notif,
-                requesting_user,
-            )
+        self.update_media(
+            library_id,
+            existing.id.clone(),
+            update,
+            notif,
+            requesting_user,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Fill the media from the EPUB package metadata and link it to a matching or new book
+    pub async fn update_epub_infos(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        requesting_user: &ConnectedUser,
+        notif: bool,
+    ) -> crate::Result<()> {
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Read)?;
+        self.cache_check_library_notcrypt(library_id).await?;
+        let existing = self
+            .get_media(library_id, media_id.to_string(), requesting_user)
+            .await?
+            .ok_or(SourcesError::UnableToFindMedia(
+                library_id.to_string(),
+                media_id.to_string(),
+                "update_epub_infos".to_string(),
+            ))?
+            .item;
+        let source: MediaSource = existing.clone().try_into()?;
+        let bytes = self
+            .read_media_bytes(library_id, &source.source, requesting_user)
             .await?;
+        let (metadata, bytes) = tokio::task::spawn_blocking(move || {
+            let metadata = read_epub_metadata(std::io::Cursor::new(&bytes));
+            (metadata, bytes)
+        })
+        .await
+        .map_err(|e| RsError::Error(format!("Unable to read epub metadata: {}", e)))?;
+        let metadata = metadata?;
+
+        // Only replace the filename given on import, not a name set by a user. Keep the
+        // extension, the name is used as download filename
+        let mut update = MediaForUpdate {
+            name: metadata
+                .title
+                .as_ref()
+                .filter(|_| is_imported_name(&existing.name, &source.source))
+                .map(|title| match existing.name.rsplit_once('.') {
+                    Some((_, extension)) => format!("{}.{}", title, extension),
+                    None => title.clone(),
+                }),
+            ..Default::default()
+        };
+        if existing.description.is_none() {
+            update.description = metadata.description.clone();
+        }
+        if existing.lang.is_none() {
+            update.lang = metadata.language.clone();
+        }
+        if existing.book.is_none() {
+            if let Some((book, created)) = self
+                .get_or_add_book_from_metadata(library_id, &metadata, requesting_user)
+                .await?
+            {
+                if created {
+                    let cover = tokio::task::spawn_blocking(move || {
+                        Self::extract_epub_cover_from_bytes(bytes)
+                    })
+                    .await;
+                    if let Ok(Ok(cover)) = cover {
+                        let r = self
+                            .update_book_image(
+                                library_id,
+                                &book.id,
+                                &ImageType::Poster,
+                                Box::pin(std::io::Cursor::new(cover)),
+                                requesting_user,
+                            )
+                            .await;
+                        if let Err(r) = r {
+                            log_error(
+                                LogServiceType::Source,
+                                format!("unable to set cover of book {}: {:?}", book.id, r),
+                            );
+                        }
+                    }
+                }
+                update.book = Some(book.id);
+            }
         }
 
+        self.update_media(
+            library_id,
+            media_id.to_owned(),
+            update,
+            notif,
+            requesting_user,
+        )
+        .await?;
         Ok(())
     }
 
@@ -3959,6 +4538,25 @@ impl ModelController {
         )
         .await?;
 
+        if let Err(e) = self.remove_media_trickplay(library_id, media_id).await {
+            log_info(
+                crate::tools::log::LogServiceType::Other,
+                format!("Failed to delete trickplay of {}: {}", media_id, e),
+            );
+        }
+        if let Err(e) = self.remove_media_chapter_images(library_id, media_id).await {
+            log_info(
+                crate::tools::log::LogServiceType::Other,
+                format!("Failed to delete chapter images of {}: {}", media_id, e),
+            );
+        }
+        if let Err(e) = self.remove_media_pdf_pages(library_id, media_id).await {
+            log_info(
+                crate::tools::log::LogServiceType::Other,
+                format!("Failed to delete pdf pages of {}: {}", media_id, e),
+            );
+        }
+
         // Delete cached face images (ignore errors - cache cleanup is best effort)
         for face_id in face_ids {
             if let Err(e) = self
@@ -7,25 +7,42 @@ pub mod server;
 pub mod store;
 pub mod users;
 
+pub mod audiobooks;
 pub mod books;
 pub mod channels;
 pub mod deleted;
+pub mod dvr;
 pub mod entity_images;
 pub mod entity_search;
+pub mod epg;
 pub mod episodes;
+pub mod face_index;
+pub mod hdhomerun;
+pub mod kosync;
+pub mod media_chapters;
+pub mod media_markers;
 pub mod media_progresses;
 pub mod media_ratings;
 pub mod medias;
 pub mod movies;
+pub mod ocr;
+pub mod opds;
+pub mod pdf;
 pub mod people;
+pub mod semantic;
+pub mod serie_gaps;
 pub mod series;
+pub mod streaming_sessions;
+pub mod tagging_models;
 pub mod tags;
+pub mod trickplay;
 
 use crate::{
     domain::{
         backup::BackupProcessStatus,
         library::{LibraryMessage, LibraryRole, LibraryStatusMessage, ServerLibrary},
         media::ConvertProgress,
+        ElementAction,
     },
     error::{RsError, RsResult},
     plugins::{
@@ -42,9 +59,12 @@ use crate::{
         image_tools::{resize_image_reader, ImageSize},
         log::log_info,
         scheduler::{
-            self, face_recognition::FaceRecognitionTask, ip::RefreshIpTask,
-            iptv_refresh::IptvRefreshTask, refresh::RefreshTask,
-            request_progress::RequestProgressTask, RsScheduler, RsTaskType,
+            self, dvr::DvrTask, face_recognition::FaceRecognitionTask,
+            face_reembed::FaceReembedTask, hdr::HdrProbeTask, ip::RefreshIpTask,
+            iptv_health::IptvHealthTask, iptv_refresh::IptvRefreshTask, kosync::KosyncHashTask,
+            media_markers::MediaMarkersTask, ocr::OcrTask, refresh::RefreshTask,
+            request_progress::RequestProgressTask, semantic::SemanticEmbeddingTask,
+            trickplay::TrickplayTask, RsScheduler, RsTaskType,
         },
     },
 };
@@ -151,6 +171,22 @@ pub struct ModelController {
     /// Media HLS sessions: key = "library:media:convert_hash"
     pub media_hls_sessions:
         Arc<RwLock<HashMap<String, crate::tools::media_hls_session::MediaHlsSession>>>,
+
+    /// Running DVR recordings: recording_id → cancellation token of the ffmpeg process
+    pub dvr_recordings: Arc<RwLock<HashMap<String, tokio_util::sync::CancellationToken>>>,
+
+    /// Stops the HDHomeRun discovery listener, running while a library enables discovery
+    pub hdhr_discovery: Arc<RwLock<Option<tokio_util::sync::CancellationToken>>>,
+
+    /// Face embedding indexes loaded per library
+    pub face_indexes: Arc<RwLock<HashMap<String, face_index::SharedFaceIndex>>>,
+
+    /// Image embeddings searched per library: library_id → model and embeddings
+    pub media_embeddings: Arc<RwLock<HashMap<String, (String, semantic::SharedMediaEmbeddings)>>>,
+
+    /// RAR and 7z comics being read: "library:source" → archive and pages
+    pub comic_archives:
+        Arc<RwLock<HashMap<String, crate::tools::comic_archive::CachedComicArchive>>>,
 }
 
 // Constructor
@@ -182,6 +218,15 @@ impl ModelController {
             active_streams: Arc::new(RwLock::new(HashMap::new())),
 
             media_hls_sessions: Arc::new(RwLock::new(HashMap::new())),
+
+            dvr_recordings: Arc::new(RwLock::new(HashMap::new())),
+
+            hdhr_discovery: Arc::new(RwLock::new(None)),
+
+            face_indexes: Arc::new(RwLock::new(HashMap::new())),
+            media_embeddings: Arc::new(RwLock::new(HashMap::new())),
+
+            comic_archives: Arc::new(RwLock::new(HashMap::new())),
         };
 
         let pm_forload = mc.plugin_manager.clone();
@@ -226,6 +271,87 @@ impl ModelController {
                 },
             )
             .await?;
+        scheduler
+            .add(
+                RsTaskType::Trickplay,
+                scheduler::RsSchedulerWhen::Every(SECONDS_IN_HOUR * 12),
+                TrickplayTask {
+                    specific_library: None,
+                },
+            )
+            .await?;
+        scheduler
+            .add(
+                RsTaskType::MediaMarkers,
+                scheduler::RsSchedulerWhen::Every(SECONDS_IN_HOUR * 24),
+                MediaMarkersTask {
+                    specific_library: None,
+                    specific_season: None,
+                },
+            )
+            .await?;
+        scheduler
+            .add(
+                RsTaskType::Dvr,
+                scheduler::RsSchedulerWhen::Every(30),
+                DvrTask {},
+            )
+            .await?;
+        scheduler
+            .add(
+                RsTaskType::IptvHealth,
+                scheduler::RsSchedulerWhen::Every(SECONDS_IN_HOUR * 6),
+                IptvHealthTask {
+                    specific_library: None,
+                },
+            )
+            .await?;
+        scheduler
+            .add(
+                RsTaskType::KosyncHash,
+                scheduler::RsSchedulerWhen::Every(SECONDS_IN_HOUR * 12),
+                KosyncHashTask {
+                    specific_library: None,
+                },
+            )
+            .await?;
+        scheduler
+            .add(
+                RsTaskType::SemanticEmbedding,
+                scheduler::RsSchedulerWhen::Every(SECONDS_IN_HOUR * 12),
+                SemanticEmbeddingTask {
+                    specific_library: None,
+                },
+            )
+            .await?;
+        scheduler
+            .add(
+                RsTaskType::Ocr,
+                scheduler::RsSchedulerWhen::Every(SECONDS_IN_HOUR * 12),
+                OcrTask {
+                    specific_library: None,
+                },
+            )
+            .await?;
+        // Only loads the face models when faces of an older model are left
+        scheduler
+            .add(
+                RsTaskType::FaceReembed,
+                scheduler::RsSchedulerWhen::Every(SECONDS_IN_HOUR * 24),
+                FaceReembedTask {
+                    specific_library: None,
+                },
+            )
+            .await?;
+        scheduler
+            .add(
+                RsTaskType::HdrProbe,
+                scheduler::RsSchedulerWhen::Every(SECONDS_IN_HOUR * 12),
+                HdrProbeTask {
+                    specific_library: None,
+                },
+            )
+            .await?;
         //scheduler.add(RsTaskType::Face, scheduler::RsSchedulerWhen::Every(SECONDS_IN_HOUR * 3), FaceRecognitionTask {specific_library:None} ).await?;
         //scheduler.add(RsTaskType::Refresh, scheduler::RsSchedulerWhen::At(0), RefreshTask {specific_library:None} ).await?;
         //scheduler.tick(mc.clone()).await;
@@ -256,10 +382,13 @@ impl ModelController {
         tokio::spawn(async move {
             loop {
                 tokio::time::sleep(std::time::Duration::from_secs(30)).await;
-                crate::tools::media_hls_session::cleanup_stale_sessions(
+                let removed = crate::tools::media_hls_session::cleanup_stale_sessions(
                     &mc_media_cleanup.media_hls_sessions,
                 )
                 .await;
+                for session in removed {
+                    mc_media_cleanup.send_streaming_session(ElementAction::Deleted, session);
+                }
             }
         });
 
@@ -295,13 +424,19 @@ impl ModelController {
     }
 
     pub async fn cache_update_library(&self, library: ServerLibrary) {
-        let mut cache = self.chache_libraries.write().await;
-        cache.remove(&library.id);
-        cache.insert(library.id.clone(), library);
+        {
+            let mut cache = self.chache_libraries.write().await;
+            cache.remove(&library.id);
+            cache.insert(library.id.clone(), library);
+        }
+        self.update_hdhr_discovery().await;
     }
     pub async fn cache_remove_library(&self, library: &str) {
-        let mut cache = self.chache_libraries.write().await;
-        cache.remove(library);
+        {
+            let mut cache = self.chache_libraries.write().await;
+            cache.remove(library);
+        }
+        self.update_hdhr_discovery().await;
     }
     pub async fn cache_update_all_libraries(&self) -> RsResult<()> {
         let libraries = self.store.get_libraries().await?;
@@ -0,0 +1,87 @@
+use crate::{
+    domain::library::LibraryRole,
+    error::{RsError, RsResult},
+    tools::{
+        image_tools::convert_image_reader,
+        ocr::{has_ocr_engine, recognize_text},
+    },
+};
+
+use super::{medias::MediaFileQuery, users::ConnectedUser, ModelController};
+
+impl ModelController {
+    /// OCR is enabled in the library settings and an engine is installed
+    pub async fn is_ocr_enabled(&self, library_id: &str) -> bool {
+        has_ocr_engine()
+            && self
+                .cache_get_library(library_id)
+                .await
+                .and_then(|l| l.settings.ocr)
+                .unwrap_or(false)
+    }
+
+    pub async fn get_media_ocr(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Option<String>> {
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Read)?;
+        let store = self.store.get_library_store(library_id)?;
+        Ok(store.get_media_ocr(media_id.to_string()).await?)
+    }
+
+    /// Recognize and store the text of a photo
+    pub async fn update_media_ocr(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Option<String>> {
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Read)?;
+        self.cache_check_library_notcrypt(library_id).await?;
+        if !self.is_ocr_enabled(library_id).await {
+            return Err(RsError::Error(format!(
+                "OCR is not enabled for library {}",
+                library_id
+            )));
+        }
+        let languages = self
+            .cache_get_library(library_id)
+            .await
+            .and_then(|l| l.settings.ocr_languages);
+
+        let reader = self
+            .library_file(
+                library_id,
+                media_id,
+                None,
+                MediaFileQuery::default(),
+                requesting_user,
+            )
+            .await?
+            .into_reader(
+                Some(library_id),
+                None,
+                None,
+                Some((self.clone(), requesting_user)),
+                None,
+            )
+            .await?;
+        let image =
+            convert_image_reader(reader.stream, image::ImageFormat::Png, None, true).await?;
+        let text = recognize_text(image, languages.as_deref()).await?;
+
+        let store = self.store.get_library_store(library_id)?;
+        store
+            .set_media_ocr(media_id.to_string(), text.clone())
+            .await?;
+        Ok(text)
+    }
+
+    /// Photos of the library never processed by OCR
+    pub async fn get_medias_without_ocr(&self, library_id: &str) -> RsResult<Vec<String>> {
+        let store = self.store.get_library_store(library_id)?;
+        Ok(store.get_medias_without_ocr().await?)
+    }
+}
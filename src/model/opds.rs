@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+
+use rs_plugin_common_interfaces::domain::ItemWithRelations;
+
+use crate::{
+    domain::{
+        book::Book,
+        library::{LibraryRole, LibraryType, ServerLibrary},
+    },
+    error::RsResult,
+    tools::{
+        auth::{sign_local, ClaimsLocal, ClaimsLocalType, ShareTokenKind},
+        clock::now,
+        opds::{
+            opensearch_description, OpdsFeed, OpdsLink, OpdsNavigation, OpdsPublication, OpdsUrls,
+            ACQUISITION_REL, OPDS_PAGE_SIZE,
+        },
+    },
+};
+
+use super::{
+    books::{BookQuery, RsBookSort},
+    error::Error,
+    medias::MediaQuery,
+    series::SerieQuery,
+    store::sql::SqlOrder,
+    users::ConnectedUser,
+    ModelController,
+};
+
+/// Validity of the tokens given to e-reader apps
+const OPDS_TOKEN_SECONDS: u64 = 365 * 24 * 60 * 60;
+
+/// Publication lists of a catalog
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpdsBookList {
+    Recent,
+    Alphabetical,
+    Serie(String),
+    Search(String),
+}
+
+impl ModelController {
+    /// Token to connect an e-reader app, as `sharetoken` or HTTP Basic password.
+    /// Its id is stored so it can be revoked
+    pub async fn get_opds_token(
+        &self,
+        library_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<String> {
+        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        self.get_opds_library(library_id).await?;
+        let user_id = requesting_user.user_id()?;
+        let token_id = self
+            .get_share_token_id(ShareTokenKind::Books, library_id, &user_id)
+            .await?;
+        let claims = ClaimsLocal {
+            cr: "service::opds".to_string(),
+            kind: ClaimsLocalType::Books(library_id.to_string(), user_id, token_id),
+            exp: ClaimsLocal::generate_seconds(OPDS_TOKEN_SECONDS),
+        };
+        let token = sign_local(claims)
+            .await
+            .map_err(|_| Error::UnableToSignShareToken)?;
+        Ok(token)
+    }
+
+    /// Revoke the OPDS token of the requesting user, e-reader apps must be set up again
+    pub async fn revoke_opds_token(
+        &self,
+        library_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<()> {
+        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        let user_id = requesting_user.user_id()?;
+        self.store
+            .remove_share_token_id(ShareTokenKind::Books, library_id.to_string(), user_id)
+            .await?;
+        Ok(())
+    }
+
+    /// Start feed of a catalog
+    pub async fn get_opds_root(
+        &self,
+        urls: &OpdsUrls,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<OpdsFeed> {
+        requesting_user.check_library_role(&urls.library_id, LibraryRole::Read)?;
+        let library = self.get_opds_library(&urls.library_id).await?;
+        let navigation = vec![
+            OpdsNavigation {
+                id: format!("urn:redseat:{}:recent", library.id),
+                title: "Recently added".to_string(),
+                href: urls.feed("recent"),
+                content: Some("Latest books added to the library".to_string()),
+                acquisition: true,
+            },
+            OpdsNavigation {
+                id: format!("urn:redseat:{}:books", library.id),
+                title: "All books".to_string(),
+                href: urls.feed("books"),
+                content: Some("Books by title".to_string()),
+                acquisition: true,
+            },
+            OpdsNavigation {
+                id: format!("urn:redseat:{}:series", library.id),
+                title: "Series".to_string(),
+                href: urls.feed("series"),
+                content: Some("Books by series".to_string()),
+                acquisition: false,
+            },
+        ];
+        Ok(OpdsFeed {
+            id: format!("urn:redseat:{}", library.id),
+            title: library.name,
+            updated: now().timestamp_millis(),
+            self_href: urls.feed(""),
+            start_href: urls.feed(""),
+            search_href: Some(urls.search()),
+            navigation,
+            ..Default::default()
+        })
+    }
+
+    /// Acquisition feed of a page (starting at 1) of books
+    pub async fn get_opds_books(
+        &self,
+        urls: &OpdsUrls,
+        list: OpdsBookList,
+        page: usize,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<OpdsFeed> {
+        requesting_user.check_library_role(&urls.library_id, LibraryRole::Read)?;
+        let library = self.get_opds_library(&urls.library_id).await?;
+        let store = self.store.get_library_store(&urls.library_id)?;
+        let series: HashMap<String, String> = store
+            .get_series(SerieQuery::new_empty())
+            .await?
+            .into_iter()
+            .map(|serie| (serie.item.id, serie.item.name))
+            .collect();
+
+        let (path, title, query) = match &list {
+            OpdsBookList::Recent => (
+                "recent".to_string(),
+                "Recently added".to_string(),
+                BookQuery {
+                    sort: RsBookSort::Added,
+                    order: SqlOrder::DESC,
+                    ..Default::default()
+                },
+            ),
+            OpdsBookList::Alphabetical => (
+                "books".to_string(),
+                "All books".to_string(),
+                BookQuery {
+                    sort: RsBookSort::Name,
+                    order: SqlOrder::ASC,
+                    ..Default::default()
+                },
+            ),
+            OpdsBookList::Serie(serie_id) => (
+                format!("series/{}", serie_id),
+                series
+                    .get(serie_id)
+                    .cloned()
+                    .ok_or(crate::Error::NotFound(format!(
+                        "Serie {} not found",
+                        serie_id
+                    )))?,
+                BookQuery {
+                    serie_ref: Some(serie_id.clone()),
+                    sort: RsBookSort::Volume,
+                    order: SqlOrder::ASC,
+                    ..Default::default()
+                },
+            ),
+            OpdsBookList::Search(search) => (
+                format!("search?query={}", urlencoding::encode(search)),
+                format!("Search: {}", search),
+                BookQuery {
+                    name: Some(search.clone()),
+                    sort: RsBookSort::Name,
+                    order: SqlOrder::ASC,
+                    ..Default::default()
+                },
+            ),
+        };
+        let books = store.get_books(query).await?;
+        let (books, next_href, previous_href) = paginate(urls, &path, books, page);
+
+        let mut authors: HashMap<String, Option<String>> = HashMap::new();
+        let mut publications = vec![];
+        for book in books {
+            publications.push(
+                self.opds_publication(urls, book, &series, &mut authors, requesting_user)
+                    .await?,
+            );
+        }
+        Ok(OpdsFeed {
+            id: format!("urn:redseat:{}:{}", library.id, path),
+            title: format!("{} - {}", library.name, title),
+            updated: publications
+                .iter()
+                .map(|p| p.updated)
+                .max()
+                .unwrap_or_else(|| now().timestamp_millis()),
+            self_href: page_href(urls, &path, page),
+            start_href: urls.feed(""),
+            search_href: Some(urls.search()),
+            next_href,
+            previous_href,
+            publications,
+            ..Default::default()
+        })
+    }
+
+    /// Navigation feed of a page of the series that have books
+    pub async fn get_opds_series(
+        &self,
+        urls: &OpdsUrls,
+        page: usize,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<OpdsFeed> {
+        requesting_user.check_library_role(&urls.library_id, LibraryRole::Read)?;
+        let library = self.get_opds_library(&urls.library_id).await?;
+        let store = self.store.get_library_store(&urls.library_id)?;
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for book in store.get_books(BookQuery::default()).await? {
+            if let Some(serie_ref) = book.item.serie_ref {
+                *counts.entry(serie_ref).or_default() += 1;
+            }
+        }
+        let mut series: Vec<_> = store
+            .get_series(SerieQuery::new_empty())
+            .await?
+            .into_iter()
+            .filter_map(|serie| {
+                counts
+                    .get(&serie.item.id)
+                    .map(|count| (serie.item.id, serie.item.name, *count))
+            })
+            .collect();
+        series.sort_by_key(|(_, name, _)| name.to_lowercase());
+
+        let (series, next_href, previous_href) = paginate(urls, "series", series, page);
+        let navigation = series
+            .into_iter()
+            .map(|(id, name, count)| OpdsNavigation {
+                id: format!("urn:redseat:{}:series:{}", library.id, id),
+                href: urls.feed(&format!("series/{}", id)),
+                title: name,
+                content: Some(match count {
+                    1 => "1 book".to_string(),
+                    count => format!("{} books", count),
+                }),
+                acquisition: true,
+            })
+            .collect();
+        Ok(OpdsFeed {
+            id: format!("urn:redseat:{}:series", library.id),
+            title: format!("{} - Series", library.name),
+            updated: now().timestamp_millis(),
+            self_href: page_href(urls, "series", page),
+            start_href: urls.feed(""),
+            search_href: Some(urls.search()),
+            next_href,
+            previous_href,
+            navigation,
+            ..Default::default()
+        })
+    }
+
+    /// OpenSearch description of a catalog (OPDS 1.2)
+    pub async fn get_opds_opensearch(
+        &self,
+        urls: &OpdsUrls,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<String> {
+        requesting_user.check_library_role(&urls.library_id, LibraryRole::Read)?;
+        let library = self.get_opds_library(&urls.library_id).await?;
+        Ok(opensearch_description(
+            &library.name,
+            &urls.opensearch_template(),
+        ))
+    }
+
+    async fn get_opds_library(&self, library_id: &str) -> RsResult<ServerLibrary> {
+        let library = self
+            .cache_get_library(library_id)
+            .await
+            .ok_or(crate::Error::NotFound(format!(
+                "Library {} not found",
+                library_id
+            )))?;
+        if library.kind != LibraryType::Books {
+            return Err(crate::Error::Error(
+                "OPDS catalogs are only available for Books libraries".to_string(),
+            ));
+        }
+        Ok(library)
+    }
+
+    async fn opds_publication(
+        &self,
+        urls: &OpdsUrls,
+        book: ItemWithRelations<Book>,
+        series: &HashMap<String, String>,
+        authors: &mut HashMap<String, Option<String>>,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<OpdsPublication> {
+        let store = self.store.get_library_store(&urls.library_id)?;
+        let mut names = vec![];
+        for person in book
+            .relations
+            .and_then(|relations| relations.people)
+            .unwrap_or_default()
+        {
+            if !authors.contains_key(&person.id) {
+                let name = store.get_person(&person.id).await?.map(|p| p.name);
+                authors.insert(person.id.clone(), name);
+            }
+            if let Some(Some(name)) = authors.get(&person.id) {
+                names.push(name.clone());
+            }
+        }
+
+        let book = book.item;
+        let medias = self
+            .get_medias(
+                &urls.library_id,
+                MediaQuery {
+                    book: Some(book.id.clone()),
+                    ..Default::default()
+                },
+                requesting_user,
+            )
+            .await?;
+        let acquisitions = medias
+            .into_iter()
+            .map(|media| media.item)
+            .filter(|media| !media.mimetype.starts_with("image/"))
+            .map(|media| OpdsLink {
+                title: Some(media.name),
+                length: media.size,
+                ..OpdsLink::new(
+                    ACQUISITION_REL,
+                    urls.library(&format!("medias/{}", media.id)),
+                    &media.mimetype,
+                )
+            })
+            .collect();
+
+        Ok(OpdsPublication {
+            id: format!("urn:redseat:{}:book:{}", urls.library_id, book.id),
+            updated: book.modified as i64,
+            summary: book.overview,
+            language: book.lang,
+            issued: book.year.map(|year| year.to_string()),
+            isbn: book.isbn13,
+            authors: names,
+            series: book
+                .serie_ref
+                .as_ref()
+                .and_then(|serie| series.get(serie).cloned()),
+            position: book.volume,
+            cover: Some(urls.library(&format!("books/{}/image", book.id))),
+            thumbnail: Some(urls.library(&format!("books/{}/image?size=thumb", book.id))),
+            acquisitions,
+            title: book.name,
+        })
+    }
+}
+
+/// Items of a page (starting at 1) with the next and previous page urls
+fn paginate<T>(
+    urls: &OpdsUrls,
+    path: &str,
+    items: Vec<T>,
+    page: usize,
+) -> (Vec<T>, Option<String>, Option<String>) {
+    let page = page.max(1);
+    let total = items.len();
+    let start = (page - 1) * OPDS_PAGE_SIZE;
+    let next = (start + OPDS_PAGE_SIZE < total).then(|| page_href(urls, path, page + 1));
+    let previous = (page > 1).then(|| page_href(urls, path, page - 1));
+    let items = items.into_iter().skip(start).take(OPDS_PAGE_SIZE).collect();
+    (items, next, previous)
+}
+
+fn page_href(urls: &OpdsUrls, path: &str, page: usize) -> String {
+    if page <= 1 {
+        urls.feed(path)
+    } else {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        urls.feed(&format!("{}{}page={}", path, separator, page))
+    }
+}
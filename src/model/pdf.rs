@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+
+use crate::{
+    domain::{library::LibraryRole, media::MediaForUpdate},
+    error::{RsError, RsResult},
+    plugins::sources::{error::SourcesError, AsyncReadPinBox},
+    tools::{
+        image_tools::{convert_image_reader, ImageSize},
+        pdf_tools::{has_pdf_renderer, pdf_page_count, render_pdf_page},
+    },
+};
+
+use super::{users::ConnectedUser, ModelController};
+
+impl ModelController {
+    /// Folder holding the rendered pages of a PDF. Hidden so `clean_temp` keeps it
+    async fn pdf_pages_folder(&self, library_id: &str, media_id: &str) -> RsResult<PathBuf> {
+        let local = self.library_source_for_library(library_id).await?;
+        Ok(local.get_full_path(&format!(".cache/.pdf/{}", media_id)))
+    }
+
+    async fn pdf_source(&self, library_id: &str, media_id: &str) -> RsResult<String> {
+        let store = self.store.get_library_store(library_id)?;
+        let existing =
+            store
+                .get_media_source(media_id)
+                .await?
+                .ok_or(SourcesError::UnableToFindMedia(
+                    library_id.to_string(),
+                    media_id.to_string(),
+                    "pdf_source".to_string(),
+                ))?;
+        Ok(existing.source)
+    }
+
+    /// Jpeg of a page (starting at 1) of a PDF, rendered on first request then cached
+    pub async fn get_pdf_page(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        page: usize,
+        size: &ImageSize,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<u8>> {
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Read)?;
+        let folder = self.pdf_pages_folder(library_id, media_id).await?;
+        let path = folder.join(format!("{}.{}.jpg", page, size));
+        if let Ok(image) = tokio::fs::read(&path).await {
+            return Ok(image);
+        }
+
+        self.cache_check_library_notcrypt(library_id).await?;
+        if self.get_library_encryption_key(library_id).await.is_some() {
+            return Err(RsError::UnavailableForCryptedLibraries);
+        }
+        let source = self.pdf_source(library_id, media_id).await?;
+        let (pdf_path, temporary) = self
+            .local_media_path(library_id, &source, requesting_user)
+            .await?;
+        let rendered = render_pdf_page(&pdf_path, page, size.to_size()).await;
+        if temporary {
+            let _ = tokio::fs::remove_file(&pdf_path).await;
+        }
+        let reader: AsyncReadPinBox = Box::pin(std::io::Cursor::new(rendered?));
+        let image = convert_image_reader(reader, image::ImageFormat::Jpeg, Some(80), false).await?;
+        tokio::fs::create_dir_all(&folder).await?;
+        tokio::fs::write(&path, &image).await?;
+        Ok(image)
+    }
+
+    /// Fill the page count of a PDF. Skipped when no PDF renderer is installed
+    pub async fn update_pdf_infos(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        requesting_user: &ConnectedUser,
+        notif: bool,
+    ) -> RsResult<()> {
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Read)?;
+        self.cache_check_library_notcrypt(library_id).await?;
+        if !has_pdf_renderer() {
+            return Ok(());
+        }
+        let source = self.pdf_source(library_id, media_id).await?;
+        let (pdf_path, temporary) = self
+            .local_media_path(library_id, &source, requesting_user)
+            .await?;
+        let pages = pdf_page_count(&pdf_path).await;
+        if temporary {
+            let _ = tokio::fs::remove_file(&pdf_path).await;
+        }
+        let update = MediaForUpdate {
+            pages: Some(pages?),
+            ..Default::default()
+        };
+        self.update_media(
+            library_id,
+            media_id.to_owned(),
+            update,
+            notif,
+            requesting_user,
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn remove_media_pdf_pages(&self, library_id: &str, media_id: &str) -> RsResult<()> {
+        let folder = self.pdf_pages_folder(library_id, media_id).await?;
+        if tokio::fs::try_exists(&folder).await.unwrap_or(false) {
+            tokio::fs::remove_dir_all(&folder).await?;
+        }
+        Ok(())
+    }
+}
@@ -16,10 +16,11 @@ use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 use crate::{
     domain::{
         deleted::RsDeleted,
-        library::LibraryRole,
+        library::{LibraryRole, LibraryStatusMessage},
         media::{FileType, Media, MediaWithAction, MediasMessage},
         people::{
-            FaceBBox, FaceEmbedding, PeopleMessage, Person, PersonWithAction, UnassignedFace,
+            FaceBBox, FaceEmbedding, FaceReembedFailure, PeopleMessage, Person, PersonWithAction,
+            UnassignedFace,
         },
         tag::Tag,
         ElementAction,
@@ -28,9 +29,10 @@ use crate::{
     model::medias::MediaFileQuery,
     plugins::sources::{error::SourcesError, AsyncReadPinBox, FileStreamResult, Source},
     tools::{
+        face_index::FACE_INDEX_BRUTE_FORCE_LIMIT,
         image_tools::{convert_image_reader, resize_image_reader, ImageSize},
         log::log_info,
-        recognition::{BBox, DetectedFace, FaceRecognitionService},
+        recognition::{current_face_model, BBox, DetectedFace, FaceRecognitionService},
         video_tools::VideoTime,
     },
 };
@@ -399,6 +401,9 @@ impl ModelController {
             ))?;
 
         store.remove_person(tag_id.to_string()).await?;
+        let person_id = tag_id.to_string();
+        self.update_face_index(library_id, move |index| index.remove_person(&person_id))
+            .await?;
         self.add_deleted(
             library_id,
             RsDeleted::person(tag_id.to_owned()),
@@ -881,7 +886,6 @@ impl ModelController {
         requesting_user: &ConnectedUser,
         service: Option<Arc<FaceRecognitionService>>,
     ) -> RsResult<Vec<DetectedFaceResult>> {
-        use crate::tools::video_tools::VideoTime;
         let service = if let Some(s) = service {
             s
         } else {
@@ -903,142 +907,9 @@ impl ModelController {
 
         // Process in a block to catch errors and update DB status
         let result: RsResult<Vec<DetectedFaceResult>> = async {
-            let mut collected_faces = Vec::new();
-
-            if media.kind == FileType::Video {
-                // Video Logic
-
-                // 1. Media Image (Thumbnail) - Optional
-                if let Ok(mut reader_response) = self
-                    .media_image(library_id, media_id, None, requesting_user)
-                    .await
-                {
-                    if let Ok(response) =
-                        crate::tools::image_tools::reader_to_image(&mut reader_response.stream)
-                            .await
-                    {
-                        let faces = service
-                            .detect_and_extract_faces_async(response.image)
-                            .await?;
-                        for face in faces {
-                            let bbox = FaceBBox {
-                                x1: face.bbox.x1,
-                                y1: face.bbox.y1,
-                                x2: face.bbox.x2,
-                                y2: face.bbox.y2,
-                                video_s: None,
-                                video_percent: None,
-                            };
-                            collected_faces.push(CollectedFace {
-                                face,
-                                video_s: None,
-                                video_percent: None,
-                                bbox,
-                            });
-                        }
-                    }
-                }
-
-                // 2. Video Frames
-                let duration = media.duration.unwrap_or(0);
-
-                // Determine percentages to scan
-                let percents = if duration < 1000 * 60 {
-                    // < 1 min
-                    vec![5, 13, 21, 29, 37, 45, 53, 61, 69, 77, 85, 93]
-                } else if duration < 1000 * 60 * 2 {
-                    // < 2 min
-                    (2..=98).step_by(5).collect::<Vec<_>>()
-                } else if duration < 1000 * 60 * 10 {
-                    // < 10 min
-                    (1..=99).step_by(2).collect::<Vec<_>>()
-                } else {
-                    (1..=99).collect::<Vec<_>>()
-                };
-
-                for percent in percents {
-                    // Get thumb as byte buffer
-                    let seconds = (duration as f64) * (percent as f64 / 100.0);
-                    let thumb = self
-                        .get_video_thumb(
-                            library_id,
-                            media_id,
-                            VideoTime::Seconds(seconds),
-                            image::ImageFormat::Png,
-                            Some(70),
-                            requesting_user,
-                        )
-                        .await?;
-
-                    // Convert to image
-                    let mut cursor = Cursor::new(thumb);
-                    if let Ok(response) =
-                        crate::tools::image_tools::reader_to_image(&mut cursor).await
-                    {
-                        let faces = service
-                            .detect_and_extract_faces_async(response.image)
-                            .await?;
-                        for face in faces {
-                            let bbox = FaceBBox {
-                                x1: face.bbox.x1,
-                                y1: face.bbox.y1,
-                                x2: face.bbox.x2,
-                                y2: face.bbox.y2,
-                                video_s: Some(seconds as f32),
-                                video_percent: None,
-                            };
-                            collected_faces.push(CollectedFace {
-                                face,
-                                video_s: Some(seconds as f32),
-                                video_percent: None,
-                                bbox,
-                            });
-                        }
-                    }
-                }
-            } else {
-                // Photo processing
-                let reader = self
-                    .library_file(
-                        library_id,
-                        media_id,
-                        None,
-                        MediaFileQuery::default(),
-                        requesting_user,
-                    )
-                    .await?;
-                let mut reader = reader
-                    .into_reader(
-                        Some(library_id),
-                        None,
-                        None,
-                        Some((self.clone(), requesting_user)),
-                        None,
-                    )
-                    .await?;
-
-                let image_result =
-                    crate::tools::image_tools::reader_to_image(&mut reader.stream).await?;
-                let faces = service
-                    .detect_and_extract_faces_async(image_result.image)
-                    .await?;
-                for face in faces {
-                    let bbox = FaceBBox {
-                        x1: face.bbox.x1,
-                        y1: face.bbox.y1,
-                        x2: face.bbox.x2,
-                        y2: face.bbox.y2,
-                        video_s: None,
-                        video_percent: None,
-                    };
-                    collected_faces.push(CollectedFace {
-                        face,
-                        video_s: None,
-                        video_percent: None,
-                        bbox,
-                    });
-                }
-            }
+            let collected_faces = self
+                .collect_media_faces(library_id, &media, &service, requesting_user)
+                .await?;
 
             // Deduplicate similar faces (keep only highest confidence from each cluster)
             let threshold = self.get_face_threshold(library_id).await?;
@@ -1047,63 +918,16 @@ impl ModelController {
             // Process deduplicated faces
             let mut results = Vec::new();
             for collected_face in deduplicated_faces {
-                // Try matching with threshold
-                if let Some((person_id, sim)) = self
-                    .match_face_to_person(library_id, &collected_face.face.embedding, threshold)
-                    .await?
-                {
-                    // High confidence -> assign directly
-                    let face_id = nanoid!();
-                    store
-                        .add_face_embedding(
-                            face_id.clone(),
-                            &person_id,
-                            collected_face.face.embedding.clone(),
-                            Some(media_id.to_string()),
-                            Some(collected_face.bbox.clone()),
-                            collected_face.face.confidence,
-                            Some(collected_face.face.pose),
-                            Some(sim),
-                        )
-                        .await?;
-                    results.push(DetectedFaceResult {
-                        face_id: Some(face_id),
-                        confidence: collected_face.face.confidence,
-                        bbox: collected_face.bbox.clone(),
-                    });
-
-                    // Notify person updated
-                    if let Ok(Some(person)) = self
-                        .get_person(library_id, person_id.clone(), requesting_user)
-                        .await
-                    {
-                        self.send_people(PeopleMessage {
-                            library: library_id.to_string(),
-                            people: vec![PersonWithAction {
-                                person: person.clone(),
-                                action: ElementAction::Updated,
-                            }],
-                        });
-                    }
-                } else {
-                    // No match -> stage for clustering
-                    let face_id = nanoid!();
-                    store
-                        .add_unassigned_face(
-                            face_id.clone(),
-                            collected_face.face.embedding.clone(),
-                            media_id.to_string(),
-                            collected_face.bbox.clone(),
-                            collected_face.face.confidence,
-                            Some(collected_face.face.pose),
-                        )
-                        .await?;
-                    results.push(DetectedFaceResult {
-                        face_id: Some(face_id),
-                        confidence: collected_face.face.confidence,
-                        bbox: collected_face.bbox.clone(),
-                    });
-                }
+                results.push(
+                    self.add_detected_face(
+                        library_id,
+                        media_id,
+                        collected_face,
+                        threshold,
+                        requesting_user,
+                    )
+                    .await?,
+                );
             }
 
             // Notify media updated
@@ -1135,6 +959,409 @@ impl ModelController {
         }
     }
 
+    /// Store a detected face, assigned to the closest person over the threshold or left
+    /// unassigned for clustering
+    async fn add_detected_face(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        collected_face: CollectedFace,
+        threshold: f32,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<DetectedFaceResult> {
+        let store = self.store.get_library_store(library_id)?;
+        let face_id = nanoid!();
+        let embedding = collected_face.face.embedding.clone();
+        // Try matching with threshold
+        if let Some((person_id, sim)) = self
+            .match_face_to_person(library_id, &embedding, threshold)
+            .await?
+        {
+            // High confidence -> assign directly
+            store
+                .add_face_embedding(
+                    face_id.clone(),
+                    &person_id,
+                    embedding.clone(),
+                    Some(media_id.to_string()),
+                    Some(collected_face.bbox.clone()),
+                    collected_face.face.confidence,
+                    Some(collected_face.face.pose),
+                    Some(sim),
+                    current_face_model(),
+                )
+                .await?;
+            let (indexed_id, indexed_person) = (face_id.clone(), person_id.clone());
+            self.update_face_index(library_id, move |index| {
+                index.add_person_face(&indexed_id, &indexed_person, embedding)
+            })
+            .await?;
+
+            // Notify person updated
+            if let Ok(Some(person)) = self
+                .get_person(library_id, person_id.clone(), requesting_user)
+                .await
+            {
+                self.send_people(PeopleMessage {
+                    library: library_id.to_string(),
+                    people: vec![PersonWithAction {
+                        person: person.clone(),
+                        action: ElementAction::Updated,
+                    }],
+                });
+            }
+        } else {
+            // No match -> stage for clustering
+            store
+                .add_unassigned_face(
+                    face_id.clone(),
+                    embedding.clone(),
+                    media_id.to_string(),
+                    collected_face.bbox.clone(),
+                    collected_face.face.confidence,
+                    Some(collected_face.face.pose),
+                    current_face_model(),
+                )
+                .await?;
+            let indexed_id = face_id.clone();
+            self.update_face_index(library_id, move |index| {
+                index.add_unassigned_face(&indexed_id, embedding)
+            })
+            .await?;
+        }
+        Ok(DetectedFaceResult {
+            face_id: Some(face_id),
+            confidence: collected_face.face.confidence,
+            bbox: collected_face.bbox,
+        })
+    }
+
+    /// Faces detected in a photo, or in the thumbnail and frames of a video
+    async fn collect_media_faces(
+        &self,
+        library_id: &str,
+        media: &Media,
+        service: &FaceRecognitionService,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<CollectedFace>> {
+        let media_id = media.id.as_str();
+        let mut collected_faces = Vec::new();
+
+        if media.kind == FileType::Video {
+            // Video Logic
+
+            // 1. Media Image (Thumbnail) - Optional
+            if let Ok(mut reader_response) = self
+                .media_image(library_id, media_id, None, requesting_user)
+                .await
+            {
+                if let Ok(response) =
+                    crate::tools::image_tools::reader_to_image(&mut reader_response.stream).await
+                {
+                    let faces = service
+                        .detect_and_extract_faces_async(response.image)
+                        .await?;
+                    for face in faces {
+                        let bbox = FaceBBox {
+                            x1: face.bbox.x1,
+                            y1: face.bbox.y1,
+                            x2: face.bbox.x2,
+                            y2: face.bbox.y2,
+                            video_s: None,
+                            video_percent: None,
+                        };
+                        collected_faces.push(CollectedFace {
+                            face,
+                            video_s: None,
+                            video_percent: None,
+                            bbox,
+                        });
+                    }
+                }
+            }
+
+            // 2. Video Frames
+            let duration = media.duration.unwrap_or(0);
+
+            // Determine percentages to scan
+            let percents = if duration < 1000 * 60 {
+                // < 1 min
+                vec![5, 13, 21, 29, 37, 45, 53, 61, 69, 77, 85, 93]
+            } else if duration < 1000 * 60 * 2 {
+                // < 2 min
+                (2..=98).step_by(5).collect::<Vec<_>>()
+            } else if duration < 1000 * 60 * 10 {
+                // < 10 min
+                (1..=99).step_by(2).collect::<Vec<_>>()
+            } else {
+                (1..=99).collect::<Vec<_>>()
+            };
+
+            for percent in percents {
+                // Get thumb as byte buffer
+                let seconds = (duration as f64) * (percent as f64 / 100.0);
+                let thumb = self
+                    .get_video_thumb(
+                        library_id,
+                        media_id,
+                        VideoTime::Seconds(seconds),
+                        image::ImageFormat::Png,
+                        Some(70),
+                        requesting_user,
+                    )
+                    .await?;
+
+                // Convert to image
+                let mut cursor = Cursor::new(thumb);
+                if let Ok(response) = crate::tools::image_tools::reader_to_image(&mut cursor).await
+                {
+                    let faces = service
+                        .detect_and_extract_faces_async(response.image)
+                        .await?;
+                    for face in faces {
+                        let bbox = FaceBBox {
+                            x1: face.bbox.x1,
+                            y1: face.bbox.y1,
+                            x2: face.bbox.x2,
+                            y2: face.bbox.y2,
+                            video_s: Some(seconds as f32),
+                            video_percent: None,
+                        };
+                        collected_faces.push(CollectedFace {
+                            face,
+                            video_s: Some(seconds as f32),
+                            video_percent: None,
+                            bbox,
+                        });
+                    }
+                }
+            }
+        } else {
+            // Photo processing
+            let reader = self
+                .library_file(
+                    library_id,
+                    media_id,
+                    None,
+                    MediaFileQuery::default(),
+                    requesting_user,
+                )
+                .await?;
+            let mut reader = reader
+                .into_reader(
+                    Some(library_id),
+                    None,
+                    None,
+                    Some((self.clone(), requesting_user)),
+                    None,
+                )
+                .await?;
+
+            let image_result =
+                crate::tools::image_tools::reader_to_image(&mut reader.stream).await?;
+            let faces = service
+                .detect_and_extract_faces_async(image_result.image)
+                .await?;
+            for face in faces {
+                let bbox = FaceBBox {
+                    x1: face.bbox.x1,
+                    y1: face.bbox.y1,
+                    x2: face.bbox.x2,
+                    y2: face.bbox.y2,
+                    video_s: None,
+                    video_percent: None,
+                };
+                collected_faces.push(CollectedFace {
+                    face,
+                    video_s: None,
+                    video_percent: None,
+                    bbox,
+                });
+            }
+        }
+
+        Ok(collected_faces)
+    }
+
+    /// Detect the faces of a media again with the current face model. Each new face takes
+    /// the id, person and cluster of the outdated face it overlaps the most, other new faces
+    /// are matched like newly detected ones. Outdated unassigned faces left alone are deleted,
+    /// outdated faces of a person are kept and the media is recorded for review
+    pub async fn reembed_media_faces(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        service: &FaceRecognitionService,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<usize> {
+        requesting_user.check_library_role(library_id, LibraryRole::Write)?;
+        let store = self.store.get_library_store(library_id)?;
+        let model = current_face_model();
+        let outdated = store
+            .get_outdated_media_faces(media_id.to_string(), model.clone())
+            .await?;
+        if outdated.is_empty() {
+            return Ok(0);
+        }
+
+        let media = self
+            .get_media(library_id, media_id.to_string(), requesting_user)
+            .await?
+            .ok_or(SourcesError::UnableToFindMedia(
+                library_id.to_string(),
+                media_id.to_string(),
+                "reembed_media_faces".to_string(),
+            ))?
+            .item;
+        let collected_faces = self
+            .collect_media_faces(library_id, &media, service, requesting_user)
+            .await?;
+        let threshold = self.get_face_threshold(library_id).await?;
+        let collected_faces = deduplicate_faces(collected_faces, threshold);
+
+        let old_boxes: Vec<FaceBBox> = outdated.iter().map(|(_, _, bbox)| bbox.clone()).collect();
+        let new_boxes: Vec<FaceBBox> = collected_faces.iter().map(|f| f.bbox.clone()).collect();
+        let pairs = pair_faces_by_overlap(&old_boxes, &new_boxes, REEMBED_MIN_OVERLAP);
+
+        let mut reembedded = std::collections::HashSet::new();
+        for (collected_face, old) in collected_faces.into_iter().zip(pairs) {
+            let Some(old) = old else {
+                self.add_detected_face(
+                    library_id,
+                    media_id,
+                    collected_face,
+                    threshold,
+                    requesting_user,
+                )
+                .await?;
+                continue;
+            };
+            let (face_id, person_id, _) = outdated[old].clone();
+            let embedding = collected_face.face.embedding;
+            store
+                .update_face_embedding(
+                    face_id.clone(),
+                    embedding.clone(),
+                    collected_face.bbox,
+                    collected_face.face.confidence,
+                    Some(collected_face.face.pose),
+                    model.clone(),
+                )
+                .await?;
+            let indexed_id = face_id.clone();
+            self.update_face_index(library_id, move |index| match person_id {
+                Some(person_id) => index.add_person_face(&indexed_id, &person_id, embedding),
+                None => index.add_unassigned_face(&indexed_id, embedding),
+            })
+            .await?;
+            // The cached crop was made from the previous bbox
+            let _ = self
+                .remove_library_image(
+                    library_id,
+                    ".faces",
+                    &face_id,
+                    &None,
+                    &None,
+                    requesting_user,
+                )
+                .await;
+            reembedded.insert(old);
+        }
+
+        let mut kept = 0;
+        for (index, (face_id, person_id, _)) in outdated.iter().enumerate() {
+            if reembedded.contains(&index) {
+                continue;
+            }
+            if person_id.is_some() {
+                kept += 1;
+            } else {
+                self.delete_face(library_id, face_id, requesting_user)
+                    .await?;
+            }
+        }
+        if kept > 0 {
+            store
+                .add_face_reembed_failure(
+                    media_id.to_string(),
+                    model,
+                    format!("{} faces assigned to people were not detected again", kept),
+                )
+                .await?;
+        }
+
+        self.send_media_update_events(library_id, &[media_id.to_string()], requesting_user)
+            .await;
+        Ok(reembedded.len())
+    }
+
+    /// Re-embed the faces of all medias of a library still using an older face model.
+    /// Returns the number of faces that kept their id and person
+    pub async fn reembed_outdated_faces(
+        &self,
+        library_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<usize> {
+        requesting_user.check_library_role(library_id, LibraryRole::Write)?;
+        let store = self.store.get_library_store(library_id)?;
+        let media_ids = store
+            .get_medias_with_outdated_faces(current_face_model())
+            .await?;
+        if media_ids.is_empty() {
+            return Ok(0);
+        }
+        let service = self.get_face_recognition_service().await?;
+
+        let total = media_ids.len();
+        let mut reembedded = 0;
+        for (index, media_id) in media_ids.iter().enumerate() {
+            match self
+                .reembed_media_faces(library_id, media_id, &service, requesting_user)
+                .await
+            {
+                Ok(count) => reembedded += count,
+                Err(e) => {
+                    crate::tools::log::log_error(
+                        crate::tools::log::LogServiceType::Scheduler,
+                        format!("Error re-embedding faces of media {}: {:#}", media_id, e),
+                    );
+                    // Not retried with this model, the media is listed for review instead
+                    store
+                        .add_face_reembed_failure(
+                            media_id.clone(),
+                            current_face_model(),
+                            format!("{:#}", e),
+                        )
+                        .await?;
+                }
+            }
+            self.send_library_status(LibraryStatusMessage {
+                message: format!(
+                    "Re-embedding faces... ({}/{}) - {}%",
+                    index + 1,
+                    total,
+                    (index + 1) * 100 / total
+                ),
+                library: library_id.to_string(),
+            });
+        }
+        self.save_face_index(library_id).await?;
+        Ok(reembedded)
+    }
+
+    /// Medias whose faces the current face model could not all re-embed
+    pub async fn get_face_reembed_failures(
+        &self,
+        library_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<FaceReembedFailure>> {
+        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        let store = self.store.get_library_store(library_id)?;
+        Ok(store
+            .get_face_reembed_failures(current_face_model())
+            .await?)
+    }
+
     pub async fn cluster_unassigned_faces(
         &self,
         library_id: &str,
@@ -1142,7 +1369,10 @@ impl ModelController {
     ) -> RsResult<ClusteringResult> {
         requesting_user.check_library_role(library_id, LibraryRole::Write)?;
         let store = self.store.get_library_store(library_id)?;
-        let unassigned = store.get_all_unassigned_faces(None, None).await?;
+        // Faces of older face models are left out until re-embedded
+        let unassigned = store
+            .get_unassigned_face_medias(current_face_model())
+            .await?;
 
         if unassigned.len() < 3 {
             return Ok(ClusteringResult {
@@ -1151,23 +1381,35 @@ impl ModelController {
         }
 
         // Collect all face IDs before moving unassigned into closure
-        let all_face_ids: Vec<String> = unassigned.iter().map(|f| f.id.clone()).collect();
+        let all_face_ids: Vec<String> = unassigned.iter().map(|f| f.0.clone()).collect();
 
         // Create lookup map from face_id to media_ref for threshold counting
-        let face_to_media: std::collections::HashMap<String, String> = unassigned
-            .iter()
-            .map(|f| (f.id.clone(), f.media_ref.clone()))
-            .collect();
+        let face_to_media: std::collections::HashMap<String, String> =
+            unassigned.into_iter().collect();
 
         // Get threshold for this library
         let threshold = self.get_face_threshold(library_id).await?;
-        let threshold_clone = threshold; // Clone for move into closure
 
-        // Chinese Whispers clustering (CPU-bound)
-        let clusters = tokio::task::spawn_blocking(move || {
-            chinese_whispers_clustering(&unassigned, threshold_clone)
-        })
-        .await
+        // Chinese Whispers clustering (CPU-bound). Small libraries compare every pair of faces,
+        // larger ones only link each face to its nearest neighbours in the face index
+        let clusters = if all_face_ids.len() <= FACE_INDEX_BRUTE_FORCE_LIMIT {
+            let mut faces = store.get_all_unassigned_faces(None, None).await?;
+            faces.retain(|f| face_to_media.contains_key(&f.id));
+            tokio::task::spawn_blocking(move || chinese_whispers_clustering(&faces, threshold))
+                .await
+        } else {
+            let index = self.get_face_index(library_id, true).await?;
+            let face_ids = all_face_ids.clone();
+            tokio::task::spawn_blocking(move || {
+                let adj = index.read().unwrap().unassigned.neighbor_graph(
+                    &face_ids,
+                    threshold,
+                    CLUSTERING_NEIGHBORS,
+                );
+                chinese_whispers(&face_ids, adj)
+            })
+            .await
+        }
         .map_err(|e| RsError::Error(format!("Clustering task failed: {}", e)))?;
 
         // Track all face IDs that were clustered (in clusters with 2+ faces)
@@ -1203,8 +1445,13 @@ impl ModelController {
                     .add_pesron(library_id, new_person, &ConnectedUser::ServerAdmin)
                     .await?;
                 store
-                    .promote_cluster_to_person(cluster_id, person.id)
+                    .promote_cluster_to_person(cluster_id, person.id.clone())
                     .await?;
+                let promoted = face_ids.clone();
+                self.update_face_index(library_id, move |index| {
+                    index.assign(&promoted, &person.id)
+                })
+                .await?;
 
                 // Get unique media IDs from the faces in this cluster and send update events
                 let cluster_media_ids: Vec<String> = face_ids
@@ -1315,6 +1562,9 @@ impl ModelController {
                 true
             }
         };
+        let removed = vec![face_id.to_string()];
+        self.update_face_index(library_id, move |index| index.remove(&removed))
+            .await?;
 
         // Delete cached face image if it exists (ignore errors - cache cleanup is best effort)
         if deleted {
@@ -1350,6 +1600,9 @@ impl ModelController {
         store
             .assign_unassigned_face_to_person(face_id.to_string(), person_id.to_string())
             .await?;
+        let (assigned, person) = (vec![face_id.to_string()], person_id.to_string());
+        self.update_face_index(library_id, move |index| index.assign(&assigned, &person))
+            .await?;
 
         // Send media update events
         self.send_media_update_events(library_id, &media_ids, requesting_user)
@@ -1390,6 +1643,9 @@ impl ModelController {
         let count = store
             .assign_unassigned_faces_to_person_batch(face_ids.to_vec(), person_id.to_string())
             .await?;
+        let (assigned, person) = (face_ids.to_vec(), person_id.to_string());
+        self.update_face_index(library_id, move |index| index.assign(&assigned, &person))
+            .await?;
 
         // Send media update events
         self.send_media_update_events(library_id, &media_ids, requesting_user)
@@ -1415,6 +1671,9 @@ impl ModelController {
         let count = store
             .unassign_faces_from_person_batch(face_ids.to_vec())
             .await?;
+        let unassigned = face_ids.to_vec();
+        self.update_face_index(library_id, move |index| index.unassign(&unassigned))
+            .await?;
 
         // Send media update events
         self.send_media_update_events(library_id, &media_ids, requesting_user)
@@ -1429,55 +1688,41 @@ impl ModelController {
         requesting_user: &ConnectedUser,
     ) -> RsResult<usize> {
         requesting_user.check_library_role(library_id, LibraryRole::Write)?;
-        let store = self.store.get_library_store(library_id)?;
-        let unassigned_faces = store.get_all_unassigned_faces(None, None).await?;
-
         let threshold = self.get_face_threshold(library_id).await?;
-        let mut matched_count = 0;
-
-        for face in unassigned_faces {
-            // Skip faces with empty embeddings
-            if face.embedding.is_empty() {
-                continue;
-            }
+        let index = self.get_face_index(library_id, true).await?;
+
+        // Best person of every unassigned face (CPU-bound)
+        let matches = tokio::task::spawn_blocking(move || {
+            let index = index.read().unwrap();
+            index
+                .unassigned
+                .entries()
+                .filter_map(|(face_id, _)| {
+                    let embedding = index.unassigned.vector(face_id)?;
+                    let best = index.people.search(embedding, 1).into_iter().next()?;
+                    (best.similarity >= threshold).then(|| (face_id.to_string(), best.label))
+                })
+                .collect::<Vec<(String, String)>>()
+        })
+        .await?;
 
-            // Try to match this face to an existing person
+        let mut matched_count = 0;
+        for (face_id, person_id) in matches {
+            // Match found - assign the face to this person
             match self
-                .match_face_to_person(library_id, &face.embedding, threshold)
+                .assign_unassigned_face_to_person(library_id, &face_id, &person_id, requesting_user)
                 .await
             {
-                Ok(Some((person_id, _sim))) => {
-                    // Match found - assign the face to this person
-                    match self
-                        .assign_unassigned_face_to_person(
-                            library_id,
-                            &face.id,
-                            &person_id,
-                            requesting_user,
-                        )
-                        .await
-                    {
-                        Ok(_) => {
-                            matched_count += 1;
-                        }
-                        Err(e) => {
-                            crate::tools::log::log_error(
-                                crate::tools::log::LogServiceType::Scheduler,
-                                format!(
-                                    "Error assigning face {} to person {}: {:#}",
-                                    face.id, person_id, e
-                                ),
-                            );
-                        }
-                    }
-                }
-                Ok(None) => {
-                    // No match found - continue to next face
+                Ok(_) => {
+                    matched_count += 1;
                 }
                 Err(e) => {
                     crate::tools::log::log_error(
                         crate::tools::log::LogServiceType::Scheduler,
-                        format!("Error matching face {}: {:#}", face.id, e),
+                        format!(
+                            "Error assigning face {} to person {}: {:#}",
+                            face_id, person_id, e
+                        ),
                     );
                 }
             }
@@ -1739,6 +1984,11 @@ impl ModelController {
         let faces_transferred = store
             .transfer_faces_between_people(source_person_id, target_person_id)
             .await?;
+        let (source, target) = (source_person_id.to_string(), target_person_id.to_string());
+        self.update_face_index(library_id, move |index| {
+            index.relabel_person(&source, &target)
+        })
+        .await?;
 
         // Delete source person (this also checks Admin permissions internally)
         self.remove_person(library_id, source_person_id, requesting_user)
@@ -1774,19 +2024,21 @@ impl ModelController {
         embedding: &[f32],
         threshold: f32,
     ) -> RsResult<Option<(String, f32)>> {
-        let store = self.store.get_library_store(library_id)?;
-        let all_embeddings = store.get_all_embeddings().await?;
-
-        let mut best_match: Option<(String, f32)> = None;
-        for (person_id, person_emb) in all_embeddings {
-            let sim = cosine_similarity(embedding, &person_emb);
-            if sim >= threshold {
-                if best_match.is_none() || sim > best_match.as_ref().unwrap().1 {
-                    best_match = Some((person_id, sim));
-                }
-            }
-        }
-        Ok(best_match)
+        let index = self.get_face_index(library_id, false).await?;
+        let embedding = embedding.to_vec();
+        let best_match = tokio::task::spawn_blocking(move || {
+            index
+                .read()
+                .unwrap()
+                .people
+                .search(&embedding, 1)
+                .into_iter()
+                .next()
+        })
+        .await?;
+        Ok(best_match
+            .filter(|m| m.similarity >= threshold)
+            .map(|m| (m.label, m.similarity)))
     }
 }
 
@@ -1799,6 +2051,58 @@ pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     a.iter().zip(b).map(|(x, y)| x * y).sum()
 }
 
+/// Minimum intersection over union for a re-detected face to take over an outdated face
+const REEMBED_MIN_OVERLAP: f32 = 0.5;
+
+fn face_bbox_iou(a: &FaceBBox, b: &FaceBBox) -> f32 {
+    let w = (a.x2.min(b.x2) - a.x1.max(b.x1)).max(0.0);
+    let h = (a.y2.min(b.y2) - a.y1.max(b.y1)).max(0.0);
+    let inter = w * h;
+    let union = (a.x2 - a.x1) * (a.y2 - a.y1) + (b.x2 - b.x1) * (b.y2 - b.y1) - inter;
+    if union <= 0.0 {
+        0.0
+    } else {
+        inter / union
+    }
+}
+
+/// Outdated face taken over by each new face: pairs from the same image or video frame are
+/// matched greedily by decreasing overlap, each outdated face at most once
+fn pair_faces_by_overlap(
+    old_faces: &[FaceBBox],
+    new_faces: &[FaceBBox],
+    min_overlap: f32,
+) -> Vec<Option<usize>> {
+    let same_frame = |a: &FaceBBox, b: &FaceBBox| match (a.video_s, b.video_s) {
+        (Some(a), Some(b)) => (a - b).abs() < 0.5,
+        (None, None) => true,
+        _ => false,
+    };
+    let mut candidates = vec![];
+    for (new, new_bbox) in new_faces.iter().enumerate() {
+        for (old, old_bbox) in old_faces.iter().enumerate() {
+            if !same_frame(old_bbox, new_bbox) {
+                continue;
+            }
+            let overlap = face_bbox_iou(old_bbox, new_bbox);
+            if overlap >= min_overlap {
+                candidates.push((overlap, new, old));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut pairs = vec![None; new_faces.len()];
+    let mut taken = vec![false; old_faces.len()];
+    for (_, new, old) in candidates {
+        if pairs[new].is_none() && !taken[old] {
+            pairs[new] = Some(old);
+            taken[old] = true;
+        }
+    }
+    pairs
+}
+
 /// Deduplicate faces by clustering similar ones and keeping only the highest confidence face from each cluster.
 /// Uses cosine similarity with the given threshold to determine if faces are similar.
 fn deduplicate_faces(collected_faces: Vec<CollectedFace>, threshold: f32) -> Vec<CollectedFace> {
@@ -1873,6 +2177,9 @@ fn deduplicate_faces(collected_faces: Vec<CollectedFace>, threshold: f32) -> Vec
     deduplicated
 }
 
+/// Neighbours linked to each face when clustering through the face index
+const CLUSTERING_NEIGHBORS: usize = 32;
+
 pub fn chinese_whispers_clustering(
     faces: &[UnassignedFace],
     threshold: f32,
@@ -1928,6 +2235,17 @@ pub fn chinese_whispers_clustering(
         );
     }
 
+    let face_ids: Vec<String> = faces.iter().map(|f| f.id.clone()).collect();
+    chinese_whispers(&face_ids, adj)
+}
+
+/// Chinese Whispers over a weighted similarity graph, returning clusters of 2+ faces
+pub fn chinese_whispers(
+    face_ids: &[String],
+    adj: Vec<Vec<(usize, f32)>>,
+) -> HashMap<String, Vec<String>> {
+    let n = face_ids.len();
+
     // Initialize labels (each node is its own class initially)
     let mut labels: Vec<usize> = (0..n).collect();
 
@@ -1988,7 +2306,7 @@ pub fn chinese_whispers_clustering(
         clusters
             .entry(cluster_id)
             .or_insert_with(Vec::new)
-            .push(faces[i].id.clone());
+            .push(face_ids[i].clone());
     }
 
     let cluster_sizes: Vec<usize> = clusters.values().map(|v| v.len()).collect();
@@ -2014,3 +2332,46 @@ pub fn chinese_whispers_clustering(
 
     clusters
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bbox(x1: f32, y1: f32, x2: f32, y2: f32, video_s: Option<f32>) -> FaceBBox {
+        FaceBBox {
+            x1,
+            y1,
+            x2,
+            y2,
+            video_s,
+            video_percent: None,
+        }
+    }
+
+    #[test]
+    fn pairs_reembedded_faces_by_overlap() {
+        let old = vec![
+            bbox(0.0, 0.0, 10.0, 10.0, None),
+            bbox(20.0, 20.0, 30.0, 30.0, None),
+            bbox(0.0, 0.0, 10.0, 10.0, Some(12.0)),
+        ];
+        let new = vec![
+            bbox(21.0, 21.0, 31.0, 31.0, None),
+            bbox(1.0, 1.0, 11.0, 11.0, None),
+            bbox(0.0, 0.0, 10.0, 10.0, Some(40.0)),
+            bbox(0.0, 0.0, 10.0, 10.0, Some(12.0)),
+            bbox(100.0, 100.0, 110.0, 110.0, None),
+        ];
+        assert_eq!(
+            pair_faces_by_overlap(&old, &new, 0.5),
+            vec![Some(1), Some(0), None, Some(2), None]
+        );
+
+        // The best overlap wins when two new faces cover the same outdated one
+        let new = vec![
+            bbox(4.0, 4.0, 14.0, 14.0, None),
+            bbox(1.0, 1.0, 11.0, 11.0, None),
+        ];
+        assert_eq!(pair_faces_by_overlap(&old, &new, 0.2), vec![None, Some(0)]);
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    collections::HashSet,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use lazy_static::lazy_static;
+use rs_plugin_common_interfaces::domain::ItemWithRelations;
+
+use crate::{
+    domain::{
+        library::{LibraryLimits, LibraryRole},
+        media::Media,
+    },
+    error::{RsError, RsResult},
+    server::get_clip_model_path,
+    tools::{
+        clip::{rank_by_similarity, ClipService},
+        image_tools::convert_image_reader,
+    },
+};
+
+use super::{medias::MediaQuery, users::ConnectedUser, ModelController};
+
+pub type SharedMediaEmbeddings = Arc<Vec<(String, Vec<f32>)>>;
+
+/// Results returned by a semantic search or similar medias without explicit limit
+pub const DEFAULT_SEMANTIC_LIMIT: usize = 100;
+/// Ranked medias checked against the query filters at once
+const SEMANTIC_FILTER_CHUNK: usize = 500;
+
+lazy_static! {
+    static ref CLIP_SERVICE: Mutex<Option<(String, Arc<ClipService>)>> = Mutex::new(None);
+}
+
+/// Order medias like the ranked ids
+pub fn order_by_ranking(medias: &mut [ItemWithRelations<Media>], ranking: &[(String, f32)]) {
+    medias.sort_by_key(|media| {
+        ranking
+            .iter()
+            .position(|(id, _)| id == &media.item.id)
+            .unwrap_or(usize::MAX)
+    });
+}
+
+impl ModelController {
+    /// CLIP model loaded from the configured path, with the path identifying the
+    /// model of stored embeddings
+    pub async fn get_clip_service(&self) -> RsResult<(String, Arc<ClipService>)> {
+        let path = get_clip_model_path().await;
+        {
+            let guard = CLIP_SERVICE.lock().unwrap();
+            if let Some((loaded_path, service)) = &*guard {
+                if loaded_path == &path {
+                    return Ok((path, service.clone()));
+                }
+            }
+        }
+        if !Path::new(&path).join("visual.onnx").exists() {
+            return Err(RsError::NoModelFound);
+        }
+
+        let service = Arc::new(ClipService::new_async(&path).await?);
+        let mut guard = CLIP_SERVICE.lock().unwrap();
+        *guard = Some((path.clone(), service.clone()));
+        Ok((path, service))
+    }
+
+    /// Compute and store the image embedding of a photo or of the thumbnail of a video
+    pub async fn update_media_embedding(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<()> {
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Read)?;
+        self.cache_check_library_notcrypt(library_id).await?;
+        let (model, service) = self.get_clip_service().await?;
+
+        let reader_response = self
+            .media_image(library_id, media_id, None, requesting_user)
+            .await?;
+        let buffer =
+            convert_image_reader(reader_response.stream, image::ImageFormat::Png, None, true)
+                .await?;
+        let image = image::load_from_memory(&buffer)?;
+        let embedding = service.embed_image_async(image).await?;
+
+        let store = self.store.get_library_store(library_id)?;
+        store
+            .set_media_embedding(media_id.to_string(), model, embedding)
+            .await?;
+        self.remove_media_embeddings_cache(library_id).await;
+        Ok(())
+    }
+
+    /// Embeddings of a library for a model, loaded once and kept until embeddings of the
+    /// library change
+    async fn get_library_embeddings(
+        &self,
+        library_id: &str,
+        model: &str,
+    ) -> RsResult<SharedMediaEmbeddings> {
+        if let Some((cached_model, embeddings)) = self.media_embeddings.read().await.get(library_id)
+        {
+            if cached_model == model {
+                return Ok(embeddings.clone());
+            }
+        }
+        let store = self.store.get_library_store(library_id)?;
+        let embeddings = Arc::new(store.get_media_embeddings(model.to_string()).await?);
+        self.media_embeddings.write().await.insert(
+            library_id.to_string(),
+            (model.to_string(), embeddings.clone()),
+        );
+        Ok(embeddings)
+    }
+
+    /// Forget the cached embeddings of a library, reloaded by the next search
+    pub async fn remove_media_embeddings_cache(&self, library_id: &str) {
+        self.media_embeddings.write().await.remove(library_id);
+    }
+
+    /// Medias matching the filters of `query`, best match of `text` first. The ranking is
+    /// filtered by chunks until the page is full, `page_key` being the number of results to skip
+    pub async fn get_medias_by_text(
+        &self,
+        library_id: &str,
+        text: &str,
+        mut query: MediaQuery,
+        limits: LibraryLimits,
+    ) -> RsResult<Vec<ItemWithRelations<Media>>> {
+        let limit = query.limit.unwrap_or(DEFAULT_SEMANTIC_LIMIT);
+        let skip = query
+            .page_key
+            .take()
+            .and_then(|key| key.parse::<usize>().ok())
+            .unwrap_or(0);
+        let (model, service) = self.get_clip_service().await?;
+        let embedding = service.embed_text_async(text.to_string()).await?;
+        let embeddings = self.get_library_embeddings(library_id, &model).await?;
+        let ranking = if query.ids.is_empty() {
+            rank_by_similarity(&embedding, &embeddings, embeddings.len())
+        } else {
+            let ids: HashSet<String> = query.ids.drain(..).collect();
+            let candidates: Vec<(String, Vec<f32>)> = embeddings
+                .iter()
+                .filter(|(id, _)| ids.contains(id))
+                .cloned()
+                .collect();
+            rank_by_similarity(&embedding, &candidates, candidates.len())
+        };
+        let store = self.store.get_library_store(library_id)?;
+
+        let mut medias = vec![];
+        for chunk in ranking.chunks(SEMANTIC_FILTER_CHUNK) {
+            let mut found = store
+                .get_medias(
+                    MediaQuery {
+                        ids: chunk.iter().map(|(id, _)| id.clone()).collect(),
+                        limit: Some(chunk.len()),
+                        ..query.clone()
+                    },
+                    limits.clone(),
+                )
+                .await?;
+            order_by_ranking(&mut found, chunk);
+            medias.extend(found);
+            if medias.len() >= skip + limit {
+                break;
+            }
+        }
+        Ok(medias.into_iter().skip(skip).take(limit).collect())
+    }
+
+    /// "More like this": medias with the image embeddings closest to the one of a media
+    pub async fn get_similar_medias(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        limit: Option<usize>,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<ItemWithRelations<Media>>> {
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Read)?;
+        let (model, _) = self.get_clip_service().await?;
+        let store = self.store.get_library_store(library_id)?;
+        let embedding = store
+            .get_media_embedding(media_id.to_string(), model.clone())
+            .await?
+            .ok_or(RsError::NotFound(format!(
+                "No embedding for media {}",
+                media_id
+            )))?;
+        let embeddings = self.get_library_embeddings(library_id, &model).await?;
+        let mut ranking = rank_by_similarity(
+            &embedding,
+            &embeddings,
+            limit.unwrap_or(DEFAULT_SEMANTIC_LIMIT) + 1,
+        );
+        ranking.retain(|(id, _)| id != media_id);
+        ranking.truncate(limit.unwrap_or(DEFAULT_SEMANTIC_LIMIT));
+        if ranking.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut medias = self
+            .get_medias(
+                library_id,
+                MediaQuery {
+                    ids: ranking.iter().map(|(id, _)| id.clone()).collect(),
+                    limit: Some(ranking.len()),
+                    ..Default::default()
+                },
+                requesting_user,
+            )
+            .await?;
+        order_by_ranking(&mut medias, &ranking);
+        Ok(medias)
+    }
+
+    /// Photos and videos without an embedding for the configured model
+    pub async fn get_medias_without_embedding(&self, library_id: &str) -> RsResult<Vec<String>> {
+        let (model, _) = self.get_clip_service().await?;
+        let store = self.store.get_library_store(library_id)?;
+        Ok(store.get_medias_without_embedding(model).await?)
+    }
+}
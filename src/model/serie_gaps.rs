@@ -0,0 +1,236 @@
+use rs_plugin_common_interfaces::{
+    domain::rs_ids::RsIds,
+    lookup::{RsLookupBook, RsLookupMetadataResult, RsLookupQuery},
+};
+
+use crate::{
+    domain::{
+        book::Book,
+        library::LibraryRole,
+        request_processing::RsRequestProcessing,
+        serie::Serie,
+        serie_gaps::{RsGapNumbering, RsSerieGapReport},
+    },
+    error::RsResult,
+    plugins::sources::error::SourcesError,
+    tools::{
+        log::{log_error, log_info, LogServiceType},
+        serie_gaps::{serie_gaps, title_has_number, GapBook},
+    },
+};
+
+use super::{books::BookQuery, users::ConnectedUser, ModelController};
+
+fn gap_number(book: &Book, numbering: RsGapNumbering) -> Option<f64> {
+    match numbering {
+        RsGapNumbering::Volume => book.volume,
+        RsGapNumbering::Chapter => book.chapter,
+    }
+}
+
+impl ModelController {
+    async fn gap_serie(
+        &self,
+        library_id: &str,
+        serie_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Serie> {
+        Ok(self
+            .get_serie(library_id, serie_id.to_string(), requesting_user)
+            .await?
+            .ok_or(SourcesError::UnableToFindSerie(
+                library_id.to_string(),
+                serie_id.to_string(),
+                "gap_serie".to_string(),
+            ))?
+            .item)
+    }
+
+    /// Books of the serie listed by the metadata providers. Only results related to one of
+    /// the serie ids are kept, a name search also returns unrelated books
+    async fn provider_serie_books(
+        &self,
+        library_id: &str,
+        serie: &Serie,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<Book>> {
+        let serie_ids = RsIds::from(serie.clone()).as_all_ids();
+        let lookup_query = RsLookupQuery::Book(RsLookupBook {
+            name: Some(serie.name.clone()),
+            author: None,
+            ids: None,
+            page_key: None,
+        });
+        let results = self
+            .exec_lookup_metadata(
+                lookup_query,
+                Some(library_id.to_string()),
+                requesting_user,
+                None,
+            )
+            .await?;
+        Ok(results
+            .results
+            .into_iter()
+            .filter_map(|result| {
+                let RsLookupMetadataResult::Book(book) = result.metadata else {
+                    return None;
+                };
+                let related = result
+                    .relations
+                    .as_ref()
+                    .and_then(|r| r.series.as_ref())
+                    .is_some_and(|series| series.iter().any(|s| serie_ids.contains(&s.id)));
+                related.then_some(book)
+            })
+            .collect())
+    }
+
+    /// Missing, duplicated and out of order volumes of a serie. Chapters are compared
+    /// instead when neither the providers nor the owned books have volume numbers
+    pub async fn get_serie_gaps(
+        &self,
+        library_id: &str,
+        serie_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<RsSerieGapReport> {
+        requesting_user.check_library_role(library_id, LibraryRole::Read)?;
+        let serie = self
+            .gap_serie(library_id, serie_id, requesting_user)
+            .await?;
+        let owned: Vec<Book> = self
+            .get_books(
+                library_id,
+                BookQuery {
+                    serie_ref: Some(serie.id.clone()),
+                    ..Default::default()
+                },
+                requesting_user,
+            )
+            .await?
+            .into_iter()
+            .map(|book| book.item)
+            .collect();
+        let listed = match self
+            .provider_serie_books(library_id, &serie, requesting_user)
+            .await
+        {
+            Ok(listed) => listed,
+            Err(e) => {
+                log_error(
+                    LogServiceType::Plugin,
+                    format!("Unable to list books of serie {}: {:?}", serie.id, e),
+                );
+                vec![]
+            }
+        };
+
+        let has_volumes = |books: &[Book]| books.iter().any(|b| b.volume.is_some());
+        let numbering = if has_volumes(&listed) || (listed.is_empty() && has_volumes(&owned)) {
+            RsGapNumbering::Volume
+        } else {
+            RsGapNumbering::Chapter
+        };
+        let expected = listed
+            .iter()
+            .filter_map(|book| gap_number(book, numbering))
+            .collect();
+        let books = owned
+            .iter()
+            .filter_map(|book| {
+                Some(GapBook {
+                    id: book.id.clone(),
+                    number: gap_number(book, numbering)?,
+                    airdate: book.airdate,
+                    year: book.year.map(|year| year as i64),
+                })
+            })
+            .collect();
+        Ok(serie_gaps(&serie.id, numbering, expected, books))
+    }
+
+    /// Search the missing volumes of a serie with the lookup plugins and add to the request
+    /// plugins the first result whose title names that volume. Volumes without such a result
+    /// are skipped
+    pub async fn request_serie_gaps(
+        &self,
+        library_id: &str,
+        serie_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<RsRequestProcessing>> {
+        requesting_user.check_library_role(library_id, LibraryRole::Write)?;
+        let serie = self
+            .gap_serie(library_id, serie_id, requesting_user)
+            .await?;
+        let report = self
+            .get_serie_gaps(library_id, serie_id, requesting_user)
+            .await?;
+        let label = match report.numbering {
+            RsGapNumbering::Volume => "Vol.",
+            RsGapNumbering::Chapter => "Ch.",
+        };
+        let mut processings = vec![];
+        for number in report.missing {
+            let lookup_query = RsLookupQuery::Book(RsLookupBook {
+                name: Some(format!("{} {} {}", serie.name, label, number)),
+                author: None,
+                ids: None,
+                page_key: None,
+            });
+            let results = match self
+                .exec_lookup(
+                    lookup_query,
+                    Some(library_id.to_string()),
+                    requesting_user,
+                    None,
+                )
+                .await
+            {
+                Ok(results) => results,
+                Err(e) => {
+                    log_error(
+                        LogServiceType::Plugin,
+                        format!(
+                            "Unable to look up {} {} of serie {}: {:?}",
+                            label, number, serie.id, e
+                        ),
+                    );
+                    continue;
+                }
+            };
+            let request = results.into_iter().find_map(|group| {
+                let group_filename = group.group_filename;
+                group.requests.into_iter().find(|request| {
+                    [&request.title, &request.filename, &group_filename]
+                        .into_iter()
+                        .flatten()
+                        .any(|title| title_has_number(title, report.numbering, number))
+                })
+            });
+            let Some(request) = request else {
+                log_info(
+                    LogServiceType::Plugin,
+                    format!(
+                        "No lookup result matching {} {} of serie {}",
+                        label, number, serie.id
+                    ),
+                );
+                continue;
+            };
+            match self
+                .exec_request_add(request, library_id, None, requesting_user, None)
+                .await
+            {
+                Ok(processing) => processings.push(processing),
+                Err(e) => log_error(
+                    LogServiceType::Plugin,
+                    format!(
+                        "Unable to request {} {} of serie {}: {:?}",
+                        label, number, serie.id, e
+                    ),
+                ),
+            }
+        }
+        Ok(processings)
+    }
+}
@@ -0,0 +1,17 @@
+CREATE TABLE kosync_users (
+  username TEXT PRIMARY KEY,
+  user_ref TEXT NOT NULL UNIQUE,
+  key TEXT NOT NULL,
+  modified INTEGER NOT NULL DEFAULT (round((julianday('now') - 2440587.5)*86400.0 * 1000))
+);
+
+CREATE TABLE kosync_progress (
+  document TEXT NOT NULL,
+  user_ref TEXT NOT NULL,
+  progress TEXT NOT NULL,
+  percentage REAL NOT NULL,
+  device TEXT NOT NULL DEFAULT '',
+  device_id TEXT NOT NULL DEFAULT '',
+  timestamp INTEGER NOT NULL,
+  PRIMARY KEY (document, user_ref)
+) WITHOUT ROWID;
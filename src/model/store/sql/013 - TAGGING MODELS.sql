@@ -0,0 +1,6 @@
+CREATE TABLE tagging_models (
+  id TEXT PRIMARY KEY,
+  name TEXT NOT NULL,
+  settings TEXT NOT NULL,
+  added INTEGER NOT NULL DEFAULT (round((julianday('now') - 2440587.5)*86400.0 * 1000))
+);
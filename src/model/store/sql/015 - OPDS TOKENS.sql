@@ -0,0 +1,7 @@
+CREATE TABLE opds_tokens (
+  id TEXT PRIMARY KEY,
+  library_ref TEXT NOT NULL,
+  user_ref TEXT NOT NULL,
+  added INTEGER NOT NULL DEFAULT (round((julianday('now') - 2440587.5)*86400.0 * 1000)),
+  UNIQUE (library_ref, user_ref)
+) WITHOUT ROWID;
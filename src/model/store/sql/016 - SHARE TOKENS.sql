@@ -0,0 +1,16 @@
+CREATE TABLE share_tokens (
+  id TEXT PRIMARY KEY,
+  kind TEXT NOT NULL,
+  library_ref TEXT NOT NULL,
+  user_ref TEXT NOT NULL,
+  added INTEGER NOT NULL DEFAULT (round((julianday('now') - 2440587.5)*86400.0 * 1000)),
+  UNIQUE (kind, library_ref, user_ref)
+) WITHOUT ROWID;
+
+INSERT INTO share_tokens (id, kind, library_ref, user_ref, added)
+  SELECT id, 'channels', library_ref, user_ref, added FROM channels_export_tokens;
+INSERT INTO share_tokens (id, kind, library_ref, user_ref, added)
+  SELECT id, 'books', library_ref, user_ref, added FROM opds_tokens;
+
+DROP TABLE channels_export_tokens;
+DROP TABLE opds_tokens;
@@ -0,0 +1,124 @@
+use rusqlite::{params, OptionalExtension, Row};
+
+use crate::{domain::kosync::KosyncProgress, model::store::SqliteStore};
+
+use super::Result;
+
+/// KOReader sync store
+impl SqliteStore {
+    /// User id and key hash of a KOReader username
+    pub async fn get_kosync_user(&self, username: String) -> Result<Option<(String, String)>> {
+        let user = self
+            .server_store
+            .call(move |conn| {
+                let user = conn
+                    .query_row(
+                        "SELECT user_ref, key FROM kosync_users WHERE username = ?",
+                        params![username],
+                        |row| Ok((row.get(0)?, row.get(1)?)),
+                    )
+                    .optional()?;
+                Ok(user)
+            })
+            .await?;
+        Ok(user)
+    }
+
+    pub async fn get_kosync_username(&self, user_ref: String) -> Result<Option<String>> {
+        let username = self
+            .server_store
+            .call(move |conn| {
+                let username = conn
+                    .query_row(
+                        "SELECT username FROM kosync_users WHERE user_ref = ?",
+                        params![user_ref],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                Ok(username)
+            })
+            .await?;
+        Ok(username)
+    }
+
+    /// Replace the KOReader credentials of a user
+    pub async fn set_kosync_user(
+        &self,
+        username: String,
+        user_ref: String,
+        key: String,
+    ) -> Result<()> {
+        self.server_store
+            .call(move |conn| {
+                conn.execute(
+                    "DELETE FROM kosync_users WHERE user_ref = ?",
+                    params![user_ref],
+                )?;
+                conn.execute(
+                    "INSERT INTO kosync_users (username, user_ref, key) VALUES (?, ?, ?)",
+                    params![username, user_ref, key],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    fn row_to_kosync_progress(row: &Row) -> rusqlite::Result<KosyncProgress> {
+        Ok(KosyncProgress {
+            document: row.get(0)?,
+            progress: row.get(1)?,
+            percentage: row.get(2)?,
+            device: row.get(3)?,
+            device_id: row.get(4)?,
+            timestamp: row.get(5)?,
+        })
+    }
+
+    pub async fn get_kosync_progress(
+        &self,
+        document: String,
+        user_ref: String,
+    ) -> Result<Option<KosyncProgress>> {
+        let progress = self
+            .server_store
+            .call(move |conn| {
+                let progress = conn
+                    .query_row(
+                        "SELECT document, progress, percentage, device, device_id, timestamp FROM kosync_progress WHERE document = ? AND user_ref = ?",
+                        params![document, user_ref],
+                        Self::row_to_kosync_progress,
+                    )
+                    .optional()?;
+                Ok(progress)
+            })
+            .await?;
+        Ok(progress)
+    }
+
+    pub async fn set_kosync_progress(
+        &self,
+        progress: KosyncProgress,
+        user_ref: String,
+    ) -> Result<()> {
+        self.server_store
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO kosync_progress (document, user_ref, progress, percentage, device, device_id, timestamp)
+            VALUES (?, ?, ?, ?, ?, ?, ?)",
+                    params![
+                        progress.document,
+                        user_ref,
+                        progress.progress,
+                        progress.percentage,
+                        progress.device,
+                        progress.device_id,
+                        progress.timestamp
+                    ],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+CREATE TABLE media_markers (
+    media_ref TEXT PRIMARY KEY,
+    intro_start REAL,
+    intro_end REAL,
+    credits_start REAL,
+    credits_end REAL,
+    manual INTEGER NOT NULL DEFAULT 0,
+    modified INTEGER,
+    added INTEGER
+) WITHOUT ROWID;
+
+CREATE TRIGGER inserted_media_markers AFTER INSERT ON media_markers
+BEGIN
+    UPDATE media_markers SET
+        modified = round((julianday('now') - 2440587.5)*86400.0 * 1000),
+        added = round((julianday('now') - 2440587.5)*86400.0 * 1000)
+    WHERE media_ref = NEW.media_ref;
+END;
+
+CREATE TRIGGER modified_media_markers AFTER UPDATE OF intro_start, intro_end, credits_start, credits_end, manual ON media_markers
+BEGIN
+    UPDATE media_markers SET modified = round((julianday('now') - 2440587.5)*86400.0 * 1000)
+    WHERE media_ref = NEW.media_ref;
+END;
+
+CREATE TRIGGER cascade_delete_media_markers AFTER DELETE ON medias
+BEGIN
+    DELETE FROM media_markers WHERE media_ref = OLD.id;
+END;
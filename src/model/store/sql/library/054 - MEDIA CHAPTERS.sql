@@ -0,0 +1,23 @@
+CREATE TABLE media_chapters (
+    media_ref TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    start REAL NOT NULL,
+    end REAL NOT NULL,
+    title TEXT,
+    modified INTEGER,
+    added INTEGER,
+    PRIMARY KEY (media_ref, position)
+) WITHOUT ROWID;
+
+CREATE TRIGGER inserted_media_chapters AFTER INSERT ON media_chapters
+BEGIN
+    UPDATE media_chapters SET
+        modified = round((julianday('now') - 2440587.5)*86400.0 * 1000),
+        added = round((julianday('now') - 2440587.5)*86400.0 * 1000)
+    WHERE media_ref = NEW.media_ref AND position = NEW.position;
+END;
+
+CREATE TRIGGER cascade_delete_media_chapters AFTER DELETE ON medias
+BEGIN
+    DELETE FROM media_chapters WHERE media_ref = OLD.id;
+END;
@@ -0,0 +1,13 @@
+CREATE TABLE epg_programmes (
+    channel TEXT NOT NULL,
+    start INTEGER NOT NULL,
+    stop INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT,
+    category TEXT,
+    episode_num TEXT,
+    icon TEXT,
+    PRIMARY KEY (channel, start)
+) WITHOUT ROWID;
+
+CREATE INDEX epg_programmes_time ON epg_programmes (start, stop);
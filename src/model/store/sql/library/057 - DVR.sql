@@ -0,0 +1,68 @@
+CREATE TABLE dvr_schedules (
+    id TEXT PRIMARY KEY,
+    channel_ref TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    target_library TEXT NOT NULL,
+    name TEXT,
+    description TEXT,
+    start INTEGER,
+    end INTEGER,
+    days TEXT,
+    time INTEGER,
+    duration INTEGER,
+    title TEXT,
+    padding_before INTEGER NOT NULL DEFAULT 0,
+    padding_after INTEGER NOT NULL DEFAULT 0,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    modified INTEGER,
+    added INTEGER
+) WITHOUT ROWID;
+
+CREATE TABLE dvr_recordings (
+    id TEXT PRIMARY KEY,
+    schedule_ref TEXT,
+    channel_ref TEXT NOT NULL,
+    target_library TEXT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    start INTEGER NOT NULL,
+    end INTEGER NOT NULL,
+    padding_before INTEGER NOT NULL DEFAULT 0,
+    padding_after INTEGER NOT NULL DEFAULT 0,
+    status TEXT NOT NULL,
+    media_ref TEXT,
+    error TEXT,
+    modified INTEGER,
+    added INTEGER,
+    UNIQUE (schedule_ref, start)
+) WITHOUT ROWID;
+
+CREATE INDEX dvr_recordings_time ON dvr_recordings (start, end);
+
+CREATE TRIGGER inserted_dvr_schedule AFTER INSERT ON dvr_schedules
+BEGIN
+    UPDATE dvr_schedules SET
+        modified = round((julianday('now') - 2440587.5)*86400.0 * 1000),
+        added = round((julianday('now') - 2440587.5)*86400.0 * 1000)
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER modified_dvr_schedule AFTER UPDATE OF name, description, padding_before, padding_after, enabled ON dvr_schedules
+BEGIN
+    UPDATE dvr_schedules SET modified = round((julianday('now') - 2440587.5)*86400.0 * 1000)
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER inserted_dvr_recording AFTER INSERT ON dvr_recordings
+BEGIN
+    UPDATE dvr_recordings SET
+        modified = round((julianday('now') - 2440587.5)*86400.0 * 1000),
+        added = round((julianday('now') - 2440587.5)*86400.0 * 1000)
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER modified_dvr_recording AFTER UPDATE OF name, description, padding_before, padding_after, status, media_ref, error ON dvr_recordings
+BEGIN
+    UPDATE dvr_recordings SET modified = round((julianday('now') - 2440587.5)*86400.0 * 1000)
+    WHERE id = NEW.id;
+END;
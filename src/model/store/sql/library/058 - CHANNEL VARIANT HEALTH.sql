@@ -0,0 +1,7 @@
+ALTER TABLE channel_variants ADD COLUMN health TEXT;
+ALTER TABLE channel_variants ADD COLUMN latency INTEGER;
+ALTER TABLE channel_variants ADD COLUMN width INTEGER;
+ALTER TABLE channel_variants ADD COLUMN height INTEGER;
+ALTER TABLE channel_variants ADD COLUMN failures INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE channel_variants ADD COLUMN checked INTEGER;
+ALTER TABLE channel_variants ADD COLUMN last_success INTEGER;
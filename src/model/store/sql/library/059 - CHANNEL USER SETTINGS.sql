@@ -0,0 +1,22 @@
+CREATE TABLE channel_user_settings (
+    channel_ref TEXT NOT NULL,
+    user_ref TEXT NOT NULL,
+    favorite INTEGER NOT NULL DEFAULT 0,
+    position INTEGER,
+    number INTEGER,
+    modified INTEGER,
+    PRIMARY KEY (channel_ref, user_ref)
+) WITHOUT ROWID;
+
+CREATE INDEX channel_user_settings_user ON channel_user_settings (user_ref);
+
+CREATE TRIGGER modified_channel_user_settings AFTER UPDATE OF favorite, position, number ON channel_user_settings
+BEGIN
+    UPDATE channel_user_settings SET modified = round((julianday('now') - 2440587.5)*86400.0 * 1000)
+    WHERE channel_ref = NEW.channel_ref AND user_ref = NEW.user_ref;
+END;
+
+CREATE TRIGGER cascade_delete_channel_user_settings AFTER DELETE ON channels
+BEGIN
+    DELETE FROM channel_user_settings WHERE channel_ref = OLD.id;
+END;
@@ -0,0 +1,3 @@
+ALTER TABLE medias ADD COLUMN kohash TEXT;
+
+CREATE INDEX medias_kohash ON medias (kohash);
@@ -0,0 +1,20 @@
+CREATE TABLE media_embeddings (
+    media_ref TEXT PRIMARY KEY NOT NULL,
+    model TEXT NOT NULL,
+    embedding BLOB NOT NULL,
+    modified INTEGER,
+    added INTEGER
+) WITHOUT ROWID;
+
+CREATE TRIGGER inserted_media_embeddings AFTER INSERT ON media_embeddings
+BEGIN
+    UPDATE media_embeddings SET
+        modified = round((julianday('now') - 2440587.5)*86400.0 * 1000),
+        added = round((julianday('now') - 2440587.5)*86400.0 * 1000)
+    WHERE media_ref = NEW.media_ref;
+END;
+
+CREATE TRIGGER cascade_delete_media_embeddings AFTER DELETE ON medias
+BEGIN
+    DELETE FROM media_embeddings WHERE media_ref = OLD.id;
+END;
@@ -0,0 +1,19 @@
+CREATE TABLE media_ocr (
+    media_ref TEXT PRIMARY KEY NOT NULL,
+    text TEXT,
+    modified INTEGER,
+    added INTEGER
+) WITHOUT ROWID;
+
+CREATE TRIGGER inserted_media_ocr AFTER INSERT ON media_ocr
+BEGIN
+    UPDATE media_ocr SET
+        modified = round((julianday('now') - 2440587.5)*86400.0 * 1000),
+        added = round((julianday('now') - 2440587.5)*86400.0 * 1000)
+    WHERE media_ref = NEW.media_ref;
+END;
+
+CREATE TRIGGER cascade_delete_media_ocr AFTER DELETE ON medias
+BEGIN
+    DELETE FROM media_ocr WHERE media_ref = OLD.id;
+END;
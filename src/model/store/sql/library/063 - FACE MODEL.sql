@@ -0,0 +1,8 @@
+-- Existing faces were all produced by the buffalo_l models
+ALTER TABLE people_faces ADD COLUMN model TEXT NOT NULL DEFAULT 'buffalo_l';
+ALTER TABLE people_faces ADD COLUMN model_version INTEGER NOT NULL DEFAULT 1;
+CREATE INDEX idx_people_faces_model ON people_faces(model, model_version);
+
+ALTER TABLE unassigned_faces ADD COLUMN model TEXT NOT NULL DEFAULT 'buffalo_l';
+ALTER TABLE unassigned_faces ADD COLUMN model_version INTEGER NOT NULL DEFAULT 1;
+CREATE INDEX idx_unassigned_faces_model ON unassigned_faces(model, model_version);
@@ -0,0 +1,24 @@
+CREATE TABLE media_markers_seasons (
+    serie_ref TEXT NOT NULL,
+    season INTEGER NOT NULL,
+    episodes INTEGER NOT NULL,
+    analyzed INTEGER,
+    PRIMARY KEY (serie_ref, season)
+) WITHOUT ROWID;
+
+CREATE TRIGGER analyzed_media_markers_seasons AFTER INSERT ON media_markers_seasons
+BEGIN
+    UPDATE media_markers_seasons SET analyzed = round((julianday('now') - 2440587.5)*86400.0 * 1000)
+    WHERE serie_ref = NEW.serie_ref AND season = NEW.season;
+END;
+
+CREATE TRIGGER reanalyzed_media_markers_seasons AFTER UPDATE OF episodes ON media_markers_seasons
+BEGIN
+    UPDATE media_markers_seasons SET analyzed = round((julianday('now') - 2440587.5)*86400.0 * 1000)
+    WHERE serie_ref = NEW.serie_ref AND season = NEW.season;
+END;
+
+CREATE TRIGGER cascade_delete_media_markers_seasons AFTER DELETE ON series
+BEGIN
+    DELETE FROM media_markers_seasons WHERE serie_ref = OLD.id;
+END;
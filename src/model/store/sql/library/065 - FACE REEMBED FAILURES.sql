@@ -0,0 +1,14 @@
+-- Medias whose faces could not all be re-embedded with a face model, skipped by later runs
+CREATE TABLE face_reembed_failures (
+    media_ref TEXT NOT NULL,
+    model TEXT NOT NULL,
+    model_version INTEGER NOT NULL,
+    error TEXT NOT NULL,
+    modified INTEGER NOT NULL DEFAULT (round((julianday('now') - 2440587.5)*86400.0 * 1000)),
+    PRIMARY KEY (media_ref, model, model_version)
+) WITHOUT ROWID;
+
+CREATE TRIGGER cascade_delete_face_reembed_failures AFTER DELETE ON medias
+BEGIN
+    DELETE FROM face_reembed_failures WHERE media_ref = OLD.id;
+END;
@@ -0,0 +1,2 @@
+-- Chapters edited by a user are kept when the media is probed again
+ALTER TABLE media_chapters ADD COLUMN manual INTEGER NOT NULL DEFAULT 0;
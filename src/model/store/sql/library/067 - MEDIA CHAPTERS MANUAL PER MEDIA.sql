@@ -0,0 +1,15 @@
+-- Medias whose chapters were edited by a user, even to remove them all
+CREATE TABLE media_chapters_manual (
+    media_ref TEXT PRIMARY KEY,
+    modified INTEGER NOT NULL DEFAULT (round((julianday('now') - 2440587.5)*86400.0 * 1000))
+) WITHOUT ROWID;
+
+INSERT INTO media_chapters_manual (media_ref)
+    SELECT DISTINCT media_ref FROM media_chapters WHERE manual = 1;
+
+ALTER TABLE media_chapters DROP COLUMN manual;
+
+CREATE TRIGGER cascade_delete_media_chapters_manual AFTER DELETE ON medias
+BEGIN
+    DELETE FROM media_chapters_manual WHERE media_ref = OLD.id;
+END;
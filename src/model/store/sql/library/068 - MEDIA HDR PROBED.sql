@@ -0,0 +1,3 @@
+-- Videos imported before HDR detection are probed again by the HDR backfill task
+ALTER TABLE medias ADD COLUMN hdr_probed INTEGER NOT NULL DEFAULT 0;
+UPDATE medias SET hdr_probed = 1 WHERE hdr IS NOT NULL;
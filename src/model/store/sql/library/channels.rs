@@ -1,11 +1,37 @@
+use std::str::FromStr;
+
 use rs_plugin_common_interfaces::ImageType;
-use rusqlite::{params, OptionalExtension, Row};
+use rusqlite::{
+    params,
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+    OptionalExtension, Row, ToSql,
+};
+
+use crate::domain::channel::{
+    Channel, ChannelForUpdate, ChannelUserSettings, ChannelUserSettingsForUpdate, ChannelVariant,
+    VariantHealth, VariantHealthUpdate,
+};
 
-use crate::domain::channel::{Channel, ChannelForUpdate, ChannelVariant};
+use crate::model::store::sql::{QueryBuilder, QueryWhereType};
 
 use super::{Result, SqliteLibraryStore};
 
+impl FromSql for VariantHealth {
+    fn column_result(value: ValueRef) -> FromSqlResult<Self> {
+        String::column_result(value).and_then(|as_string| {
+            VariantHealth::from_str(&as_string).map_err(|_| FromSqlError::InvalidType)
+        })
+    }
+}
+
+impl ToSql for VariantHealth {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
 const CHANNEL_SELECT: &str = "SELECT c.id, c.name, c.tvg_id, c.logo, c.channel_number, c.posterv, c.modified, c.added, (SELECT GROUP_CONCAT(tag_ref) FROM channel_tag_mapping WHERE channel_ref = c.id) AS tags FROM channels c";
+const VARIANT_SELECT: &str = "SELECT id, channel_ref, quality, stream_url, name, tvg_name, modified, added, health, latency, width, height, failures, checked, last_success FROM channel_variants";
 
 impl SqliteLibraryStore {
     fn row_to_channel(row: &Row) -> rusqlite::Result<Channel> {
@@ -22,6 +48,8 @@ impl SqliteLibraryStore {
             modified: row.get(6)?,
             added: row.get(7)?,
             variants: None,
+            favorite: None,
+            position: None,
         })
     }
 
@@ -35,6 +63,13 @@ impl SqliteLibraryStore {
             tvg_name: row.get(5)?,
             modified: row.get(6)?,
             added: row.get(7)?,
+            health: row.get(8)?,
+            latency: row.get(9)?,
+            width: row.get(10)?,
+            height: row.get(11)?,
+            failures: row.get(12)?,
+            checked: row.get(13)?,
+            last_success: row.get(14)?,
         })
     }
 
@@ -316,11 +351,13 @@ impl SqliteLibraryStore {
         let rows = self
             .connection
             .call(move |conn| {
-                let mut statement = conn.prepare(
-                    "SELECT id, channel_ref, quality, stream_url, name, tvg_name, modified, added FROM channel_variants WHERE channel_ref = ? ORDER BY quality ASC",
-                )?;
+                let mut statement = conn.prepare(&format!(
+                    "{} WHERE channel_ref = ? ORDER BY quality ASC",
+                    VARIANT_SELECT
+                ))?;
                 let rows = statement.query_map([channel_ref], Self::row_to_variant)?;
-                let variants = rows.collect::<std::result::Result<Vec<ChannelVariant>, rusqlite::Error>>()?;
+                let variants =
+                    rows.collect::<std::result::Result<Vec<ChannelVariant>, rusqlite::Error>>()?;
                 Ok(variants)
             })
             .await?;
@@ -357,9 +394,10 @@ impl SqliteLibraryStore {
         let row = self
             .connection
             .call(move |conn| {
-                let mut statement = conn.prepare(
-                    "SELECT id, channel_ref, quality, stream_url, name, tvg_name, modified, added FROM channel_variants WHERE channel_ref = ? AND tvg_name = ? LIMIT 1",
-                )?;
+                let mut statement = conn.prepare(&format!(
+                    "{} WHERE channel_ref = ? AND tvg_name = ? LIMIT 1",
+                    VARIANT_SELECT
+                ))?;
                 let row = statement
                     .query_row(params![channel_ref, tvg_name], Self::row_to_variant)
                     .optional()?;
@@ -369,6 +407,48 @@ impl SqliteLibraryStore {
         Ok(row)
     }
 
+    pub async fn get_all_channel_variants(&self) -> Result<Vec<ChannelVariant>> {
+        let rows = self
+            .connection
+            .call(move |conn| {
+                let mut statement =
+                    conn.prepare(&format!("{} ORDER BY checked ASC", VARIANT_SELECT))?;
+                let rows = statement.query_map([], Self::row_to_variant)?;
+                let variants =
+                    rows.collect::<std::result::Result<Vec<ChannelVariant>, rusqlite::Error>>()?;
+                Ok(variants)
+            })
+            .await?;
+        Ok(rows)
+    }
+
+    pub async fn update_channel_variant_health(
+        &self,
+        variant_id: &str,
+        update: VariantHealthUpdate,
+    ) -> Result<()> {
+        let variant_id = variant_id.to_string();
+        self.connection
+            .call(move |conn| {
+                conn.execute(
+                    "UPDATE channel_variants SET health = ?, latency = ?, width = ?, height = ?, failures = ?, checked = ?, last_success = COALESCE(?, last_success) WHERE id = ?",
+                    params![
+                        update.health,
+                        update.latency,
+                        update.width,
+                        update.height,
+                        update.failures,
+                        update.checked,
+                        update.last_success,
+                        variant_id,
+                    ],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
     pub async fn remove_channel_variant(&self, variant_id: &str) -> Result<()> {
         let variant_id = variant_id.to_string();
         self.connection
@@ -393,4 +473,96 @@ impl SqliteLibraryStore {
             .await?;
         Ok(())
     }
+
+    // --- Channel User Settings ---
+
+    pub async fn get_channel_user_settings(
+        &self,
+        user_ref: &str,
+    ) -> Result<Vec<ChannelUserSettings>> {
+        let user_ref = user_ref.to_string();
+        let rows = self
+            .connection
+            .call(move |conn| {
+                let mut statement = conn.prepare(
+                    "SELECT channel_ref, user_ref, favorite, position, number FROM channel_user_settings WHERE user_ref = ?",
+                )?;
+                let rows = statement.query_map([user_ref], |row| {
+                    Ok(ChannelUserSettings {
+                        channel_ref: row.get(0)?,
+                        user_ref: row.get(1)?,
+                        favorite: row.get(2)?,
+                        position: row.get(3)?,
+                        number: row.get(4)?,
+                    })
+                })?;
+                let settings = rows.collect::<std::result::Result<Vec<ChannelUserSettings>, rusqlite::Error>>()?;
+                Ok(settings)
+            })
+            .await?;
+        Ok(rows)
+    }
+
+    pub async fn update_channel_user_settings(
+        &self,
+        channel_ref: &str,
+        user_ref: &str,
+        update: ChannelUserSettingsForUpdate,
+    ) -> Result<()> {
+        let channel_ref = channel_ref.to_string();
+        let user_ref = user_ref.to_string();
+        self.connection
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT OR IGNORE INTO channel_user_settings (channel_ref, user_ref) VALUES (?, ?)",
+                    params![channel_ref, user_ref],
+                )?;
+                let mut where_query = QueryBuilder::new();
+                where_query.add_update(&update.favorite, "favorite");
+                where_query.add_update(&update.position, "position");
+                where_query.add_update(&update.number, "number");
+                if where_query.format_update().is_empty() {
+                    return Ok(());
+                }
+                where_query.add_where(QueryWhereType::Equal("channel_ref", &channel_ref));
+                where_query.add_where(QueryWhereType::Equal("user_ref", &user_ref));
+                let update_sql = format!(
+                    "UPDATE channel_user_settings SET {} {}",
+                    where_query.format_update(),
+                    where_query.format()
+                );
+                conn.execute(&update_sql, where_query.values())?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Replace the channel order of a user, channels not listed lose their position
+    pub async fn set_channel_user_positions(
+        &self,
+        user_ref: &str,
+        channels: Vec<String>,
+    ) -> Result<()> {
+        let user_ref = user_ref.to_string();
+        self.connection
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                tx.execute(
+                    "UPDATE channel_user_settings SET position = NULL WHERE user_ref = ?",
+                    [&user_ref],
+                )?;
+                for (position, channel_ref) in channels.iter().enumerate() {
+                    tx.execute(
+                        "INSERT INTO channel_user_settings (channel_ref, user_ref, position) VALUES (?, ?, ?)
+                        ON CONFLICT (channel_ref, user_ref) DO UPDATE SET position = excluded.position",
+                        params![channel_ref, user_ref, position as u32],
+                    )?;
+                }
+                tx.commit()?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
 }
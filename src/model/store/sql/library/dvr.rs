@@ -0,0 +1,473 @@
+use std::str::FromStr;
+
+use rusqlite::{
+    params, params_from_iter,
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+    OptionalExtension, Row, ToSql,
+};
+
+use super::{Result, SqliteLibraryStore};
+use crate::{
+    domain::dvr::{
+        DvrRecording, DvrRecordingStatus, DvrSchedule, DvrScheduleForUpdate, DvrScheduleKind,
+    },
+    model::store::sql::{QueryBuilder, QueryWhereType},
+};
+
+const SCHEDULE_SELECT: &str = "SELECT id, channel_ref, kind, target_library, name, description, start, end, days, time, duration, title, padding_before, padding_after, enabled, modified, added FROM dvr_schedules";
+const RECORDING_SELECT: &str = "SELECT id, schedule_ref, channel_ref, target_library, name, description, start, end, padding_before, padding_after, status, media_ref, error, modified, added FROM dvr_recordings";
+
+impl FromSql for DvrScheduleKind {
+    fn column_result(value: ValueRef) -> FromSqlResult<Self> {
+        String::column_result(value).and_then(|as_string| {
+            DvrScheduleKind::from_str(&as_string).map_err(|_| FromSqlError::InvalidType)
+        })
+    }
+}
+
+impl ToSql for DvrScheduleKind {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+impl FromSql for DvrRecordingStatus {
+    fn column_result(value: ValueRef) -> FromSqlResult<Self> {
+        String::column_result(value).and_then(|as_string| {
+            DvrRecordingStatus::from_str(&as_string).map_err(|_| FromSqlError::InvalidType)
+        })
+    }
+}
+
+impl ToSql for DvrRecordingStatus {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+impl SqliteLibraryStore {
+    fn row_to_dvr_schedule(row: &Row) -> rusqlite::Result<DvrSchedule> {
+        let days: Option<String> = row.get(8)?;
+        Ok(DvrSchedule {
+            id: row.get(0)?,
+            channel: row.get(1)?,
+            kind: row.get(2)?,
+            target_library: row.get(3)?,
+            name: row.get(4)?,
+            description: row.get(5)?,
+            start: row.get(6)?,
+            end: row.get(7)?,
+            days: days.map(|d| d.split(',').filter_map(|d| d.parse().ok()).collect()),
+            time: row.get(9)?,
+            duration: row.get(10)?,
+            title: row.get(11)?,
+            padding_before: row.get(12)?,
+            padding_after: row.get(13)?,
+            enabled: row.get(14)?,
+            modified: row.get(15)?,
+            added: row.get(16)?,
+        })
+    }
+
+    fn row_to_dvr_recording(row: &Row) -> rusqlite::Result<DvrRecording> {
+        Ok(DvrRecording {
+            id: row.get(0)?,
+            schedule: row.get(1)?,
+            channel: row.get(2)?,
+            target_library: row.get(3)?,
+            name: row.get(4)?,
+            description: row.get(5)?,
+            start: row.get(6)?,
+            end: row.get(7)?,
+            padding_before: row.get(8)?,
+            padding_after: row.get(9)?,
+            status: row.get(10)?,
+            media: row.get(11)?,
+            error: row.get(12)?,
+            modified: row.get(13)?,
+            added: row.get(14)?,
+        })
+    }
+
+    pub async fn get_dvr_schedules(&self) -> Result<Vec<DvrSchedule>> {
+        let rows = self
+            .connection
+            .call(move |conn| {
+                let mut query = conn.prepare(&format!("{} ORDER BY added", SCHEDULE_SELECT))?;
+                let rows = query.query_map([], Self::row_to_dvr_schedule)?;
+                Ok(rows.collect::<rusqlite::Result<Vec<DvrSchedule>>>()?)
+            })
+            .await?;
+        Ok(rows)
+    }
+
+    pub async fn get_dvr_schedule(&self, schedule_id: &str) -> Result<Option<DvrSchedule>> {
+        let schedule_id = schedule_id.to_string();
+        let row = self
+            .connection
+            .call(move |conn| {
+                let mut query = conn.prepare(&format!("{} WHERE id = ?", SCHEDULE_SELECT))?;
+                Ok(query
+                    .query_row([schedule_id], Self::row_to_dvr_schedule)
+                    .optional()?)
+            })
+            .await?;
+        Ok(row)
+    }
+
+    pub async fn add_dvr_schedule(&self, schedule: DvrSchedule) -> Result<()> {
+        self.connection
+            .call(move |conn| {
+                let days = schedule.days.map(|days| {
+                    days.iter()
+                        .map(|d| d.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                });
+                conn.execute(
+                    "INSERT INTO dvr_schedules (id, channel_ref, kind, target_library, name, description, start, end, days, time, duration, title, padding_before, padding_after, enabled) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    params![
+                        schedule.id,
+                        schedule.channel,
+                        schedule.kind,
+                        schedule.target_library,
+                        schedule.name,
+                        schedule.description,
+                        schedule.start,
+                        schedule.end,
+                        days,
+                        schedule.time,
+                        schedule.duration,
+                        schedule.title,
+                        schedule.padding_before,
+                        schedule.padding_after,
+                        schedule.enabled,
+                    ],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn update_dvr_schedule(
+        &self,
+        schedule_id: &str,
+        update: DvrScheduleForUpdate,
+    ) -> Result<()> {
+        let id = schedule_id.to_string();
+        self.connection
+            .call(move |conn| {
+                let mut where_query = QueryBuilder::new();
+                where_query.add_update(&update.name, "name");
+                where_query.add_update(&update.description, "description");
+                where_query.add_update(&update.padding_before, "padding_before");
+                where_query.add_update(&update.padding_after, "padding_after");
+                where_query.add_update(&update.enabled, "enabled");
+                if where_query.format_update().is_empty() {
+                    return Ok(());
+                }
+                where_query.add_where(QueryWhereType::Equal("id", &id));
+
+                let update_sql = format!(
+                    "UPDATE dvr_schedules SET {} {}",
+                    where_query.format_update(),
+                    where_query.format()
+                );
+                conn.execute(&update_sql, where_query.values())?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove_dvr_schedule(&self, schedule_id: &str) -> Result<()> {
+        let schedule_id = schedule_id.to_string();
+        self.connection
+            .call(move |conn| {
+                conn.execute("DELETE FROM dvr_schedules WHERE id = ?", [schedule_id])?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Recordings ordered by start, optionally restricted to some statuses
+    pub async fn get_dvr_recordings(
+        &self,
+        statuses: Option<Vec<DvrRecordingStatus>>,
+    ) -> Result<Vec<DvrRecording>> {
+        let rows = self
+            .connection
+            .call(move |conn| {
+                let mut sql = RECORDING_SELECT.to_string();
+                let values: Vec<String> = statuses
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect();
+                if !values.is_empty() {
+                    sql.push_str(&format!(
+                        " WHERE status IN ({})",
+                        vec!["?"; values.len()].join(", ")
+                    ));
+                }
+                sql.push_str(" ORDER BY start, added");
+                let mut query = conn.prepare(&sql)?;
+                let rows = query.query_map(params_from_iter(values), Self::row_to_dvr_recording)?;
+                Ok(rows.collect::<rusqlite::Result<Vec<DvrRecording>>>()?)
+            })
+            .await?;
+        Ok(rows)
+    }
+
+    pub async fn get_dvr_recording(&self, recording_id: &str) -> Result<Option<DvrRecording>> {
+        let recording_id = recording_id.to_string();
+        let row = self
+            .connection
+            .call(move |conn| {
+                let mut query = conn.prepare(&format!("{} WHERE id = ?", RECORDING_SELECT))?;
+                Ok(query
+                    .query_row([recording_id], Self::row_to_dvr_recording)
+                    .optional()?)
+            })
+            .await?;
+        Ok(row)
+    }
+
+    /// Insert a recording unless its schedule already has one at the same start.
+    /// Returns true when inserted
+    pub async fn add_dvr_recording(&self, recording: DvrRecording) -> Result<bool> {
+        let inserted = self
+            .connection
+            .call(move |conn| {
+                let inserted = conn.execute(
+                    "INSERT OR IGNORE INTO dvr_recordings (id, schedule_ref, channel_ref, target_library, name, description, start, end, padding_before, padding_after, status) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    params![
+                        recording.id,
+                        recording.schedule,
+                        recording.channel,
+                        recording.target_library,
+                        recording.name,
+                        recording.description,
+                        recording.start,
+                        recording.end,
+                        recording.padding_before,
+                        recording.padding_after,
+                        recording.status,
+                    ],
+                )?;
+                Ok(inserted > 0)
+            })
+            .await?;
+        Ok(inserted)
+    }
+
+    pub async fn update_dvr_recording_status(
+        &self,
+        recording_id: &str,
+        status: DvrRecordingStatus,
+        media: Option<String>,
+        error: Option<String>,
+    ) -> Result<()> {
+        let recording_id = recording_id.to_string();
+        self.connection
+            .call(move |conn| {
+                conn.execute(
+                    "UPDATE dvr_recordings SET status = ?, media_ref = COALESCE(?, media_ref), error = ? WHERE id = ?",
+                    params![status, media, error, recording_id],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Remove recordings of a schedule that did not start yet and return them
+    pub async fn remove_pending_dvr_recordings(
+        &self,
+        schedule_id: &str,
+    ) -> Result<Vec<DvrRecording>> {
+        let schedule_id = schedule_id.to_string();
+        let removed = self
+            .connection
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                let removed = {
+                    let mut query = tx.prepare(&format!(
+                        "{} WHERE schedule_ref = ? AND status IN (?, ?)",
+                        RECORDING_SELECT
+                    ))?;
+                    let rows = query.query_map(
+                        params![
+                            schedule_id,
+                            DvrRecordingStatus::Scheduled,
+                            DvrRecordingStatus::Conflict
+                        ],
+                        Self::row_to_dvr_recording,
+                    )?;
+                    rows.collect::<rusqlite::Result<Vec<DvrRecording>>>()?
+                };
+                tx.execute(
+                    "DELETE FROM dvr_recordings WHERE schedule_ref = ? AND status IN (?, ?)",
+                    params![
+                        schedule_id,
+                        DvrRecordingStatus::Scheduled,
+                        DvrRecordingStatus::Conflict
+                    ],
+                )?;
+                tx.commit()?;
+                Ok(removed)
+            })
+            .await?;
+        Ok(removed)
+    }
+
+    /// Remove the schedules of a removed channel and its recordings that did not start yet,
+    /// returning them. Running and past recordings are kept
+    pub async fn remove_channel_dvr(&self, channel_id: &str) -> Result<Vec<DvrRecording>> {
+        let channel_id = channel_id.to_string();
+        let removed = self
+            .connection
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                let removed = {
+                    let mut query = tx.prepare(&format!(
+                        "{} WHERE channel_ref = ? AND status IN (?, ?)",
+                        RECORDING_SELECT
+                    ))?;
+                    let rows = query.query_map(
+                        params![
+                            channel_id,
+                            DvrRecordingStatus::Scheduled,
+                            DvrRecordingStatus::Conflict
+                        ],
+                        Self::row_to_dvr_recording,
+                    )?;
+                    rows.collect::<rusqlite::Result<Vec<DvrRecording>>>()?
+                };
+                tx.execute(
+                    "DELETE FROM dvr_recordings WHERE channel_ref = ? AND status IN (?, ?)",
+                    params![
+                        channel_id,
+                        DvrRecordingStatus::Scheduled,
+                        DvrRecordingStatus::Conflict
+                    ],
+                )?;
+                tx.execute(
+                    "DELETE FROM dvr_schedules WHERE channel_ref = ?",
+                    params![channel_id],
+                )?;
+                tx.commit()?;
+                Ok(removed)
+            })
+            .await?;
+        Ok(removed)
+    }
+
+    pub async fn remove_dvr_recording(&self, recording_id: &str) -> Result<()> {
+        let recording_id = recording_id.to_string();
+        self.connection
+            .call(move |conn| {
+                conn.execute("DELETE FROM dvr_recordings WHERE id = ?", [recording_id])?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::channel::Channel;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn dvr_schedules_and_recordings_roundtrip() {
+        let connection = tokio_rusqlite::Connection::open_in_memory().await.unwrap();
+        let store = SqliteLibraryStore::new(connection).await.unwrap();
+        store
+            .add_channel(Channel {
+                id: "channel".to_string(),
+                name: "Channel".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let schedule = DvrSchedule {
+            id: "weekly".to_string(),
+            channel: "channel".to_string(),
+            kind: DvrScheduleKind::Weekly,
+            target_library: "videos".to_string(),
+            days: Some(vec![0, 4]),
+            time: Some(1230),
+            duration: Some(45),
+            padding_after: 60,
+            enabled: true,
+            ..Default::default()
+        };
+        store.add_dvr_schedule(schedule.clone()).await.unwrap();
+        store
+            .update_dvr_schedule(
+                "weekly",
+                DvrScheduleForUpdate {
+                    name: Some("Show".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        let stored = store.get_dvr_schedule("weekly").await.unwrap().unwrap();
+        assert_eq!(stored.days, Some(vec![0, 4]));
+        assert_eq!(stored.kind, DvrScheduleKind::Weekly);
+        assert_eq!(stored.name.as_deref(), Some("Show"));
+
+        let recording = |id: &str, start: i64| DvrRecording {
+            id: id.to_string(),
+            schedule: Some("weekly".to_string()),
+            channel: "channel".to_string(),
+            target_library: "videos".to_string(),
+            name: "Show".to_string(),
+            start,
+            end: start + 100,
+            ..Default::default()
+        };
+        assert!(store.add_dvr_recording(recording("a", 100)).await.unwrap());
+        assert!(!store.add_dvr_recording(recording("b", 100)).await.unwrap());
+        assert!(store.add_dvr_recording(recording("c", 500)).await.unwrap());
+        store
+            .update_dvr_recording_status(
+                "a",
+                DvrRecordingStatus::Completed,
+                Some("media".to_string()),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let completed = store
+            .get_dvr_recordings(Some(vec![DvrRecordingStatus::Completed]))
+            .await
+            .unwrap();
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].media.as_deref(), Some("media"));
+
+        let removed = store.remove_pending_dvr_recordings("weekly").await.unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].id, "c");
+        assert_eq!(store.get_dvr_recordings(None).await.unwrap().len(), 1);
+
+        // Removing the channel drops its schedules and pending recordings, not the history
+        assert!(store.add_dvr_recording(recording("d", 900)).await.unwrap());
+        let removed = store.remove_channel_dvr("channel").await.unwrap();
+        store.remove_channel("channel".to_string()).await.unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].id, "d");
+        assert!(store.get_dvr_schedule("weekly").await.unwrap().is_none());
+        let kept = store.get_dvr_recordings(None).await.unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].id, "a");
+    }
+}
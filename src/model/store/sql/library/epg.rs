@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use super::{Result, SqliteLibraryStore};
+use crate::domain::epg::EpgProgramme;
+use rusqlite::{params, params_from_iter, Row};
+
+impl SqliteLibraryStore {
+    fn row_to_epg_programme(row: &Row) -> rusqlite::Result<EpgProgramme> {
+        Ok(EpgProgramme {
+            channel: row.get(0)?,
+            start: row.get(1)?,
+            stop: row.get(2)?,
+            title: row.get(3)?,
+            description: row.get(4)?,
+            category: row.get(5)?,
+            episode_num: row.get(6)?,
+            icon: row.get(7)?,
+        })
+    }
+
+    /// Replace the schedule of each imported channel from its first imported programme
+    /// and remove programmes ended before `prune_before`
+    pub async fn replace_epg_programmes(
+        &self,
+        programmes: Vec<EpgProgramme>,
+        prune_before: i64,
+    ) -> Result<()> {
+        self.connection
+            .call(move |conn| {
+                let mut earliest: HashMap<&str, i64> = HashMap::new();
+                for programme in &programmes {
+                    let start = earliest.entry(&programme.channel).or_insert(programme.start);
+                    *start = (*start).min(programme.start);
+                }
+                let tx = conn.transaction()?;
+                for (channel, start) in earliest {
+                    tx.execute(
+                        "DELETE FROM epg_programmes WHERE channel = ? AND start >= ?",
+                        params![channel, start],
+                    )?;
+                }
+                {
+                    let mut insert = tx.prepare(
+                        "INSERT OR REPLACE INTO epg_programmes (channel, start, stop, title, description, category, episode_num, icon) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                    )?;
+                    for p in &programmes {
+                        insert.execute(params![
+                            p.channel,
+                            p.start,
+                            p.stop,
+                            p.title,
+                            p.description,
+                            p.category,
+                            p.episode_num,
+                            p.icon
+                        ])?;
+                    }
+                }
+                tx.execute(
+                    "DELETE FROM epg_programmes WHERE stop < ?",
+                    params![prune_before],
+                )?;
+                tx.commit()?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Programmes overlapping [from, to[, optionally restricted to some channel tvg ids
+    pub async fn get_epg_programmes(
+        &self,
+        channels: Option<Vec<String>>,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<EpgProgramme>> {
+        let rows = self
+            .connection
+            .call(move |conn| {
+                let mut sql = "SELECT channel, start, stop, title, description, category, episode_num, icon FROM epg_programmes WHERE stop > ? AND start < ?".to_string();
+                let mut values: Vec<rusqlite::types::Value> = vec![from.into(), to.into()];
+                if let Some(channels) = channels {
+                    sql.push_str(&format!(
+                        " AND channel IN ({})",
+                        vec!["?"; channels.len()].join(", ")
+                    ));
+                    values.extend(channels.into_iter().map(rusqlite::types::Value::from));
+                }
+                sql.push_str(" ORDER BY channel, start");
+                let mut query = conn.prepare(&sql)?;
+                let rows = query.query_map(params_from_iter(values), Self::row_to_epg_programme)?;
+                Ok(rows.collect::<rusqlite::Result<Vec<EpgProgramme>>>()?)
+            })
+            .await?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn programme(channel: &str, start: i64, stop: i64, title: &str) -> EpgProgramme {
+        EpgProgramme {
+            channel: channel.to_string(),
+            start,
+            stop,
+            title: title.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn epg_programmes_replace_and_prune() {
+        let connection = tokio_rusqlite::Connection::open_in_memory().await.unwrap();
+        let store = SqliteLibraryStore::new(connection).await.unwrap();
+
+        store
+            .replace_epg_programmes(
+                vec![
+                    programme("a", 0, 100, "old"),
+                    programme("a", 100, 200, "first"),
+                    programme("a", 200, 300, "second"),
+                    programme("b", 100, 300, "other"),
+                ],
+                0,
+            )
+            .await
+            .unwrap();
+        let all = store.get_epg_programmes(None, 0, 1000).await.unwrap();
+        assert_eq!(all.len(), 4);
+
+        // New schedule of "a" replaces programmes from its first start, "b" is untouched
+        store
+            .replace_epg_programmes(
+                vec![
+                    programme("a", 150, 250, "moved"),
+                    programme("a", 250, 400, "last"),
+                ],
+                101,
+            )
+            .await
+            .unwrap();
+        let a = store
+            .get_epg_programmes(Some(vec!["a".to_string()]), 0, 1000)
+            .await
+            .unwrap();
+        let titles: Vec<&str> = a.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles, vec!["first", "moved", "last"]);
+
+        let window = store.get_epg_programmes(None, 260, 270).await.unwrap();
+        let titles: Vec<&str> = window.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles, vec!["last", "other"]);
+    }
+}
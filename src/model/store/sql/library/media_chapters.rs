@@ -0,0 +1,160 @@
+use super::{Result, SqliteLibraryStore};
+use crate::domain::media_chapters::RsMediaChapter;
+use rusqlite::params;
+
+impl SqliteLibraryStore {
+    pub async fn get_media_chapters(&self, media_ref: String) -> Result<Vec<RsMediaChapter>> {
+        let rows = self
+            .connection
+            .call(move |conn| {
+                let mut query = conn.prepare(
+                    "SELECT start, end, title FROM media_chapters WHERE media_ref = ? ORDER BY position",
+                )?;
+                let rows = query.query_map(params![media_ref], |row| {
+                    Ok(RsMediaChapter {
+                        start: row.get(0)?,
+                        end: row.get(1)?,
+                        title: row.get(2)?,
+                    })
+                })?;
+                Ok(rows.collect::<rusqlite::Result<Vec<RsMediaChapter>>>()?)
+            })
+            .await?;
+        Ok(rows)
+    }
+
+    /// True when the chapters of a media were edited by a user
+    pub async fn get_media_chapters_manual(&self, media_ref: String) -> Result<bool> {
+        let manual = self
+            .connection
+            .call(move |conn| {
+                let manual: bool = conn.query_row(
+                    "SELECT EXISTS(SELECT 1 FROM media_chapters_manual WHERE media_ref = ?)",
+                    params![media_ref],
+                    |row| row.get(0),
+                )?;
+                Ok(manual)
+            })
+            .await?;
+        Ok(manual)
+    }
+
+    /// Replace all chapters of a media. `manual` chapters are kept when the media is probed again
+    pub async fn set_media_chapters(
+        &self,
+        media_ref: String,
+        chapters: Vec<RsMediaChapter>,
+        manual: bool,
+    ) -> Result<()> {
+        self.connection
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                tx.execute(
+                    "DELETE FROM media_chapters WHERE media_ref = ?",
+                    params![media_ref],
+                )?;
+                for (position, chapter) in chapters.into_iter().enumerate() {
+                    tx.execute(
+                        "INSERT INTO media_chapters (media_ref, position, start, end, title) VALUES (?, ?, ?, ?, ?)",
+                        params![media_ref, position, chapter.start, chapter.end, chapter.title],
+                    )?;
+                }
+                if manual {
+                    tx.execute(
+                        "INSERT OR REPLACE INTO media_chapters_manual (media_ref) VALUES (?)",
+                        params![media_ref],
+                    )?;
+                } else {
+                    tx.execute(
+                        "DELETE FROM media_chapters_manual WHERE media_ref = ?",
+                        params![media_ref],
+                    )?;
+                }
+                tx.commit()?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn media_chapters_roundtrip() {
+        let connection = tokio_rusqlite::Connection::open_in_memory().await.unwrap();
+        let store = SqliteLibraryStore::new(connection).await.unwrap();
+        store
+            .connection
+            .call(|conn| {
+                conn.execute(
+                    "INSERT INTO medias (id, name, type, mimetype) VALUES ('m1', 'movie', 'video', 'video/mp4')",
+                    [],
+                )?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        let chapters = vec![
+            RsMediaChapter {
+                start: 0.0,
+                end: 90.5,
+                title: Some("Opening".to_string()),
+            },
+            RsMediaChapter {
+                start: 90.5,
+                end: 1200.0,
+                title: None,
+            },
+        ];
+        store
+            .set_media_chapters("m1".to_string(), chapters.clone(), false)
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_media_chapters("m1".to_string()).await.unwrap(),
+            chapters
+        );
+        assert!(!store
+            .get_media_chapters_manual("m1".to_string())
+            .await
+            .unwrap());
+
+        store
+            .set_media_chapters("m1".to_string(), chapters[1..].to_vec(), true)
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_media_chapters("m1".to_string()).await.unwrap(),
+            chapters[1..].to_vec()
+        );
+        assert!(store
+            .get_media_chapters_manual("m1".to_string())
+            .await
+            .unwrap());
+
+        // Removing every chapter by hand is still a manual edit
+        store
+            .set_media_chapters("m1".to_string(), vec![], true)
+            .await
+            .unwrap();
+        assert!(store
+            .get_media_chapters("m1".to_string())
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(store
+            .get_media_chapters_manual("m1".to_string())
+            .await
+            .unwrap());
+
+        store.remove_media("m1".to_string()).await.unwrap();
+        assert!(!store
+            .get_media_chapters_manual("m1".to_string())
+            .await
+            .unwrap());
+    }
+}
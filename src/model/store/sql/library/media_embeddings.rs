@@ -0,0 +1,139 @@
+use super::{Result, SqliteLibraryStore};
+use rusqlite::{params, OptionalExtension};
+
+impl SqliteLibraryStore {
+    pub async fn get_media_embedding(
+        &self,
+        media_ref: String,
+        model: String,
+    ) -> Result<Option<Vec<f32>>> {
+        let embedding = self
+            .connection
+            .call(move |conn| {
+                let embedding_blob: Option<Vec<u8>> = conn
+                    .query_row(
+                        "SELECT embedding FROM media_embeddings WHERE media_ref = ? AND model = ?",
+                        params![media_ref, model],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                Ok(embedding_blob.map(|embedding_blob| {
+                    bytemuck::cast_slice::<u8, f32>(&embedding_blob).to_vec()
+                }))
+            })
+            .await?;
+        Ok(embedding)
+    }
+
+    /// All embeddings computed with a model, by media id
+    pub async fn get_media_embeddings(&self, model: String) -> Result<Vec<(String, Vec<f32>)>> {
+        let rows = self
+            .connection
+            .call(move |conn| {
+                let mut query = conn
+                    .prepare("SELECT media_ref, embedding FROM media_embeddings WHERE model = ?")?;
+                let rows = query.query_map(params![model], |row| {
+                    let embedding_blob: Vec<u8> = row.get(1)?;
+                    Ok((
+                        row.get(0)?,
+                        bytemuck::cast_slice::<u8, f32>(&embedding_blob).to_vec(),
+                    ))
+                })?;
+                Ok(rows.collect::<rusqlite::Result<Vec<(String, Vec<f32>)>>>()?)
+            })
+            .await?;
+        Ok(rows)
+    }
+
+    /// Replace the embedding of a media
+    pub async fn set_media_embedding(
+        &self,
+        media_ref: String,
+        model: String,
+        embedding: Vec<f32>,
+    ) -> Result<()> {
+        self.connection
+            .call(move |conn| {
+                let embedding_blob = bytemuck::cast_slice::<f32, u8>(&embedding).to_vec();
+                conn.execute(
+                    "INSERT OR REPLACE INTO media_embeddings (media_ref, model, embedding) VALUES (?, ?, ?)",
+                    params![media_ref, model, embedding_blob],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Photos and videos without an embedding for this model
+    pub async fn get_medias_without_embedding(&self, model: String) -> Result<Vec<String>> {
+        let rows = self
+            .connection
+            .call(move |conn| {
+                let mut query = conn.prepare(
+                    "SELECT m.id FROM medias m
+                    LEFT JOIN media_embeddings e ON e.media_ref = m.id AND e.model = ?
+                    WHERE m.type IN ('photo', 'video') AND e.media_ref IS NULL
+                    ORDER BY m.added DESC",
+                )?;
+                let rows = query.query_map(params![model], |row| row.get(0))?;
+                Ok(rows.collect::<rusqlite::Result<Vec<String>>>()?)
+            })
+            .await?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn media_embeddings_roundtrip() {
+        let connection = tokio_rusqlite::Connection::open_in_memory().await.unwrap();
+        let store = SqliteLibraryStore::new(connection).await.unwrap();
+        store
+            .connection
+            .call(|conn| {
+                conn.execute(
+                    "INSERT INTO medias (id, name, type, mimetype) VALUES ('m1', 'photo', 'photo', 'image/jpeg'), ('m2', 'movie', 'video', 'video/mp4')",
+                    [],
+                )?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+        let model = "clip".to_string();
+
+        store
+            .set_media_embedding("m1".to_string(), model.clone(), vec![0.6, 0.8])
+            .await
+            .unwrap();
+        assert_eq!(
+            store
+                .get_media_embedding("m1".to_string(), model.clone())
+                .await
+                .unwrap(),
+            Some(vec![0.6, 0.8])
+        );
+        assert_eq!(
+            store
+                .get_medias_without_embedding(model.clone())
+                .await
+                .unwrap(),
+            vec!["m2".to_string()]
+        );
+
+        store
+            .set_media_embedding("m1".to_string(), model.clone(), vec![1.0, 0.0])
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_media_embeddings(model.clone()).await.unwrap(),
+            vec![("m1".to_string(), vec![1.0, 0.0])]
+        );
+
+        store.remove_media("m1".to_string()).await.unwrap();
+        assert!(store.get_media_embeddings(model).await.unwrap().is_empty());
+    }
+}
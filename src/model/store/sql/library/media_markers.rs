@@ -0,0 +1,253 @@
+use super::{Result, SqliteLibraryStore};
+use crate::domain::media_markers::RsMediaMarkers;
+use rusqlite::{params, params_from_iter, OptionalExtension, Row};
+
+impl SqliteLibraryStore {
+    fn row_to_media_markers(row: &Row) -> rusqlite::Result<RsMediaMarkers> {
+        Ok(RsMediaMarkers {
+            media_ref: row.get(0)?,
+            intro_start: row.get(1)?,
+            intro_end: row.get(2)?,
+            credits_start: row.get(3)?,
+            credits_end: row.get(4)?,
+            manual: row.get(5)?,
+            modified: row.get(6)?,
+        })
+    }
+
+    pub async fn get_media_markers(&self, media_ref: String) -> Result<Option<RsMediaMarkers>> {
+        let row = self
+            .connection
+            .call(move |conn| {
+                let mut query = conn.prepare(
+                    "SELECT media_ref, intro_start, intro_end, credits_start, credits_end, manual, modified FROM media_markers WHERE media_ref = ?",
+                )?;
+                let row = query
+                    .query_row(params![media_ref], Self::row_to_media_markers)
+                    .optional()?;
+                Ok(row)
+            })
+            .await?;
+        Ok(row)
+    }
+
+    /// Markers of several medias, medias without markers are omitted
+    pub async fn get_medias_markers(&self, media_refs: Vec<String>) -> Result<Vec<RsMediaMarkers>> {
+        let rows = self
+            .connection
+            .call(move |conn| {
+                let mut markers = Vec::with_capacity(media_refs.len());
+                for chunk in media_refs.chunks(500) {
+                    let mut query = conn.prepare(&format!(
+                        "SELECT media_ref, intro_start, intro_end, credits_start, credits_end, manual, modified FROM media_markers WHERE media_ref IN ({})",
+                        vec!["?"; chunk.len()].join(",")
+                    ))?;
+                    let rows = query.query_map(params_from_iter(chunk), Self::row_to_media_markers)?;
+                    for row in rows {
+                        markers.push(row?);
+                    }
+                }
+                Ok(markers)
+            })
+            .await?;
+        Ok(rows)
+    }
+
+    pub async fn set_media_markers(&self, markers: RsMediaMarkers) -> Result<()> {
+        self.connection
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT INTO media_markers (media_ref, intro_start, intro_end, credits_start, credits_end, manual)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(media_ref) DO UPDATE SET intro_start = excluded.intro_start, intro_end = excluded.intro_end,
+            credits_start = excluded.credits_start, credits_end = excluded.credits_end, manual = excluded.manual",
+                    params![
+                        markers.media_ref,
+                        markers.intro_start,
+                        markers.intro_end,
+                        markers.credits_start,
+                        markers.credits_end,
+                        markers.manual
+                    ],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove_media_markers(&self, media_ref: String) -> Result<()> {
+        self.connection
+            .call(move |conn| {
+                conn.execute(
+                    "DELETE FROM media_markers WHERE media_ref = ?",
+                    params![media_ref],
+                )?;
+                conn.execute(
+                    "DELETE FROM media_markers_seasons WHERE (serie_ref, season) IN
+                    (SELECT serie_ref, season FROM media_serie_mapping WHERE media_ref = ?)",
+                    params![media_ref],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Record that a season was analyzed with this number of episodes, so it is not analyzed
+    /// again until its episodes change, even if no markers could be stored
+    pub async fn set_season_markers_analyzed(
+        &self,
+        serie_ref: String,
+        season: u32,
+        episodes: u32,
+    ) -> Result<()> {
+        self.connection
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT INTO media_markers_seasons (serie_ref, season, episodes) VALUES (?, ?, ?)
+            ON CONFLICT(serie_ref, season) DO UPDATE SET episodes = excluded.episodes",
+                    params![serie_ref, season, episodes],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Seasons (serie_ref, season) having at least one video never analyzed for markers,
+    /// unless the season was already analyzed with the same number of episodes
+    pub async fn get_seasons_without_markers(&self) -> Result<Vec<(String, u32)>> {
+        let rows = self
+            .connection
+            .call(move |conn| {
+                let mut query = conn.prepare(
+                    "SELECT DISTINCT msm.serie_ref, msm.season FROM media_serie_mapping msm
+                    JOIN medias m ON m.id = msm.media_ref
+                    WHERE m.type = 'video' AND msm.season IS NOT NULL
+                    AND NOT EXISTS (SELECT 1 FROM media_markers mm WHERE mm.media_ref = msm.media_ref)
+                    AND NOT EXISTS (SELECT 1 FROM media_markers_seasons mms
+                        WHERE mms.serie_ref = msm.serie_ref AND mms.season = msm.season
+                        AND mms.episodes = (SELECT COUNT(DISTINCT e.media_ref) FROM media_serie_mapping e
+                            JOIN medias em ON em.id = e.media_ref
+                            WHERE em.type = 'video' AND e.serie_ref = msm.serie_ref
+                            AND e.season = msm.season AND e.episode IS NOT NULL))",
+                )?;
+                let rows = query.query_map(params![], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                Ok(rows.collect::<rusqlite::Result<Vec<(String, u32)>>>()?)
+            })
+            .await?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn media_markers_roundtrip() {
+        let connection = tokio_rusqlite::Connection::open_in_memory().await.unwrap();
+        let store = SqliteLibraryStore::new(connection).await.unwrap();
+        store
+            .connection
+            .call(|conn| {
+                conn.execute("INSERT INTO series (id, name) VALUES ('s1', 'Serie')", [])?;
+                conn.execute(
+                    "INSERT INTO medias (id, name, type, mimetype) VALUES ('m1', 'ep1', 'video', 'video/mp4')",
+                    [],
+                )?;
+                conn.execute(
+                    "INSERT INTO media_serie_mapping (media_ref, serie_ref, season, episode) VALUES ('m1', 's1', 1, 1)",
+                    [],
+                )?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        let seasons = store.get_seasons_without_markers().await.unwrap();
+        assert_eq!(seasons, vec![("s1".to_string(), 1)]);
+
+        // A single episode season analyzed without result is skipped until episodes change
+        store
+            .set_season_markers_analyzed("s1".to_string(), 1, 1)
+            .await
+            .unwrap();
+        assert!(store
+            .get_seasons_without_markers()
+            .await
+            .unwrap()
+            .is_empty());
+        store
+            .connection
+            .call(|conn| {
+                conn.execute(
+                    "INSERT INTO medias (id, name, type, mimetype) VALUES ('m2', 'ep2', 'video', 'video/mp4')",
+                    [],
+                )?;
+                conn.execute(
+                    "INSERT INTO media_serie_mapping (media_ref, serie_ref, season, episode) VALUES ('m2', 's1', 1, 2)",
+                    [],
+                )?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_seasons_without_markers().await.unwrap(),
+            vec![("s1".to_string(), 1)]
+        );
+        store.remove_media("m2".to_string()).await.unwrap();
+
+        let markers = RsMediaMarkers {
+            media_ref: "m1".to_string(),
+            intro_start: Some(12.5),
+            intro_end: Some(62.0),
+            ..Default::default()
+        };
+        store.set_media_markers(markers.clone()).await.unwrap();
+        let stored = store
+            .get_media_markers("m1".to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.intro_start, Some(12.5));
+        assert_eq!(stored.credits_start, None);
+        assert!(stored.modified > 0);
+        let batch = store
+            .get_medias_markers(vec!["m1".to_string(), "m2".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0].media_ref, "m1");
+        assert!(store
+            .get_seasons_without_markers()
+            .await
+            .unwrap()
+            .is_empty());
+
+        store
+            .set_media_markers(RsMediaMarkers {
+                credits_start: Some(1200.0),
+                manual: true,
+                ..markers
+            })
+            .await
+            .unwrap();
+        let stored = store
+            .get_media_markers("m1".to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.credits_start, Some(1200.0));
+        assert!(stored.manual);
+
+        store.remove_media("m1".to_string()).await.unwrap();
+        assert!(store
+            .get_media_markers("m1".to_string())
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
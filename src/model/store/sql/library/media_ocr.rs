@@ -0,0 +1,107 @@
+use super::{Result, SqliteLibraryStore};
+use rusqlite::{params, OptionalExtension};
+
+impl SqliteLibraryStore {
+    pub async fn get_media_ocr(&self, media_ref: String) -> Result<Option<String>> {
+        let text = self
+            .connection
+            .call(move |conn| {
+                let text: Option<Option<String>> = conn
+                    .query_row(
+                        "SELECT text FROM media_ocr WHERE media_ref = ?",
+                        params![media_ref],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                Ok(text.flatten())
+            })
+            .await?;
+        Ok(text)
+    }
+
+    /// Replace the recognized text of a media. An empty result is stored as NULL so the
+    /// media is not processed again
+    pub async fn set_media_ocr(&self, media_ref: String, text: Option<String>) -> Result<()> {
+        self.connection
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO media_ocr (media_ref, text) VALUES (?, ?)",
+                    params![media_ref, text],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Photos never processed by OCR
+    pub async fn get_medias_without_ocr(&self) -> Result<Vec<String>> {
+        let rows = self
+            .connection
+            .call(move |conn| {
+                let mut query = conn.prepare(
+                    "SELECT m.id FROM medias m
+                    LEFT JOIN media_ocr o ON o.media_ref = m.id
+                    WHERE m.type = 'photo' AND o.media_ref IS NULL
+                    ORDER BY m.added DESC",
+                )?;
+                let rows = query.query_map([], |row| row.get(0))?;
+                Ok(rows.collect::<rusqlite::Result<Vec<String>>>()?)
+            })
+            .await?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{domain::library::LibraryLimits, model::medias::MediaQuery};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn media_ocr_roundtrip_and_search() {
+        let connection = tokio_rusqlite::Connection::open_in_memory().await.unwrap();
+        let store = SqliteLibraryStore::new(connection).await.unwrap();
+        store
+            .connection
+            .call(|conn| {
+                conn.execute(
+                    "INSERT INTO medias (id, name, type, mimetype) VALUES ('m1', 'IMG_0001.jpg', 'photo', 'image/jpeg'), ('m2', 'IMG_0002.jpg', 'photo', 'image/jpeg')",
+                    [],
+                )?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        store
+            .set_media_ocr("m1".to_string(), Some("TOTAL 12.50 EUR".to_string()))
+            .await
+            .unwrap();
+        store.set_media_ocr("m2".to_string(), None).await.unwrap();
+        assert_eq!(
+            store.get_media_ocr("m1".to_string()).await.unwrap(),
+            Some("TOTAL 12.50 EUR".to_string())
+        );
+        assert_eq!(store.get_media_ocr("m2".to_string()).await.unwrap(), None);
+        assert!(store.get_medias_without_ocr().await.unwrap().is_empty());
+
+        let found = store
+            .get_medias(
+                MediaQuery {
+                    text: Some("12.50".to_string()),
+                    ..Default::default()
+                },
+                LibraryLimits::default(),
+            )
+            .await
+            .unwrap();
+        let ids: Vec<&str> = found.iter().map(|m| m.item.id.as_str()).collect();
+        assert_eq!(ids, vec!["m1"]);
+
+        store.remove_media("m1".to_string()).await.unwrap();
+        assert_eq!(store.get_media_ocr("m1".to_string()).await.unwrap(), None);
+        assert!(store.get_medias_without_ocr().await.unwrap().is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use std::u64;
+use std::{collections::HashMap, str::FromStr, u64};
 
 use chrono::Utc;
 use rs_plugin_common_interfaces::{
@@ -19,6 +19,7 @@ use super::{Result, SqliteLibraryStore};
 use crate::model::Error;
 use crate::{
     domain::{
+        ffmpeg::HdrFormat,
         library::LibraryLimits,
         media::{
             self, FileEpisode, FileType, Media, MediaForInsert, MediaForUpdate, MediaItemReference,
@@ -56,6 +57,20 @@ pub struct MediaBackup {
     pub hash: String,
 }
 
+impl FromSql for HdrFormat {
+    fn column_result(value: ValueRef) -> FromSqlResult<Self> {
+        String::column_result(value).and_then(|as_string| {
+            HdrFormat::from_str(&as_string).map_err(|_| FromSqlError::InvalidType)
+        })
+    }
+}
+
+impl ToSql for HdrFormat {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
 impl RsSort {
     pub fn to_media_query(&self) -> String {
         match self {
@@ -272,6 +287,10 @@ impl SqliteLibraryStore {
             where_query.add_where(SqlWhereType::Or(vec![
                 SqlWhereType::Like("name".to_owned(), Box::new(text.clone())),
                 SqlWhereType::Like("description".to_owned(), Box::new(text.clone())),
+                SqlWhereType::Custom(
+                    "m.id IN (SELECT media_ref FROM media_ocr WHERE text like ?)".to_owned(),
+                    Box::new(text.clone()),
+                ),
             ]));
         }
         let sort = query.sort.to_media_query();
@@ -453,6 +472,14 @@ impl SqliteLibraryStore {
         if let Some(book) = query.book {
             where_query.add_where(SqlWhereType::Equal("book".to_string(), Box::new(book)));
         }
+        if !query.ids.is_empty() {
+            let ids = query
+                .ids
+                .into_iter()
+                .map(|id| Box::new(id) as Box<dyn ToSql>)
+                .collect();
+            where_query.add_where(SqlWhereType::In("m.id".to_string(), ids));
+        }
 
         if let Some(duration) = query.min_duration {
             where_query.add_where(SqlWhereType::After(
@@ -501,6 +528,14 @@ impl SqliteLibraryStore {
             ));
         }
 
+        if let Some(hdr) = query.hdr {
+            where_query.add_where(SqlWhereType::Static(if hdr {
+                "m.hdr IS NOT NULL".to_string()
+            } else {
+                "m.hdr IS NULL".to_string()
+            }));
+        }
+
         if let Some(key) = query.uploadkey {
             where_query.add_where(SqlWhereType::Equal("uploadkey".to_string(), Box::new(key)));
         }
@@ -722,6 +757,153 @@ impl SqliteLibraryStore {
         Ok(rows)
     }
 
+    /// Get ids of all medias of a given type, newest first
+    pub async fn get_media_ids_by_type(&self, kind: FileType) -> Result<Vec<String>> {
+        let rows = self
+            .connection
+            .call(move |conn| {
+                let mut query =
+                    conn.prepare("SELECT id FROM medias WHERE type = ? ORDER BY added DESC")?;
+                let rows = query.query_map(params![kind], |row| row.get::<_, String>(0))?;
+                Ok(rows.collect::<rusqlite::Result<Vec<String>>>()?)
+            })
+            .await?;
+        Ok(rows)
+    }
+
+    pub async fn get_media_hdr(&self, media_id: String) -> Result<Option<HdrFormat>> {
+        let hdr = self
+            .connection
+            .call(move |conn| {
+                let hdr = conn
+                    .query_row(
+                        "SELECT hdr FROM medias WHERE id = ?",
+                        params![media_id],
+                        |row| row.get::<_, Option<HdrFormat>>(0),
+                    )
+                    .optional()?;
+                Ok(hdr.flatten())
+            })
+            .await?;
+        Ok(hdr)
+    }
+
+    pub async fn set_media_hdr(&self, media_id: String, hdr: Option<HdrFormat>) -> Result<()> {
+        self.connection
+            .call(move |conn| {
+                conn.execute(
+                    "UPDATE medias SET hdr = ?, hdr_probed = 1 WHERE id = ?",
+                    params![hdr, media_id],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// HDR format of the listed medias, medias without HDR are left out
+    pub async fn get_medias_hdr(
+        &self,
+        media_ids: Vec<String>,
+    ) -> Result<HashMap<String, HdrFormat>> {
+        if media_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let hdrs = self
+            .connection
+            .call(move |conn| {
+                let sql = format!(
+                    "SELECT id, hdr FROM medias WHERE hdr IS NOT NULL AND id IN ({})",
+                    vec!["?"; media_ids.len()].join(", ")
+                );
+                let mut query = conn.prepare(&sql)?;
+                let rows = query.query_map(params_from_iter(media_ids), |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, HdrFormat>(1)?))
+                })?;
+                Ok(rows.collect::<rusqlite::Result<HashMap<String, HdrFormat>>>()?)
+            })
+            .await?;
+        Ok(hdrs)
+    }
+
+    /// Ids of videos never probed for HDR, imported before HDR detection
+    pub async fn get_media_ids_without_hdr_probe(&self) -> Result<Vec<String>> {
+        let ids = self
+            .connection
+            .call(move |conn| {
+                let mut query =
+                    conn.prepare("SELECT id FROM medias WHERE hdr_probed = 0 AND type = ?")?;
+                let rows =
+                    query.query_map(params![FileType::Video], |row| row.get::<_, String>(0))?;
+                Ok(rows.collect::<std::result::Result<Vec<String>, rusqlite::Error>>()?)
+            })
+            .await?;
+        Ok(ids)
+    }
+
+    /// Set the KOReader partial md5 of a media
+    pub async fn set_media_kohash(&self, media_id: &str, hash: &str) -> Result<()> {
+        let id = media_id.to_string();
+        let hash = hash.to_string();
+        self.connection
+            .call(move |conn| {
+                conn.execute(
+                    "UPDATE medias SET kohash = ? WHERE id = ?",
+                    params![hash, id],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_media_ids_by_kohash(&self, hash: &str) -> Result<Vec<String>> {
+        let hash = hash.to_string();
+        let ids = self
+            .connection
+            .call(move |conn| {
+                let mut query = conn.prepare("SELECT id FROM medias WHERE kohash = ?")?;
+                let rows = query.query_map(params![hash], |row| row.get::<_, String>(0))?;
+                Ok(rows.collect::<std::result::Result<Vec<String>, rusqlite::Error>>()?)
+            })
+            .await?;
+        Ok(ids)
+    }
+
+    /// Ids of books and comics without KOReader hash
+    pub async fn get_media_ids_without_kohash(&self) -> Result<Vec<String>> {
+        let ids = self
+            .connection
+            .call(move |conn| {
+                let mut query = conn.prepare(
+                    "SELECT id FROM medias WHERE kohash IS NULL AND type IN (?, ?)",
+                )?;
+                let rows = query.query_map(params![FileType::Book, FileType::Album], |row| {
+                    row.get::<_, String>(0)
+                })?;
+                Ok(rows.collect::<std::result::Result<Vec<String>, rusqlite::Error>>()?)
+            })
+            .await?;
+        Ok(ids)
+    }
+
+    /// Set a single key of the media `params` json, keeping the other keys
+    pub async fn set_media_param(&self, media_id: &str, key: &str, value: &str) -> Result<()> {
+        let id = media_id.to_string();
+        let path = format!("$.{}", key);
+        let value = value.to_string();
+        self.connection
+            .call(move |conn| {
+                conn.execute(
+                    "UPDATE medias SET params = json_set(COALESCE(params, '{}'), ?, ?) WHERE id = ?",
+                    params![path, value, id],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
     /// Update the source field for a media record (used after re-encrypting plugin files)
     pub async fn update_media_source(&self, media_id: &str, new_source: &str) -> Result<()> {
         let id = media_id.to_string();
@@ -1293,6 +1475,23 @@ impl SqliteLibraryStore {
         Ok(())
     }
 
+    /// Remove the AI generated tags of a media before tagging it again. Tags rejected by
+    /// users (confidence -1) are kept
+    pub async fn remove_media_generated_tags(&self, media_id: String) -> Result<()> {
+        self.connection
+            .call(move |conn| {
+                conn.execute(
+                    "DELETE FROM media_tag_mapping WHERE media_ref = ?
+                    AND (confidence IS NULL OR confidence != -1)
+                    AND tag_ref IN (SELECT id FROM tags WHERE generated = 1 AND path LIKE '/ai/%')",
+                    params![media_id],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
     pub async fn remove_media(&self, media_id: String) -> Result<()> {
         self.connection
             .call(move |conn| {
@@ -1382,4 +1581,40 @@ mod tests {
         assert_eq!(by_book.len(), 1);
         assert_eq!(by_book[0].item.id, media_id);
     }
+
+    #[tokio::test]
+    async fn remove_generated_tags_keeps_user_and_rejected_tags() {
+        let connection = tokio_rusqlite::Connection::open_in_memory().await.unwrap();
+        let store = SqliteLibraryStore::new(connection).await.unwrap();
+        store
+            .connection
+            .call(|conn| {
+                conn.execute_batch(
+                    "INSERT INTO medias (id, name, type, mimetype) VALUES ('m1', 'a.jpg', 'photo', 'image/jpeg');
+                    INSERT INTO tags (id, name, path, generated) VALUES ('ai', 'ai', '/', 1), ('cat', 'cat', '/ai/', 1), ('dog', 'dog', '/ai/', 1), ('holidays', 'holidays', '/', 0);
+                    INSERT INTO media_tag_mapping (media_ref, tag_ref, confidence) VALUES ('m1', 'cat', 80), ('m1', 'dog', -1), ('m1', 'holidays', NULL);",
+                )?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        store
+            .remove_media_generated_tags("m1".to_string())
+            .await
+            .unwrap();
+
+        let tags = store
+            .connection
+            .call(|conn| {
+                let mut query = conn.prepare(
+                    "SELECT tag_ref FROM media_tag_mapping WHERE media_ref = 'm1' ORDER BY tag_ref",
+                )?;
+                let rows = query.query_map([], |row| row.get(0))?;
+                Ok(rows.collect::<rusqlite::Result<Vec<String>>>()?)
+            })
+            .await
+            .unwrap();
+        assert_eq!(tags, vec!["dog".to_string(), "holidays".to_string()]);
+    }
 }
@@ -14,7 +14,13 @@ use super::Result;
 pub mod books;
 pub mod channels;
 pub mod deleted;
+pub mod dvr;
+pub mod epg;
 pub mod episodes;
+pub mod media_chapters;
+pub mod media_embeddings;
+pub mod media_markers;
+pub mod media_ocr;
 pub mod media_progress;
 pub mod media_ratings;
 pub mod medias;
@@ -329,6 +335,191 @@ impl SqliteLibraryStore {
                         format!("Update Library Database to version: {}", version),
                     );
                 }
+                if version < 53 {
+                    let initial =
+                        String::from_utf8_lossy(include_bytes!("053 - MEDIA MARKERS.sql"));
+                    conn.execute_batch(&initial)?;
+                    version = 53;
+                    conn.pragma_update(None, "user_version", version)?;
+                    log_info(
+                        LogServiceType::Database,
+                        format!("Update Library Database to version: {}", version),
+                    );
+                }
+                if version < 54 {
+                    let initial =
+                        String::from_utf8_lossy(include_bytes!("054 - MEDIA CHAPTERS.sql"));
+                    conn.execute_batch(&initial)?;
+                    version = 54;
+                    conn.pragma_update(None, "user_version", version)?;
+                    log_info(
+                        LogServiceType::Database,
+                        format!("Update Library Database to version: {}", version),
+                    );
+                }
+                if version < 55 {
+                    let initial = String::from_utf8_lossy(include_bytes!("055 - MEDIA HDR.sql"));
+                    conn.execute_batch(&initial)?;
+                    version = 55;
+                    conn.pragma_update(None, "user_version", version)?;
+                    log_info(
+                        LogServiceType::Database,
+                        format!("Update Library Database to version: {}", version),
+                    );
+                }
+                if version < 56 {
+                    let initial =
+                        String::from_utf8_lossy(include_bytes!("056 - EPG PROGRAMMES.sql"));
+                    conn.execute_batch(&initial)?;
+                    version = 56;
+                    conn.pragma_update(None, "user_version", version)?;
+                    log_info(
+                        LogServiceType::Database,
+                        format!("Update Library Database to version: {}", version),
+                    );
+                }
+                if version < 57 {
+                    let initial = String::from_utf8_lossy(include_bytes!("057 - DVR.sql"));
+                    conn.execute_batch(&initial)?;
+                    version = 57;
+                    conn.pragma_update(None, "user_version", version)?;
+                    log_info(
+                        LogServiceType::Database,
+                        format!("Update Library Database to version: {}", version),
+                    );
+                }
+
+                if version < 58 {
+                    let initial = String::from_utf8_lossy(include_bytes!(
+                        "058 - CHANNEL VARIANT HEALTH.sql"
+                    ));
+                    conn.execute_batch(&initial)?;
+                    version = 58;
+                    conn.pragma_update(None, "user_version", version)?;
+                    log_info(
+                        LogServiceType::Database,
+                        format!("Update Library Database to version: {}", version),
+                    );
+                }
+
+                if version < 59 {
+                    let initial = String::from_utf8_lossy(include_bytes!(
+                        "059 - CHANNEL USER SETTINGS.sql"
+                    ));
+                    conn.execute_batch(&initial)?;
+                    version = 59;
+                    conn.pragma_update(None, "user_version", version)?;
+                    log_info(
+                        LogServiceType::Database,
+                        format!("Update Library Database to version: {}", version),
+                    );
+                }
+
+                if version < 60 {
+                    let initial = String::from_utf8_lossy(include_bytes!("060 - KOSYNC HASH.sql"));
+                    conn.execute_batch(&initial)?;
+                    version = 60;
+                    conn.pragma_update(None, "user_version", version)?;
+                    log_info(
+                        LogServiceType::Database,
+                        format!("Update Library Database to version: {}", version),
+                    );
+                }
+
+                if version < 61 {
+                    let initial =
+                        String::from_utf8_lossy(include_bytes!("061 - MEDIA EMBEDDINGS.sql"));
+                    conn.execute_batch(&initial)?;
+                    version = 61;
+                    conn.pragma_update(None, "user_version", version)?;
+                    log_info(
+                        LogServiceType::Database,
+                        format!("Update Library Database to version: {}", version),
+                    );
+                }
+
+                if version < 62 {
+                    let initial = String::from_utf8_lossy(include_bytes!("062 - MEDIA OCR.sql"));
+                    conn.execute_batch(&initial)?;
+                    version = 62;
+                    conn.pragma_update(None, "user_version", version)?;
+                    log_info(
+                        LogServiceType::Database,
+                        format!("Update Library Database to version: {}", version),
+                    );
+                }
+
+                if version < 63 {
+                    let initial = String::from_utf8_lossy(include_bytes!("063 - FACE MODEL.sql"));
+                    conn.execute_batch(&initial)?;
+                    version = 63;
+                    conn.pragma_update(None, "user_version", version)?;
+                    log_info(
+                        LogServiceType::Database,
+                        format!("Update Library Database to version: {}", version),
+                    );
+                }
+
+                if version < 64 {
+                    let initial =
+                        String::from_utf8_lossy(include_bytes!("064 - MEDIA MARKERS SEASONS.sql"));
+                    conn.execute_batch(&initial)?;
+                    version = 64;
+                    conn.pragma_update(None, "user_version", version)?;
+                    log_info(
+                        LogServiceType::Database,
+                        format!("Update Library Database to version: {}", version),
+                    );
+                }
+
+                if version < 65 {
+                    let initial =
+                        String::from_utf8_lossy(include_bytes!("065 - FACE REEMBED FAILURES.sql"));
+                    conn.execute_batch(&initial)?;
+                    version = 65;
+                    conn.pragma_update(None, "user_version", version)?;
+                    log_info(
+                        LogServiceType::Database,
+                        format!("Update Library Database to version: {}", version),
+                    );
+                }
+
+                if version < 66 {
+                    let initial =
+                        String::from_utf8_lossy(include_bytes!("066 - MEDIA CHAPTERS MANUAL.sql"));
+                    conn.execute_batch(&initial)?;
+                    version = 66;
+                    conn.pragma_update(None, "user_version", version)?;
+                    log_info(
+                        LogServiceType::Database,
+                        format!("Update Library Database to version: {}", version),
+                    );
+                }
+
+                if version < 67 {
+                    let initial = String::from_utf8_lossy(include_bytes!(
+                        "067 - MEDIA CHAPTERS MANUAL PER MEDIA.sql"
+                    ));
+                    conn.execute_batch(&initial)?;
+                    version = 67;
+                    conn.pragma_update(None, "user_version", version)?;
+                    log_info(
+                        LogServiceType::Database,
+                        format!("Update Library Database to version: {}", version),
+                    );
+                }
+
+                if version < 68 {
+                    let initial =
+                        String::from_utf8_lossy(include_bytes!("068 - MEDIA HDR PROBED.sql"));
+                    conn.execute_batch(&initial)?;
+                    version = 68;
+                    conn.pragma_update(None, "user_version", version)?;
+                    log_info(
+                        LogServiceType::Database,
+                        format!("Update Library Database to version: {}", version),
+                    );
+                }
 
                 conn.execute("VACUUM;", params![])?;
                 Ok((initial_version, version))
@@ -379,7 +570,7 @@ mod tests {
         let connection = tokio_rusqlite::Connection::open_in_memory().await.unwrap();
         let store = SqliteLibraryStore::new(connection).await.unwrap();
         let version = store.migrate().await.unwrap();
-        assert_eq!(version, 52);
+        assert_eq!(version, 68);
 
         // Set up: insert a book and a media attached to it
         store
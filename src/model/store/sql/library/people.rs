@@ -4,7 +4,9 @@ use rusqlite::{params, params_from_iter, types::FromSqlError, OptionalExtension,
 use super::{Result, SqliteLibraryStore};
 use crate::model::Error;
 use crate::{
-    domain::people::{FaceBBox, FaceEmbedding, Person, UnassignedFace},
+    domain::people::{
+        FaceBBox, FaceEmbedding, FaceModel, FaceReembedFailure, Person, UnassignedFace,
+    },
     model::{
         people::{PeopleQuery, PersonForInsert, PersonForUpdate},
         store::{
@@ -311,6 +313,7 @@ else 0 end) as score", q, q, q, q, q, q);
         bbox: FaceBBox,
         confidence: f32,
         pose: Option<(f32, f32, f32)>,
+        model: FaceModel,
     ) -> Result<()> {
         self.connection.call(move |conn| {
             let embedding_blob = bytemuck::cast_slice::<f32, u8>(&embedding).to_vec();
@@ -322,9 +325,9 @@ else 0 end) as score", q, q, q, q, q, q);
             };
             
             conn.execute(
-                "INSERT INTO unassigned_faces (id, embedding, media_ref, bbox, confidence, pose, created) 
-                 VALUES (?, ?, ?, ?, ?, ?, ?)",
-                params![face_id, embedding_blob, media_id, bbox_json, confidence, pose_json, chrono::Utc::now().timestamp_millis()]
+                "INSERT INTO unassigned_faces (id, embedding, media_ref, bbox, confidence, pose, created, model, model_version) 
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                params![face_id, embedding_blob, media_id, bbox_json, confidence, pose_json, chrono::Utc::now().timestamp_millis(), model.name, model.version]
             )?;
             Ok(())
         }).await?;
@@ -466,6 +469,7 @@ else 0 end) as score", q, q, q, q, q, q);
         confidence: f32,
         pose: Option<(f32, f32, f32)>,
         similarity: Option<f32>,
+        model: FaceModel,
     ) -> Result<()> {
         let pid = person_id.to_string();
         let media_id_clone = media_id.clone();
@@ -487,9 +491,9 @@ else 0 end) as score", q, q, q, q, q, q);
 
             // Insert into people_faces
             tx.execute(
-                "INSERT INTO people_faces (id, people_ref, embedding, media_ref, bbox, confidence, pose, similarity, created) 
-                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
-                params![face_id, pid.clone(), embedding_blob, media_id_clone.clone(), bbox_json, confidence, pose_json, similarity, chrono::Utc::now().timestamp_millis()]
+                "INSERT INTO people_faces (id, people_ref, embedding, media_ref, bbox, confidence, pose, similarity, created, model, model_version) 
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                params![face_id, pid.clone(), embedding_blob, media_id_clone.clone(), bbox_json, confidence, pose_json, similarity, chrono::Utc::now().timestamp_millis(), model.name, model.version]
             )?;
             
             // Also insert or update media_people_mapping if media_id is provided
@@ -701,6 +705,231 @@ else 0 end) as score", q, q, q, q, q, q);
         Ok(res)
     }
 
+    /// Ids of the faces of a model assigned to people with their person, and of the unassigned
+    /// faces of this model
+    pub async fn get_face_ids(
+        &self,
+        model: FaceModel,
+    ) -> Result<(Vec<(String, String)>, Vec<String>)> {
+        let res = self
+            .connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT id, people_ref FROM people_faces WHERE model = ? AND model_version = ?",
+                )?;
+                let people = stmt
+                    .query_map(params![model.name, model.version], |row| {
+                        Ok((row.get(0)?, row.get(1)?))
+                    })?
+                    .collect::<rusqlite::Result<Vec<(String, String)>>>()?;
+                let mut stmt = conn.prepare(
+                    "SELECT id FROM unassigned_faces WHERE model = ? AND model_version = ?",
+                )?;
+                let unassigned = stmt
+                    .query_map(params![model.name, model.version], |row| row.get(0))?
+                    .collect::<rusqlite::Result<Vec<String>>>()?;
+                Ok((people, unassigned))
+            })
+            .await?;
+        Ok(res)
+    }
+
+    /// Unassigned face ids of a model with their media
+    pub async fn get_unassigned_face_medias(
+        &self,
+        model: FaceModel,
+    ) -> Result<Vec<(String, String)>> {
+        let res = self
+            .connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT id, media_ref FROM unassigned_faces WHERE model = ? AND model_version = ?",
+                )?;
+                let rows = stmt.query_map(params![model.name, model.version], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })?;
+                Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+            })
+            .await?;
+        Ok(res)
+    }
+
+    /// Embeddings of assigned or unassigned faces with the person of assigned ones
+    pub async fn get_face_embeddings_by_ids(
+        &self,
+        face_ids: Vec<String>,
+    ) -> Result<Vec<(String, Option<String>, Vec<f32>)>> {
+        let res = self
+            .connection
+            .call(move |conn| {
+                let mut faces = Vec::with_capacity(face_ids.len());
+                for chunk in face_ids.chunks(500) {
+                    let placeholders = vec!["?"; chunk.len()].join(",");
+                    let mut stmt = conn.prepare(&format!(
+                        "SELECT id, people_ref, embedding FROM people_faces WHERE id IN ({0})
+                        UNION ALL
+                        SELECT id, NULL, embedding FROM unassigned_faces WHERE id IN ({0})",
+                        placeholders
+                    ))?;
+                    let ids = params_from_iter(chunk.iter().chain(chunk));
+                    let rows = stmt.query_map(ids, |row| {
+                        let embedding_blob: Vec<u8> = row.get(2)?;
+                        let embedding = if embedding_blob.len() % 4 == 0 {
+                            bytemuck::cast_slice::<u8, f32>(&embedding_blob).to_vec()
+                        } else {
+                            Vec::new()
+                        };
+                        Ok((row.get(0)?, row.get(1)?, embedding))
+                    })?;
+                    for row in rows {
+                        faces.push(row?);
+                    }
+                }
+                Ok(faces)
+            })
+            .await?;
+        Ok(res)
+    }
+
+    /// Medias with faces embedded by another model or model version, except the ones this
+    /// model already failed to re-embed
+    pub async fn get_medias_with_outdated_faces(&self, model: FaceModel) -> Result<Vec<String>> {
+        let res = self
+            .connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT media_ref FROM (
+                        SELECT media_ref FROM people_faces
+                            WHERE media_ref IS NOT NULL AND (model != ?1 OR model_version != ?2)
+                        UNION
+                        SELECT media_ref FROM unassigned_faces WHERE model != ?1 OR model_version != ?2
+                    ) WHERE media_ref NOT IN (
+                        SELECT media_ref FROM face_reembed_failures WHERE model = ?1 AND model_version = ?2
+                    )",
+                )?;
+                let rows = stmt.query_map(params![model.name, model.version], |row| row.get(0))?;
+                Ok(rows.collect::<rusqlite::Result<Vec<String>>>()?)
+            })
+            .await?;
+        Ok(res)
+    }
+
+    /// Record that a model could not re-embed all faces of a media
+    pub async fn add_face_reembed_failure(
+        &self,
+        media_id: String,
+        model: FaceModel,
+        error: String,
+    ) -> Result<()> {
+        self.connection
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO face_reembed_failures (media_ref, model, model_version, error)
+                        VALUES (?, ?, ?, ?)",
+                    params![media_id, model.name, model.version, error],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_face_reembed_failures(
+        &self,
+        model: FaceModel,
+    ) -> Result<Vec<FaceReembedFailure>> {
+        let res = self
+            .connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT media_ref, error, modified FROM face_reembed_failures
+                        WHERE model = ? AND model_version = ? ORDER BY modified DESC",
+                )?;
+                let rows = stmt.query_map(params![model.name, model.version], |row| {
+                    Ok(FaceReembedFailure {
+                        media_ref: row.get(0)?,
+                        error: row.get(1)?,
+                        modified: row.get(2)?,
+                    })
+                })?;
+                Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+            })
+            .await?;
+        Ok(res)
+    }
+
+    /// Faces of a media embedded by another model or model version, with their person
+    pub async fn get_outdated_media_faces(
+        &self,
+        media_id: String,
+        model: FaceModel,
+    ) -> Result<Vec<(String, Option<String>, FaceBBox)>> {
+        let res = self
+            .connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT id, people_ref, bbox FROM people_faces
+                        WHERE media_ref = ?1 AND (model != ?2 OR model_version != ?3)
+                    UNION ALL
+                    SELECT id, NULL, bbox FROM unassigned_faces
+                        WHERE media_ref = ?1 AND (model != ?2 OR model_version != ?3)",
+                )?;
+                let rows = stmt.query_map(params![media_id, model.name, model.version], |row| {
+                    let bbox: Option<String> = row.get(2)?;
+                    let bbox = bbox
+                        .and_then(|b| serde_json::from_str(&b).ok())
+                        .unwrap_or_default();
+                    Ok((row.get(0)?, row.get(1)?, bbox))
+                })?;
+                Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+            })
+            .await?;
+        Ok(res)
+    }
+
+    /// Replace the embedding of an assigned or unassigned face detected again by a new
+    /// model, keeping its id and person
+    pub async fn update_face_embedding(
+        &self,
+        face_id: String,
+        embedding: Vec<f32>,
+        bbox: FaceBBox,
+        confidence: f32,
+        pose: Option<(f32, f32, f32)>,
+        model: FaceModel,
+    ) -> Result<()> {
+        self.connection
+            .call(move |conn| {
+                let embedding_blob = bytemuck::cast_slice::<f32, u8>(&embedding).to_vec();
+                let bbox_json = serde_json::to_string(&bbox)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                let pose_json = pose
+                    .map(|p| serde_json::to_string(&p))
+                    .transpose()
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                for table in ["people_faces", "unassigned_faces"] {
+                    conn.execute(
+                        &format!(
+                            "UPDATE {} SET embedding = ?, bbox = ?, confidence = ?, pose = ?, model = ?, model_version = ? WHERE id = ?",
+                            table
+                        ),
+                        params![
+                            embedding_blob,
+                            bbox_json,
+                            confidence,
+                            pose_json,
+                            model.name,
+                            model.version,
+                            face_id
+                        ],
+                    )?;
+                }
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
     pub async fn delete_face_embedding(&self, face_id: &str) -> Result<()> {
         let fid = face_id.to_string();
         self.connection
@@ -853,6 +1082,7 @@ else 0 end) as score", q, q, q, q, q, q);
                 for (id, emb, mref, bbox, conf, pose, created) in &faces {
                     stmt.execute(params![id, pid.clone(), emb, mref, bbox, conf, pose, None::<f32>, created])?;
                 }
+                tx.execute("UPDATE people_faces SET (model, model_version) = (SELECT u.model, u.model_version FROM unassigned_faces u WHERE u.id = people_faces.id) WHERE id IN (SELECT id FROM unassigned_faces WHERE cluster_id = ?)", params![cluster_id])?;
             }
 
             // 3. Insert or update media_people_mapping for unique media_refs
@@ -918,6 +1148,7 @@ else 0 end) as score", q, q, q, q, q, q);
             {
                 let mut stmt = tx.prepare("INSERT INTO people_faces (id, people_ref, embedding, media_ref, bbox, confidence, pose, similarity, created) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
                 stmt.execute(params![fid.clone(), pid.clone(), embedding_blob, media_ref.clone(), bbox_str, confidence, pose_json, 1.0, created])?;
+                tx.execute("UPDATE people_faces SET (model, model_version) = (SELECT model, model_version FROM unassigned_faces WHERE id = ?1) WHERE id = ?1", params![fid.clone()])?;
             }
             
             // 3. Insert or update media_people_mapping
@@ -979,6 +1210,7 @@ else 0 end) as score", q, q, q, q, q, q);
                 {
                     let mut stmt = tx.prepare("INSERT INTO people_faces (id, people_ref, embedding, media_ref, bbox, confidence, pose, similarity, created) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
                     stmt.execute(params![face_id, pid.clone(), embedding_blob, media_ref.clone(), bbox_str, confidence, pose_json, 1.0, created])?;
+                    tx.execute("UPDATE people_faces SET (model, model_version) = (SELECT model, model_version FROM unassigned_faces WHERE id = ?1) WHERE id = ?1", params![face_id])?;
                 }
                 
                 // 3. Insert or update media_people_mapping
@@ -1063,6 +1295,7 @@ else 0 end) as score", q, q, q, q, q, q);
                 {
                     let mut stmt = tx.prepare("INSERT INTO unassigned_faces (id, embedding, media_ref, bbox, confidence, pose, created) VALUES (?, ?, ?, ?, ?, ?, ?)")?;
                     stmt.execute(params![face_id, embedding_blob, media_ref.clone(), bbox_str.clone(), confidence, pose_json, created])?;
+                    tx.execute("UPDATE unassigned_faces SET (model, model_version) = (SELECT model, model_version FROM people_faces WHERE id = ?1) WHERE id = ?1", params![face_id])?;
                 }
                 
                 // 3. Delete from media_people_mapping where people_face_ref matches
@@ -1187,3 +1420,170 @@ else 0 end) as score", q, q, q, q, q, q);
         Ok(res)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(version: u32) -> FaceModel {
+        FaceModel {
+            name: "buffalo_l".to_string(),
+            version,
+        }
+    }
+
+    #[tokio::test]
+    async fn face_ids_and_embeddings_by_ids() {
+        let connection = tokio_rusqlite::Connection::open_in_memory().await.unwrap();
+        let store = SqliteLibraryStore::new(connection).await.unwrap();
+        store
+            .add_unassigned_face(
+                "u1".to_string(),
+                vec![0.0, 1.0],
+                "m1".to_string(),
+                FaceBBox::default(),
+                0.9,
+                None,
+                model(1),
+            )
+            .await
+            .unwrap();
+        store
+            .add_face_embedding(
+                "f1".to_string(),
+                "p1",
+                vec![1.0, 0.0],
+                None,
+                None,
+                0.9,
+                None,
+                None,
+                model(1),
+            )
+            .await
+            .unwrap();
+
+        let (people, unassigned) = store.get_face_ids(model(1)).await.unwrap();
+        assert_eq!(people, vec![("f1".to_string(), "p1".to_string())]);
+        assert_eq!(unassigned, vec!["u1".to_string()]);
+        assert_eq!(
+            store.get_unassigned_face_medias(model(1)).await.unwrap(),
+            vec![("u1".to_string(), "m1".to_string())]
+        );
+
+        let mut faces = store
+            .get_face_embeddings_by_ids(vec!["u1".to_string(), "f1".to_string()])
+            .await
+            .unwrap();
+        faces.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            faces,
+            vec![
+                ("f1".to_string(), Some("p1".to_string()), vec![1.0, 0.0]),
+                ("u1".to_string(), None, vec![0.0, 1.0]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn outdated_faces_are_reembedded_in_place() {
+        let connection = tokio_rusqlite::Connection::open_in_memory().await.unwrap();
+        let store = SqliteLibraryStore::new(connection).await.unwrap();
+        let bbox = FaceBBox {
+            x1: 10.0,
+            y1: 10.0,
+            x2: 50.0,
+            y2: 50.0,
+            ..Default::default()
+        };
+        store
+            .add_face_embedding(
+                "f1".to_string(),
+                "p1",
+                vec![1.0, 0.0],
+                Some("m1".to_string()),
+                Some(bbox.clone()),
+                0.9,
+                None,
+                None,
+                model(1),
+            )
+            .await
+            .unwrap();
+        store
+            .add_unassigned_face(
+                "u1".to_string(),
+                vec![0.0, 1.0],
+                "m2".to_string(),
+                bbox.clone(),
+                0.9,
+                None,
+                model(2),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_medias_with_outdated_faces(model(2)).await.unwrap(),
+            vec!["m1".to_string()]
+        );
+        let outdated = store
+            .get_outdated_media_faces("m1".to_string(), model(2))
+            .await
+            .unwrap();
+        assert_eq!(outdated.len(), 1);
+        assert_eq!(outdated[0].0, "f1");
+        assert_eq!(outdated[0].1, Some("p1".to_string()));
+        assert_eq!(outdated[0].2.x2, 50.0);
+        assert_eq!(store.get_face_ids(model(2)).await.unwrap().0, vec![]);
+
+        store
+            .update_face_embedding(
+                "f1".to_string(),
+                vec![0.0, 0.0, 1.0],
+                bbox,
+                0.8,
+                None,
+                model(2),
+            )
+            .await
+            .unwrap();
+        assert!(store
+            .get_medias_with_outdated_faces(model(2))
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            store.get_face_ids(model(2)).await.unwrap().0,
+            vec![("f1".to_string(), "p1".to_string())]
+        );
+        assert_eq!(
+            store
+                .get_face_embeddings_by_ids(vec!["f1".to_string()])
+                .await
+                .unwrap()[0]
+                .2,
+            vec![0.0, 0.0, 1.0]
+        );
+
+        store
+            .add_face_reembed_failure("m2".to_string(), model(3), "No face".to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            store
+                .get_medias_with_outdated_faces(model(3))
+                .await
+                .unwrap(),
+            vec!["m1".to_string()]
+        );
+        let failures = store.get_face_reembed_failures(model(3)).await.unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].media_ref, "m2");
+        assert!(store
+            .get_face_reembed_failures(model(2))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -1,9 +1,12 @@
 pub mod backups;
 pub mod credentials;
+pub mod kosync;
 pub mod libraries;
 pub mod library;
 pub mod plugin_convert_queue;
 pub mod plugins;
+pub mod share_tokens;
+pub mod tagging_models;
 pub mod users;
 
 use std::fmt::Display;
@@ -111,8 +114,49 @@ pub async fn migrate_database(connection: &Connection) -> Result<usize> {
                 println!("Update SQL to version 11 (plugin convert queue)")
             }
 
+            if version < 12 {
+                let update = String::from_utf8_lossy(include_bytes!("012 - KOSYNC.sql"));
+                conn.execute_batch(&update)?;
+
+                conn.pragma_update(None, "user_version", 12)?;
+                println!("Update SQL to version 12 (kosync)")
+            }
+
+            if version < 13 {
+                let update = String::from_utf8_lossy(include_bytes!("013 - TAGGING MODELS.sql"));
+                conn.execute_batch(&update)?;
+
+                conn.pragma_update(None, "user_version", 13)?;
+                println!("Update SQL to version 13 (tagging models)")
+            }
+
+            if version < 14 {
+                let update =
+                    String::from_utf8_lossy(include_bytes!("014 - CHANNELS EXPORT TOKENS.sql"));
+                conn.execute_batch(&update)?;
+
+                conn.pragma_update(None, "user_version", 14)?;
+                println!("Update SQL to version 14 (channels export tokens)")
+            }
+
+            if version < 15 {
+                let update = String::from_utf8_lossy(include_bytes!("015 - OPDS TOKENS.sql"));
+                conn.execute_batch(&update)?;
+
+                conn.pragma_update(None, "user_version", 15)?;
+                println!("Update SQL to version 15 (opds tokens)")
+            }
+
+            if version < 16 {
+                let update = String::from_utf8_lossy(include_bytes!("016 - SHARE TOKENS.sql"));
+                conn.execute_batch(&update)?;
+
+                conn.pragma_update(None, "user_version", 16)?;
+                println!("Update SQL to version 16 (share tokens)")
+            }
+
             conn.execute("VACUUM;", params![])?;
-            Ok(11)
+            Ok(16)
         })
         .await?;
 
@@ -0,0 +1,85 @@
+use rusqlite::{params, OptionalExtension};
+
+use crate::{model::store::SqliteStore, tools::auth::ShareTokenKind};
+
+use super::Result;
+
+/// Ids of the revocable share tokens still valid, one per kind, library and user
+impl SqliteStore {
+    pub async fn get_share_token_id(
+        &self,
+        kind: ShareTokenKind,
+        library_ref: String,
+        user_ref: String,
+    ) -> Result<Option<String>> {
+        let id = self
+            .server_store
+            .call(move |conn| {
+                let id = conn
+                    .query_row(
+                        "SELECT id FROM share_tokens WHERE kind = ? AND library_ref = ? AND user_ref = ?",
+                        params![kind.to_string(), library_ref, user_ref],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                Ok(id)
+            })
+            .await?;
+        Ok(id)
+    }
+
+    pub async fn has_share_token_id(&self, kind: ShareTokenKind, id: String) -> Result<bool> {
+        let exists = self
+            .server_store
+            .call(move |conn| {
+                let exists = conn
+                    .query_row(
+                        "SELECT 1 FROM share_tokens WHERE kind = ? AND id = ?",
+                        params![kind.to_string(), id],
+                        |_| Ok(()),
+                    )
+                    .optional()?;
+                Ok(exists.is_some())
+            })
+            .await?;
+        Ok(exists)
+    }
+
+    pub async fn add_share_token_id(
+        &self,
+        kind: ShareTokenKind,
+        id: String,
+        library_ref: String,
+        user_ref: String,
+    ) -> Result<()> {
+        self.server_store
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT INTO share_tokens (id, kind, library_ref, user_ref) VALUES (?, ?, ?, ?)",
+                    params![id, kind.to_string(), library_ref, user_ref],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Revoke the share token of a user, the next request gets a new one
+    pub async fn remove_share_token_id(
+        &self,
+        kind: ShareTokenKind,
+        library_ref: String,
+        user_ref: String,
+    ) -> Result<()> {
+        self.server_store
+            .call(move |conn| {
+                conn.execute(
+                    "DELETE FROM share_tokens WHERE kind = ? AND library_ref = ? AND user_ref = ?",
+                    params![kind.to_string(), library_ref, user_ref],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use rusqlite::{
+    params,
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+    OptionalExtension, Row, ToSql,
+};
+
+use crate::{
+    domain::tagging_model::{RsTaggingModel, RsTaggingModelSettings},
+    model::store::SqliteStore,
+};
+
+use super::Result;
+
+impl FromSql for RsTaggingModelSettings {
+    fn column_result(value: ValueRef) -> FromSqlResult<Self> {
+        String::column_result(value).and_then(|as_string| {
+            serde_json::from_str::<RsTaggingModelSettings>(&as_string)
+                .map_err(|_| FromSqlError::InvalidType)
+        })
+    }
+}
+
+impl ToSql for RsTaggingModelSettings {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let r = serde_json::to_string(&self)
+            .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?;
+        Ok(ToSqlOutput::from(r))
+    }
+}
+
+/// Registry of the ONNX tagging models
+impl SqliteStore {
+    fn row_to_tagging_model(row: &Row) -> rusqlite::Result<RsTaggingModel> {
+        Ok(RsTaggingModel {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            settings: row.get(2)?,
+            added: row.get(3)?,
+        })
+    }
+
+    pub async fn get_tagging_models(&self) -> Result<Vec<RsTaggingModel>> {
+        let models = self
+            .server_store
+            .call(move |conn| {
+                let mut query = conn
+                    .prepare("SELECT id, name, settings, added FROM tagging_models ORDER BY name")?;
+                let rows = query.query_map([], Self::row_to_tagging_model)?;
+                Ok(rows.collect::<rusqlite::Result<Vec<RsTaggingModel>>>()?)
+            })
+            .await?;
+        Ok(models)
+    }
+
+    pub async fn get_tagging_model(&self, id: String) -> Result<Option<RsTaggingModel>> {
+        let model = self
+            .server_store
+            .call(move |conn| {
+                let model = conn
+                    .query_row(
+                        "SELECT id, name, settings, added FROM tagging_models WHERE id = ?",
+                        params![id],
+                        Self::row_to_tagging_model,
+                    )
+                    .optional()?;
+                Ok(model)
+            })
+            .await?;
+        Ok(model)
+    }
+
+    pub async fn add_tagging_model(
+        &self,
+        id: String,
+        name: String,
+        settings: RsTaggingModelSettings,
+    ) -> Result<()> {
+        self.server_store
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT INTO tagging_models (id, name, settings) VALUES (?, ?, ?)",
+                    params![id, name, settings],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove_tagging_model(&self, id: String) -> Result<()> {
+        self.server_store
+            .call(move |conn| {
+                conn.execute("DELETE FROM tagging_models WHERE id = ?", params![id])?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+}
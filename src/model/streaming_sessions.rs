@@ -0,0 +1,71 @@
+use crate::{
+    domain::{
+        streaming_session::{RsStreamingSession, StreamingSessionMessage},
+        ElementAction,
+    },
+    error::{RsError, RsResult},
+    routes::sse::SseEvent,
+};
+
+use super::{
+    users::{ConnectedUser, UserRole},
+    ModelController,
+};
+
+impl ModelController {
+    pub fn send_streaming_session(&self, action: ElementAction, session: RsStreamingSession) {
+        self.broadcast_sse(SseEvent::StreamingSessions(StreamingSessionMessage {
+            action,
+            session,
+        }));
+    }
+
+    /// All active media and channel HLS streams, most recently active first
+    pub async fn get_streaming_sessions(
+        &self,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<RsStreamingSession>> {
+        requesting_user.check_role(&UserRole::Admin)?;
+        let mut sessions: Vec<RsStreamingSession> = {
+            let media_sessions = self.media_hls_sessions.read().await;
+            media_sessions
+                .values()
+                .map(|s| s.to_streaming_session())
+                .collect()
+        };
+        {
+            let channel_sessions = self.hls_sessions.read().await;
+            sessions.extend(channel_sessions.values().map(|s| s.to_streaming_session()));
+        }
+        sessions.sort_by(|a, b| b.last_active.cmp(&a.last_active));
+        Ok(sessions)
+    }
+
+    /// Terminate a stream: its FFmpeg process is killed and its segments removed
+    pub async fn kill_streaming_session(
+        &self,
+        session_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<()> {
+        requesting_user.check_role(&UserRole::Admin)?;
+        if let Some(session) =
+            crate::tools::media_hls_session::stop_session(session_id, &self.media_hls_sessions)
+                .await
+        {
+            self.send_streaming_session(ElementAction::Deleted, session);
+            return Ok(());
+        }
+
+        let is_channel = self.hls_sessions.read().await.contains_key(session_id);
+        if is_channel {
+            // The channel supervisor releases the stream slot and sends the event
+            crate::tools::hls_session::stop_session(session_id, &self.hls_sessions).await;
+            return Ok(());
+        }
+
+        Err(RsError::NotFound(format!(
+            "Streaming session {}",
+            session_id
+        )))
+    }
+}
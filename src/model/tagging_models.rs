@@ -0,0 +1,175 @@
+use std::path::PathBuf;
+
+use nanoid::nanoid;
+use ort::value::ValueType;
+use tokio::{
+    fs::{self, File},
+    io::{AsyncRead, BufWriter},
+};
+
+use crate::{
+    domain::{
+        media::FileType,
+        tagging_model::{RsTaggingModel, RsTaggingModelForAdd},
+    },
+    error::{RsError, RsResult},
+    server::get_server_file_path_array,
+    tools::prediction::{
+        parse_labels, predict_tagging_model, preload_model, PredictionTag, PredictionTagResult,
+    },
+};
+
+use super::{
+    users::{ConnectedUser, UserRole},
+    ModelController,
+};
+
+async fn tagging_model_path(id: &str, extension: &str) -> RsResult<PathBuf> {
+    Ok(
+        get_server_file_path_array(vec!["models", "tagging", &format!("{}.{}", id, extension)])
+            .await?,
+    )
+}
+
+async fn tagging_model_labels(model: &RsTaggingModel) -> RsResult<Vec<PredictionTag>> {
+    let content = fs::read_to_string(tagging_model_path(&model.id, "csv").await?).await?;
+    Ok(parse_labels(&content, model.settings.labels, &model.id))
+}
+
+impl ModelController {
+    pub async fn get_tagging_models(
+        &self,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<Vec<RsTaggingModel>> {
+        requesting_user.check_role(&UserRole::Admin)?;
+        Ok(self.store.get_tagging_models().await?)
+    }
+
+    pub async fn get_tagging_model(
+        &self,
+        id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<RsTaggingModel> {
+        requesting_user.check_role(&UserRole::Admin)?;
+        self.store
+            .get_tagging_model(id.to_string())
+            .await?
+            .ok_or(RsError::NotFound(format!("Tagging model {}", id)))
+    }
+
+    /// Register an ONNX tagging model with its labels. The model is loaded once to check
+    /// that its outputs match the labels
+    pub async fn add_tagging_model(
+        &self,
+        model: RsTaggingModelForAdd,
+        labels: String,
+        reader: &mut (dyn AsyncRead + Unpin + Send),
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<RsTaggingModel> {
+        requesting_user.check_role(&UserRole::Admin)?;
+        let id = nanoid!();
+        let parsed = parse_labels(&labels, model.settings.labels, &id);
+        if parsed.is_empty() {
+            return Err(RsError::Error("Tagging model labels are empty".to_string()));
+        }
+
+        let onnx_path = tagging_model_path(&id, "onnx").await?;
+        let mut file = BufWriter::new(File::create(&onnx_path).await?);
+        tokio::io::copy(reader, &mut file).await?;
+        drop(file);
+
+        let outputs = match preload_model(&onnx_path).await {
+            Ok(session) => session.outputs.first().and_then(|o| match &o.output_type {
+                ValueType::Tensor { shape, .. } => shape.last().copied(),
+                _ => None,
+            }),
+            Err(error) => {
+                let _ = fs::remove_file(&onnx_path).await;
+                return Err(error);
+            }
+        };
+        if let Some(outputs) = outputs.filter(|o| *o > 0) {
+            if outputs as usize != parsed.len() {
+                let _ = fs::remove_file(&onnx_path).await;
+                return Err(RsError::Error(format!(
+                    "Tagging model has {} outputs but {} labels",
+                    outputs,
+                    parsed.len()
+                )));
+            }
+        }
+
+        fs::write(tagging_model_path(&id, "csv").await?, labels).await?;
+        self.store
+            .add_tagging_model(id.clone(), model.name, model.settings)
+            .await?;
+        self.get_tagging_model(&id, requesting_user).await
+    }
+
+    pub async fn remove_tagging_model(
+        &self,
+        id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<RsTaggingModel> {
+        let model = self.get_tagging_model(id, requesting_user).await?;
+        self.store.remove_tagging_model(id.to_string()).await?;
+        for extension in ["onnx", "csv"] {
+            let _ = fs::remove_file(tagging_model_path(id, extension).await?).await;
+        }
+        Ok(model)
+    }
+
+    /// Tagging model requested explicitly or selected in the library settings
+    pub async fn library_tagging_model(
+        &self,
+        library_id: &str,
+        model_id: Option<String>,
+    ) -> RsResult<Option<RsTaggingModel>> {
+        if let Some(model_id) = model_id {
+            let model = self.store.get_tagging_model(model_id.clone()).await?;
+            return model
+                .map(Some)
+                .ok_or(RsError::NotFound(format!("Tagging model {}", model_id)));
+        }
+        let selected = self
+            .cache_get_library(library_id)
+            .await
+            .and_then(|l| l.settings.preduction_model);
+        match selected {
+            Some(model_id) => Ok(self.store.get_tagging_model(model_id).await?),
+            None => Ok(None),
+        }
+    }
+
+    /// Tags of PNG images with a registered tagging model
+    pub async fn predict_with_tagging_model(
+        &self,
+        model: &RsTaggingModel,
+        images: Vec<Vec<u8>>,
+    ) -> RsResult<Vec<PredictionTagResult>> {
+        let labels = tagging_model_labels(model).await?;
+        let mut session = preload_model(&tagging_model_path(&model.id, "onnx").await?).await?;
+        let settings = model.settings.clone();
+        tokio::task::spawn_blocking(move || -> RsResult<Vec<PredictionTagResult>> {
+            let mut predictions = vec![];
+            for image in images {
+                predictions.append(&mut predict_tagging_model(
+                    &mut session,
+                    &settings,
+                    &labels,
+                    &image,
+                )?);
+            }
+            Ok(predictions)
+        })
+        .await?
+    }
+
+    /// Photos and videos of a library to tag again
+    pub async fn get_medias_to_tag(&self, library_id: &str) -> RsResult<Vec<String>> {
+        let store = self.store.get_library_store(library_id)?;
+        let mut ids = store.get_media_ids_by_type(FileType::Photo).await?;
+        ids.append(&mut store.get_media_ids_by_type(FileType::Video).await?);
+        Ok(ids)
+    }
+}
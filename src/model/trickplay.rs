@@ -0,0 +1,185 @@
+use std::path::PathBuf;
+
+use crate::{
+    domain::{library::LibraryRole, media::FileType},
+    error::{RsError, RsResult},
+    plugins::sources::error::SourcesError,
+    tools::{
+        log::{log_error, log_info, LogServiceType},
+        trickplay::{
+            generate_trickplay, is_valid_sprite_name, rewrite_vtt, TrickplayLayout,
+            TRICKPLAY_VTT_NAME,
+        },
+        video_tools::probe_video,
+    },
+};
+
+use super::{users::ConnectedUser, ModelController};
+
+/// Validity of the share token embedded in the served VTT track
+const TRICKPLAY_TOKEN_DURATION: u64 = 6 * 60 * 60;
+
+impl ModelController {
+    /// Folder holding the trickplay sprites of a media. Hidden so `clean_temp` keeps it
+    async fn trickplay_folder(&self, library_id: &str, media_id: &str) -> RsResult<PathBuf> {
+        let local = self.library_source_for_library(library_id).await?;
+        Ok(local.get_full_path(&format!(".cache/.trickplay/{}", media_id)))
+    }
+
+    pub async fn has_trickplay(&self, library_id: &str, media_id: &str) -> bool {
+        match self.trickplay_folder(library_id, media_id).await {
+            Ok(folder) => tokio::fs::try_exists(folder.join(TRICKPLAY_VTT_NAME))
+                .await
+                .unwrap_or(false),
+            Err(_) => false,
+        }
+    }
+
+    pub async fn generate_media_trickplay(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        force: bool,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<()> {
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Read)?;
+        self.cache_check_library_notcrypt(library_id).await?;
+        if self.get_library_encryption_key(library_id).await.is_some() {
+            return Err(RsError::UnavailableForCryptedLibraries);
+        }
+
+        let media = self
+            .get_media(library_id, media_id.to_owned(), requesting_user)
+            .await?
+            .ok_or(SourcesError::UnableToFindMedia(
+                library_id.to_string(),
+                media_id.to_string(),
+                "generate_media_trickplay".to_string(),
+            ))?
+            .item;
+        if media.kind != FileType::Video {
+            return Err(RsError::Error(format!(
+                "Trickplay is only available for videos ({})",
+                media_id
+            )));
+        }
+
+        if !force && self.has_trickplay(library_id, media_id).await {
+            return Ok(());
+        }
+
+        let folder = self.trickplay_folder(library_id, media_id).await?;
+        if tokio::fs::try_exists(&folder).await.unwrap_or(false) {
+            tokio::fs::remove_dir_all(&folder).await?;
+        }
+
+        let uri = self.get_media_uri(library_id, media_id, Some(3600)).await?;
+        let probe = probe_video(&uri).await?;
+        let duration = probe
+            .duration()
+            .ok_or(RsError::Error("Unable to get video duration".to_string()))?;
+        let (width, height) = probe.size();
+        let layout = TrickplayLayout::for_video(width, height);
+
+        if let Err(error) = generate_trickplay(&uri, &folder, duration, &layout).await {
+            let _ = tokio::fs::remove_dir_all(&folder).await;
+            return Err(error);
+        }
+        log_info(
+            LogServiceType::Source,
+            format!("Generated trickplay for media {}", media_id),
+        );
+        Ok(())
+    }
+
+    pub fn generate_media_trickplay_spawn(
+        &self,
+        library_id: String,
+        media_id: String,
+        requesting_user: ConnectedUser,
+    ) {
+        let mc = self.clone();
+        tokio::spawn(async move {
+            let r = mc
+                .generate_media_trickplay(&library_id, &media_id, false, &requesting_user)
+                .await;
+            if let Err(error) = r {
+                log_error(
+                    LogServiceType::Source,
+                    format!("Unable to generate trickplay for {}: {:?}", media_id, error),
+                );
+            }
+        });
+    }
+
+    /// WebVTT thumbnails track with sprite urls usable directly by players
+    pub async fn get_media_trickplay_vtt(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<String> {
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Read)?;
+        let folder = self.trickplay_folder(library_id, media_id).await?;
+        let vtt = tokio::fs::read_to_string(folder.join(TRICKPLAY_VTT_NAME))
+            .await
+            .map_err(|_| RsError::NotFound(format!("No trickplay for media {}", media_id)))?;
+
+        let token = self
+            .get_file_share_token(
+                library_id,
+                media_id,
+                TRICKPLAY_TOKEN_DURATION,
+                requesting_user,
+            )
+            .await?;
+        Ok(rewrite_vtt(&vtt, |name| {
+            format!(
+                "/libraries/{}/medias/{}/trickplay/{}?sharetoken={}",
+                library_id, media_id, name, token
+            )
+        }))
+    }
+
+    pub async fn get_media_trickplay_sprite(
+        &self,
+        library_id: &str,
+        media_id: &str,
+        sprite: &str,
+        requesting_user: &ConnectedUser,
+    ) -> RsResult<PathBuf> {
+        requesting_user.check_file_role(library_id, media_id, LibraryRole::Read)?;
+        if !is_valid_sprite_name(sprite) {
+            return Err(RsError::NotFound(format!("Invalid sprite: {}", sprite)));
+        }
+        let path = self
+            .trickplay_folder(library_id, media_id)
+            .await?
+            .join(sprite);
+        if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            return Err(RsError::NotFound(format!("Sprite not found: {}", sprite)));
+        }
+        Ok(path)
+    }
+
+    pub async fn remove_media_trickplay(&self, library_id: &str, media_id: &str) -> RsResult<()> {
+        let folder = self.trickplay_folder(library_id, media_id).await?;
+        if tokio::fs::try_exists(&folder).await.unwrap_or(false) {
+            tokio::fs::remove_dir_all(&folder).await?;
+        }
+        Ok(())
+    }
+
+    /// Ids of library videos that don't have trickplay sprites yet
+    pub async fn get_medias_without_trickplay(&self, library_id: &str) -> RsResult<Vec<String>> {
+        let store = self.store.get_library_store(library_id)?;
+        let ids = store.get_media_ids_by_type(FileType::Video).await?;
+        let mut missing = vec![];
+        for id in ids {
+            if !self.has_trickplay(library_id, &id).await {
+                missing.push(id);
+            }
+        }
+        Ok(missing)
+    }
+}
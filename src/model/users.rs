@@ -17,7 +17,7 @@ use crate::{
     error::RsResult,
     routes::sse::SseEvent,
     tools::{
-        auth::{ClaimsLocal, ClaimsLocalType},
+        auth::{ClaimsLocal, ClaimsLocalType, ShareTokenKind},
         clock::now,
     },
 };
@@ -113,6 +113,8 @@ impl ConnectedUser {
                 ClaimsLocalType::RequestUrl(_) => Err(Error::ShareTokenInsufficient),
                 ClaimsLocalType::UserRole(_) => Err(Error::ShareTokenInsufficient),
                 ClaimsLocalType::Admin => Ok(()),
+                ClaimsLocalType::Channels(_, _, _) => Err(Error::ShareTokenInsufficient),
+                ClaimsLocalType::Books(_, _, _) => Err(Error::ShareTokenInsufficient),
             }
         } else if let ConnectedUser::Server(user) = &self {
             if user.has_role(role) {
@@ -152,6 +154,14 @@ impl ConnectedUser {
                         Err(Error::ShareTokenInsufficient)
                     }
                 }
+                ClaimsLocalType::Channels(library, user, _)
+                | ClaimsLocalType::Books(library, user, _) => {
+                    if library == library_id && role == LibraryRole::Read {
+                        Ok(LibraryLimits::init_with_user(Some(user.clone())))
+                    } else {
+                        Err(Error::ShareTokenInsufficient)
+                    }
+                }
                 _ => Err(Error::ShareTokenInsufficient),
             }
         } else if let ConnectedUser::UploadKey(key) = &self {
@@ -192,6 +202,13 @@ impl ConnectedUser {
                         Err(Error::ShareTokenInsufficient)
                     }
                 }
+                ClaimsLocalType::Books(library, _, _) => {
+                    if library == library_id && role == LibraryRole::Read {
+                        Ok(())
+                    } else {
+                        Err(Error::ShareTokenInsufficient)
+                    }
+                }
                 _ => Err(Error::ShareTokenInsufficient),
             }
         } else {
@@ -728,6 +745,44 @@ impl ModelController {
         Ok(())
     }
 
+    /// Stored id of the share token of a user for a library, created on first request
+    pub async fn get_share_token_id(
+        &self,
+        kind: ShareTokenKind,
+        library_id: &str,
+        user_id: &str,
+    ) -> RsResult<String> {
+        let existing = self
+            .store
+            .get_share_token_id(kind, library_id.to_string(), user_id.to_string())
+            .await?;
+        if let Some(token_id) = existing {
+            return Ok(token_id);
+        }
+        let token_id = nanoid::nanoid!();
+        self.store
+            .add_share_token_id(
+                kind,
+                token_id.clone(),
+                library_id.to_string(),
+                user_id.to_string(),
+            )
+            .await?;
+        Ok(token_id)
+    }
+
+    /// Whether a share token was not revoked
+    pub async fn is_share_token_valid(
+        &self,
+        kind: ShareTokenKind,
+        token_id: &str,
+    ) -> RsResult<bool> {
+        Ok(self
+            .store
+            .has_share_token_id(kind, token_id.to_string())
+            .await?)
+    }
+
     pub async fn redeem_invitation(&self, code: String, user: ConnectedUser) -> RsResult<String> {
         let connected_user = match user {
             ConnectedUser::Server(u) => Ok(u),
@@ -22,7 +22,10 @@ use tokio_util::io::{ReaderStream, StreamReader};
 use serde::Deserialize;
 
 use crate::{
-    domain::book::{Book, BookForUpdate},
+    domain::{
+        audiobook::RsAudiobook,
+        book::{Book, BookForUpdate},
+    },
     model::{books::BookQuery, medias::MediaQuery, users::ConnectedUser, ModelController},
     routes::{
         ImageRequestOptions, ImageUploadOptions, RatingUpdateBody, SearchQuery, SearchResultGroup,
@@ -59,6 +62,10 @@ pub fn routes(mc: ModelController) -> Router {
         .route("/:id", patch(handler_patch))
         .route("/:id", delete(handler_delete))
         .route("/:id/medias", get(handler_medias))
+        .route("/:id/audiobook", get(handler_audiobook))
+        .route("/:id/audiobook/progress", patch(handler_audiobook_progress))
+        .route("/:id/audiobook/hls", post(handler_audiobook_hls_start))
+        .route("/:id/audiobook/hls", delete(handler_audiobook_hls_stop))
         .route("/:id/image", get(handler_image))
         .route("/:id/image/search", get(handler_image_search))
         .route("/:id/image/fetch", post(handler_image_fetch))
@@ -300,6 +307,59 @@ async fn handler_medias(
     Ok(Json(json!(medias)))
 }
 
+async fn handler_audiobook(
+    Path((library_id, book_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<RsAudiobook>> {
+    let audiobook = mc.get_audiobook(&library_id, &book_id, &user).await?;
+    Ok(Json(audiobook))
+}
+
+#[derive(Debug, Deserialize)]
+struct AudiobookProgressBody {
+    /// Position in the whole book in milliseconds
+    position: u64,
+}
+
+async fn handler_audiobook_progress(
+    Path((library_id, book_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    Json(body): Json<AudiobookProgressBody>,
+) -> Result<Json<RsAudiobook>> {
+    let audiobook = mc
+        .set_audiobook_progress(&library_id, &book_id, body.position, &user)
+        .await?;
+    Ok(Json(audiobook))
+}
+
+/// Playlist and segments are served by the media HLS routes, with the book id as media id
+async fn handler_audiobook_hls_start(
+    Path((library_id, book_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    let key = mc
+        .get_or_create_audiobook_hls_session(&library_id, &book_id, &user)
+        .await?;
+
+    Ok(Json(json!({
+        "key": key,
+        "playlistUrl": format!("/libraries/{}/medias/{}/hls/playlist.m3u8?session={}", library_id, book_id, key)
+    })))
+}
+
+async fn handler_audiobook_hls_stop(
+    Path((library_id, book_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    user.check_library_role(&library_id, crate::domain::library::LibraryRole::Read)?;
+    mc.stop_media_hls_session(&library_id, &book_id).await?;
+    Ok(Json(json!({"status": "ok"})))
+}
+
 async fn handler_image(
     Path((library_id, book_id)): Path<(String, String)>,
     State(mc): State<ModelController>,
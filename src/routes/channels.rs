@@ -2,36 +2,73 @@ use axum::{
     body::Body,
     extract::{Path, Query, State},
     response::{IntoResponse, Response},
-    routing::{delete, get, post},
+    routing::{delete, get, patch, post},
     Json, Router,
 };
 use futures::StreamExt;
-use http::header::{CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, TRANSFER_ENCODING};
+use http::{
+    header::{CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, TRANSFER_ENCODING},
+    HeaderMap,
+};
 use serde::Deserialize;
 use serde_json::{json, Value};
 use tokio_util::io::ReaderStream;
 
 use crate::{
-    domain::channel::Channel,
+    domain::{
+        channel::{Channel, ChannelUserSettingsForUpdate},
+        dvr::{DvrRecording, DvrSchedule, DvrScheduleForAdd, DvrScheduleForUpdate},
+        epg::{EpgChannelGuide, EpgNowNext},
+    },
     model::{
-        channels::{ChannelQuery, ImportRequest, StreamQuery},
+        channels::{ChannelExport, ChannelExportQuery, ChannelQuery, ImportRequest, StreamQuery},
+        dvr::DvrRecordingQuery,
+        epg::{EpgGridQuery, EpgNowQuery},
         users::ConnectedUser,
         ModelController,
     },
-    tools::hls_session::PLAYLIST_READY_TIMEOUT_MS,
+    tools::{
+        auth::ClaimsLocalType,
+        hls_session::{rewrite_playlist, PLAYLIST_READY_TIMEOUT_MS},
+    },
     Error, Result,
 };
 
-use super::ImageRequestOptions;
+use super::{request_base_url, ImageRequestOptions};
 
 pub fn routes(mc: ModelController) -> Router {
     Router::new()
         .route("/", get(handler_list))
         .route("/import", post(handler_import))
         .route("/refresh", post(handler_refresh))
+        .route("/health", post(handler_health_check))
+        .route("/order", post(handler_order))
+        .route("/export", get(handler_export))
+        .route("/export", delete(handler_export_revoke))
+        .route("/export.m3u", get(handler_export_m3u))
+        .route("/epg.xml", get(handler_export_xmltv))
+        .route("/epg/now", get(handler_epg_now))
+        .route("/epg/grid", get(handler_epg_grid))
+        .route("/epg/refresh", post(handler_epg_refresh))
+        .route("/dvr/schedules", get(handler_dvr_schedules))
+        .route("/dvr/schedules", post(handler_dvr_schedule_add))
+        .route(
+            "/dvr/schedules/:scheduleid",
+            patch(handler_dvr_schedule_update),
+        )
+        .route(
+            "/dvr/schedules/:scheduleid",
+            delete(handler_dvr_schedule_delete),
+        )
+        .route("/dvr/recordings", get(handler_dvr_recordings))
+        .route(
+            "/dvr/recordings/:recordingid",
+            delete(handler_dvr_recording_delete),
+        )
         .route("/:id", get(handler_get))
         .route("/:id", delete(handler_delete))
         .route("/:id/image", get(handler_image))
+        .route("/:id/user", patch(handler_user_settings))
         .route("/:id/tags", post(handler_add_tag))
         .route("/:id/tags/:tagid", delete(handler_remove_tag))
         .route("/:id/stream", get(handler_stream))
@@ -101,11 +138,243 @@ async fn handler_refresh(
     State(mc): State<ModelController>,
     user: ConnectedUser,
 ) -> Result<Json<Value>> {
-    // Refresh is the same as import but without URL override
-    let result = mc.import_m3u(&library_id, None, &user).await?;
+    // Refresh is the same as import but without URL override, from the configured source
+    let result = mc.import_iptv(&library_id, &user).await?;
     Ok(Json(json!(result)))
 }
 
+async fn handler_health_check(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    use crate::tools::scheduler::{iptv_health::IptvHealthTask, RsSchedulerWhen, RsTaskType};
+    user.check_library_role(&library_id, crate::domain::library::LibraryRole::Admin)?;
+
+    let task = IptvHealthTask {
+        specific_library: Some(library_id),
+    };
+    mc.scheduler
+        .add(RsTaskType::IptvHealth, RsSchedulerWhen::At(0), task)
+        .await?;
+
+    Ok(Json(json!({"status": "started"})))
+}
+
+// -- Per user settings and export --
+
+async fn handler_user_settings(
+    Path((library_id, channel_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    Json(update): Json<ChannelUserSettingsForUpdate>,
+) -> Result<Json<Channel>> {
+    let channel = mc
+        .update_channel_user_settings(&library_id, &channel_id, update, &user)
+        .await?;
+    Ok(Json(channel))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OrderBody {
+    channels: Vec<String>,
+}
+
+async fn handler_order(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    Json(body): Json<OrderBody>,
+) -> Result<Json<Vec<Channel>>> {
+    let channels = mc
+        .set_channel_order(&library_id, body.channels, &user)
+        .await?;
+    Ok(Json(channels))
+}
+
+/// Token of the export urls: the channels token used for this request, or a new one
+async fn export_token(
+    mc: &ModelController,
+    library_id: &str,
+    query: &ChannelExportQuery,
+    user: &ConnectedUser,
+) -> Result<String> {
+    if let (ConnectedUser::Share(claims), Some(token)) = (user, &query.sharetoken) {
+        if matches!(claims.kind, ClaimsLocalType::Channels(_, _, _)) {
+            return Ok(token.clone());
+        }
+    }
+    Ok(mc.get_channels_export_token(library_id, user).await?)
+}
+
+async fn handler_export(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    headers: HeaderMap,
+) -> Result<Json<ChannelExport>> {
+    let token = mc.get_channels_export_token(&library_id, &user).await?;
+    Ok(Json(mc.get_channels_export_urls(
+        &library_id,
+        token,
+        &request_base_url(&headers),
+    )))
+}
+
+async fn handler_export_revoke(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    mc.revoke_channels_export_token(&library_id, &user).await?;
+    Ok(Json(json!({"status": "ok"})))
+}
+
+async fn handler_export_m3u(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    headers: HeaderMap,
+    Query(query): Query<ChannelExportQuery>,
+) -> Result<Response> {
+    let token = export_token(&mc, &library_id, &query, &user).await?;
+    let playlist = mc
+        .export_channels_m3u(
+            &library_id,
+            &query,
+            &token,
+            &request_base_url(&headers),
+            &user,
+        )
+        .await?;
+    Response::builder()
+        .header(CONTENT_TYPE, "audio/x-mpegurl")
+        .header(CACHE_CONTROL, "no-cache")
+        .body(Body::from(playlist))
+        .map_err(|e| Error::Error(format!("Failed to build response: {}", e)))
+}
+
+async fn handler_export_xmltv(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    headers: HeaderMap,
+    Query(query): Query<ChannelExportQuery>,
+) -> Result<Response> {
+    let token = export_token(&mc, &library_id, &query, &user).await?;
+    let guide = mc
+        .export_channels_xmltv(
+            &library_id,
+            &query,
+            &token,
+            &request_base_url(&headers),
+            &user,
+        )
+        .await?;
+    Response::builder()
+        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+        .header(CACHE_CONTROL, "no-cache")
+        .body(Body::from(guide))
+        .map_err(|e| Error::Error(format!("Failed to build response: {}", e)))
+}
+
+// -- Program guide --
+
+async fn handler_epg_now(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    Query(query): Query<EpgNowQuery>,
+) -> Result<Json<Vec<EpgNowNext>>> {
+    let guide = mc.get_epg_now_next(&library_id, query, &user).await?;
+    Ok(Json(guide))
+}
+
+async fn handler_epg_grid(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    Query(query): Query<EpgGridQuery>,
+) -> Result<Json<Vec<EpgChannelGuide>>> {
+    let guide = mc.get_epg_grid(&library_id, query, &user).await?;
+    Ok(Json(guide))
+}
+
+async fn handler_epg_refresh(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    let programmes = mc.import_epg(&library_id, None, &user).await?;
+    Ok(Json(json!({"programmes": programmes})))
+}
+
+// -- DVR --
+
+async fn handler_dvr_schedules(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Vec<DvrSchedule>>> {
+    let schedules = mc.get_dvr_schedules(&library_id, &user).await?;
+    Ok(Json(schedules))
+}
+
+async fn handler_dvr_schedule_add(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    Json(schedule): Json<DvrScheduleForAdd>,
+) -> Result<Json<DvrSchedule>> {
+    let schedule = mc.add_dvr_schedule(&library_id, schedule, &user).await?;
+    Ok(Json(schedule))
+}
+
+async fn handler_dvr_schedule_update(
+    Path((library_id, schedule_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    Json(update): Json<DvrScheduleForUpdate>,
+) -> Result<Json<DvrSchedule>> {
+    let schedule = mc
+        .update_dvr_schedule(&library_id, &schedule_id, update, &user)
+        .await?;
+    Ok(Json(schedule))
+}
+
+async fn handler_dvr_schedule_delete(
+    Path((library_id, schedule_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<DvrSchedule>> {
+    let schedule = mc
+        .remove_dvr_schedule(&library_id, &schedule_id, &user)
+        .await?;
+    Ok(Json(schedule))
+}
+
+async fn handler_dvr_recordings(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    Query(query): Query<DvrRecordingQuery>,
+) -> Result<Json<Vec<DvrRecording>>> {
+    let recordings = mc.get_dvr_recordings(&library_id, query, &user).await?;
+    Ok(Json(recordings))
+}
+
+async fn handler_dvr_recording_delete(
+    Path((library_id, recording_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<DvrRecording>> {
+    let recording = mc
+        .remove_dvr_recording(&library_id, &recording_id, &user)
+        .await?;
+    Ok(Json(recording))
+}
+
 // -- Channel tag management --
 
 #[derive(Debug, Deserialize)]
@@ -141,16 +410,16 @@ async fn handler_remove_tag(
 struct StreamGuard {
     mc: ModelController,
     library_id: String,
-    channel_id: String,
+    slot: String,
 }
 
 impl Drop for StreamGuard {
     fn drop(&mut self) {
         let mc = self.mc.clone();
         let library_id = self.library_id.clone();
-        let channel_id = self.channel_id.clone();
+        let slot = self.slot.clone();
         tokio::spawn(async move {
-            mc.release_stream_slot(&library_id, &channel_id).await;
+            mc.release_stream_slot(&library_id, &slot).await;
         });
     }
 }
@@ -160,17 +429,30 @@ async fn handler_stream(
     State(mc): State<ModelController>,
     user: ConnectedUser,
     Query(query): Query<StreamQuery>,
+) -> Result<Response> {
+    let slot = channel_id.clone();
+    proxy_channel_stream(mc, library_id, channel_id, slot, query.quality, &user).await
+}
+
+/// Proxy a channel stream, holding the stream slot `slot` until the client disconnects
+pub async fn proxy_channel_stream(
+    mc: ModelController,
+    library_id: String,
+    channel_id: String,
+    slot: String,
+    quality: Option<String>,
+    user: &ConnectedUser,
 ) -> Result<Response> {
     // Enforce concurrent stream limit
-    mc.acquire_stream_slot(&library_id, &channel_id).await?;
+    mc.acquire_stream_slot(&library_id, &slot).await?;
 
     let stream_url = match mc
-        .get_channel_stream_url(&library_id, &channel_id, query.quality, &user)
+        .get_channel_stream_url(&library_id, &channel_id, quality, user)
         .await
     {
         Ok(url) => url,
         Err(e) => {
-            mc.release_stream_slot(&library_id, &channel_id).await;
+            mc.release_stream_slot(&library_id, &slot).await;
             return Err(e.into());
         }
     };
@@ -180,7 +462,7 @@ async fn handler_stream(
     let upstream = match client.get(&stream_url).send().await {
         Ok(resp) => resp,
         Err(e) => {
-            mc.release_stream_slot(&library_id, &channel_id).await;
+            mc.release_stream_slot(&library_id, &slot).await;
             return Err(Error::Error(format!("Failed to connect to stream: {}", e)));
         }
     };
@@ -196,7 +478,7 @@ async fn handler_stream(
     let guard = StreamGuard {
         mc: mc.clone(),
         library_id,
-        channel_id,
+        slot,
     };
 
     let byte_stream = upstream.bytes_stream();
@@ -222,6 +504,11 @@ async fn handler_stream(
 #[serde(rename_all = "camelCase")]
 pub struct HlsQuery {
     pub quality: Option<String>,
+    /// Start position in unix milliseconds, within the timeshift buffer
+    pub start: Option<i64>,
+    /// Start at the beginning of the programme airing now
+    #[serde(default)]
+    pub restart: bool,
 }
 
 async fn handler_hls_playlist(
@@ -263,21 +550,26 @@ async fn handler_hls_playlist(
         .map(|q| format!("?quality={}", q))
         .unwrap_or_default();
 
+    let timeshift = mc.get_timeshift_minutes(&library_id).await > 0;
+    let start = if timeshift && query.restart {
+        mc.get_epg_current_start(&library_id, &channel_id, &user)
+            .await?
+    } else {
+        query.start.filter(|_| timeshift)
+    };
+
     // Rewrite segment filenames to proxy URLs
-    let rewritten: String = content
-        .lines()
-        .map(|line| {
-            if line.ends_with(".ts") && !line.starts_with('#') {
-                format!(
-                    "/libraries/{}/channels/{}/hls/{}{}",
-                    library_id, channel_id, line, quality_param
-                )
-            } else {
-                line.to_string()
-            }
-        })
-        .collect::<Vec<_>>()
-        .join("\n");
+    let rewritten = rewrite_playlist(
+        &content,
+        |segment| {
+            format!(
+                "/libraries/{}/channels/{}/hls/{}{}",
+                library_id, channel_id, segment, quality_param
+            )
+        },
+        timeshift,
+        start,
+    );
 
     let response = Response::builder()
         .header(CONTENT_TYPE, "application/vnd.apple.mpegurl")
@@ -306,20 +598,15 @@ async fn handler_hls_segment(
     let quality_key = query.quality.as_deref().unwrap_or("best");
     let key = format!("{}:{}:{}", library_id, channel_id, quality_key);
 
-    let (output_dir, session_found) = {
+    let (output_dir, stats) = {
         let sessions = mc.hls_sessions.read().await;
-        if let Some(session) = sessions.get(&key) {
-            session.touch();
-            (session.output_dir.clone(), true)
-        } else {
-            (std::path::PathBuf::new(), false)
-        }
+        let session = sessions
+            .get(&key)
+            .ok_or_else(|| Error::NotFound("HLS session not found".to_string()))?;
+        session.touch();
+        (session.output_dir.clone(), session.stats.clone())
     };
 
-    if !session_found {
-        return Err(Error::NotFound("HLS session not found".to_string()));
-    }
-
     let segment_path = output_dir.join(&segment);
     let file = tokio::fs::File::open(&segment_path)
         .await
@@ -331,6 +618,7 @@ async fn handler_hls_segment(
         })?;
 
     let file_size = file.metadata().await.map(|m| m.len()).unwrap_or(0);
+    stats.record_segment(file_size);
 
     let stream = ReaderStream::new(file);
     let body = Body::from_stream(stream);
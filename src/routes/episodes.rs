@@ -62,6 +62,7 @@ pub fn routes(mc: ModelController) -> Router {
             "/seasons/:season/episodes",
             get(handler_list_season_episodes),
         )
+        .route("/seasons/:season/markers", post(handler_detect_markers))
         .route("/seasons/:season/episodes/:number", get(handler_get))
         .route(
             "/seasons/:season/episodes/:number",
@@ -393,8 +394,43 @@ async fn handler_medias(
             &user,
         )
         .await?;
-    let body = Json(json!(libraries));
-    Ok(body)
+    let mut markers = mc
+        .get_medias_markers(
+            &library_id,
+            libraries.iter().map(|m| m.item.id.clone()).collect(),
+            &user,
+        )
+        .await?;
+    let mut body = json!(libraries);
+    if let Some(values) = body.as_array_mut() {
+        for (value, media) in values.iter_mut().zip(libraries.iter()) {
+            if let (Some(object), Some(markers)) =
+                (value.as_object_mut(), markers.remove(&media.item.id))
+            {
+                object.insert("markers".to_string(), json!(markers));
+            }
+        }
+    }
+    Ok(Json(body))
+}
+
+async fn handler_detect_markers(
+    Path((library_id, serie_id, season)): Path<(String, String, u32)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    use crate::tools::scheduler::{media_markers::MediaMarkersTask, RsSchedulerWhen, RsTaskType};
+    user.check_library_role(&library_id, crate::domain::library::LibraryRole::Write)?;
+
+    let task = MediaMarkersTask {
+        specific_library: Some(library_id),
+        specific_season: Some((serie_id, season)),
+    };
+    mc.scheduler
+        .add(RsTaskType::MediaMarkers, RsSchedulerWhen::At(0), task)
+        .await?;
+
+    Ok(Json(json!({"status": "started"})))
 }
 
 async fn handler_post(
@@ -0,0 +1,94 @@
+use axum::{
+    extract::{Path, State},
+    response::Response,
+    routing::{get, post},
+    Json, Router,
+};
+use http::HeaderMap;
+use nanoid::nanoid;
+use serde_json::{json, Value};
+
+use crate::{
+    domain::hdhomerun::{HdhrDiscover, HdhrLineupEntry, HdhrLineupStatus},
+    model::{users::ConnectedUser, ModelController},
+    Error, Result,
+};
+
+use super::{channels::proxy_channel_stream, request_base_url};
+
+/// Emulated HDHomeRun tuner of an IPTV library. Clients can't authenticate:
+/// the library `hdhrKey` setting is part of every url instead
+pub fn routes(mc: ModelController) -> Router {
+    Router::new()
+        .route("/discover.json", get(handler_discover))
+        .route("/lineup_status.json", get(handler_lineup_status))
+        .route("/lineup.json", get(handler_lineup))
+        .route("/lineup.post", post(handler_lineup_post))
+        .route("/auto/:channel", get(handler_stream))
+        .with_state(mc)
+}
+
+/// Device url as reached by the client
+fn base_url(headers: &HeaderMap, library_id: &str, key: &str) -> String {
+    format!("{}/hdhr/{}/{}", request_base_url(headers), library_id, key)
+}
+
+async fn handler_discover(
+    Path((library_id, key)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    headers: HeaderMap,
+) -> Result<Json<HdhrDiscover>> {
+    let base_url = base_url(&headers, &library_id, &key);
+    let discover = mc.get_hdhr_discover(&library_id, &key, &base_url).await?;
+    Ok(Json(discover))
+}
+
+async fn handler_lineup_status(
+    Path((library_id, key)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+) -> Result<Json<HdhrLineupStatus>> {
+    let status = mc.get_hdhr_lineup_status(&library_id, &key).await?;
+    Ok(Json(status))
+}
+
+async fn handler_lineup(
+    Path((library_id, key)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<HdhrLineupEntry>>> {
+    let base_url = base_url(&headers, &library_id, &key);
+    let lineup = mc.get_hdhr_lineup(&library_id, &key, &base_url).await?;
+    Ok(Json(lineup))
+}
+
+/// Channel scans are no-ops: the lineup always reflects the library channels
+async fn handler_lineup_post(
+    Path((library_id, key)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+) -> Result<Json<Value>> {
+    mc.get_hdhr_library(&library_id, &key).await?;
+    Ok(Json(json!({"status": "ok"})))
+}
+
+async fn handler_stream(
+    Path((library_id, key, channel)): Path<(String, String, String)>,
+    State(mc): State<ModelController>,
+) -> Result<Response> {
+    let guide_number = channel
+        .strip_prefix('v')
+        .unwrap_or(&channel)
+        .parse::<u32>()
+        .map_err(|_| Error::NotFound(format!("Channel {} not found", channel)))?;
+    let channel_id = mc.get_hdhr_channel(&library_id, &key, guide_number).await?;
+    // Each tuner connection takes its own slot so `max_streams` caps concurrent tuners
+    let slot = format!("hdhr:{}", nanoid!());
+    proxy_channel_stream(
+        mc,
+        library_id,
+        channel_id,
+        slot,
+        None,
+        &ConnectedUser::ServerAdmin,
+    )
+    .await
+}
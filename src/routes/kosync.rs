@@ -0,0 +1,141 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Json, Router,
+};
+use http::{HeaderMap, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    domain::kosync::KosyncProgress,
+    model::{users::ConnectedUser, ModelController},
+    Result,
+};
+
+/// KOReader progress sync server (kosync protocol). Set `<server>/kosync` as custom sync
+/// server in KOReader, with credentials created from `POST /kosync/credentials`
+pub fn routes(mc: ModelController) -> Router {
+    Router::new()
+        .route("/credentials", get(handler_get_credentials))
+        .route("/credentials", post(handler_post_credentials))
+        .route("/users/create", post(handler_create_user))
+        .route("/users/auth", get(handler_auth))
+        .route("/syncs/progress", put(handler_put_progress))
+        .route("/syncs/progress/:document", get(handler_get_progress))
+        .with_state(mc)
+}
+
+const ERROR_UNAUTHORIZED_USER: u32 = 2001;
+const ERROR_INVALID_FIELDS: u32 = 2003;
+const ERROR_DOCUMENT_FIELD_MISSING: u32 = 2004;
+const ERROR_REGISTRATION_DISABLED: u32 = 2005;
+
+fn kosync_error(status: StatusCode, code: u32, message: &str) -> Response {
+    (status, Json(json!({ "code": code, "message": message }))).into_response()
+}
+
+/// Redseat user of the `x-auth-user` and `x-auth-key` headers
+async fn kosync_user(
+    mc: &ModelController,
+    headers: &HeaderMap,
+) -> std::result::Result<ConnectedUser, Response> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let user = match (header("x-auth-user"), header("x-auth-key")) {
+        (Some(username), Some(key)) => mc.kosync_authenticate(username, key).await.ok(),
+        _ => None,
+    };
+    user.ok_or_else(|| {
+        kosync_error(
+            StatusCode::UNAUTHORIZED,
+            ERROR_UNAUTHORIZED_USER,
+            "Unauthorized",
+        )
+    })
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct KosyncCredentialsQuery {
+    pub username: Option<String>,
+}
+
+async fn handler_get_credentials(
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    let username = mc.get_kosync_username(&user).await?;
+    Ok(Json(json!({ "username": username })))
+}
+
+async fn handler_post_credentials(
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    body: Option<Json<KosyncCredentialsQuery>>,
+) -> Result<Json<Value>> {
+    let username = body.and_then(|Json(body)| body.username);
+    let credentials = mc.create_kosync_credentials(username, &user).await?;
+    Ok(Json(json!(credentials)))
+}
+
+async fn handler_create_user() -> Response {
+    kosync_error(
+        StatusCode::FORBIDDEN,
+        ERROR_REGISTRATION_DISABLED,
+        "Registration is disabled, create KOReader credentials in Redseat",
+    )
+}
+
+async fn handler_auth(State(mc): State<ModelController>, headers: HeaderMap) -> Response {
+    match kosync_user(&mc, &headers).await {
+        Ok(_) => Json(json!({ "authorized": "OK" })).into_response(),
+        Err(response) => response,
+    }
+}
+
+async fn handler_put_progress(
+    State(mc): State<ModelController>,
+    headers: HeaderMap,
+    body: Option<Json<KosyncProgress>>,
+) -> Result<Response> {
+    let user = match kosync_user(&mc, &headers).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let Some(Json(progress)) = body else {
+        return Ok(kosync_error(
+            StatusCode::FORBIDDEN,
+            ERROR_INVALID_FIELDS,
+            "Invalid request",
+        ));
+    };
+    if progress.document.is_empty() {
+        return Ok(kosync_error(
+            StatusCode::FORBIDDEN,
+            ERROR_DOCUMENT_FIELD_MISSING,
+            "Field 'document' not provided.",
+        ));
+    }
+    let progress = mc.set_kosync_progress(progress, &user).await?;
+    Ok(Json(json!({
+        "document": progress.document,
+        "timestamp": progress.timestamp,
+    }))
+    .into_response())
+}
+
+async fn handler_get_progress(
+    Path(document): Path<String>,
+    State(mc): State<ModelController>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let user = match kosync_user(&mc, &headers).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let progress = mc.get_kosync_progress(&document, &user).await?;
+    Ok(match progress {
+        Some(progress) => Json(json!(progress)).into_response(),
+        None => Json(json!({})).into_response(),
+    })
+}
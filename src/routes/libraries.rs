@@ -7,12 +7,15 @@ use crate::{
         libraries::{ServerLibraryForAdd, ServerLibraryForUpdate},
         media_progresses::MediaProgressesQuery,
         media_ratings::MediaRatingsQuery,
-        users::ConnectedUser,
+        users::{ConnectedUser, UserRole},
         ModelController,
     },
     tools::{
         log::log_info,
-        scheduler::{backup::BackupTask, refresh::RefreshTask, RsSchedulerTask},
+        scheduler::{
+            backup::BackupTask, refresh::RefreshTask, tagging::TaggingTask, RsSchedulerTask,
+            RsSchedulerWhen, RsTaskType,
+        },
     },
     Error, Result,
 };
@@ -53,6 +56,7 @@ pub fn routes(mc: ModelController) -> Router {
         .route("/import", post(handler_import))
         .route("/:id/clean", get(handler_clean))
         .route("/:id/refresh", get(handler_refresh))
+        .route("/:id/tagging", post(handler_tagging))
         .route("/:id/invitation", post(handler_invitation))
         .merge(delete_routes)
         .with_state(mc)
@@ -276,6 +280,33 @@ async fn handler_refresh(
     Ok(Json(json!({"started": true})))
 }
 
+#[derive(Deserialize)]
+struct HandlerTaggingQuery {
+    model: Option<String>,
+}
+
+/// Queue tagging of all photos and videos of the library, replacing previous AI tags
+async fn handler_tagging(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    Query(query): Query<HandlerTaggingQuery>,
+) -> Result<Json<Value>> {
+    user.check_role(&UserRole::Admin)?;
+    if let Some(model) = &query.model {
+        mc.get_tagging_model(model, &user).await?;
+    }
+    let task = TaggingTask {
+        specific_library: Some(library_id),
+        model: query.model,
+    };
+    mc.scheduler
+        .add(RsTaskType::Tagging, RsSchedulerWhen::At(0), task)
+        .await?;
+
+    Ok(Json(json!({"started": true})))
+}
+
 #[derive(Deserialize)]
 struct HandlerInvitationQuery {
     role: LibraryRole,
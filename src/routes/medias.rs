@@ -7,6 +7,8 @@ use crate::{
             self, ConvertMessage, ConvertProgress, ItemWithRelations, MediaForUpdate,
             MediaItemReference, MediaWithAction, MediasMessage, VideoMergeRequest,
         },
+        media_chapters::RsMediaChapter,
+        media_markers::RsMediaMarkersForUpdate,
         ElementAction,
     },
     error::RsError,
@@ -14,7 +16,7 @@ use crate::{
         self,
         medias::{MediaFileQuery, MediaQuery},
         series::{SerieForUpdate, SerieQuery},
-        users::ConnectedUser,
+        users::{ConnectedUser, UserRole},
         ModelController,
     },
     plugins::sources::{error::SourcesError, SourceRead},
@@ -29,7 +31,7 @@ use axum::{
     debug_handler,
     extract::{Multipart, Path, State},
     response::{IntoResponse, Response},
-    routing::{delete, get, patch, post},
+    routing::{delete, get, patch, post, put},
     Json, Router,
 };
 use axum_extra::extract::Query;
@@ -71,6 +73,19 @@ pub fn routes(mc: ModelController) -> Router {
         .route("/:id/hls", delete(handler_media_hls_stop))
         .route("/:id/hls/playlist.m3u8", get(handler_media_hls_playlist))
         .route("/:id/hls/:segment", get(handler_media_hls_segment))
+        .route("/tasks/trickplay", post(handler_start_trickplay_task))
+        .route("/:id/trickplay", post(handler_generate_trickplay))
+        .route("/:id/trickplay/thumbnails.vtt", get(handler_trickplay_vtt))
+        .route("/:id/trickplay/:sprite", get(handler_trickplay_sprite))
+        .route("/:id/markers", get(handler_get_markers))
+        .route("/:id/markers", patch(handler_patch_markers))
+        .route("/:id/markers", delete(handler_delete_markers))
+        .route("/:id/chapters", get(handler_get_chapters))
+        .route("/:id/chapters", put(handler_put_chapters))
+        .route("/:id/chapters/:position/image", get(handler_chapter_image))
+        .route("/:id/similar", get(handler_similar))
+        .route("/:id/ocr", get(handler_get_ocr))
+        .route("/:id/ocr", post(handler_process_ocr))
         .route("/:id", get(handler_get_file))
         .route("/:id/backup/last", get(handler_get_last_backup))
         .route("/:id/backup/:backupid", get(handler_get_backup))
@@ -93,17 +108,28 @@ async fn handler_list(
     user: ConnectedUser,
     Query(query): Query<MediaQuery>,
 ) -> Result<Json<Value>> {
-    if let Some(filter) = &query.filter {
+    let libraries = if let Some(filter) = &query.filter {
         let old_query = serde_json::from_str::<MediaQuery>(filter)?;
         //old_query.page_key = query.page_key;
-        let libraries = mc.get_medias(&library_id, old_query, &user).await?;
-        let body = Json(json!(libraries));
-        Ok(body)
+        mc.get_medias(&library_id, old_query, &user).await?
     } else {
-        let libraries = mc.get_medias(&library_id, query, &user).await?;
-        let body = Json(json!(libraries));
-        Ok(body)
+        mc.get_medias(&library_id, query, &user).await?
+    };
+    let ids = libraries.iter().map(|m| m.item.id.clone()).collect();
+    let hdrs = mc.get_medias_hdr(&library_id, ids, &user).await?;
+    let mut body = json!(libraries);
+    if let Some(medias) = body.as_array_mut() {
+        for media in medias.iter_mut().filter_map(|m| m.as_object_mut()) {
+            let hdr = media
+                .get("id")
+                .and_then(|id| id.as_str())
+                .and_then(|id| hdrs.get(id));
+            if let Some(hdr) = hdr {
+                media.insert("hdr".to_string(), json!(hdr));
+            }
+        }
     }
+    Ok(Json(body))
 }
 
 async fn handler_count(
@@ -250,8 +276,22 @@ async fn handler_get(
             .await?;
         media.item.backups = Some(backups);
     }
-    let body = Json(json!(library));
-    Ok(body)
+    let mut body = json!(library);
+    if library.is_some() {
+        let markers = mc.get_media_markers(&library_id, &media_id, &user).await?;
+        if let (Some(object), Some(markers)) = (body.as_object_mut(), markers) {
+            object.insert("markers".to_string(), json!(markers));
+        }
+        let hdr = mc.get_media_hdr(&library_id, &media_id, &user).await?;
+        if let (Some(object), Some(hdr)) = (body.as_object_mut(), hdr) {
+            object.insert("hdr".to_string(), json!(hdr));
+        }
+        let chapters = mc.get_media_chapters(&library_id, &media_id, &user).await?;
+        if let Some(object) = body.as_object_mut().filter(|_| !chapters.is_empty()) {
+            object.insert("chapters".to_string(), json!(chapters));
+        }
+    }
+    Ok(Json(body))
 }
 
 async fn handler_refresh(
@@ -283,6 +323,8 @@ async fn handler_sharetoken(
 struct PredictOption {
     #[serde(default)]
     pub tag: bool,
+    /// Registered tagging model to test instead of the library models
+    pub model: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -297,8 +339,11 @@ async fn handler_predict(
     user: ConnectedUser,
     Query(query): Query<PredictOption>,
 ) -> Result<Json<Value>> {
+    if query.model.is_some() {
+        user.check_role(&UserRole::Admin)?;
+    }
     let prediction = mc
-        .prediction(&library_id, &media_id, query.tag, &user, true)
+        .prediction(&library_id, &media_id, query.model, query.tag, &user, true)
         .await?;
     let body = Json(json!(prediction));
     //println!("BODY {:?}", body);
@@ -883,11 +928,11 @@ async fn handler_media_hls_segment(
         return Err(Error::NotFound(format!("Invalid segment: {}", segment)));
     }
 
-    let output_dir = {
+    let (output_dir, stats) = {
         let sessions = mc.media_hls_sessions.read().await;
         let session = find_media_hls_session(&sessions, &query.session, &library_id, &media_id)
             .ok_or_else(|| Error::NotFound("Media HLS session not found".to_string()))?;
-        session.output_dir.clone()
+        (session.output_dir.clone(), session.stats.clone())
     };
 
     let segment_path = output_dir.join(&segment);
@@ -901,6 +946,7 @@ async fn handler_media_hls_segment(
         })?;
 
     let file_size = file.metadata().await.map(|m| m.len()).unwrap_or(0);
+    stats.record_segment(file_size);
 
     let stream = ReaderStream::new(file);
     let body = Body::from_stream(stream);
@@ -928,3 +974,189 @@ async fn handler_media_hls_stop(
     mc.stop_media_hls_session(&library_id, &media_id).await?;
     Ok(Json(json!({"status": "ok"})))
 }
+
+async fn handler_start_trickplay_task(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    use crate::tools::scheduler::{trickplay::TrickplayTask, RsSchedulerWhen, RsTaskType};
+    user.check_library_role(&library_id, crate::domain::library::LibraryRole::Admin)?;
+
+    let task = TrickplayTask {
+        specific_library: Some(library_id),
+    };
+    mc.scheduler
+        .add(RsTaskType::Trickplay, RsSchedulerWhen::At(0), task)
+        .await?;
+
+    Ok(Json(json!({"status": "started"})))
+}
+
+#[derive(Deserialize)]
+struct TrickplayGenerateQuery {
+    #[serde(default)]
+    force: bool,
+}
+
+async fn handler_generate_trickplay(
+    Path((library_id, media_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    Query(query): Query<TrickplayGenerateQuery>,
+) -> Result<Json<Value>> {
+    user.check_library_role(&library_id, crate::domain::library::LibraryRole::Write)?;
+    mc.generate_media_trickplay(&library_id, &media_id, query.force, &user)
+        .await?;
+    Ok(Json(json!({"status": "ok"})))
+}
+
+async fn handler_trickplay_vtt(
+    Path((library_id, media_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Response> {
+    use http::header::{CACHE_CONTROL, CONTENT_TYPE};
+
+    let vtt = mc
+        .get_media_trickplay_vtt(&library_id, &media_id, &user)
+        .await?;
+    let response = Response::builder()
+        .header(CONTENT_TYPE, "text/vtt")
+        .header(CACHE_CONTROL, "no-cache")
+        .body(Body::from(vtt))
+        .map_err(|e| Error::Error(format!("Failed to build response: {}", e)))?;
+    Ok(response)
+}
+
+async fn handler_trickplay_sprite(
+    Path((library_id, media_id, sprite)): Path<(String, String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Response> {
+    use http::header::{CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE};
+
+    let path = mc
+        .get_media_trickplay_sprite(&library_id, &media_id, &sprite, &user)
+        .await?;
+    let file = tokio::fs::File::open(&path)
+        .await
+        .map_err(|e| Error::Error(format!("Failed to open sprite: {}", e)))?;
+    let file_size = file.metadata().await.map(|m| m.len()).unwrap_or(0);
+
+    let mut response_builder = Response::builder()
+        .header(CONTENT_TYPE, "image/jpeg")
+        .header(CACHE_CONTROL, "private, max-age=86400");
+    if file_size > 0 {
+        response_builder = response_builder.header(CONTENT_LENGTH, file_size);
+    }
+    let response = response_builder
+        .body(Body::from_stream(ReaderStream::new(file)))
+        .map_err(|e| Error::Error(format!("Failed to build response: {}", e)))?;
+    Ok(response)
+}
+
+async fn handler_get_markers(
+    Path((library_id, media_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    let markers = mc.get_media_markers(&library_id, &media_id, &user).await?;
+    Ok(Json(json!(markers)))
+}
+
+async fn handler_patch_markers(
+    Path((library_id, media_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    Json(update): Json<RsMediaMarkersForUpdate>,
+) -> Result<Json<Value>> {
+    let markers = mc
+        .set_media_markers(&library_id, &media_id, update, &user)
+        .await?;
+    Ok(Json(json!(markers)))
+}
+
+async fn handler_delete_markers(
+    Path((library_id, media_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    mc.remove_media_markers(&library_id, &media_id, &user)
+        .await?;
+    Ok(Json(json!({"status": "ok"})))
+}
+
+async fn handler_get_chapters(
+    Path((library_id, media_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    let chapters = mc.get_media_chapters(&library_id, &media_id, &user).await?;
+    Ok(Json(json!(chapters)))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SimilarQuery {
+    pub limit: Option<usize>,
+}
+
+async fn handler_similar(
+    Path((library_id, media_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    Query(query): Query<SimilarQuery>,
+) -> Result<Json<Value>> {
+    let medias = mc
+        .get_similar_medias(&library_id, &media_id, query.limit, &user)
+        .await?;
+    Ok(Json(json!(medias)))
+}
+
+async fn handler_get_ocr(
+    Path((library_id, media_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    let text = mc.get_media_ocr(&library_id, &media_id, &user).await?;
+    Ok(Json(json!({"text": text})))
+}
+
+async fn handler_process_ocr(
+    Path((library_id, media_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    let text = mc.update_media_ocr(&library_id, &media_id, &user).await?;
+    Ok(Json(json!({"text": text})))
+}
+
+async fn handler_put_chapters(
+    Path((library_id, media_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    Json(chapters): Json<Vec<RsMediaChapter>>,
+) -> Result<Json<Value>> {
+    let chapters = mc
+        .set_media_chapters(&library_id, &media_id, chapters, &user)
+        .await?;
+    Ok(Json(json!(chapters)))
+}
+
+async fn handler_chapter_image(
+    Path((library_id, media_id, position)): Path<(String, String, usize)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Response> {
+    use http::header::{CACHE_CONTROL, CONTENT_TYPE};
+
+    let image = mc
+        .get_media_chapter_image(&library_id, &media_id, position, &user)
+        .await?;
+    let response = Response::builder()
+        .header(CONTENT_TYPE, "image/jpeg")
+        .header(CACHE_CONTROL, "private, max-age=86400")
+        .body(Body::from(image))
+        .map_err(|e| Error::Error(format!("Failed to build response: {}", e)))?;
+    Ok(response)
+}
@@ -1,3 +1,4 @@
+use http::{header::HOST, HeaderMap};
 use rs_plugin_common_interfaces::{
     lookup::{RsLookupMatchType, RsLookupMetadataResults},
     request::{RsGroupDownload, RsRequest},
@@ -15,16 +16,21 @@ pub mod mw_auth;
 pub mod mw_range;
 pub mod ping;
 pub mod plugins;
+pub mod sessions;
 pub mod sse;
+pub mod tagging_models;
 pub mod upload_keys;
 pub mod users;
 
 pub mod books;
 pub mod channels;
 pub mod episodes;
+pub mod hdhomerun;
+pub mod kosync;
 pub mod library_plugins;
 pub mod medias;
 pub mod movies;
+pub mod opds;
 pub mod people;
 pub mod search;
 pub mod series;
@@ -120,3 +126,16 @@ pub struct ImageUploadOptions {
 pub struct RatingUpdateBody {
     pub rating: f64,
 }
+
+/// Server url as reached by the client, honoring a reverse proxy scheme
+pub fn request_base_url(headers: &HeaderMap) -> String {
+    let host = headers
+        .get(HOST)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("localhost");
+    let scheme = headers
+        .get("x-forwarded-proto")
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("http");
+    format!("{}://{}", scheme, host)
+}
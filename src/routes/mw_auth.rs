@@ -1,9 +1,11 @@
 use axum::async_trait;
 use axum::extract::{FromRequestParts, Query, Request, State};
+use axum::http::header::WWW_AUTHENTICATE;
 use axum::http::request::Parts;
-use axum::http::HeaderMap;
+use axum::http::{HeaderMap, StatusCode};
 use axum::middleware::Next;
-use axum::response::Response;
+use axum::response::{IntoResponse, Response};
+use base64::prelude::{Engine as _, BASE64_STANDARD};
 use serde::{Deserialize, Serialize};
 
 use crate::model::server::AuthMessage;
@@ -14,6 +16,7 @@ use crate::tools::auth::{verify, verify_local, ClaimsLocalType};
 use crate::{error::Error, Result};
 
 const BEARER: &str = "Bearer ";
+const BASIC: &str = "Basic ";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TokenParams {
@@ -42,6 +45,18 @@ pub async fn mw_must_be_admin(user: ConnectedUser, req: Request, next: Next) ->
     Ok(next.run(req).await)
 }
 
+/// Ask anonymous clients for HTTP Basic credentials, for apps that cannot pass a token
+pub async fn mw_must_be_connected_basic(user: ConnectedUser, req: Request, next: Next) -> Response {
+    if user == ConnectedUser::Anonymous {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(WWW_AUTHENTICATE, "Basic realm=\"Redseat\"")],
+        )
+            .into_response();
+    }
+    next.run(req).await
+}
+
 pub async fn mw_token_resolver(
     mc: State<ModelController>,
     headers: HeaderMap,
@@ -49,13 +64,16 @@ pub async fn mw_token_resolver(
     mut req: Request,
     next: Next,
 ) -> Result<Response> {
-    let token: Option<String> = match headers.get("AUTHORIZATION").and_then(|t| t.to_str().ok()) {
+    let authorization = headers.get("AUTHORIZATION").and_then(|t| t.to_str().ok());
+    // HTTP Basic carries a share token as password, for apps that only support Basic (OPDS readers)
+    let basic_token = authorization.and_then(basic_auth_password);
+    let token: Option<String> = match authorization.filter(|_| basic_token.is_none()) {
         Some(token) => Some(token.replace(BEARER, "")),
         None => query.token.clone(),
     };
     let sharetoken: Option<String> = match headers.get("SHARETOKEN").and_then(|t| t.to_str().ok()) {
         Some(token) => Some(token.replace(BEARER, "")),
-        None => query.sharetoken.clone(),
+        None => query.sharetoken.clone().or(basic_token),
     };
     let auth_message = AuthMessage {
         token,
@@ -63,11 +81,60 @@ pub async fn mw_token_resolver(
         key: query.key.clone(),
     };
     let connected_user = parse_auth_message(&auth_message, &mc.0).await?;
+    if let ConnectedUser::Share(claims) = &connected_user {
+        if !share_allows_path(&claims.kind, req.uri().path()) {
+            return Err(Error::Forbiden);
+        }
+    }
     req.extensions_mut().insert(connected_user);
 
     Ok(next.run(req).await)
 }
 
+/// Routes a share token can be used on. Channels tokens, embedded in playlists given to IPTV
+/// players, only reach the playlist, guide, images and streams of their library. Books tokens,
+/// stored in e-reader apps, only reach the OPDS feeds, media downloads and covers
+fn share_allows_path(kind: &ClaimsLocalType, path: &str) -> bool {
+    match kind {
+        ClaimsLocalType::Channels(library, _, _) => {
+            let Some(route) = path.strip_prefix(&format!("/libraries/{}/channels/", library))
+            else {
+                return false;
+            };
+            match route.split_once('/') {
+                None => route == "export.m3u" || route == "epg.xml",
+                Some((_, channel_route)) => {
+                    channel_route == "stream"
+                        || channel_route == "image"
+                        || channel_route.starts_with("hls/")
+                }
+            }
+        }
+        ClaimsLocalType::Books(library, _, _) => {
+            let Some(route) = path.strip_prefix(&format!("/libraries/{}/", library)) else {
+                return false;
+            };
+            let parts: Vec<&str> = route.split('/').collect();
+            match parts.as_slice() {
+                ["opds", "token"] => false,
+                ["opds", ..] => true,
+                ["medias", _] | ["medias", _, "image"] | ["books", _, "image"] => true,
+                _ => false,
+            }
+        }
+        _ => true,
+    }
+}
+
+/// Password of an HTTP Basic authorization header
+fn basic_auth_password(authorization: &str) -> Option<String> {
+    let encoded = authorization.strip_prefix(BASIC)?;
+    let decoded = BASE64_STANDARD.decode(encoded.trim()).ok()?;
+    let credentials = String::from_utf8(decoded).ok()?;
+    let (_, password) = credentials.split_once(':')?;
+    (!password.is_empty()).then(|| password.to_string())
+}
+
 pub async fn parse_auth_message(auth: &AuthMessage, mc: &ModelController) -> Result<ConnectedUser> {
     let server_id = get_server_id().await;
 
@@ -90,6 +157,13 @@ pub async fn parse_auth_message(auth: &AuthMessage, mc: &ModelController) -> Res
         }
     } else if let Some(sharetoken) = &auth.sharetoken {
         let claims = verify_local(sharetoken).await?;
+        let valid = match claims.kind.share_token() {
+            Some((kind, token_id)) => mc.is_share_token_valid(kind, token_id).await?,
+            None => true,
+        };
+        if !valid {
+            return Err(Error::AuthFail);
+        }
 
         Ok(ConnectedUser::Share(claims))
     } else if let Some(key) = &auth.key {
@@ -122,3 +196,59 @@ impl<S: Send + Sync> FromRequestParts<S> for ConnectedUser {
         return Ok(server_user);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels_tokens_only_reach_channel_streams() {
+        let kind =
+            ClaimsLocalType::Channels("lib".to_string(), "user".to_string(), "id".to_string());
+        for allowed in [
+            "/libraries/lib/channels/export.m3u",
+            "/libraries/lib/channels/epg.xml",
+            "/libraries/lib/channels/c1/stream",
+            "/libraries/lib/channels/c1/image",
+            "/libraries/lib/channels/c1/hls/playlist.m3u8",
+        ] {
+            assert!(share_allows_path(&kind, allowed), "{}", allowed);
+        }
+        for denied in [
+            "/libraries/lib/medias",
+            "/libraries/lib/channels/export",
+            "/libraries/lib/channels/c1",
+            "/libraries/lib/channels/c1/hls",
+            "/libraries/lib/channels/dvr/recordings",
+            "/libraries/other/channels/c1/stream",
+            "/users/me",
+        ] {
+            assert!(!share_allows_path(&kind, denied), "{}", denied);
+        }
+    }
+
+    #[test]
+    fn books_tokens_only_reach_opds_and_downloads() {
+        let kind = ClaimsLocalType::Books("lib".to_string(), "user".to_string(), "id".to_string());
+        for allowed in [
+            "/libraries/lib/opds/v1",
+            "/libraries/lib/opds/v2/series/s1",
+            "/libraries/lib/medias/m1",
+            "/libraries/lib/medias/m1/image",
+            "/libraries/lib/books/b1/image",
+        ] {
+            assert!(share_allows_path(&kind, allowed), "{}", allowed);
+        }
+        for denied in [
+            "/libraries/lib/opds/token",
+            "/libraries/lib/medias",
+            "/libraries/lib/medias/m1/progress",
+            "/libraries/lib/books/b1",
+            "/libraries/lib/channels/c1/stream",
+            "/libraries/other/opds/v1",
+            "/users/me",
+        ] {
+            assert!(!share_allows_path(&kind, denied), "{}", denied);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    middleware,
+    response::Response,
+    routing::{delete, get},
+    Json, Router,
+};
+use http::{header::CONTENT_TYPE, HeaderMap};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    model::{opds::OpdsBookList, users::ConnectedUser, ModelController},
+    tools::opds::{
+        OpdsFeed, OpdsUrls, OpdsVersion, ATOM_ACQUISITION_TYPE, ATOM_NAVIGATION_TYPE, OPDS2_TYPE,
+        OPENSEARCH_TYPE,
+    },
+    Error, Result,
+};
+
+use super::{mw_auth, request_base_url};
+
+pub fn routes(mc: ModelController) -> Router {
+    Router::new()
+        .route("/token", get(handler_token))
+        .route("/token", delete(handler_token_revoke))
+        .route("/:version", get(handler_root))
+        .route("/:version/recent", get(handler_recent))
+        .route("/:version/books", get(handler_books))
+        .route("/:version/series", get(handler_series))
+        .route("/:version/series/:serieid", get(handler_serie))
+        .route("/:version/search", get(handler_search))
+        .route("/:version/opensearch.xml", get(handler_opensearch))
+        .route_layer(middleware::from_fn(mw_auth::mw_must_be_connected_basic))
+        .with_state(mc)
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct OpdsQuery {
+    pub sharetoken: Option<String>,
+    #[serde(alias = "q")]
+    pub query: Option<String>,
+    pub page: Option<usize>,
+}
+
+fn opds_urls(
+    library_id: String,
+    version: OpdsVersion,
+    query: &OpdsQuery,
+    headers: &HeaderMap,
+) -> OpdsUrls {
+    OpdsUrls {
+        base_url: request_base_url(headers),
+        library_id,
+        version,
+        sharetoken: query.sharetoken.clone(),
+    }
+}
+
+fn feed_response(feed: OpdsFeed, version: OpdsVersion) -> Result<Response> {
+    let (content_type, body) = match version {
+        OpdsVersion::V1 => {
+            let content_type = if feed.navigation.is_empty() {
+                ATOM_ACQUISITION_TYPE
+            } else {
+                ATOM_NAVIGATION_TYPE
+            };
+            (content_type, feed.to_atom())
+        }
+        OpdsVersion::V2 => (OPDS2_TYPE, feed.to_json().to_string()),
+    };
+    Response::builder()
+        .header(CONTENT_TYPE, content_type)
+        .body(Body::from(body))
+        .map_err(|e| Error::Error(format!("Failed to build response: {}", e)))
+}
+
+async fn handler_token(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    let token = mc.get_opds_token(&library_id, &user).await?;
+    Ok(Json(json!({ "token": token })))
+}
+
+async fn handler_token_revoke(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    mc.revoke_opds_token(&library_id, &user).await?;
+    Ok(Json(json!({"status": "ok"})))
+}
+
+async fn handler_root(
+    Path((library_id, version)): Path<(String, OpdsVersion)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    headers: HeaderMap,
+    Query(query): Query<OpdsQuery>,
+) -> Result<Response> {
+    let urls = opds_urls(library_id, version, &query, &headers);
+    let feed = mc.get_opds_root(&urls, &user).await?;
+    feed_response(feed, version)
+}
+
+async fn handler_recent(
+    Path((library_id, version)): Path<(String, OpdsVersion)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    headers: HeaderMap,
+    Query(query): Query<OpdsQuery>,
+) -> Result<Response> {
+    let urls = opds_urls(library_id, version, &query, &headers);
+    let feed = mc
+        .get_opds_books(&urls, OpdsBookList::Recent, query.page.unwrap_or(1), &user)
+        .await?;
+    feed_response(feed, version)
+}
+
+async fn handler_books(
+    Path((library_id, version)): Path<(String, OpdsVersion)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    headers: HeaderMap,
+    Query(query): Query<OpdsQuery>,
+) -> Result<Response> {
+    let urls = opds_urls(library_id, version, &query, &headers);
+    let feed = mc
+        .get_opds_books(
+            &urls,
+            OpdsBookList::Alphabetical,
+            query.page.unwrap_or(1),
+            &user,
+        )
+        .await?;
+    feed_response(feed, version)
+}
+
+async fn handler_series(
+    Path((library_id, version)): Path<(String, OpdsVersion)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    headers: HeaderMap,
+    Query(query): Query<OpdsQuery>,
+) -> Result<Response> {
+    let urls = opds_urls(library_id, version, &query, &headers);
+    let feed = mc
+        .get_opds_series(&urls, query.page.unwrap_or(1), &user)
+        .await?;
+    feed_response(feed, version)
+}
+
+async fn handler_serie(
+    Path((library_id, version, serie_id)): Path<(String, OpdsVersion, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    headers: HeaderMap,
+    Query(query): Query<OpdsQuery>,
+) -> Result<Response> {
+    let urls = opds_urls(library_id, version, &query, &headers);
+    let feed = mc
+        .get_opds_books(
+            &urls,
+            OpdsBookList::Serie(serie_id),
+            query.page.unwrap_or(1),
+            &user,
+        )
+        .await?;
+    feed_response(feed, version)
+}
+
+async fn handler_search(
+    Path((library_id, version)): Path<(String, OpdsVersion)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    headers: HeaderMap,
+    Query(query): Query<OpdsQuery>,
+) -> Result<Response> {
+    let urls = opds_urls(library_id, version, &query, &headers);
+    let search = query.query.clone().unwrap_or_default();
+    let feed = mc
+        .get_opds_books(
+            &urls,
+            OpdsBookList::Search(search),
+            query.page.unwrap_or(1),
+            &user,
+        )
+        .await?;
+    feed_response(feed, version)
+}
+
+async fn handler_opensearch(
+    Path((library_id, version)): Path<(String, OpdsVersion)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    headers: HeaderMap,
+    Query(query): Query<OpdsQuery>,
+) -> Result<Response> {
+    let urls = opds_urls(library_id, version, &query, &headers);
+    let description = mc.get_opds_opensearch(&urls, &user).await?;
+    Response::builder()
+        .header(CONTENT_TYPE, OPENSEARCH_TYPE)
+        .body(Body::from(description))
+        .map_err(|e| Error::Error(format!("Failed to build response: {}", e)))
+}
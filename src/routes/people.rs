@@ -52,6 +52,11 @@ pub fn routes(mc: ModelController) -> Router {
             "/tasks/face-recognition",
             post(handler_start_face_recognition_task),
         )
+        .route("/tasks/face-reembed", post(handler_start_face_reembed_task))
+        .route(
+            "/tasks/face-reembed/failures",
+            get(handler_face_reembed_failures),
+        )
         .route("/:id", get(handler_get))
         .route("/:id", patch(handler_patch))
         .route("/:id", delete(handler_delete))
@@ -65,6 +70,7 @@ pub fn routes(mc: ModelController) -> Router {
         .route("/:id/faces", get(handler_get_person_faces))
         .route("/faces/:face_id", delete(handler_delete_face))
         .route("/faces/:face_id/image", get(handler_get_face_image))
+        .route("/faces/:face_id/similar", get(handler_get_similar_faces))
         .with_state(mc)
 }
 
@@ -390,6 +396,18 @@ async fn handler_delete_face(
     Ok(Json(json!({"status": "deleted"})))
 }
 
+async fn handler_get_similar_faces(
+    Path((library_id, face_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    Query(query): Query<SimilarFacesQuery>,
+) -> Result<Json<Value>> {
+    let faces = mc
+        .get_similar_faces(&library_id, &face_id, query.limit, &user)
+        .await?;
+    Ok(Json(json!(faces)))
+}
+
 async fn handler_get_face_image(
     Path((library_id, face_id)): Path<(String, String)>,
     State(mc): State<ModelController>,
@@ -451,6 +469,11 @@ struct UnassignedFacesQuery {
     created_before: Option<i64>,
 }
 
+#[derive(Deserialize)]
+struct SimilarFacesQuery {
+    limit: Option<usize>,
+}
+
 async fn handler_assign_face_to_person(
     Path(library_id): Path<String>,
     State(mc): State<ModelController>,
@@ -519,3 +542,35 @@ async fn handler_start_face_recognition_task(
         "message": "Face recognition task has been queued to start immediately"
     })))
 }
+
+async fn handler_start_face_reembed_task(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    use crate::domain::library::LibraryRole;
+    use crate::tools::scheduler::{face_reembed::FaceReembedTask, RsSchedulerWhen, RsTaskType};
+
+    user.check_library_role(&library_id, LibraryRole::Write)?;
+    let task = FaceReembedTask {
+        specific_library: Some(library_id.clone()),
+    };
+
+    mc.scheduler
+        .add(RsTaskType::FaceReembed, RsSchedulerWhen::At(0), task)
+        .await?;
+
+    Ok(Json(json!({
+        "status": "started",
+        "message": "Face re-embedding task has been queued to start immediately"
+    })))
+}
+
+async fn handler_face_reembed_failures(
+    Path(library_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    let failures = mc.get_face_reembed_failures(&library_id, &user).await?;
+    Ok(Json(json!(failures)))
+}
@@ -1,7 +1,7 @@
 use std::{convert::Infallible, io::Cursor, time::Duration};
 
 use crate::{
-    domain::serie::Serie,
+    domain::{serie::Serie, serie_gaps::RsSerieGapReport},
     error::RsError,
     model::{
         books::BookQuery,
@@ -63,6 +63,8 @@ pub fn routes(mc: ModelController) -> Router {
         .route("/:id/rating", get(handler_rating_get))
         .route("/:id/rating", patch(handler_rating_set))
         .route("/:id/books", get(handler_list_books))
+        .route("/:id/gaps", get(handler_gaps))
+        .route("/:id/gaps/request", post(handler_gaps_request))
         .with_state(mc.clone())
         .nest("/:id/", super::episodes::routes(mc))
 }
@@ -107,6 +109,24 @@ async fn handler_list_books(
     Ok(Json(json!(books)))
 }
 
+async fn handler_gaps(
+    Path((library_id, serie_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<RsSerieGapReport>> {
+    let report = mc.get_serie_gaps(&library_id, &serie_id, &user).await?;
+    Ok(Json(report))
+}
+
+async fn handler_gaps_request(
+    Path((library_id, serie_id)): Path<(String, String)>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    let processings = mc.request_serie_gaps(&library_id, &serie_id, &user).await?;
+    Ok(Json(json!(processings)))
+}
+
 async fn handler_list(
     Path(library_id): Path<String>,
     State(mc): State<ModelController>,
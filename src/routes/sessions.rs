@@ -0,0 +1,34 @@
+use crate::{
+    model::{users::ConnectedUser, ModelController},
+    Result,
+};
+use axum::{
+    extract::{Path, State},
+    routing::{delete, get},
+    Json, Router,
+};
+use serde_json::{json, Value};
+
+pub fn routes(mc: ModelController) -> Router {
+    Router::new()
+        .route("/", get(handler_list))
+        .route("/:id", delete(handler_delete))
+        .with_state(mc)
+}
+
+async fn handler_list(
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    let sessions = mc.get_streaming_sessions(&user).await?;
+    Ok(Json(json!(sessions)))
+}
+
+async fn handler_delete(
+    Path(session_id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    mc.kill_streaming_session(&session_id, &user).await?;
+    Ok(Json(json!({"status": "ok"})))
+}
@@ -14,6 +14,7 @@ use crate::{
         backup::{BackupFileProgress, BackupMessage},
         book::BooksMessage,
         channel::ChannelMessage,
+        dvr::DvrRecordingMessage,
         episode::EpisodesMessage,
         library::{LibraryMessage, LibraryRole, LibraryStatusMessage},
         media::{ConvertMessage, MediasMessage, UploadProgressMessage},
@@ -21,6 +22,7 @@ use crate::{
         people::PeopleMessage,
         request_processing::RequestProcessingMessage,
         serie::SeriesMessage,
+        streaming_session::StreamingSessionMessage,
         tag::TagMessage,
         watched::{Unwatched, Watched},
     },
@@ -53,6 +55,8 @@ pub enum SseEvent {
     Unwatched(Unwatched),
     RequestProcessing(RequestProcessingMessage),
     Channels(ChannelMessage),
+    StreamingSessions(StreamingSessionMessage),
+    DvrRecordings(DvrRecordingMessage),
 }
 
 impl SseEvent {
@@ -78,6 +82,8 @@ impl SseEvent {
             SseEvent::Unwatched(_) => "unwatched",
             SseEvent::RequestProcessing(_) => "request_processing",
             SseEvent::Channels(_) => "channels",
+            SseEvent::StreamingSessions(_) => "streaming_sessions",
+            SseEvent::DvrRecordings(_) => "dvr_recordings",
         }
     }
 
@@ -103,6 +109,8 @@ impl SseEvent {
             SseEvent::Unwatched(_) => None,
             SseEvent::RequestProcessing(m) => Some(&m.library),
             SseEvent::Channels(m) => Some(&m.library),
+            SseEvent::StreamingSessions(m) => Some(&m.session.library),
+            SseEvent::DvrRecordings(m) => Some(&m.library),
         }
     }
 
@@ -116,6 +124,7 @@ impl SseEvent {
                 .check_library_role(&m.library, LibraryRole::Admin)
                 .is_ok(),
             SseEvent::BackupsFiles(_) => user.check_role(&UserRole::Admin).is_ok(),
+            SseEvent::StreamingSessions(_) => user.check_role(&UserRole::Admin).is_ok(),
 
             // Backup events: library admin or server admin
             SseEvent::Backups(m) => {
@@ -0,0 +1,91 @@
+use crate::{
+    domain::tagging_model::RsTaggingModelForAdd,
+    error::RsError,
+    model::{users::ConnectedUser, ModelController},
+    Result,
+};
+use axum::{
+    extract::{Multipart, Path, State},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use futures::{pin_mut, TryStreamExt};
+use serde_json::{json, Value};
+use tokio_util::io::StreamReader;
+
+pub fn routes(mc: ModelController) -> Router {
+    Router::new()
+        .route("/", get(handler_list))
+        .route("/", post(handler_upload))
+        .route("/:id", get(handler_get))
+        .route("/:id", delete(handler_delete))
+        .with_state(mc)
+}
+
+async fn handler_list(
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    let models = mc.get_tagging_models(&user).await?;
+    Ok(Json(json!(models)))
+}
+
+async fn handler_get(
+    Path(id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    let model = mc.get_tagging_model(&id, &user).await?;
+    Ok(Json(json!(model)))
+}
+
+/// Multipart upload with a `settings` JSON field and a `labels` CSV field sent before
+/// the `model` ONNX file
+async fn handler_upload(
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+    mut multipart: Multipart,
+) -> Result<Json<Value>> {
+    let mut settings: Option<RsTaggingModelForAdd> = None;
+    let mut labels: Option<String> = None;
+    while let Some(field) = multipart.next_field().await? {
+        let name = field.name().unwrap_or_default().to_string();
+        match name.as_str() {
+            "settings" => {
+                let text = field.text().await?;
+                settings = Some(serde_json::from_str(&text)?);
+            }
+            "labels" => {
+                labels = Some(field.text().await?);
+            }
+            "model" => {
+                let settings = settings.take().ok_or(RsError::Error(
+                    "Tagging model settings must be sent before the model".to_string(),
+                ))?;
+                let labels = labels.take().ok_or(RsError::Error(
+                    "Tagging model labels must be sent before the model".to_string(),
+                ))?;
+                let stream = field.map_err(|multipart_error| {
+                    std::io::Error::new(std::io::ErrorKind::Other, multipart_error)
+                });
+                let reader = StreamReader::new(stream);
+                pin_mut!(reader);
+                let model = mc
+                    .add_tagging_model(settings, labels, &mut reader, &user)
+                    .await?;
+                return Ok(Json(json!(model)));
+            }
+            _ => {}
+        }
+    }
+    Err(RsError::Error("Missing tagging model file".to_string()))
+}
+
+async fn handler_delete(
+    Path(id): Path<String>,
+    State(mc): State<ModelController>,
+    user: ConnectedUser,
+) -> Result<Json<Value>> {
+    let model = mc.remove_tagging_model(&id, &user).await?;
+    Ok(Json(json!(model)))
+}
@@ -37,6 +37,7 @@ const ENV_EXP_PORT: &str = "REDSEAT_EXP_PORT";
 const ENV_DIR: &str = "REDSEAT_DIR";
 const ENV_DOMAIN: &str = "REDSEAT_DOMAIN";
 const ENV_NOCERT: &str = "REDSEAT_NOCERT";
+const ENV_CLIP_MODEL: &str = "REDSEAT_CLIP_MODEL";
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ServerConfig {
     pub id: Option<String>,
@@ -51,6 +52,8 @@ pub struct ServerConfig {
     pub token: Option<String>,
     #[serde(default = "default_false")]
     pub imagesUseIm: bool,
+    /// Folder of the CLIP model used for semantic search (visual.onnx, textual.onnx, tokenizer.json)
+    pub clip_model: Option<String>,
 }
 
 impl ServerConfig {
@@ -147,6 +150,13 @@ pub async fn get_server_port() -> u16 {
         .or_else(|| config_port)
         .unwrap_or(8080)
 }
+pub async fn get_clip_model_path() -> String {
+    let config_path = get_config().await.clip_model;
+    env::var(ENV_CLIP_MODEL)
+        .ok()
+        .or(config_path)
+        .unwrap_or("models/clip".to_string())
+}
 pub async fn get_server_exposed_port() -> u16 {
     let config_port = get_config().await.exp_port;
     env::var(ENV_EXP_PORT)
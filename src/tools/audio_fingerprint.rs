@@ -0,0 +1,350 @@
+use std::{collections::HashMap, process::Stdio};
+
+use tokio::process::Command;
+
+use crate::error::{RsError, RsResult};
+
+use super::video_tools::VideoCommandBuilder;
+
+/// Audio is downmixed and resampled to this rate before fingerprinting
+pub const FINGERPRINT_SAMPLE_RATE: u32 = 11025;
+const FRAME_SIZE: usize = 4096;
+const FRAME_HOP: usize = 1365;
+const MIN_FREQ: f64 = 28.0;
+const MAX_FREQ: f64 = 3520.0;
+/// Frames quieter than this RMS (i16 scale) are marked silent and never match
+const SILENCE_RMS: f64 = 100.0;
+/// Maximum number of differing bits for two points to be considered equal
+pub const MAX_BIT_ERRORS: u32 = 6;
+const MAX_CANDIDATE_SHIFTS: usize = 20;
+
+/// Duration in seconds covered by one fingerprint point
+pub fn point_duration() -> f64 {
+    FRAME_HOP as f64 / FINGERPRINT_SAMPLE_RATE as f64
+}
+
+/// Decode a portion of the audio track as mono signed 16 bits samples
+pub async fn decode_audio(uri: &str, start: f64, duration: f64) -> RsResult<Vec<i16>> {
+    let output = Command::new(VideoCommandBuilder::get_ffmpeg_path())
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-ss")
+        .arg(start.to_string())
+        .arg("-t")
+        .arg(duration.to_string())
+        .arg("-i")
+        .arg(uri)
+        .arg("-vn")
+        .arg("-sn")
+        .arg("-ac")
+        .arg("1")
+        .arg("-ar")
+        .arg(FINGERPRINT_SAMPLE_RATE.to_string())
+        .arg("-f")
+        .arg("s16le")
+        .arg("pipe:1")
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(RsError::Error(format!(
+            "Unable to decode audio: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(output
+        .stdout
+        .chunks_exact(2)
+        .map(|c| i16::from_le_bytes([c[0], c[1]]))
+        .collect())
+}
+
+/// In place iterative radix-2 FFT, `re` and `im` length must be a power of two
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * std::f64::consts::PI / len as f64;
+        let (wr, wi) = (angle.cos(), angle.sin());
+        for start in (0..n).step_by(len) {
+            let (mut cr, mut ci) = (1.0, 0.0);
+            for k in 0..len / 2 {
+                let a = start + k;
+                let b = a + len / 2;
+                let tr = re[b] * cr - im[b] * ci;
+                let ti = re[b] * ci + im[b] * cr;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+                let next = cr * wr - ci * wi;
+                ci = cr * wi + ci * wr;
+                cr = next;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// 32 bits sub-fingerprint: pitch class contour, temporal change and chord shape
+fn chroma_bits(chroma: &[f64; 12], previous: &[f64; 12]) -> u32 {
+    let mut bits = 0u32;
+    for i in 0..12 {
+        if chroma[i] > chroma[(i + 1) % 12] {
+            bits |= 1 << i;
+        }
+        if chroma[i] > previous[i] {
+            bits |= 1 << (12 + i);
+        }
+    }
+    for i in 0..8 {
+        if chroma[i] + chroma[(i + 4) % 12] > chroma[(i + 2) % 12] + chroma[(i + 6) % 12] {
+            bits |= 1 << (24 + i);
+        }
+    }
+    // 0 is reserved for silence
+    bits.max(1)
+}
+
+/// Chromaprint-style fingerprint: one 32 bits point every `point_duration()` seconds
+pub fn fingerprint(samples: &[i16]) -> Vec<u32> {
+    if samples.len() < FRAME_SIZE {
+        return vec![];
+    }
+    let window: Vec<f64> = (0..FRAME_SIZE)
+        .map(|i| {
+            0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / (FRAME_SIZE - 1) as f64).cos()
+        })
+        .collect();
+    let classes: Vec<Option<usize>> = (0..FRAME_SIZE / 2)
+        .map(|k| {
+            let freq = k as f64 * FINGERPRINT_SAMPLE_RATE as f64 / FRAME_SIZE as f64;
+            if !(MIN_FREQ..=MAX_FREQ).contains(&freq) {
+                None
+            } else {
+                let note = 12.0 * (freq / 440.0).log2() + 69.0;
+                Some((note.round() as i64).rem_euclid(12) as usize)
+            }
+        })
+        .collect();
+
+    let mut re = vec![0f64; FRAME_SIZE];
+    let mut im = vec![0f64; FRAME_SIZE];
+    let mut previous = [0f64; 12];
+    let mut points = Vec::with_capacity(samples.len() / FRAME_HOP);
+    let mut start = 0;
+    while start + FRAME_SIZE <= samples.len() {
+        let frame = &samples[start..start + FRAME_SIZE];
+        let energy: f64 = frame.iter().map(|s| (*s as f64) * (*s as f64)).sum();
+        let rms = (energy / FRAME_SIZE as f64).sqrt();
+        for i in 0..FRAME_SIZE {
+            re[i] = frame[i] as f64 * window[i];
+            im[i] = 0.0;
+        }
+        fft(&mut re, &mut im);
+
+        let mut chroma = [0f64; 12];
+        for (k, class) in classes.iter().enumerate() {
+            if let Some(class) = class {
+                chroma[*class] += (re[k] * re[k] + im[k] * im[k]).sqrt();
+            }
+        }
+        let norm = chroma.iter().map(|c| c * c).sum::<f64>().sqrt();
+        if norm > 0.0 {
+            chroma.iter_mut().for_each(|c| *c /= norm);
+        }
+
+        points.push(if rms < SILENCE_RMS {
+            0
+        } else {
+            chroma_bits(&chroma, &previous)
+        });
+        previous = chroma;
+        start += FRAME_HOP;
+    }
+    points
+}
+
+/// Matching range between two fingerprints, in points (end exclusive)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedSegment {
+    pub a_start: usize,
+    pub a_end: usize,
+    pub b_start: usize,
+    pub b_end: usize,
+}
+
+impl SharedSegment {
+    pub fn len(&self) -> usize {
+        self.a_end - self.a_start
+    }
+}
+
+fn is_match(a: u32, b: u32) -> bool {
+    a != 0 && b != 0 && (a ^ b).count_ones() <= MAX_BIT_ERRORS
+}
+
+/// Longest run of matching points for a given alignment, tolerating `max_gap` missed points
+fn longest_run(a: &[u32], b: &[u32], shift: isize, max_gap: usize) -> Option<SharedSegment> {
+    let mut best: Option<(usize, usize)> = None;
+    let mut current: Option<(usize, usize)> = None;
+    for i in 0..a.len() {
+        let j = i as isize + shift;
+        if j < 0 {
+            continue;
+        }
+        let j = j as usize;
+        if j >= b.len() {
+            break;
+        }
+        if !is_match(a[i], b[j]) {
+            continue;
+        }
+        current = match current {
+            Some((start, last)) if i - last <= max_gap + 1 => Some((start, i)),
+            _ => Some((i, i)),
+        };
+        if let Some((start, last)) = current {
+            if best.map(|(s, l)| last - start > l - s).unwrap_or(true) {
+                best = Some((start, last));
+            }
+        }
+    }
+    best.map(|(start, last)| SharedSegment {
+        a_start: start,
+        a_end: last + 1,
+        b_start: (start as isize + shift) as usize,
+        b_end: (last as isize + shift) as usize + 1,
+    })
+}
+
+/// Find the longest audio segment present in both fingerprints
+pub fn find_shared_segment(
+    a: &[u32],
+    b: &[u32],
+    min_points: usize,
+    max_gap: usize,
+) -> Option<SharedSegment> {
+    let mut index: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (j, value) in b.iter().enumerate() {
+        if *value != 0 {
+            index.entry(*value).or_default().push(j);
+        }
+    }
+    let mut votes: HashMap<isize, usize> = HashMap::new();
+    for (i, value) in a.iter().enumerate() {
+        if let Some(positions) = index.get(value) {
+            for j in positions {
+                *votes.entry(*j as isize - i as isize).or_default() += 1;
+            }
+        }
+    }
+    let mut shifts: Vec<(isize, usize)> = votes.into_iter().collect();
+    shifts.sort_by(|x, y| y.1.cmp(&x.1).then(x.0.cmp(&y.0)));
+
+    let mut best: Option<SharedSegment> = None;
+    for (shift, _) in shifts.into_iter().take(MAX_CANDIDATE_SHIFTS) {
+        if let Some(segment) = longest_run(a, b, shift, max_gap) {
+            if segment.len() >= min_points
+                && best
+                    .as_ref()
+                    .map(|b| segment.len() > b.len())
+                    .unwrap_or(true)
+            {
+                best = Some(segment);
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic sequence of random chords, one every half second
+    fn chords(seed: u64, seconds: usize) -> Vec<i16> {
+        let rate = FINGERPRINT_SAMPLE_RATE as usize;
+        let mut state = seed;
+        let mut next = || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as usize
+        };
+        let mut samples = Vec::with_capacity(seconds * rate);
+        for _ in 0..seconds * 2 {
+            let notes: Vec<f64> = (0..3)
+                .map(|_| 220.0 * 2f64.powf((next() % 24) as f64 / 12.0))
+                .collect();
+            for n in 0..rate / 2 {
+                let t = n as f64 / rate as f64;
+                let value: f64 = notes
+                    .iter()
+                    .map(|f| (2.0 * std::f64::consts::PI * f * t).sin())
+                    .sum();
+                samples.push((value * 6000.0) as i16);
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn fft_finds_pure_tone() {
+        let mut re: Vec<f64> = (0..64)
+            .map(|i| (2.0 * std::f64::consts::PI * 4.0 * i as f64 / 64.0).cos())
+            .collect();
+        let mut im = vec![0f64; 64];
+        fft(&mut re, &mut im);
+        let peak = (0..32)
+            .max_by(|a, b| re[*a].abs().partial_cmp(&re[*b].abs()).unwrap())
+            .unwrap();
+        assert_eq!(peak, 4);
+    }
+
+    #[test]
+    fn silence_never_matches() {
+        let silence = vec![0i16; FINGERPRINT_SAMPLE_RATE as usize * 20];
+        let points = fingerprint(&silence);
+        assert!(points.iter().all(|p| *p == 0));
+        assert_eq!(find_shared_segment(&points, &points, 10, 5), None);
+    }
+
+    #[test]
+    fn finds_intro_at_different_offsets() {
+        let intro = chords(42, 30);
+        let mut first = chords(1, 10);
+        first.extend(&intro);
+        first.extend(chords(2, 20));
+        let mut second = chords(3, 25);
+        second.extend(&intro);
+        second.extend(chords(4, 10));
+
+        let a = fingerprint(&first);
+        let b = fingerprint(&second);
+        let min_points = (15.0 / point_duration()) as usize;
+        let max_gap = (3.0 / point_duration()) as usize;
+        let segment = find_shared_segment(&a, &b, min_points, max_gap).unwrap();
+
+        let a_start = segment.a_start as f64 * point_duration();
+        let a_end = segment.a_end as f64 * point_duration();
+        let b_start = segment.b_start as f64 * point_duration();
+        assert!((a_start - 10.0).abs() < 1.5, "start {}", a_start);
+        assert!((a_end - 40.0).abs() < 1.5, "end {}", a_end);
+        assert!((b_start - 25.0).abs() < 1.5, "start {}", b_start);
+    }
+}
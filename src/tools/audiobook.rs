@@ -0,0 +1,151 @@
+use crate::domain::{audiobook::RsAudiobookPart, ffmpeg::FormatTags};
+
+use super::opf_parser::OpfMetadata;
+
+/// M4B/M4A and MP3 files, the audio formats read as audiobooks in Books libraries
+pub const AUDIOBOOK_MIMES: [&str; 7] = [
+    "audio/mp4",
+    "audio/x-m4b",
+    "audio/m4b",
+    "audio/x-m4a",
+    "audio/m4a",
+    "audio/mpeg",
+    "audio/mp3",
+];
+
+pub fn is_audiobook_mime(mime: &str) -> bool {
+    AUDIOBOOK_MIMES.contains(&mime)
+}
+
+/// Part number from a `track` tag, `3` or `3/12`
+pub fn parse_part_number(track: &str) -> Option<u32> {
+    track.split('/').next()?.trim().parse::<u32>().ok()
+}
+
+/// Book metadata from the tags of an audio file. Multi-file audiobooks share the
+/// album tag and have the chapter name as title
+pub fn audiobook_metadata(tags: &FormatTags) -> OpfMetadata {
+    let not_empty = |value: &Option<String>| {
+        value
+            .as_ref()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let title = not_empty(&tags.album).or_else(|| not_empty(&tags.title));
+    let authors = not_empty(&tags.album_artist)
+        .or_else(|| not_empty(&tags.artist))
+        .map(|author| vec![author])
+        .unwrap_or_default();
+    let year = tags
+        .date
+        .as_ref()
+        .and_then(|date| date.get(0..4))
+        .and_then(|year| year.parse::<u16>().ok());
+    OpfMetadata {
+        title,
+        authors,
+        language: not_empty(&tags.language),
+        description: not_empty(&tags.comment),
+        year,
+        ..Default::default()
+    }
+}
+
+/// Order parts by part number then name and set their start in the whole book
+pub fn order_parts(mut parts: Vec<RsAudiobookPart>) -> Vec<RsAudiobookPart> {
+    parts.sort_by(|a, b| {
+        (a.part.unwrap_or(u32::MAX), &a.name).cmp(&(b.part.unwrap_or(u32::MAX), &b.name))
+    });
+    let mut start = 0;
+    for part in parts.iter_mut() {
+        part.start = start;
+        start += part.duration;
+    }
+    parts
+}
+
+/// Part playing at a position of the whole book and the position inside this part.
+/// Positions past the end stay at the end of the last part
+pub fn part_at(parts: &[RsAudiobookPart], position: u64) -> Option<(&RsAudiobookPart, u64)> {
+    let part = parts
+        .iter()
+        .find(|part| position < part.start + part.duration)
+        .or(parts.last())?;
+    Some((part, position.saturating_sub(part.start).min(part.duration)))
+}
+
+/// Position in the whole book of a progress inside a part
+pub fn global_position(part: &RsAudiobookPart, progress: u64) -> u64 {
+    part.start + progress.min(part.duration)
+}
+
+/// List for FFmpeg's concat demuxer, inputs are paths or urls
+pub fn ffconcat_list(inputs: &[String]) -> String {
+    let mut content = String::from("ffconcat version 1.0\n");
+    for input in inputs {
+        content.push_str(&format!("file '{}'\n", input.replace('\'', "'\\''")));
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(media: &str, number: Option<u32>, duration: u64) -> RsAudiobookPart {
+        RsAudiobookPart {
+            media: media.to_string(),
+            name: format!("{}.mp3", media),
+            part: number,
+            duration,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_track_tags() {
+        assert_eq!(parse_part_number("3"), Some(3));
+        assert_eq!(parse_part_number("03/12"), Some(3));
+        assert_eq!(parse_part_number("side A"), None);
+    }
+
+    #[test]
+    fn metadata_from_tags() {
+        let metadata = audiobook_metadata(&FormatTags {
+            title: Some("Chapter 1".to_string()),
+            album: Some("The Book".to_string()),
+            artist: Some("Narrator".to_string()),
+            album_artist: Some("Author".to_string()),
+            date: Some("2019-05-01".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(metadata.title.as_deref(), Some("The Book"));
+        assert_eq!(metadata.authors, vec!["Author".to_string()]);
+        assert_eq!(metadata.year, Some(2019));
+    }
+
+    #[test]
+    fn global_positions_across_parts() {
+        let parts = order_parts(vec![
+            part("c", None, 500),
+            part("b", Some(2), 2000),
+            part("a", Some(1), 1000),
+        ]);
+        let order: Vec<&str> = parts.iter().map(|p| p.media.as_str()).collect();
+        assert_eq!(order, vec!["a", "b", "c"]);
+        assert_eq!(parts[2].start, 3000);
+
+        let (current, offset) = part_at(&parts, 1500).unwrap();
+        assert_eq!((current.media.as_str(), offset), ("b", 500));
+        assert_eq!(global_position(current, offset), 1500);
+        let (current, offset) = part_at(&parts, 10000).unwrap();
+        assert_eq!((current.media.as_str(), offset), ("c", 500));
+        assert!(part_at(&[], 0).is_none());
+    }
+
+    #[test]
+    fn concat_list_escapes_quotes() {
+        let list = ffconcat_list(&["/books/it's.mp3".to_string()]);
+        assert_eq!(list, "ffconcat version 1.0\nfile '/books/it'\\''s.mp3'\n");
+    }
+}
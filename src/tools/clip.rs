@@ -0,0 +1,321 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use image::{imageops::FilterType, DynamicImage, GenericImageView};
+use ndarray::{Array2, Array4};
+use ort::{
+    inputs,
+    session::{builder::GraphOptimizationLevel, Session},
+    tensor::TensorElementType,
+    value::{DynValue, Tensor, ValueType},
+};
+use tokenizers::Tokenizer;
+
+use crate::error::{RsError, RsResult};
+
+/// Normalization of the images the CLIP visual encoders were trained with
+const CLIP_MEAN: [f32; 3] = [0.481_454_66, 0.457_827_5, 0.408_210_73];
+const CLIP_STD: [f32; 3] = [0.268_629_54, 0.261_302_6, 0.275_777_1];
+const DEFAULT_IMAGE_SIZE: u32 = 224;
+const DEFAULT_CONTEXT_LENGTH: usize = 77;
+
+/// Input of the text encoder: token ids and optional attention mask
+struct TextInputs {
+    ids: String,
+    mask: Option<String>,
+    ty: TensorElementType,
+    context_length: usize,
+}
+
+/// CLIP compatible image and text encoders sharing the same embedding space.
+/// The model folder contains `visual.onnx`, `textual.onnx` and `tokenizer.json`
+#[derive(Clone)]
+pub struct ClipService {
+    visual_session: Arc<Mutex<Session>>,
+    textual_session: Arc<Mutex<Session>>,
+    tokenizer: Arc<Tokenizer>,
+    image_size: u32,
+    text_inputs: Arc<TextInputs>,
+}
+
+fn tensor_input(session: &Session, index: usize) -> Option<(String, TensorElementType, Vec<i64>)> {
+    let input = session.inputs.get(index)?;
+    match &input.input_type {
+        ValueType::Tensor { ty, shape, .. } => {
+            Some((input.name.clone(), *ty, shape.iter().copied().collect()))
+        }
+        _ => None,
+    }
+}
+
+/// Output holding the embeddings, `image_embeds`/`text_embeds` when exported by
+/// transformers or else the first one
+fn embeds_output(session: &Session, name: &str) -> RsResult<String> {
+    session
+        .outputs
+        .iter()
+        .find(|output| output.name == name)
+        .or(session.outputs.first())
+        .map(|output| output.name.clone())
+        .ok_or(RsError::Error("Clip model does not have outputs".into()))
+}
+
+fn load_session(path: &Path) -> RsResult<Session> {
+    Ok(Session::builder()?
+        .with_optimization_level(GraphOptimizationLevel::Level3)?
+        .with_intra_threads(1)?
+        .commit_from_file(path)?)
+}
+
+impl ClipService {
+    pub async fn new_async(models_path: &str) -> RsResult<Self> {
+        let path = models_path.to_string();
+        tokio::task::spawn_blocking(move || Self::new(&path))
+            .await
+            .map_err(|e| RsError::Error(format!("Join error: {}", e)))?
+    }
+
+    pub fn new(models_path: &str) -> RsResult<Self> {
+        let folder = Path::new(models_path);
+        let visual_session = load_session(&folder.join("visual.onnx"))?;
+        let textual_session = load_session(&folder.join("textual.onnx"))?;
+        let tokenizer = Tokenizer::from_file(folder.join("tokenizer.json"))
+            .map_err(|e| RsError::Error(format!("Unable to load clip tokenizer: {}", e)))?;
+
+        let image_size = tensor_input(&visual_session, 0)
+            .and_then(|(_, _, shape)| shape.get(2).copied())
+            .filter(|size| *size > 0)
+            .map(|size| size as u32)
+            .unwrap_or(DEFAULT_IMAGE_SIZE);
+        let (ids, ty, shape) = tensor_input(&textual_session, 0).ok_or(RsError::Error(
+            "Clip text model does not have inputs".into(),
+        ))?;
+        let context_length = shape
+            .get(1)
+            .copied()
+            .filter(|length| *length > 0)
+            .map(|length| length as usize)
+            .unwrap_or(DEFAULT_CONTEXT_LENGTH);
+        let mask = tensor_input(&textual_session, 1).map(|(name, _, _)| name);
+
+        Ok(Self {
+            visual_session: Arc::new(Mutex::new(visual_session)),
+            textual_session: Arc::new(Mutex::new(textual_session)),
+            tokenizer: Arc::new(tokenizer),
+            image_size,
+            text_inputs: Arc::new(TextInputs {
+                ids,
+                mask,
+                ty,
+                context_length,
+            }),
+        })
+    }
+
+    pub async fn embed_image_async(&self, image: DynamicImage) -> RsResult<Vec<f32>> {
+        let service = self.clone();
+        tokio::task::spawn_blocking(move || service.embed_image(&image))
+            .await
+            .map_err(|e| RsError::Error(format!("Join error: {}", e)))?
+    }
+
+    pub async fn embed_text_async(&self, text: String) -> RsResult<Vec<f32>> {
+        let service = self.clone();
+        tokio::task::spawn_blocking(move || service.embed_text(&text))
+            .await
+            .map_err(|e| RsError::Error(format!("Join error: {}", e)))?
+    }
+
+    /// Normalized embedding of an image
+    pub fn embed_image(&self, image: &DynamicImage) -> RsResult<Vec<f32>> {
+        let input = preprocess_for_clip(image, self.image_size);
+        let tensor = Tensor::from_array(input)?;
+        let mut session = self
+            .visual_session
+            .lock()
+            .map_err(|e| RsError::Error(format!("Failed to lock session: {:?}", e)))?;
+        let input_name = session
+            .inputs
+            .first()
+            .ok_or(RsError::Error(
+                "Clip image model does not have inputs".into(),
+            ))?
+            .name
+            .clone();
+        let output_name = embeds_output(&session, "image_embeds")?;
+        let outputs = session.run(inputs![input_name => tensor])?;
+        let (_, data) = outputs[output_name.as_str()].try_extract_tensor::<f32>()?;
+        Ok(normalize(data))
+    }
+
+    /// Normalized embedding of a text query
+    pub fn embed_text(&self, text: &str) -> RsResult<Vec<f32>> {
+        let encoding = self
+            .tokenizer
+            .encode(text, true)
+            .map_err(|e| RsError::Error(format!("Unable to tokenize query: {}", e)))?;
+        let context_length = self.text_inputs.context_length;
+        let (ids, mask) = pad_tokens(encoding.get_ids(), context_length);
+        let ids = token_tensor(ids, context_length, self.text_inputs.ty)?;
+
+        let mut session = self
+            .textual_session
+            .lock()
+            .map_err(|e| RsError::Error(format!("Failed to lock session: {:?}", e)))?;
+        let output_name = embeds_output(&session, "text_embeds")?;
+        let outputs = match &self.text_inputs.mask {
+            Some(mask_name) => {
+                let mask = token_tensor(mask, context_length, self.text_inputs.ty)?;
+                session.run(
+                    inputs![self.text_inputs.ids.as_str() => ids, mask_name.as_str() => mask],
+                )?
+            }
+            None => session.run(inputs![self.text_inputs.ids.as_str() => ids])?,
+        };
+        let (_, data) = outputs[output_name.as_str()].try_extract_tensor::<f32>()?;
+        Ok(normalize(data))
+    }
+}
+
+fn token_tensor(values: Vec<i64>, length: usize, ty: TensorElementType) -> RsResult<DynValue> {
+    Ok(match ty {
+        TensorElementType::Int32 => Tensor::from_array(Array2::from_shape_vec(
+            (1, length),
+            values.into_iter().map(|v| v as i32).collect(),
+        )?)?
+        .into_dyn(),
+        _ => Tensor::from_array(Array2::from_shape_vec((1, length), values)?)?.into_dyn(),
+    })
+}
+
+/// Token ids truncated or zero padded to the context length of the text encoder,
+/// with the matching attention mask
+pub fn pad_tokens(ids: &[u32], length: usize) -> (Vec<i64>, Vec<i64>) {
+    let mut tokens: Vec<i64> = ids.iter().take(length).map(|id| *id as i64).collect();
+    let mut mask = vec![1; tokens.len()];
+    tokens.resize(length, 0);
+    mask.resize(length, 0);
+    (tokens, mask)
+}
+
+/// Resize the shortest side, center crop and normalize to a NCHW tensor
+pub fn preprocess_for_clip(image: &DynamicImage, size: u32) -> Array4<f32> {
+    let (width, height) = image.dimensions();
+    let scale = size as f32 / width.min(height).max(1) as f32;
+    let resized = image.resize_exact(
+        ((width as f32 * scale).round() as u32).max(size),
+        ((height as f32 * scale).round() as u32).max(size),
+        FilterType::CatmullRom,
+    );
+    let (width, height) = resized.dimensions();
+    let rgb = resized
+        .crop_imm((width - size) / 2, (height - size) / 2, size, size)
+        .to_rgb8();
+
+    let mut input = Array4::<f32>::zeros((1, 3, size as usize, size as usize));
+    for (x, y, pixel) in rgb.enumerate_pixels() {
+        for channel in 0..3 {
+            input[[0, channel, y as usize, x as usize]] =
+                (pixel[channel] as f32 / 255.0 - CLIP_MEAN[channel]) / CLIP_STD[channel];
+        }
+    }
+    input
+}
+
+pub fn normalize(v: &[f32]) -> Vec<f32> {
+    let norm: f32 = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm < 1e-6 {
+        v.to_vec()
+    } else {
+        v.iter().map(|x| x / norm).collect()
+    }
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a < 1e-6 || norm_b < 1e-6 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// Ids of the candidates most similar to the query, best first
+pub fn rank_by_similarity(
+    query: &[f32],
+    candidates: &[(String, Vec<f32>)],
+    limit: usize,
+) -> Vec<(String, f32)> {
+    let mut ranked: Vec<(String, f32)> = candidates
+        .iter()
+        .map(|(id, embedding)| (id.clone(), cosine_similarity(query, embedding)))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked.truncate(limit);
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    #[test]
+    fn rank_candidates() {
+        let candidates = vec![
+            ("a".to_string(), vec![0.0, 1.0]),
+            ("b".to_string(), vec![1.0, 0.1]),
+            ("c".to_string(), vec![-1.0, 0.0]),
+            ("d".to_string(), vec![1.0, 0.0, 0.0]),
+        ];
+        let ranked = rank_by_similarity(&[2.0, 0.0], &candidates, 3);
+        let ids: Vec<&str> = ranked.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "a", "d"]);
+        assert!((cosine_similarity(&[1.0, 0.0], &normalize(&[3.0, 0.0])) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn pad_and_truncate_tokens() {
+        assert_eq!(
+            pad_tokens(&[49406, 320, 49407], 5),
+            (vec![49406, 320, 49407, 0, 0], vec![1, 1, 1, 0, 0])
+        );
+        assert_eq!(pad_tokens(&[1, 2, 3], 2), (vec![1, 2], vec![1, 1]));
+    }
+
+    #[test]
+    fn preprocess_center_crop() {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_fn(40, 20, |x, _| {
+            if !(10..30).contains(&x) {
+                Rgb([0, 0, 0])
+            } else {
+                Rgb([255, 255, 255])
+            }
+        }));
+        let input = preprocess_for_clip(&image, 8);
+        assert_eq!(input.shape(), &[1, 3, 8, 8]);
+        let white = (1.0 - CLIP_MEAN[0]) / CLIP_STD[0];
+        assert!((input[[0, 0, 4, 4]] - white).abs() < 1e-3);
+    }
+
+    #[test]
+    fn fixture_model_matches_colors() {
+        let service = ClipService::new("test_data/clip").unwrap();
+        let image =
+            |color: [u8; 3]| DynamicImage::ImageRgb8(RgbImage::from_pixel(6, 4, Rgb(color)));
+        let red = service.embed_image(&image([255, 0, 0])).unwrap();
+        let blue = service.embed_image(&image([0, 0, 255])).unwrap();
+        let query = service.embed_text("Red").unwrap();
+        assert!(cosine_similarity(&query, &red) > cosine_similarity(&query, &blue));
+        let query = service.embed_text("blue").unwrap();
+        assert!(cosine_similarity(&query, &blue) > cosine_similarity(&query, &red));
+    }
+}
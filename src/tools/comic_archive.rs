@@ -0,0 +1,331 @@
+use std::{
+    cmp::Ordering,
+    io::{Read, Seek},
+    iter::Peekable,
+    path::{Path, PathBuf},
+    process::Stdio,
+    str::Chars,
+    sync::OnceLock,
+    time::Instant,
+};
+
+use tokio::{io::AsyncReadExt, process::Command};
+use which::which;
+
+use crate::error::{RsError, RsResult};
+
+pub const COMIC_INFO: &str = "ComicInfo.xml";
+const PAGE_EXTENSIONS: [&str; 7] = ["jpg", "jpeg", "png", "webp", "gif", "avif", "bmp"];
+
+static UNRAR: OnceLock<bool> = OnceLock::new();
+
+/// Whether the `unrar` command needed by RAR (CBR) comics is installed, checked on the first call
+pub fn has_unrar() -> bool {
+    *UNRAR.get_or_init(|| which("unrar").is_ok())
+}
+
+fn check_unrar() -> RsResult<()> {
+    if has_unrar() {
+        Ok(())
+    } else {
+        Err(RsError::Error(
+            "RAR (CBR) comics need the unrar command (install unrar)".to_string(),
+        ))
+    }
+}
+
+/// Comic archive with its pages in reading order, kept while a reader turns pages so
+/// encrypted or remote sources are copied and listed once
+#[derive(Debug, Clone)]
+pub struct CachedComicArchive {
+    pub path: PathBuf,
+    /// `path` is a temporary copy to remove when the archive leaves the cache
+    pub temporary: bool,
+    pub kind: ComicArchive,
+    pub pages: Vec<String>,
+    pub last_used: Instant,
+}
+
+/// Archive format of a comic book
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComicArchive {
+    Zip,
+    Rar,
+    SevenZip,
+}
+
+impl ComicArchive {
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        match mime {
+            "application/zip"
+            | "application/x-cbz"
+            | "application/vnd.comicbook+zip"
+            | "application/vnd.comicbook+cbz" => Some(ComicArchive::Zip),
+            "application/x-cbr"
+            | "application/vnd.comicbook-rar"
+            | "application/vnd.rar"
+            | "application/x-rar-compressed" => Some(ComicArchive::Rar),
+            "application/x-cb7" | "application/x-7z-compressed" => Some(ComicArchive::SevenZip),
+            _ => None,
+        }
+    }
+
+    /// Format from the first bytes of the file, mimes of comics are often wrong
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        if header.starts_with(b"PK\x03\x04") {
+            Some(ComicArchive::Zip)
+        } else if header.starts_with(b"Rar!\x1a\x07") {
+            Some(ComicArchive::Rar)
+        } else if header.starts_with(b"7z\xbc\xaf\x27\x1c") {
+            Some(ComicArchive::SevenZip)
+        } else {
+            None
+        }
+    }
+
+    /// Format from the first bytes of a file
+    pub async fn detect_file(path: &Path) -> Option<Self> {
+        let mut file = tokio::fs::File::open(path).await.ok()?;
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header).await.ok()?;
+        Self::detect(&header)
+    }
+}
+
+pub fn is_page(name: &str) -> bool {
+    let name = name.to_lowercase();
+    !name.ends_with('/')
+        && !name.starts_with("__macosx/")
+        && name
+            .rsplit_once('.')
+            .map(|(_, extension)| PAGE_EXTENSIONS.contains(&extension))
+            .unwrap_or(false)
+}
+
+pub fn is_comic_info(name: &str) -> bool {
+    name.rsplit('/')
+        .next()
+        .map(|file| file.eq_ignore_ascii_case(COMIC_INFO))
+        .unwrap_or(false)
+}
+
+/// Page entries in reading order: `page2` before `page10`
+pub fn sort_pages(mut names: Vec<String>) -> Vec<String> {
+    names.retain(|name| is_page(name));
+    names.sort_by(|a, b| natural_cmp(&a.to_lowercase(), &b.to_lowercase()));
+    names
+}
+
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x, y) = (take_number(&mut a), take_number(&mut b));
+                let ordering = x.len().cmp(&y.len()).then(x.cmp(&y));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_number(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+        digits.push(*c);
+        chars.next();
+    }
+    digits.trim_start_matches('0').to_string()
+}
+
+/// Entry names of a 7z archive
+pub fn sevenz_entries<R: Read + Seek>(reader: R, len: u64) -> RsResult<Vec<String>> {
+    let archive = sevenz_rust::SevenZReader::new(reader, len, sevenz_rust::Password::empty())?;
+    Ok(archive
+        .archive()
+        .files
+        .iter()
+        .filter(|entry| entry.has_stream() && !entry.is_directory())
+        .map(|entry| entry.name().to_string())
+        .collect())
+}
+
+/// Content of an entry of a 7z archive
+pub fn sevenz_entry<R: Read + Seek>(reader: R, len: u64, name: &str) -> RsResult<Vec<u8>> {
+    let mut archive = sevenz_rust::SevenZReader::new(reader, len, sevenz_rust::Password::empty())?;
+    let mut data = None;
+    archive.for_each_entries(|entry, entry_reader| {
+        if entry.name() == name {
+            let mut content = Vec::new();
+            entry_reader.read_to_end(&mut content)?;
+            data = Some(content);
+            return Ok(false);
+        }
+        // Solid archives are decoded sequentially
+        std::io::copy(entry_reader, &mut std::io::sink())?;
+        Ok(true)
+    })?;
+    data.ok_or(RsError::Error(format!(
+        "Unable to find {} in 7z archive",
+        name
+    )))
+}
+
+/// Entry names of a RAR archive, read with the `unrar` command
+pub async fn rar_entries(path: &Path) -> RsResult<Vec<String>> {
+    check_unrar()?;
+    let output = Command::new("unrar")
+        .arg("lb")
+        .arg("-p-")
+        .arg("--")
+        .arg(path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(RsError::Error(format!(
+            "Unable to list RAR archive ({})",
+            output.status
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim().replace('\\', "/"))
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+/// Content of an entry of a RAR archive, read with the `unrar` command
+pub async fn rar_entry(path: &Path, name: &str) -> RsResult<Vec<u8>> {
+    check_unrar()?;
+    let output = Command::new("unrar")
+        .arg("p")
+        .arg("-inul")
+        .arg("-p-")
+        // Entry names come from the archive, they must not be read as switches
+        .arg("--")
+        .arg(path)
+        .arg(name)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .await?;
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(RsError::Error(format!(
+            "Unable to find {} in RAR archive",
+            name
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Entry names of a comic archive stored at `path`
+pub async fn comic_entries(kind: ComicArchive, path: &Path) -> RsResult<Vec<String>> {
+    match kind {
+        ComicArchive::Rar => rar_entries(path).await,
+        ComicArchive::Zip => {
+            let path = path.to_path_buf();
+            tokio::task::spawn_blocking(move || {
+                let file = std::io::BufReader::new(std::fs::File::open(path)?);
+                let archive = zip::ZipArchive::new(file)
+                    .map_err(|_| RsError::Error("Unable to open zip file".to_string()))?;
+                Ok(archive.file_names().map(|name| name.to_string()).collect())
+            })
+            .await?
+        }
+        ComicArchive::SevenZip => {
+            let path = path.to_path_buf();
+            tokio::task::spawn_blocking(move || {
+                let file = std::fs::File::open(path)?;
+                let len = file.metadata()?.len();
+                sevenz_entries(std::io::BufReader::new(file), len)
+            })
+            .await?
+        }
+    }
+}
+
+/// Content of an entry of a comic archive stored at `path`
+pub async fn comic_entry(kind: ComicArchive, path: &Path, name: &str) -> RsResult<Vec<u8>> {
+    match kind {
+        ComicArchive::Rar => rar_entry(path, name).await,
+        ComicArchive::Zip => {
+            let (path, name) = (path.to_path_buf(), name.to_string());
+            tokio::task::spawn_blocking(move || {
+                let file = std::io::BufReader::new(std::fs::File::open(path)?);
+                let mut archive = zip::ZipArchive::new(file)
+                    .map_err(|_| RsError::Error("Unable to open zip file".to_string()))?;
+                let mut entry = archive
+                    .by_name(&name)
+                    .map_err(|_| RsError::Error(format!("Unable to find {} in zip", name)))?;
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data)?;
+                Ok(data)
+            })
+            .await?
+        }
+        ComicArchive::SevenZip => {
+            let (path, name) = (path.to_path_buf(), name.to_string());
+            tokio::task::spawn_blocking(move || {
+                let file = std::fs::File::open(path)?;
+                let len = file.metadata()?.len();
+                sevenz_entry(std::io::BufReader::new(file), len, &name)
+            })
+            .await?
+        }
+    }
+}
+
+/// Pages in reading order and the `ComicInfo.xml` entry, if any
+pub async fn comic_pages(
+    kind: ComicArchive,
+    path: &Path,
+) -> RsResult<(Vec<String>, Option<String>)> {
+    let entries = comic_entries(kind, path).await?;
+    let comic_info = entries.iter().find(|name| is_comic_info(name)).cloned();
+    Ok((sort_pages(entries), comic_info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_in_reading_order() {
+        let entries = vec![
+            "Comic/page10.jpg".to_string(),
+            "Comic/".to_string(),
+            "ComicInfo.xml".to_string(),
+            "Comic/page2.JPG".to_string(),
+            "__MACOSX/Comic/._page1.jpg".to_string(),
+            "Comic/page1.png".to_string(),
+        ];
+        assert_eq!(
+            sort_pages(entries),
+            vec!["Comic/page1.png", "Comic/page2.JPG", "Comic/page10.jpg"]
+        );
+        assert!(is_comic_info("Comic/comicinfo.xml"));
+        assert_eq!(
+            ComicArchive::detect(b"Rar!\x1a\x07\x01\x00"),
+            Some(ComicArchive::Rar)
+        );
+        assert_eq!(
+            ComicArchive::from_mime("application/x-cb7"),
+            Some(ComicArchive::SevenZip)
+        );
+    }
+}
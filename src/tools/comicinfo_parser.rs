@@ -0,0 +1,138 @@
+use super::{
+    opf_parser::{parse_isbn13, OpfMetadata},
+    xml_tools::first_text,
+};
+
+/// Reading direction of a comic
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReadingDirection {
+    LeftToRight,
+    RightToLeft,
+}
+
+impl ReadingDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReadingDirection::LeftToRight => "ltr",
+            ReadingDirection::RightToLeft => "rtl",
+        }
+    }
+}
+
+/// Metadata of a `ComicInfo.xml` (ComicRack schema)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ComicInfo {
+    pub title: Option<String>,
+    pub series: Option<String>,
+    pub number: Option<f64>,
+    pub volume: Option<f64>,
+    pub summary: Option<String>,
+    pub year: Option<u16>,
+    pub writers: Vec<String>,
+    pub pencillers: Vec<String>,
+    pub language: Option<String>,
+    pub isbn13: Option<String>,
+    pub direction: Option<ReadingDirection>,
+}
+
+impl ComicInfo {
+    /// Book metadata: writers and pencillers as authors, the volume as position in the serie
+    /// and the issue number as chapter
+    pub fn book_metadata(&self) -> OpfMetadata {
+        let title = self.title.clone().or_else(|| {
+            let series = self.series.clone()?;
+            Some(match self.number {
+                Some(number) => format!("{} #{}", series, number),
+                None => series,
+            })
+        });
+        let mut authors = self.writers.clone();
+        for penciller in &self.pencillers {
+            if !authors.contains(penciller) {
+                authors.push(penciller.clone());
+            }
+        }
+        OpfMetadata {
+            title,
+            authors,
+            isbn13: self.isbn13.clone(),
+            language: self.language.clone(),
+            description: self.summary.clone(),
+            year: self.year,
+            series: self.series.clone(),
+            series_index: self.volume,
+            chapter: self.number,
+            ..Default::default()
+        }
+    }
+}
+
+pub fn parse_comic_info(content: &str) -> ComicInfo {
+    let number = |name: &str| -> Option<f64> {
+        first_text(content, name).and_then(|value| value.trim().parse().ok())
+    };
+    // Credits are comma separated
+    let names = |name: &str| -> Vec<String> {
+        first_text(content, name)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let direction = match first_text(content, "Manga").as_deref() {
+        Some("YesAndRightToLeft") => Some(ReadingDirection::RightToLeft),
+        Some("Yes") | Some("No") => Some(ReadingDirection::LeftToRight),
+        _ => None,
+    };
+    ComicInfo {
+        title: first_text(content, "Title"),
+        series: first_text(content, "Series"),
+        number: number("Number"),
+        volume: number("Volume"),
+        summary: first_text(content, "Summary"),
+        year: number("Year")
+            .map(|year| year as u16)
+            .filter(|year| *year > 0),
+        writers: names("Writer"),
+        pencillers: names("Penciller"),
+        language: first_text(content, "LanguageISO"),
+        isbn13: first_text(content, "GTIN").and_then(|gtin| parse_isbn13(&gtin)),
+        direction,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_comic_info() {
+        let content = r#"<?xml version="1.0" encoding="utf-8"?>
+<ComicInfo xmlns:xsd="http://www.w3.org/2001/XMLSchema">
+  <Series>One Piece</Series>
+  <Number>1</Number>
+  <Volume>1</Volume>
+  <Summary>Luffy &amp; friends</Summary>
+  <Year>1997</Year>
+  <Writer>Eiichiro Oda</Writer>
+  <Penciller>Eiichiro Oda, Assistant</Penciller>
+  <LanguageISO>ja</LanguageISO>
+  <Manga>YesAndRightToLeft</Manga>
+</ComicInfo>"#;
+        let info = parse_comic_info(content);
+        assert_eq!(info.series.as_deref(), Some("One Piece"));
+        assert_eq!(info.summary.as_deref(), Some("Luffy & friends"));
+        assert_eq!(info.year, Some(1997));
+        assert_eq!(info.direction, Some(ReadingDirection::RightToLeft));
+
+        let metadata = info.book_metadata();
+        assert_eq!(metadata.title.as_deref(), Some("One Piece #1"));
+        assert_eq!(metadata.authors, vec!["Eiichiro Oda", "Assistant"]);
+        assert_eq!(metadata.series_index, Some(1.0));
+        assert_eq!(metadata.chapter, Some(1.0));
+    }
+}
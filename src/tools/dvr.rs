@@ -0,0 +1,343 @@
+use std::{collections::HashSet, path::Path, process::Stdio};
+
+use chrono::{Datelike, Duration, NaiveTime, TimeZone};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    process::Command,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    domain::{
+        dvr::{DvrRecording, DvrRecordingStatus, DvrSchedule, DvrScheduleKind},
+        epg::EpgProgramme,
+    },
+    error::{RsError, RsResult},
+};
+
+use super::video_tools::VideoCommandBuilder;
+
+/// Programme window produced by a schedule
+#[derive(Debug, Clone, PartialEq)]
+pub struct DvrOccurrence {
+    pub start: i64,
+    pub end: i64,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// Starts of a weekly window in [from, to[, `time` in minutes from local midnight
+pub fn weekly_starts<Tz: TimeZone>(
+    tz: &Tz,
+    days: &[u8],
+    time: u32,
+    from: i64,
+    to: i64,
+) -> Vec<i64> {
+    let (Some(from_date), Some(to_date)) = (
+        tz.timestamp_millis_opt(from).earliest(),
+        tz.timestamp_millis_opt(to).earliest(),
+    ) else {
+        return vec![];
+    };
+    let Some(offset) = NaiveTime::from_hms_opt(0, 0, 0) else {
+        return vec![];
+    };
+    let mut starts = vec![];
+    // Start the day before so windows crossing a timezone shift are not missed
+    let mut date = from_date.date_naive() - Duration::days(1);
+    while date <= to_date.date_naive() {
+        if days.contains(&(date.weekday().num_days_from_monday() as u8)) {
+            let local = date.and_time(offset) + Duration::minutes(time as i64);
+            if let Some(start) = tz.from_local_datetime(&local).earliest() {
+                let start = start.timestamp_millis();
+                if start >= from && start < to {
+                    starts.push(start);
+                }
+            }
+        }
+        date += Duration::days(1);
+    }
+    starts
+}
+
+/// Occurrences of a schedule starting in [from, to[ (or still running for `Once`)
+pub fn schedule_occurrences<Tz: TimeZone>(
+    schedule: &DvrSchedule,
+    tz: &Tz,
+    programmes: &[EpgProgramme],
+    default_name: &str,
+    from: i64,
+    to: i64,
+) -> Vec<DvrOccurrence> {
+    let name = schedule
+        .name
+        .clone()
+        .unwrap_or_else(|| default_name.to_string());
+    match schedule.kind {
+        DvrScheduleKind::Once => match (schedule.start, schedule.end) {
+            (Some(start), Some(end)) if end > start && end > from && start < to => {
+                vec![DvrOccurrence {
+                    start,
+                    end,
+                    name,
+                    description: schedule.description.clone(),
+                }]
+            }
+            _ => vec![],
+        },
+        DvrScheduleKind::Weekly => {
+            let (Some(days), Some(time), Some(duration)) =
+                (&schedule.days, schedule.time, schedule.duration)
+            else {
+                return vec![];
+            };
+            weekly_starts(tz, days, time, from, to)
+                .into_iter()
+                .map(|start| DvrOccurrence {
+                    start,
+                    end: start + duration as i64 * 60 * 1000,
+                    name: name.clone(),
+                    description: schedule.description.clone(),
+                })
+                .collect()
+        }
+        DvrScheduleKind::Programme => {
+            let Some(title) = schedule.title.as_ref().map(|t| t.trim().to_lowercase()) else {
+                return vec![];
+            };
+            programmes
+                .iter()
+                .filter(|p| p.title.trim().to_lowercase() == title)
+                .filter(|p| p.start >= from && p.start < to)
+                .map(|p| {
+                    let name = schedule.name.clone().unwrap_or_else(|| p.title.clone());
+                    DvrOccurrence {
+                        start: p.start,
+                        end: p.stop,
+                        name: match &p.episode_num {
+                            Some(episode) => format!("{} {}", name, episode),
+                            None => name,
+                        },
+                        description: p
+                            .description
+                            .clone()
+                            .or_else(|| schedule.description.clone()),
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+/// Pending recordings that can't get a stream slot. Running recordings are always kept,
+/// then recordings are accepted in start order while slots are available
+pub fn find_conflicts(recordings: &[DvrRecording], max_streams: usize) -> HashSet<String> {
+    let mut candidates: Vec<&DvrRecording> = recordings
+        .iter()
+        .filter(|r| r.status == DvrRecordingStatus::Recording || r.status.is_pending())
+        .collect();
+    candidates.sort_by_key(|r| {
+        (
+            r.status != DvrRecordingStatus::Recording,
+            r.padded_start(),
+            r.added,
+        )
+    });
+
+    let mut accepted: Vec<&DvrRecording> = vec![];
+    let mut conflicts = HashSet::new();
+    for recording in candidates {
+        let (start, end) = (recording.padded_start(), recording.padded_end());
+        // Concurrency inside a window is maximal at one of the starts it contains
+        let points = std::iter::once(start).chain(
+            accepted
+                .iter()
+                .map(|a| a.padded_start())
+                .filter(|s| *s >= start && *s < end),
+        );
+        let busy = points
+            .map(|point| {
+                accepted
+                    .iter()
+                    .filter(|a| a.padded_start() <= point && a.padded_end() > point)
+                    .count()
+            })
+            .max()
+            .unwrap_or(0);
+        if busy >= max_streams && recording.status != DvrRecordingStatus::Recording {
+            conflicts.insert(recording.id.clone());
+        } else {
+            accepted.push(recording);
+        }
+    }
+    conflicts
+}
+
+/// Time given to ffmpeg to finalize the file of a stopped recording before it is killed
+const RECORD_STOP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Remux a live stream into a matroska file until `duration` seconds are captured.
+/// A cancelled recording is stopped cleanly so the file captured so far stays readable
+pub async fn record_stream(
+    url: &str,
+    output: &Path,
+    duration: u64,
+    token: CancellationToken,
+) -> RsResult<()> {
+    let mut cmd = Command::new(VideoCommandBuilder::get_ffmpeg_path());
+    cmd.arg("-hide_banner").arg("-loglevel").arg("error");
+    if url.starts_with("http") {
+        cmd.arg("-reconnect")
+            .arg("1")
+            .arg("-reconnect_streamed")
+            .arg("1")
+            .arg("-reconnect_delay_max")
+            .arg("10");
+    }
+    cmd.arg("-i")
+        .arg(url)
+        .arg("-map")
+        .arg("0:v?")
+        .arg("-map")
+        .arg("0:a?")
+        .arg("-c")
+        .arg("copy")
+        .arg("-t")
+        .arg(duration.to_string())
+        .arg("-f")
+        .arg("matroska")
+        .arg("-y")
+        .arg(output)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = cmd.spawn()?;
+    let mut stderr = child.stderr.take();
+    let stderr_reader = tokio::spawn(async move {
+        let mut error = String::new();
+        if let Some(stderr) = stderr.as_mut() {
+            let _ = stderr.read_to_string(&mut error).await;
+        }
+        error
+    });
+
+    tokio::select! {
+        status = child.wait() => {
+            let status = status?;
+            if status.success() {
+                Ok(())
+            } else {
+                let error = stderr_reader.await.unwrap_or_default();
+                Err(RsError::Error(format!("Recording failed ({}): {}", status, error.trim())))
+            }
+        }
+        _ = token.cancelled() => {
+            // `q` makes ffmpeg write the matroska index before exiting
+            if let Some(mut stdin) = child.stdin.take() {
+                let _ = stdin.write_all(b"q").await;
+            }
+            if tokio::time::timeout(RECORD_STOP_TIMEOUT, child.wait()).await.is_err() {
+                let _ = child.kill().await;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, TimeZone, Utc};
+
+    use super::*;
+
+    fn ms(tz: &FixedOffset, y: i32, m: u32, d: u32, h: u32, min: u32) -> i64 {
+        tz.with_ymd_and_hms(y, m, d, h, min, 0)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    #[test]
+    fn weekly_windows_use_local_time() {
+        let tz = FixedOffset::east_opt(2 * 3600).unwrap();
+        // Monday 2024-01-01 to Monday 2024-01-08 (exclusive)
+        let from = ms(&tz, 2024, 1, 1, 0, 0);
+        let to = ms(&tz, 2024, 1, 8, 0, 0);
+        let starts = weekly_starts(&tz, &[0, 2], 20 * 60 + 30, from, to);
+        assert_eq!(
+            starts,
+            vec![ms(&tz, 2024, 1, 1, 20, 30), ms(&tz, 2024, 1, 3, 20, 30)]
+        );
+    }
+
+    #[test]
+    fn programme_schedule_matches_titles() {
+        let schedule = DvrSchedule {
+            kind: DvrScheduleKind::Programme,
+            title: Some("The News".to_string()),
+            ..Default::default()
+        };
+        let programmes = vec![
+            EpgProgramme {
+                channel: "tf1".to_string(),
+                start: 100,
+                stop: 200,
+                title: "the news".to_string(),
+                episode_num: Some("S01E02".to_string()),
+                ..Default::default()
+            },
+            EpgProgramme {
+                channel: "tf1".to_string(),
+                start: 200,
+                stop: 300,
+                title: "Movie".to_string(),
+                ..Default::default()
+            },
+            EpgProgramme {
+                channel: "tf1".to_string(),
+                start: 5000,
+                stop: 6000,
+                title: "The News".to_string(),
+                ..Default::default()
+            },
+        ];
+        let found = schedule_occurrences(&schedule, &Utc, &programmes, "TF1", 0, 1000);
+        assert_eq!(
+            found,
+            vec![DvrOccurrence {
+                start: 100,
+                end: 200,
+                name: "the news S01E02".to_string(),
+                description: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn conflicts_when_slots_are_exhausted() {
+        let recording = |id: &str, start: i64, end: i64, status: DvrRecordingStatus| DvrRecording {
+            id: id.to_string(),
+            start,
+            end,
+            status,
+            ..Default::default()
+        };
+        let recordings = vec![
+            recording("running", 0, 100, DvrRecordingStatus::Recording),
+            recording("a", 50, 150, DvrRecordingStatus::Scheduled),
+            recording("b", 60, 80, DvrRecordingStatus::Conflict),
+            recording("c", 100, 200, DvrRecordingStatus::Scheduled),
+            recording("done", 0, 500, DvrRecordingStatus::Completed),
+        ];
+        assert_eq!(
+            find_conflicts(&recordings, 2),
+            HashSet::from(["b".to_string()])
+        );
+        assert_eq!(
+            find_conflicts(&recordings, 1),
+            HashSet::from(["a".to_string(), "b".to_string()])
+        );
+    }
+}
@@ -0,0 +1,714 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap, HashSet},
+    io::{self, Read, Write},
+};
+
+/// Below this number of faces searches compare every embedding
+pub const FACE_INDEX_BRUTE_FORCE_LIMIT: usize = 2000;
+
+/// Links per node on upper layers, doubled on the base layer
+const MAX_LINKS: usize = 16;
+const EF_CONSTRUCTION: usize = 100;
+const MAX_LEVEL: usize = 12;
+
+const FILE_MAGIC: &[u8; 4] = b"RSFI";
+const FILE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaceIndexMatch {
+    pub id: String,
+    pub label: String,
+    pub similarity: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    similarity: f32,
+    node: u32,
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.similarity
+            .total_cmp(&other.similarity)
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FaceIndexNode {
+    id: String,
+    label: String,
+    vector: Vec<f32>,
+    links: Vec<Vec<u32>>,
+    deleted: bool,
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// HNSW graph over L2-normalized face embeddings, similarity being the dot product.
+/// Removed faces stay in the graph for navigation until the index is compacted
+#[derive(Debug, Clone)]
+pub struct FaceIndex {
+    dim: usize,
+    nodes: Vec<FaceIndexNode>,
+    positions: HashMap<String, u32>,
+    entry: Option<u32>,
+    max_level: usize,
+    seed: u64,
+}
+
+impl Default for FaceIndex {
+    fn default() -> Self {
+        Self {
+            dim: 0,
+            nodes: vec![],
+            positions: HashMap::new(),
+            entry: None,
+            max_level: 0,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+}
+
+impl FaceIndex {
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.positions.contains_key(id)
+    }
+
+    pub fn label(&self, id: &str) -> Option<&str> {
+        self.positions
+            .get(id)
+            .map(|node| self.nodes[*node as usize].label.as_str())
+    }
+
+    pub fn vector(&self, id: &str) -> Option<&[f32]> {
+        self.positions
+            .get(id)
+            .map(|node| self.nodes[*node as usize].vector.as_slice())
+    }
+
+    /// Ids and labels of the faces in the index
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.positions.values().map(|node| {
+            let node = &self.nodes[*node as usize];
+            (node.id.as_str(), node.label.as_str())
+        })
+    }
+
+    fn random_level(&mut self) -> usize {
+        // xorshift64*, the graph only needs a cheap geometric distribution
+        self.seed ^= self.seed >> 12;
+        self.seed ^= self.seed << 25;
+        self.seed ^= self.seed >> 27;
+        let value = self.seed.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        let uniform = ((value >> 11) as f64 + 0.5) / (1u64 << 53) as f64;
+        let level = -uniform.ln() / (MAX_LINKS as f64).ln();
+        (level as usize).min(MAX_LEVEL)
+    }
+
+    fn similarity(&self, query: &[f32], node: u32) -> f32 {
+        dot(query, &self.nodes[node as usize].vector)
+    }
+
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entries: &[u32],
+        ef: usize,
+        level: usize,
+    ) -> Vec<Candidate> {
+        let mut visited: HashSet<u32> = HashSet::new();
+        let mut candidates: BinaryHeap<Candidate> = BinaryHeap::new();
+        let mut results: BinaryHeap<Reverse<Candidate>> = BinaryHeap::new();
+        for &node in entries {
+            if visited.insert(node) {
+                let candidate = Candidate {
+                    similarity: self.similarity(query, node),
+                    node,
+                };
+                candidates.push(candidate);
+                results.push(Reverse(candidate));
+            }
+        }
+        while results.len() > ef {
+            results.pop();
+        }
+
+        while let Some(candidate) = candidates.pop() {
+            let worst = results.peek().map(|r| r.0.similarity).unwrap_or(f32::MIN);
+            if results.len() >= ef && candidate.similarity < worst {
+                break;
+            }
+            let links = match self.nodes[candidate.node as usize].links.get(level) {
+                Some(links) => links,
+                None => continue,
+            };
+            for &neighbor in links {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let similarity = self.similarity(query, neighbor);
+                let worst = results.peek().map(|r| r.0.similarity).unwrap_or(f32::MIN);
+                if results.len() < ef || similarity > worst {
+                    let next = Candidate {
+                        similarity,
+                        node: neighbor,
+                    };
+                    candidates.push(next);
+                    results.push(Reverse(next));
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        let mut found: Vec<Candidate> = results.into_iter().map(|r| r.0).collect();
+        found.sort_by(|a, b| b.cmp(a));
+        found
+    }
+
+    /// Add or replace a face. Embeddings with another dimension than the first one are ignored
+    pub fn insert(&mut self, id: &str, label: &str, vector: Vec<f32>) -> bool {
+        if vector.is_empty() || (self.dim != 0 && vector.len() != self.dim) {
+            return false;
+        }
+        self.dim = vector.len();
+        self.remove(id);
+
+        let level = self.random_level();
+        let node = self.nodes.len() as u32;
+        self.nodes.push(FaceIndexNode {
+            id: id.to_string(),
+            label: label.to_string(),
+            vector,
+            links: vec![vec![]; level + 1],
+            deleted: false,
+        });
+        self.positions.insert(id.to_string(), node);
+
+        let Some(entry) = self.entry else {
+            self.entry = Some(node);
+            self.max_level = level;
+            return true;
+        };
+
+        let query = self.nodes[node as usize].vector.clone();
+        let mut entries = vec![entry];
+        for layer in (level + 1..=self.max_level).rev() {
+            if let Some(closest) = self.search_layer(&query, &entries, 1, layer).first() {
+                entries = vec![closest.node];
+            }
+        }
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let found = self.search_layer(&query, &entries, EF_CONSTRUCTION, layer);
+            let neighbors: Vec<u32> = found
+                .iter()
+                .filter(|c| c.node != node)
+                .take(MAX_LINKS)
+                .map(|c| c.node)
+                .collect();
+            let max_links = if layer == 0 { MAX_LINKS * 2 } else { MAX_LINKS };
+            for &neighbor in &neighbors {
+                self.nodes[neighbor as usize].links[layer].push(node);
+                if self.nodes[neighbor as usize].links[layer].len() > max_links {
+                    self.prune(neighbor, layer, max_links);
+                }
+            }
+            self.nodes[node as usize].links[layer] = neighbors;
+            entries = found.iter().map(|c| c.node).collect();
+        }
+        if level > self.max_level {
+            self.entry = Some(node);
+            self.max_level = level;
+        }
+        true
+    }
+
+    fn prune(&mut self, node: u32, layer: usize, max_links: usize) {
+        let vector = &self.nodes[node as usize].vector;
+        let mut links: Vec<Candidate> = self.nodes[node as usize].links[layer]
+            .iter()
+            .map(|&link| Candidate {
+                similarity: dot(vector, &self.nodes[link as usize].vector),
+                node: link,
+            })
+            .collect();
+        links.sort_by(|a, b| b.cmp(a));
+        links.truncate(max_links);
+        self.nodes[node as usize].links[layer] = links.into_iter().map(|c| c.node).collect();
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool {
+        match self.positions.remove(id) {
+            Some(node) => {
+                self.nodes[node as usize].deleted = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove a face and return its label and embedding
+    pub fn take(&mut self, id: &str) -> Option<(String, Vec<f32>)> {
+        let node = *self.positions.get(id)?;
+        self.remove(id);
+        let node = &self.nodes[node as usize];
+        Some((node.label.clone(), node.vector.clone()))
+    }
+
+    pub fn set_label(&mut self, id: &str, label: &str) {
+        if let Some(node) = self.positions.get(id) {
+            self.nodes[*node as usize].label = label.to_string();
+        }
+    }
+
+    /// Removed faces outnumber the live ones
+    pub fn needs_compaction(&self) -> bool {
+        let deleted = self.nodes.len() - self.positions.len();
+        deleted > FACE_INDEX_BRUTE_FORCE_LIMIT && deleted > self.positions.len()
+    }
+
+    /// Rebuild the graph without the removed faces
+    pub fn compact(&mut self) {
+        let nodes = std::mem::take(&mut self.nodes);
+        let seed = self.seed;
+        *self = Self {
+            seed,
+            ..Default::default()
+        };
+        for node in nodes.into_iter().filter(|n| !n.deleted) {
+            self.insert(&node.id, &node.label, node.vector);
+        }
+    }
+
+    /// Most similar faces, best first. Small indexes are searched exhaustively
+    pub fn search(&self, query: &[f32], limit: usize) -> Vec<FaceIndexMatch> {
+        if limit == 0 || query.len() != self.dim {
+            return vec![];
+        }
+        let found: Vec<Candidate> = if self.positions.len() <= FACE_INDEX_BRUTE_FORCE_LIMIT {
+            let mut all: Vec<Candidate> = self
+                .positions
+                .values()
+                .map(|&node| Candidate {
+                    similarity: self.similarity(query, node),
+                    node,
+                })
+                .collect();
+            all.sort_by(|a, b| b.cmp(a));
+            all.truncate(limit);
+            all
+        } else {
+            let Some(entry) = self.entry else {
+                return vec![];
+            };
+            let mut entries = vec![entry];
+            for layer in (1..=self.max_level).rev() {
+                if let Some(closest) = self.search_layer(query, &entries, 1, layer).first() {
+                    entries = vec![closest.node];
+                }
+            }
+            let ef = (limit * 2).max(64);
+            self.search_layer(query, &entries, ef, 0)
+                .into_iter()
+                .filter(|c| !self.nodes[c.node as usize].deleted)
+                .take(limit)
+                .collect()
+        };
+        found
+            .into_iter()
+            .map(|c| {
+                let node = &self.nodes[c.node as usize];
+                FaceIndexMatch {
+                    id: node.id.clone(),
+                    label: node.label.clone(),
+                    similarity: c.similarity,
+                }
+            })
+            .collect()
+    }
+
+    /// Weighted similarity graph between the given faces, linking each face to its nearest
+    /// neighbours over the threshold
+    pub fn neighbor_graph(
+        &self,
+        ids: &[String],
+        threshold: f32,
+        neighbors: usize,
+    ) -> Vec<Vec<(usize, f32)>> {
+        let positions: HashMap<&str, usize> = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.as_str(), i))
+            .collect();
+        let mut edges: HashMap<(usize, usize), f32> = HashMap::new();
+        for (i, id) in ids.iter().enumerate() {
+            let Some(vector) = self.vector(id) else {
+                continue;
+            };
+            for found in self.search(vector, neighbors + 1) {
+                if found.similarity < threshold {
+                    break;
+                }
+                if let Some(&j) = positions.get(found.id.as_str()) {
+                    if i != j {
+                        edges.insert((i.min(j), i.max(j)), found.similarity);
+                    }
+                }
+            }
+        }
+        let mut graph = vec![Vec::new(); ids.len()];
+        for ((i, j), similarity) in edges {
+            graph[i].push((j, similarity));
+            graph[j].push((i, similarity));
+        }
+        graph
+    }
+
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        fn write_u32(writer: &mut impl Write, value: u32) -> io::Result<()> {
+            writer.write_all(&value.to_le_bytes())
+        }
+        fn write_str(writer: &mut impl Write, value: &str) -> io::Result<()> {
+            write_u32(writer, value.len() as u32)?;
+            writer.write_all(value.as_bytes())
+        }
+
+        writer.write_all(FILE_MAGIC)?;
+        write_u32(writer, FILE_VERSION)?;
+        write_u32(writer, self.dim as u32)?;
+        write_u32(writer, self.nodes.len() as u32)?;
+        write_u32(writer, self.entry.unwrap_or(u32::MAX))?;
+        write_u32(writer, self.max_level as u32)?;
+        writer.write_all(&self.seed.to_le_bytes())?;
+        for node in &self.nodes {
+            write_str(writer, &node.id)?;
+            write_str(writer, &node.label)?;
+            writer.write_all(&[node.deleted as u8])?;
+            writer.write_all(bytemuck::cast_slice::<f32, u8>(&node.vector))?;
+            write_u32(writer, node.links.len() as u32)?;
+            for links in &node.links {
+                write_u32(writer, links.len() as u32)?;
+                writer.write_all(bytemuck::cast_slice::<u32, u8>(links))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        fn invalid(message: &str) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+        }
+        fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+            let mut buffer = [0u8; 4];
+            reader.read_exact(&mut buffer)?;
+            Ok(u32::from_le_bytes(buffer))
+        }
+        fn read_bytes(reader: &mut impl Read, len: usize) -> io::Result<Vec<u8>> {
+            let mut buffer = vec![0u8; len];
+            reader.read_exact(&mut buffer)?;
+            Ok(buffer)
+        }
+        fn read_str(reader: &mut impl Read) -> io::Result<String> {
+            let len = read_u32(reader)? as usize;
+            String::from_utf8(read_bytes(reader, len)?).map_err(|_| invalid("Invalid face id"))
+        }
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != FILE_MAGIC || read_u32(reader)? != FILE_VERSION {
+            return Err(invalid("Unsupported face index file"));
+        }
+        let dim = read_u32(reader)? as usize;
+        let count = read_u32(reader)? as usize;
+        let entry = read_u32(reader)?;
+        let max_level = read_u32(reader)? as usize;
+        let mut seed = [0u8; 8];
+        reader.read_exact(&mut seed)?;
+
+        let mut index = Self {
+            dim,
+            nodes: Vec::with_capacity(count),
+            positions: HashMap::with_capacity(count),
+            entry: (entry != u32::MAX).then_some(entry),
+            max_level,
+            seed: u64::from_le_bytes(seed),
+        };
+        for position in 0..count {
+            let id = read_str(reader)?;
+            let label = read_str(reader)?;
+            let deleted = read_bytes(reader, 1)?[0] == 1;
+            let vector = bytemuck::pod_collect_to_vec::<u8, f32>(&read_bytes(reader, dim * 4)?);
+            let levels = read_u32(reader)? as usize;
+            let mut links = Vec::with_capacity(levels);
+            for _ in 0..levels {
+                let len = read_u32(reader)? as usize;
+                let layer = bytemuck::pod_collect_to_vec::<u8, u32>(&read_bytes(reader, len * 4)?);
+                if layer.iter().any(|l| *l as usize >= count) {
+                    return Err(invalid("Invalid face index link"));
+                }
+                links.push(layer);
+            }
+            if !deleted {
+                index.positions.insert(id.clone(), position as u32);
+            }
+            index.nodes.push(FaceIndexNode {
+                id,
+                label,
+                vector,
+                links,
+                deleted,
+            });
+        }
+        if index.entry.is_some_and(|e| e as usize >= count) {
+            return Err(invalid("Invalid face index entry"));
+        }
+        Ok(index)
+    }
+}
+
+/// Face indexes of a library: faces assigned to people labelled with the person id and
+/// unassigned faces
+#[derive(Debug, Clone, Default)]
+pub struct LibraryFaceIndex {
+    pub people: FaceIndex,
+    pub unassigned: FaceIndex,
+    /// Changed since it was loaded or saved
+    pub dirty: bool,
+}
+
+impl LibraryFaceIndex {
+    pub fn add_person_face(&mut self, face_id: &str, person_id: &str, embedding: Vec<f32>) {
+        self.unassigned.remove(face_id);
+        self.dirty |= self.people.insert(face_id, person_id, embedding);
+    }
+
+    pub fn add_unassigned_face(&mut self, face_id: &str, embedding: Vec<f32>) {
+        self.people.remove(face_id);
+        self.dirty |= self.unassigned.insert(face_id, "", embedding);
+    }
+
+    pub fn assign(&mut self, face_ids: &[String], person_id: &str) {
+        for face_id in face_ids {
+            if let Some((_, embedding)) = self.unassigned.take(face_id) {
+                self.people.insert(face_id, person_id, embedding);
+                self.dirty = true;
+            }
+        }
+    }
+
+    pub fn unassign(&mut self, face_ids: &[String]) {
+        for face_id in face_ids {
+            if let Some((_, embedding)) = self.people.take(face_id) {
+                self.unassigned.insert(face_id, "", embedding);
+                self.dirty = true;
+            }
+        }
+    }
+
+    pub fn remove(&mut self, face_ids: &[String]) {
+        for face_id in face_ids {
+            self.dirty |= self.people.remove(face_id) | self.unassigned.remove(face_id);
+        }
+    }
+
+    /// Faces of a person moved to another one
+    pub fn relabel_person(&mut self, from: &str, to: &str) {
+        let face_ids: Vec<String> = self
+            .people
+            .entries()
+            .filter(|(_, label)| *label == from)
+            .map(|(id, _)| id.to_string())
+            .collect();
+        for face_id in &face_ids {
+            self.people.set_label(face_id, to);
+        }
+        self.dirty |= !face_ids.is_empty();
+    }
+
+    pub fn remove_person(&mut self, person_id: &str) {
+        let face_ids: Vec<String> = self
+            .people
+            .entries()
+            .filter(|(_, label)| *label == person_id)
+            .map(|(id, _)| id.to_string())
+            .collect();
+        self.remove(&face_ids);
+    }
+
+    /// Align the index with the faces of the database, given as assigned faces with their
+    /// person and unassigned face ids. Returns the faces missing from the index, which must
+    /// be added with their embeddings
+    pub fn sync(&mut self, people: &[(String, String)], unassigned: &[String]) -> Vec<String> {
+        let people_ids: HashSet<&str> = people.iter().map(|(id, _)| id.as_str()).collect();
+        let unassigned_ids: HashSet<&str> = unassigned.iter().map(|id| id.as_str()).collect();
+
+        let removed: Vec<String> = self
+            .people
+            .entries()
+            .filter(|(id, _)| !people_ids.contains(id))
+            .chain(
+                self.unassigned
+                    .entries()
+                    .filter(|(id, _)| !unassigned_ids.contains(id)),
+            )
+            .map(|(id, _)| id.to_string())
+            .collect();
+        self.remove(&removed);
+
+        let mut missing = vec![];
+        for (face_id, person_id) in people {
+            match self.people.label(face_id) {
+                Some(label) if label == person_id => {}
+                Some(_) => {
+                    self.people.set_label(face_id, person_id);
+                    self.dirty = true;
+                }
+                None => missing.push(face_id.clone()),
+            }
+        }
+        missing.extend(
+            unassigned
+                .iter()
+                .filter(|id| !self.unassigned.contains(id))
+                .cloned(),
+        );
+
+        if self.people.needs_compaction() {
+            self.people.compact();
+        }
+        if self.unassigned.needs_compaction() {
+            self.unassigned.compact();
+        }
+        missing
+    }
+
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        self.people.write_to(writer)?;
+        self.unassigned.write_to(writer)
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        Ok(Self {
+            people: FaceIndex::read_from(reader)?,
+            unassigned: FaceIndex::read_from(reader)?,
+            dirty: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_embeddings(count: usize, dim: usize, mut seed: u64) -> Vec<Vec<f32>> {
+        (0..count)
+            .map(|_| {
+                let vector: Vec<f32> = (0..dim)
+                    .map(|_| {
+                        seed = seed
+                            .wrapping_mul(6364136223846793005)
+                            .wrapping_add(1442695040888963407);
+                        ((seed >> 33) as f32 / (1u64 << 31) as f32) - 0.5
+                    })
+                    .collect();
+                let norm = dot(&vector, &vector).sqrt();
+                vector.into_iter().map(|v| v / norm).collect()
+            })
+            .collect()
+    }
+
+    fn exact(embeddings: &[Vec<f32>], query: &[f32], limit: usize) -> Vec<String> {
+        let mut all: Vec<(usize, f32)> = embeddings
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (i, dot(e, query)))
+            .collect();
+        all.sort_by(|a, b| b.1.total_cmp(&a.1));
+        all.into_iter()
+            .take(limit)
+            .map(|(i, _)| i.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn graph_search_recall() {
+        let embeddings = random_embeddings(FACE_INDEX_BRUTE_FORCE_LIMIT + 1000, 32, 7);
+        let mut index = FaceIndex::default();
+        for (i, embedding) in embeddings.iter().enumerate() {
+            assert!(index.insert(&i.to_string(), "", embedding.clone()));
+        }
+        let mut hits = 0;
+        let queries = random_embeddings(50, 32, 99);
+        for query in &queries {
+            let expected = exact(&embeddings, query, 10);
+            let found = index.search(query, 10);
+            hits += found.iter().filter(|m| expected.contains(&m.id)).count();
+        }
+        assert!(hits as f32 / (queries.len() * 10) as f32 > 0.9);
+
+        let ids: Vec<String> = (0..embeddings.len()).map(|i| i.to_string()).collect();
+        let graph = index.neighbor_graph(&ids, 0.0, 5);
+        assert!(graph.iter().all(|links| !links.is_empty()));
+        assert!(graph[7]
+            .iter()
+            .all(|(j, _)| graph[*j].iter().any(|(k, _)| *k == 7)));
+
+        let first = index.search(&embeddings[42], 1);
+        assert_eq!(first[0].id, "42");
+        index.remove("42");
+        assert_ne!(index.search(&embeddings[42], 1)[0].id, "42");
+    }
+
+    #[test]
+    fn assign_sync_and_persist() {
+        let embeddings = random_embeddings(4, 8, 3);
+        let mut index = LibraryFaceIndex::default();
+        index.add_unassigned_face("f1", embeddings[0].clone());
+        index.add_unassigned_face("f2", embeddings[1].clone());
+        index.add_person_face("f3", "p1", embeddings[2].clone());
+        index.assign(&["f1".to_string()], "p2");
+        assert_eq!(index.people.search(&embeddings[0], 1)[0].label, "p2");
+        assert_eq!(index.unassigned.len(), 1);
+
+        index.relabel_person("p2", "p1");
+        assert_eq!(index.people.label("f1"), Some("p1"));
+
+        let missing = index.sync(
+            &[("f1".to_string(), "p3".to_string())],
+            &["f2".to_string(), "f4".to_string()],
+        );
+        assert_eq!(missing, vec!["f4".to_string()]);
+        assert!(!index.people.contains("f3"));
+        assert_eq!(index.people.label("f1"), Some("p3"));
+
+        let mut buffer = vec![];
+        index.write_to(&mut buffer).unwrap();
+        let loaded = LibraryFaceIndex::read_from(&mut buffer.as_slice()).unwrap();
+        assert_eq!(loaded.people.len(), 1);
+        assert_eq!(loaded.unassigned.len(), 1);
+        assert_eq!(loaded.people.search(&embeddings[0], 1)[0].id, "f1");
+        assert!(LibraryFaceIndex::read_from(&mut &buffer[..10]).is_err());
+    }
+}
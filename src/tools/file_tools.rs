@@ -27,6 +27,25 @@ pub fn remove_extension(filename: &str) -> &str {
     }
 }
 
+/// Whether a media name is still the filename it was imported with. Sources get a `-1`
+/// like suffix when the filename is already taken
+pub fn is_imported_name(name: &str, source: &str) -> bool {
+    let Some(filename) = filename_from_path(source) else {
+        return false;
+    };
+    if filename == name {
+        return true;
+    }
+    let stem = remove_extension(name);
+    let extension = &name[stem.len()..];
+    filename
+        .strip_suffix(extension)
+        .and_then(|f| f.strip_prefix(stem))
+        .and_then(|f| f.strip_prefix('-'))
+        .map(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+        .unwrap_or(false)
+}
+
 pub fn get_mime_from_filename(path: &str) -> Option<String> {
     let mime = mime_guess::from_path(&path);
     if let Some(mime) = mime.first() {
@@ -61,9 +80,16 @@ pub fn file_type_from_mime(mime: &str) -> FileType {
         FileType::Video
     } else if mime == "application/zip" {
         FileType::Album
-    } else if mime == "application/vnd.comicbook+cbz" || mime == "application/x-cbr" {
+    } else if matches!(
+        mime,
+        "application/vnd.comicbook+cbz"
+            | "application/vnd.comicbook+zip"
+            | "application/x-cbr"
+            | "application/vnd.comicbook-rar"
+            | "application/x-cb7"
+    ) {
         FileType::Album
-    } else if mime == "application/epub+zip" {
+    } else if mime == "application/epub+zip" || mime == "application/pdf" {
         FileType::Book
     } else {
         FileType::Other
@@ -159,3 +185,17 @@ pub async fn extract_zip(
 
     Ok(extract_result?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imported_names() {
+        assert!(is_imported_name("book.epub", "books/book.epub"));
+        assert!(is_imported_name("book.epub", "books/book-2.epub"));
+        assert!(is_imported_name("book", "book-1"));
+        assert!(!is_imported_name("My title.epub", "books/book.epub"));
+        assert!(!is_imported_name("book.epub", "books/book-copy.epub"));
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::HashSet;
+
+use crate::domain::channel::Channel;
+
+/// SiliconDust discovery protocol (libhdhomerun `hdhomerun_pkt.h`)
+pub const HDHR_DISCOVER_PORT: u16 = 65001;
+const TYPE_DISCOVER_REQ: u16 = 0x0002;
+const TYPE_DISCOVER_RPY: u16 = 0x0003;
+const TAG_DEVICE_TYPE: u8 = 0x01;
+const TAG_DEVICE_ID: u8 = 0x02;
+const TAG_TUNER_COUNT: u8 = 0x10;
+const TAG_LINEUP_URL: u8 = 0x27;
+const TAG_BASE_URL: u8 = 0x2A;
+const TAG_DEVICE_AUTH_STR: u8 = 0x2B;
+pub const DEVICE_TYPE_TUNER: u32 = 0x0000_0001;
+const WILDCARD: u32 = 0xFFFF_FFFF;
+
+const DEVICE_ID_CHECKSUM: [u32; 16] = [
+    0xA, 0x5, 0xF, 0x6, 0x7, 0xC, 0x1, 0xB, 0x9, 0x2, 0x8, 0xD, 0x4, 0x3, 0xE, 0x0,
+];
+
+fn device_id_checksum(device_id: u32) -> u32 {
+    let mut checksum = 0;
+    for index in 0..8 {
+        let nibble = (device_id >> (28 - index * 4)) & 0x0F;
+        checksum ^= if index % 2 == 0 {
+            DEVICE_ID_CHECKSUM[nibble as usize]
+        } else {
+            nibble
+        };
+    }
+    checksum
+}
+
+pub fn is_valid_device_id(device_id: u32) -> bool {
+    device_id_checksum(device_id) == 0
+}
+
+/// Stable device id derived from the library id, with the check digit clients verify
+pub fn device_id(library_id: &str) -> u32 {
+    // FNV-1a
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in library_id.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    let base = hash & 0xFFFF_FFF0;
+    base | device_id_checksum(base)
+}
+
+/// Guide number of each channel: its `channel_number` when set and unique,
+/// otherwise the next free number after the highest one, in list order
+pub fn guide_numbers(channels: &[Channel]) -> Vec<(u32, &Channel)> {
+    let mut used = HashSet::new();
+    let mut numbered: Vec<(Option<u32>, &Channel)> = channels
+        .iter()
+        .map(|channel| {
+            let number = channel
+                .channel_number
+                .and_then(|n| u32::try_from(n).ok())
+                .filter(|n| *n > 0 && used.insert(*n));
+            (number, channel)
+        })
+        .collect();
+    let mut next = used.iter().max().copied().unwrap_or(0);
+    for (number, _) in numbered.iter_mut().filter(|(n, _)| n.is_none()) {
+        next += 1;
+        *number = Some(next);
+    }
+    let mut numbered: Vec<(u32, &Channel)> = numbered
+        .into_iter()
+        .map(|(number, channel)| (number.unwrap_or_default(), channel))
+        .collect();
+    numbered.sort_by_key(|(number, _)| *number);
+    numbered
+}
+
+/// Device answered in discovery replies
+#[derive(Debug, Clone, PartialEq)]
+pub struct HdhrDiscoverDevice {
+    pub device_id: u32,
+    pub tuner_count: u8,
+    pub base_url: String,
+    pub lineup_url: String,
+    pub device_auth: String,
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn write_tag(payload: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    payload.push(tag);
+    let len = value.len();
+    if len <= 127 {
+        payload.push(len as u8);
+    } else {
+        payload.push((len & 0x7F) as u8 | 0x80);
+        payload.push((len >> 7) as u8);
+    }
+    payload.extend_from_slice(value);
+}
+
+fn frame(kind: u16, payload: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(payload.len() + 8);
+    packet.extend_from_slice(&kind.to_be_bytes());
+    packet.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    packet.extend_from_slice(payload);
+    let crc = crc32(&packet);
+    packet.extend_from_slice(&crc.to_le_bytes());
+    packet
+}
+
+/// Requested device type and id of a discover request, `None` if the packet is not one
+pub fn parse_discover_request(packet: &[u8]) -> Option<(u32, u32)> {
+    if packet.len() < 8 {
+        return None;
+    }
+    let (body, crc) = packet.split_at(packet.len() - 4);
+    if crc32(body).to_le_bytes() != crc {
+        return None;
+    }
+    if u16::from_be_bytes([body[0], body[1]]) != TYPE_DISCOVER_REQ {
+        return None;
+    }
+    let length = u16::from_be_bytes([body[2], body[3]]) as usize;
+    let payload = body.get(4..4 + length)?;
+
+    let (mut device_type, mut device_id) = (WILDCARD, WILDCARD);
+    let mut index = 0;
+    while index + 2 <= payload.len() {
+        let tag = payload[index];
+        let mut len = payload[index + 1] as usize;
+        index += 2;
+        if len & 0x80 != 0 {
+            len = (len & 0x7F) | ((*payload.get(index)? as usize) << 7);
+            index += 1;
+        }
+        let value = payload.get(index..index + len)?;
+        index += len;
+        if value.len() == 4 {
+            let value = u32::from_be_bytes([value[0], value[1], value[2], value[3]]);
+            match tag {
+                TAG_DEVICE_TYPE => device_type = value,
+                TAG_DEVICE_ID => device_id = value,
+                _ => {}
+            }
+        }
+    }
+    Some((device_type, device_id))
+}
+
+/// Whether a device matches the requested type and id
+pub fn discover_matches(request: (u32, u32), device_id: u32) -> bool {
+    let (requested_type, requested_id) = request;
+    (requested_type == WILDCARD || requested_type == DEVICE_TYPE_TUNER)
+        && (requested_id == WILDCARD || requested_id == device_id)
+}
+
+pub fn discover_reply(device: &HdhrDiscoverDevice) -> Vec<u8> {
+    let mut payload = vec![];
+    write_tag(
+        &mut payload,
+        TAG_DEVICE_TYPE,
+        &DEVICE_TYPE_TUNER.to_be_bytes(),
+    );
+    write_tag(&mut payload, TAG_DEVICE_ID, &device.device_id.to_be_bytes());
+    write_tag(&mut payload, TAG_TUNER_COUNT, &[device.tuner_count]);
+    write_tag(
+        &mut payload,
+        TAG_DEVICE_AUTH_STR,
+        device.device_auth.as_bytes(),
+    );
+    write_tag(&mut payload, TAG_BASE_URL, device.base_url.as_bytes());
+    write_tag(&mut payload, TAG_LINEUP_URL, device.lineup_url.as_bytes());
+    frame(TYPE_DISCOVER_RPY, &payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_ids_are_valid() {
+        for library in ["a", "library", "x6Yq_2z"] {
+            let id = device_id(library);
+            assert!(is_valid_device_id(id), "{:08X}", id);
+            assert!(!is_valid_device_id(id ^ 1));
+            assert_eq!(id, device_id(library));
+        }
+    }
+
+    #[test]
+    fn guide_numbers_fill_missing() {
+        let channel = |id: &str, number: Option<u16>| Channel {
+            id: id.to_string(),
+            channel_number: number.map(|n| n.into()),
+            ..Default::default()
+        };
+        let channels = vec![
+            channel("a", None),
+            channel("b", Some(5)),
+            channel("c", Some(2)),
+            channel("d", Some(5)),
+        ];
+        let numbers: Vec<(u32, &str)> = guide_numbers(&channels)
+            .into_iter()
+            .map(|(n, c)| (n, c.id.as_str()))
+            .collect();
+        assert_eq!(numbers, vec![(2, "c"), (5, "b"), (6, "a"), (7, "d")]);
+    }
+
+    #[test]
+    fn discovery_roundtrip() {
+        let mut payload = vec![];
+        write_tag(
+            &mut payload,
+            TAG_DEVICE_TYPE,
+            &DEVICE_TYPE_TUNER.to_be_bytes(),
+        );
+        write_tag(&mut payload, TAG_DEVICE_ID, &WILDCARD.to_be_bytes());
+        let request = frame(TYPE_DISCOVER_REQ, &payload);
+        let parsed = parse_discover_request(&request).unwrap();
+        assert_eq!(parsed, (DEVICE_TYPE_TUNER, WILDCARD));
+        assert!(discover_matches(parsed, 0x1234_5678));
+
+        let mut corrupted = request.clone();
+        corrupted[5] ^= 1;
+        assert_eq!(parse_discover_request(&corrupted), None);
+
+        let device = HdhrDiscoverDevice {
+            device_id: device_id("lib"),
+            tuner_count: 2,
+            base_url: format!("http://192.168.1.2:8080/hdhr/lib/{}", "k".repeat(150)),
+            lineup_url: "http://192.168.1.2:8080/hdhr/lib/key/lineup.json".to_string(),
+            device_auth: "auth".to_string(),
+        };
+        let reply = discover_reply(&device);
+        assert_eq!(u16::from_be_bytes([reply[0], reply[1]]), TYPE_DISCOVER_RPY);
+        let (body, crc) = reply.split_at(reply.len() - 4);
+        assert_eq!(crc32(body).to_le_bytes(), crc);
+        assert_eq!(
+            u16::from_be_bytes([reply[2], reply[3]]) as usize,
+            reply.len() - 8
+        );
+    }
+}
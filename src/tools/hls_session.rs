@@ -7,6 +7,7 @@ use std::{
     },
 };
 
+use chrono::DateTime;
 use tokio::{
     io::{AsyncBufReadExt, BufReader},
     process::Command,
@@ -15,6 +16,10 @@ use tokio::{
 use tokio_util::sync::CancellationToken;
 
 use crate::{
+    domain::{
+        streaming_session::{RsStreamingSession, StreamingMode, StreamingSessionKind},
+        ElementAction,
+    },
     server::get_server_folder_path_array,
     tools::{
         get_time,
@@ -25,6 +30,8 @@ use crate::{
 
 pub const HLS_SEGMENT_DURATION: u32 = 6;
 pub const HLS_LIST_SIZE: u32 = 10;
+/// Longest timeshift buffer a library can keep per session
+pub const MAX_TIMESHIFT_MINUTES: u32 = 24 * 60;
 pub const INACTIVITY_TIMEOUT_SECS: u64 = 30;
 const MAX_FFMPEG_RESTARTS: u32 = 5;
 pub const PLAYLIST_READY_TIMEOUT_MS: u64 = 15000;
@@ -40,14 +47,42 @@ const STALL_CHECK_INTERVAL_SECS: u64 = 15;
 /// Timeout for child.kill() to prevent supervisor from hanging
 const KILL_TIMEOUT_SECS: u64 = 5;
 
+/// Usage counters shared by channel and media HLS sessions
+pub struct HlsSessionStats {
+    pub user: Option<String>,
+    /// Unix time in seconds
+    pub started: u64,
+    pub segments_served: AtomicU64,
+    pub bytes_sent: AtomicU64,
+}
+
+impl HlsSessionStats {
+    pub fn new(user: Option<String>) -> Self {
+        Self {
+            user,
+            started: get_time().as_secs(),
+            segments_served: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record_segment(&self, bytes: u64) {
+        self.segments_served.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
 pub struct HlsSession {
     pub key: String,
     pub library_id: String,
     pub channel_id: String,
+    /// Minutes of segments kept behind the live edge, 0 without timeshift
+    pub timeshift_minutes: u32,
     pub output_dir: PathBuf,
     pub playlist_path: PathBuf,
     pub cancel_token: CancellationToken,
     pub last_active: Arc<AtomicU64>,
+    pub stats: Arc<HlsSessionStats>,
     _supervisor_handle: tokio::task::JoinHandle<()>,
 }
 
@@ -61,6 +96,103 @@ impl HlsSession {
         let last = self.last_active.load(Ordering::Relaxed);
         get_time().as_secs().saturating_sub(last) > INACTIVITY_TIMEOUT_SECS
     }
+
+    pub fn to_streaming_session(&self) -> RsStreamingSession {
+        RsStreamingSession {
+            id: self.key.clone(),
+            kind: StreamingSessionKind::Channel,
+            library: self.library_id.clone(),
+            media: None,
+            channel: Some(self.channel_id.clone()),
+            user: self.stats.user.clone(),
+            // Live channels are always remuxed
+            mode: StreamingMode::Copy,
+            encoder: None,
+            started: self.stats.started * 1000,
+            last_active: self.last_active.load(Ordering::Relaxed) * 1000,
+            segments_served: self.stats.segments_served.load(Ordering::Relaxed),
+            bytes_sent: self.stats.bytes_sent.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Segments kept in the playlist: the rolling window, or the whole timeshift buffer
+pub fn playlist_size(timeshift_minutes: u32) -> u32 {
+    let seconds = timeshift_minutes
+        .min(MAX_TIMESHIFT_MINUTES)
+        .saturating_mul(60);
+    (seconds / HLS_SEGMENT_DURATION).max(HLS_LIST_SIZE)
+}
+
+/// Parse an `#EXT-X-PROGRAM-DATE-TIME` value into unix milliseconds
+fn parse_program_date_time(value: &str) -> Option<i64> {
+    let value = value.trim();
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f%z"))
+        .ok()
+        .map(|date| date.timestamp_millis())
+}
+
+/// Rewrite the FFmpeg playlist for clients: segments are mapped with `segment_url`.
+/// With timeshift the playlist is announced as EVENT until the buffer is full, and
+/// `start` (unix milliseconds) sets the position players start at when it is in the buffer
+pub fn rewrite_playlist<F: Fn(&str) -> String>(
+    content: &str,
+    segment_url: F,
+    timeshift: bool,
+    start: Option<i64>,
+) -> String {
+    let mut lines: Vec<String> = vec![];
+    let mut growing = false;
+    let mut offset = 0.0;
+    let mut start_offset: Option<f64> = None;
+    let mut segment_date: Option<i64> = None;
+    let mut duration = 0.0;
+    for line in content.lines() {
+        if let Some(sequence) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+            growing = sequence.trim() == "0";
+        } else if let Some(value) = line.strip_prefix("#EXT-X-PROGRAM-DATE-TIME:") {
+            segment_date = parse_program_date_time(value);
+        } else if let Some(value) = line.strip_prefix("#EXTINF:") {
+            duration = value
+                .split(',')
+                .next()
+                .and_then(|d| d.trim().parse::<f64>().ok())
+                .unwrap_or(0.0);
+        } else if line.ends_with(".ts") && !line.starts_with('#') {
+            let duration_ms = (duration * 1000.0) as i64;
+            if let (Some(start), Some(date), None) = (start, segment_date, start_offset) {
+                if start < date + duration_ms {
+                    start_offset = Some(offset + (start - date).max(0) as f64 / 1000.0);
+                }
+            }
+            segment_date = segment_date.map(|date| date + duration_ms);
+            offset += duration;
+            lines.push(segment_url(line));
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+
+    let mut headers = vec![];
+    if timeshift && growing && !content.contains("#EXT-X-PLAYLIST-TYPE") {
+        headers.push("#EXT-X-PLAYLIST-TYPE:EVENT".to_string());
+    }
+    if let Some(start_offset) = start_offset {
+        headers.push(format!(
+            "#EXT-X-START:TIME-OFFSET={:.3},PRECISE=YES",
+            start_offset
+        ));
+    }
+    if !headers.is_empty() {
+        let position = lines
+            .iter()
+            .position(|l| l.starts_with("#EXTM3U"))
+            .map(|p| p + 1)
+            .unwrap_or(0);
+        lines.splice(position..position, headers);
+    }
+    lines.join("\n")
 }
 
 /// Find the highest segment number in the output directory to continue numbering
@@ -88,6 +220,7 @@ async fn spawn_ffmpeg(
     output_dir: &PathBuf,
     playlist_path: &PathBuf,
     start_number: u32,
+    list_size: u32,
     is_restart: bool,
 ) -> std::io::Result<tokio::process::Child> {
     let ffmpeg_path = VideoCommandBuilder::get_ffmpeg_path();
@@ -123,7 +256,7 @@ async fn spawn_ffmpeg(
         // HLS output format
         .args(["-f", "hls"])
         .args(["-hls_time", &HLS_SEGMENT_DURATION.to_string()])
-        .args(["-hls_list_size", &HLS_LIST_SIZE.to_string()])
+        .args(["-hls_list_size", &list_size.to_string()])
         .args(["-hls_flags", &hls_flags])
         .args(["-hls_segment_filename", &segment_pattern.to_string_lossy()])
         .args(["-hls_allow_cache", "1"])
@@ -154,12 +287,14 @@ async fn kill_ffmpeg(child: &mut tokio::process::Child, session_key: &str) {
     }
 }
 
-/// Supervisor loop: manages FFmpeg lifecycle with restart logic
+/// Supervisor loop: manages FFmpeg lifecycle with restart logic.
+/// When FFmpeg exits without producing a segment, the next stream url is tried
 async fn supervisor_loop(
     session_key: String,
     library_id: String,
     channel_id: String,
-    stream_url: String,
+    stream_urls: Vec<String>,
+    list_size: u32,
     output_dir: PathBuf,
     playlist_path: PathBuf,
     cancel_token: CancellationToken,
@@ -167,16 +302,19 @@ async fn supervisor_loop(
     mc: crate::model::ModelController,
 ) {
     let mut restart_count = 0u32;
+    let mut url_index = 0usize;
 
     loop {
         let start_number = find_next_segment_number(&output_dir).await;
-        let is_restart = restart_count > 0;
+        let is_restart = restart_count > 0 || url_index > 0;
+        let stream_url = &stream_urls[url_index];
 
         let child = spawn_ffmpeg(
-            &stream_url,
+            stream_url,
             &output_dir,
             &playlist_path,
             start_number,
+            list_size,
             is_restart,
         )
         .await;
@@ -197,8 +335,11 @@ async fn supervisor_loop(
         log_info(
             LogServiceType::Other,
             format!(
-                "HLS [{}]: FFmpeg started (restart #{})",
-                session_key, restart_count
+                "HLS [{}]: FFmpeg started (variant {}/{}, restart #{})",
+                session_key,
+                url_index + 1,
+                stream_urls.len(),
+                restart_count
             ),
         );
 
@@ -262,8 +403,20 @@ async fn supervisor_loop(
                 };
 
                 let ran_for = get_time().as_secs().saturating_sub(spawned_at);
+                let produced = find_next_segment_number(&output_dir).await > start_number;
 
-                if ran_for >= STABLE_RUN_SECS {
+                if !produced && url_index + 1 < stream_urls.len() {
+                    // The input could not be opened, fail over to the next variant
+                    url_index += 1;
+                    restart_count = 0;
+                    log_error(
+                        LogServiceType::Other,
+                        format!(
+                            "HLS [{}]: FFmpeg exited without output ({}), trying variant {}/{}",
+                            session_key, exit_info, url_index + 1, stream_urls.len()
+                        ),
+                    );
+                } else if ran_for >= STABLE_RUN_SECS {
                     // FFmpeg ran long enough — this is a transient failure, not a crash loop
                     restart_count = 0;
                     log_info(
@@ -306,18 +459,29 @@ async fn supervisor_loop(
                     ),
                 );
                 kill_ffmpeg(&mut child, &session_key).await;
-                break;
+                let produced = find_next_segment_number(&output_dir).await > start_number;
+                if produced || url_index + 1 >= stream_urls.len() {
+                    break;
+                }
+                // Never started on this variant, fail over to the next one
+                url_index += 1;
+                restart_count = 0;
             }
         }
     }
 
     // Cleanup: remove temp directory, session from map, and release stream slot
     let _ = tokio::fs::remove_dir_all(&output_dir).await;
-    {
+    let removed = {
         let mut sessions = hls_sessions.write().await;
-        sessions.remove(&session_key);
-    }
+        sessions
+            .remove(&session_key)
+            .map(|s| s.to_streaming_session())
+    };
     mc.release_stream_slot(&library_id, &channel_id).await;
+    if let Some(session) = removed {
+        mc.send_streaming_session(ElementAction::Deleted, session);
+    }
     log_info(
         LogServiceType::Other,
         format!("HLS [{}]: Session cleaned up", session_key),
@@ -329,7 +493,9 @@ pub async fn create_session(
     key: String,
     library_id: String,
     channel_id: String,
-    stream_url: String,
+    stream_urls: Vec<String>,
+    timeshift_minutes: u32,
+    user: Option<String>,
     hls_sessions: Arc<RwLock<HashMap<String, HlsSession>>>,
     mc: crate::model::ModelController,
 ) -> crate::error::RsResult<(PathBuf, PathBuf)> {
@@ -344,25 +510,29 @@ pub async fn create_session(
         key.clone(),
         library_id.clone(),
         channel_id.clone(),
-        stream_url.clone(),
+        stream_urls,
+        playlist_size(timeshift_minutes),
         output_dir.clone(),
         playlist_path.clone(),
         cancel_token.clone(),
         hls_sessions.clone(),
-        mc,
+        mc.clone(),
     ));
 
     let session = HlsSession {
         key: key.clone(),
         library_id,
         channel_id,
+        timeshift_minutes,
         output_dir: output_dir.clone(),
         playlist_path: playlist_path.clone(),
         cancel_token,
         last_active,
+        stats: Arc::new(HlsSessionStats::new(user)),
         _supervisor_handle: supervisor_handle,
     };
 
+    mc.send_streaming_session(ElementAction::Added, session.to_streaming_session());
     {
         let mut sessions = hls_sessions.write().await;
         sessions.insert(key, session);
@@ -428,3 +598,49 @@ pub async fn cleanup_orphaned_dirs() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYLIST: &str = "#EXTM3U
+#EXT-X-VERSION:3
+#EXT-X-TARGETDURATION:6
+#EXT-X-MEDIA-SEQUENCE:0
+#EXT-X-PROGRAM-DATE-TIME:2024-01-31T20:30:00.000+0000
+#EXTINF:6.000000,
+seg_00000.ts
+#EXT-X-PROGRAM-DATE-TIME:2024-01-31T20:30:06.000+0000
+#EXTINF:6.000000,
+seg_00001.ts
+#EXTINF:6.000000,
+seg_00002.ts";
+
+    #[test]
+    fn timeshift_playlist_rewrite() {
+        let url = |segment: &str| format!("/hls/{}", segment);
+        // 20:30:14 is 2 seconds into the third segment
+        let rewritten = rewrite_playlist(PLAYLIST, url, true, Some(1_706_733_014_000));
+        let lines: Vec<&str> = rewritten.lines().collect();
+        assert_eq!(lines[1], "#EXT-X-PLAYLIST-TYPE:EVENT");
+        assert_eq!(lines[2], "#EXT-X-START:TIME-OFFSET=14.000,PRECISE=YES");
+        assert!(lines.contains(&"/hls/seg_00002.ts"));
+
+        // Before the buffer starts at its beginning, after it at the live edge
+        let rewritten = rewrite_playlist(PLAYLIST, url, true, Some(1_706_732_000_000));
+        assert!(rewritten.contains("#EXT-X-START:TIME-OFFSET=0.000,PRECISE=YES"));
+        let rewritten = rewrite_playlist(PLAYLIST, url, true, Some(1_706_734_000_000));
+        assert!(!rewritten.contains("#EXT-X-START"));
+
+        // Full buffer or no timeshift: plain live playlist
+        let sliding = PLAYLIST.replace("MEDIA-SEQUENCE:0", "MEDIA-SEQUENCE:42");
+        assert!(!rewrite_playlist(&sliding, url, true, None).contains("PLAYLIST-TYPE"));
+        assert!(!rewrite_playlist(PLAYLIST, url, false, None).contains("PLAYLIST-TYPE"));
+        assert_eq!(playlist_size(0), HLS_LIST_SIZE);
+        assert_eq!(playlist_size(60), 600);
+        assert_eq!(
+            playlist_size(u32::MAX),
+            MAX_TIMESHIFT_MINUTES * 60 / HLS_SEGMENT_DURATION
+        );
+    }
+}
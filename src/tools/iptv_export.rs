@@ -0,0 +1,151 @@
+use chrono::DateTime;
+
+use crate::{domain::epg::EpgProgramme, tools::text_tools::escape_xml};
+
+/// Channel as written in an exported playlist and guide
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportChannel {
+    pub tvg_id: String,
+    pub name: String,
+    pub number: u32,
+    pub logo: Option<String>,
+    pub group: Option<String>,
+    pub url: String,
+}
+
+/// Write an extended M3U playlist, pointing players to `epg_url` for the guide
+pub fn write_m3u(channels: &[ExportChannel], epg_url: Option<&str>) -> String {
+    let mut playlist = match epg_url {
+        Some(url) => format!("#EXTM3U url-tvg=\"{}\"\n", m3u_attribute(url)),
+        None => "#EXTM3U\n".to_string(),
+    };
+    for channel in channels {
+        let mut attributes = vec![
+            format!("tvg-id=\"{}\"", m3u_attribute(&channel.tvg_id)),
+            format!("tvg-name=\"{}\"", m3u_attribute(&channel.name)),
+            format!("tvg-chno=\"{}\"", channel.number),
+        ];
+        if let Some(logo) = &channel.logo {
+            attributes.push(format!("tvg-logo=\"{}\"", m3u_attribute(logo)));
+        }
+        if let Some(group) = &channel.group {
+            attributes.push(format!("group-title=\"{}\"", m3u_attribute(group)));
+        }
+        playlist.push_str(&format!(
+            "#EXTINF:-1 {},{}\n{}\n",
+            attributes.join(" "),
+            single_line(&channel.name),
+            channel.url
+        ));
+    }
+    playlist
+}
+
+/// Write an XMLTV guide with the channels and their programmes
+pub fn write_xmltv(channels: &[ExportChannel], programmes: &[EpgProgramme]) -> String {
+    let mut guide = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE tv SYSTEM \"xmltv.dtd\">\n<tv generator-info-name=\"Redseat\">\n",
+    );
+    for channel in channels {
+        guide.push_str(&format!(
+            "  <channel id=\"{}\">\n    <display-name>{}</display-name>\n    <lcn>{}</lcn>\n",
+            escape_xml(&channel.tvg_id),
+            escape_xml(&channel.name),
+            channel.number
+        ));
+        if let Some(logo) = &channel.logo {
+            guide.push_str(&format!("    <icon src=\"{}\" />\n", escape_xml(logo)));
+        }
+        guide.push_str("  </channel>\n");
+    }
+    for programme in programmes {
+        guide.push_str(&format!(
+            "  <programme start=\"{}\" stop=\"{}\" channel=\"{}\">\n    <title>{}</title>\n",
+            format_xmltv_time(programme.start),
+            format_xmltv_time(programme.stop),
+            escape_xml(&programme.channel),
+            escape_xml(&programme.title)
+        ));
+        if let Some(description) = &programme.description {
+            guide.push_str(&format!("    <desc>{}</desc>\n", escape_xml(description)));
+        }
+        if let Some(category) = &programme.category {
+            guide.push_str(&format!(
+                "    <category>{}</category>\n",
+                escape_xml(category)
+            ));
+        }
+        if let Some(episode) = &programme.episode_num {
+            guide.push_str(&format!(
+                "    <episode-num system=\"onscreen\">{}</episode-num>\n",
+                escape_xml(episode)
+            ));
+        }
+        if let Some(icon) = &programme.icon {
+            guide.push_str(&format!("    <icon src=\"{}\" />\n", escape_xml(icon)));
+        }
+        guide.push_str("  </programme>\n");
+    }
+    guide.push_str("</tv>\n");
+    guide
+}
+
+/// XMLTV time (`20240131203000 +0000`) of a timestamp in milliseconds
+pub fn format_xmltv_time(millis: i64) -> String {
+    DateTime::from_timestamp_millis(millis)
+        .unwrap_or_default()
+        .format("%Y%m%d%H%M%S +0000")
+        .to_string()
+}
+
+/// M3U attributes are quoted and cannot be escaped
+fn m3u_attribute(value: &str) -> String {
+    single_line(value).replace('"', "'")
+}
+
+fn single_line(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{m3u_parser::parse_m3u, xmltv_parser::parse_xmltv};
+
+    #[test]
+    fn exports_parse_back() {
+        let channels = vec![ExportChannel {
+            tvg_id: "TF1.fr".to_string(),
+            name: "TF1 \"HD\"".to_string(),
+            number: 1,
+            logo: Some("http://host/libraries/l/channels/c/image?sharetoken=t".to_string()),
+            group: Some("News & Info".to_string()),
+            url: "http://host/libraries/l/channels/c/stream?sharetoken=t".to_string(),
+        }];
+        let playlist = write_m3u(&channels, Some("http://host/epg.xml?sharetoken=t"));
+        let parsed = parse_m3u(&playlist);
+        assert_eq!(
+            parsed.header.url_tvg.as_deref(),
+            Some("http://host/epg.xml?sharetoken=t")
+        );
+        assert_eq!(parsed.entries.len(), 1);
+        let entry = &parsed.entries[0];
+        assert_eq!(entry.tvg_id.as_deref(), Some("TF1.fr"));
+        assert_eq!(entry.tvg_name.as_deref(), Some("TF1 'HD'"));
+        assert_eq!(entry.group_title.as_deref(), Some("News & Info"));
+        assert_eq!(entry.url, channels[0].url);
+
+        let programmes = vec![EpgProgramme {
+            channel: "TF1.fr".to_string(),
+            start: 1_706_733_000_000,
+            stop: 1_706_736_600_000,
+            title: "Journal & Météo".to_string(),
+            description: Some("<Live>".to_string()),
+            episode_num: Some("S01E02".to_string()),
+            ..Default::default()
+        }];
+        let guide = write_xmltv(&channels, &programmes);
+        assert!(guide.contains("start=\"20240131203000 +0000\""));
+        assert_eq!(parse_xmltv(&guide), programmes);
+    }
+}
@@ -0,0 +1,226 @@
+use std::{process::Stdio, time::Duration};
+
+use tokio::process::Command;
+
+use crate::domain::channel::{ChannelVariant, VariantHealth, VariantHealthUpdate};
+
+use super::{m3u_parser::QUALITY_ORDER, video_tools::VideoCommandBuilder};
+
+/// Consecutive failed checks after which a variant is skipped
+pub const DEAD_AFTER_FAILURES: u32 = 3;
+const HTTP_TIMEOUT_SECS: u64 = 10;
+const FFPROBE_TIMEOUT_SECS: u64 = 20;
+
+/// Result of probing a variant url
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VariantProbe {
+    pub latency: Option<u32>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub error: Option<String>,
+}
+
+/// Check that the url answers with data, then ffprobe its video resolution
+pub async fn probe_variant(url: &str) -> VariantProbe {
+    let mut probe = VariantProbe::default();
+    if url.starts_with("http") {
+        match first_bytes_latency(url).await {
+            Ok(latency) => probe.latency = Some(latency),
+            Err(error) => {
+                probe.error = Some(error);
+                return probe;
+            }
+        }
+    }
+    match probe_resolution(url).await {
+        Ok(resolution) => {
+            if let Some((width, height)) = resolution {
+                probe.width = Some(width);
+                probe.height = Some(height);
+            }
+        }
+        Err(error) => probe.error = Some(error),
+    }
+    probe
+}
+
+/// Milliseconds until the first bytes of the body are received
+async fn first_bytes_latency(url: &str) -> Result<u32, String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(HTTP_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let started = std::time::Instant::now();
+    let mut response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    match response.chunk().await {
+        Ok(Some(chunk)) if !chunk.is_empty() => Ok(started.elapsed().as_millis() as u32),
+        Ok(_) => Err("Empty response".to_string()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// Resolution of the first video stream, `None` for audio only streams
+async fn probe_resolution(url: &str) -> Result<Option<(u32, u32)>, String> {
+    let output = Command::new(VideoCommandBuilder::get_ffprobe_path())
+        .args(["-v", "error"])
+        .args(["-rw_timeout", "10000000"])
+        .args(["-select_streams", "v:0"])
+        .args(["-show_entries", "stream=width,height"])
+        .args(["-of", "csv=s=x:p=0"])
+        .arg(url)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(Duration::from_secs(FFPROBE_TIMEOUT_SECS), output)
+        .await
+        .map_err(|_| "Probe timed out".to_string())?
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Probe failed: {}", error.trim()));
+    }
+    Ok(parse_resolution(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse the `1920x1080` output of ffprobe
+pub fn parse_resolution(output: &str) -> Option<(u32, u32)> {
+    let (width, height) = output.lines().next()?.trim().split_once('x')?;
+    Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+}
+
+/// New health of a variant after a probe
+pub fn health_update(
+    variant: &ChannelVariant,
+    probe: &VariantProbe,
+    now: i64,
+) -> VariantHealthUpdate {
+    if probe.error.is_none() {
+        VariantHealthUpdate {
+            health: Some(VariantHealth::Ok),
+            latency: probe.latency,
+            width: probe.width,
+            height: probe.height,
+            failures: 0,
+            checked: now,
+            last_success: Some(now),
+        }
+    } else {
+        let failures = variant.failures + 1;
+        VariantHealthUpdate {
+            health: Some(if failures >= DEAD_AFTER_FAILURES {
+                VariantHealth::Dead
+            } else {
+                VariantHealth::Failing
+            }),
+            latency: None,
+            width: variant.width,
+            height: variant.height,
+            failures,
+            checked: now,
+            last_success: None,
+        }
+    }
+}
+
+/// Variants in the order they should be tried: the requested quality, then by
+/// `QUALITY_ORDER`. Dead variants are skipped
+pub fn rank_variants<'a>(
+    variants: &'a [ChannelVariant],
+    quality: Option<&str>,
+) -> Vec<&'a ChannelVariant> {
+    let mut ranked: Vec<&ChannelVariant> = variants.iter().filter(|v| !v.is_dead()).collect();
+    ranked.sort_by_key(|variant| {
+        let variant_quality = variant.quality.as_deref();
+        (
+            quality.is_none() || variant_quality != quality,
+            variant_quality
+                .and_then(|q| QUALITY_ORDER.iter().position(|o| *o == q))
+                .unwrap_or(QUALITY_ORDER.len()),
+        )
+    });
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variant(id: &str, quality: &str, health: Option<VariantHealth>) -> ChannelVariant {
+        ChannelVariant {
+            id: id.to_string(),
+            quality: Some(quality.to_string()),
+            health,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn dead_variants_are_skipped() {
+        let variants = vec![
+            variant("sd", "SD", None),
+            variant("fhd", "FHD", Some(VariantHealth::Dead)),
+            variant("other", "default", Some(VariantHealth::Ok)),
+            variant("hd", "HD", Some(VariantHealth::Failing)),
+        ];
+        let ids = |ranked: Vec<&ChannelVariant>| -> Vec<String> {
+            ranked.into_iter().map(|v| v.id.clone()).collect()
+        };
+        assert_eq!(
+            ids(rank_variants(&variants, None)),
+            vec!["hd", "sd", "other"]
+        );
+        assert_eq!(
+            ids(rank_variants(&variants, Some("SD"))),
+            vec!["sd", "hd", "other"]
+        );
+        assert_eq!(
+            ids(rank_variants(&variants, Some("FHD"))),
+            vec!["hd", "sd", "other"]
+        );
+    }
+
+    #[test]
+    fn failures_accumulate_until_dead() {
+        let mut current = ChannelVariant {
+            width: Some(1920),
+            height: Some(1080),
+            ..Default::default()
+        };
+        let failed = VariantProbe {
+            error: Some("HTTP 404".to_string()),
+            ..Default::default()
+        };
+        for (now, expected) in [
+            (1, VariantHealth::Failing),
+            (2, VariantHealth::Failing),
+            (3, VariantHealth::Dead),
+        ] {
+            let update = health_update(&current, &failed, now);
+            assert_eq!(update.health, Some(expected));
+            assert_eq!(update.width, Some(1920));
+            current.failures = update.failures;
+        }
+        let update = health_update(
+            &current,
+            &VariantProbe {
+                latency: Some(120),
+                ..Default::default()
+            },
+            4,
+        );
+        assert_eq!(update.health, Some(VariantHealth::Ok));
+        assert_eq!(update.failures, 0);
+        assert_eq!(update.last_success, Some(4));
+        assert_eq!(update.width, None);
+    }
+
+    #[test]
+    fn resolution_parsing() {
+        assert_eq!(parse_resolution("1920x1080\n"), Some((1920, 1080)));
+        assert_eq!(parse_resolution(""), None);
+        assert_eq!(parse_resolution("N/AxN/A"), None);
+    }
+}
@@ -0,0 +1,105 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use md5::{Digest, Md5};
+
+/// Reflowable documents (EPUB) have no pages: their progress is the percentage on this scale
+pub const KOSYNC_PERCENT_SCALE: f64 = 10000.0;
+const SAMPLE_SIZE: usize = 1024;
+
+/// KOReader document hash: md5 of 1 KiB samples at offsets 0 and 1024 * 4^i for i in 0..=10
+pub fn partial_md5<R: Read + Seek>(mut reader: R) -> std::io::Result<String> {
+    let mut hasher = Md5::new();
+    let mut buffer = [0u8; SAMPLE_SIZE];
+    for i in -1i32..=10 {
+        let offset = if i < 0 {
+            0
+        } else {
+            (SAMPLE_SIZE as u64) << (2 * i)
+        };
+        reader.seek(SeekFrom::Start(offset))?;
+        let read = read_sample(&mut reader, &mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn read_sample<R: Read>(reader: &mut R, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read)
+}
+
+/// Key sent by KOReader in `x-auth-key`: md5 of the password
+pub fn kosync_key(password: &str) -> String {
+    hex::encode(Md5::digest(password.as_bytes()))
+}
+
+/// Redseat progress of a KOReader position: the page of paged documents (PDF, comics),
+/// the scaled percentage of reflowable ones
+pub fn progress_from_kosync(progress: &str, percentage: f64, pages: Option<usize>) -> u64 {
+    let percentage = percentage.clamp(0.0, 1.0);
+    match pages.filter(|pages| *pages > 0) {
+        Some(pages) => progress
+            .trim()
+            .parse::<u64>()
+            .unwrap_or_else(|_| (percentage * pages as f64).round() as u64)
+            .clamp(1, pages as u64),
+        None => (percentage * KOSYNC_PERCENT_SCALE).round() as u64,
+    }
+}
+
+/// KOReader position (page and percentage) of a Redseat progress. Only paged documents
+/// can be positioned, KOReader needs an xpointer for reflowable ones
+pub fn kosync_from_progress(progress: u64, pages: Option<usize>) -> Option<(String, f64)> {
+    let pages = pages.filter(|pages| *pages > 0)?;
+    let page = progress.clamp(1, pages as u64);
+    Some((page.to_string(), page as f64 / pages as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn hashes_samples_like_koreader() {
+        assert_eq!(
+            partial_md5(Cursor::new(b"hello")).unwrap(),
+            "5d41402abc4b2a76b9719d911017c592"
+        );
+        // Samples at 0 and 1024, nothing at 4096
+        let data: Vec<u8> = (0..3000).map(|i| (i % 251) as u8).collect();
+        assert_eq!(
+            partial_md5(Cursor::new(&data)).unwrap(),
+            hex::encode(Md5::digest(&data[..2048]))
+        );
+        assert_eq!(kosync_key("hello"), "5d41402abc4b2a76b9719d911017c592");
+    }
+
+    #[test]
+    fn maps_positions() {
+        assert_eq!(progress_from_kosync("12", 0.5, Some(40)), 12);
+        assert_eq!(
+            progress_from_kosync("/body/DocFragment[3]", 0.5, Some(40)),
+            20
+        );
+        assert_eq!(
+            progress_from_kosync("/body/DocFragment[3]", 0.4567, None),
+            4567
+        );
+        assert_eq!(
+            kosync_from_progress(10, Some(40)),
+            Some(("10".to_string(), 0.25))
+        );
+        assert_eq!(kosync_from_progress(4567, None), None);
+    }
+}
@@ -40,16 +40,23 @@ pub enum M3uContentType {
     Series,
 }
 
-impl M3uEntry {
-    pub fn content_type(&self) -> M3uContentType {
-        if self.url.contains("/movie/") {
+impl M3uContentType {
+    /// Xtream style playlists serve VOD under `/movie/` and series under `/series/`
+    pub fn from_url(url: &str) -> Self {
+        if url.contains("/movie/") {
             M3uContentType::Vod
-        } else if self.url.contains("/series/") {
+        } else if url.contains("/series/") {
             M3uContentType::Series
         } else {
             M3uContentType::Live
         }
     }
+}
+
+impl M3uEntry {
+    pub fn content_type(&self) -> M3uContentType {
+        M3uContentType::from_url(&self.url)
+    }
 
     pub fn quality(&self) -> Option<String> {
         let name = self.tvg_name.as_deref().unwrap_or(&self.display_name);
@@ -15,9 +15,12 @@ use tokio::{
 use tokio_util::sync::CancellationToken;
 
 use crate::{
+    domain::streaming_session::{RsStreamingSession, StreamingMode, StreamingSessionKind},
     server::get_server_folder_path_array,
     tools::{
+        audiobook::ffconcat_list,
         get_time,
+        hls_session::HlsSessionStats,
         log::{log_error, log_info, LogServiceType},
         video_tools::{probe_video, VideoCommandBuilder},
     },
@@ -38,6 +41,9 @@ pub struct MediaHlsSession {
     pub last_active: Arc<AtomicU64>,
     /// True when FFmpeg exits cleanly (all segments generated)
     pub finished: Arc<AtomicBool>,
+    pub stats: Arc<HlsSessionStats>,
+    /// Video encoder, `None` when the video stream is copied
+    pub encoder: Option<String>,
     _supervisor_handle: tokio::task::JoinHandle<()>,
 }
 
@@ -51,6 +57,27 @@ impl MediaHlsSession {
         let last = self.last_active.load(Ordering::Relaxed);
         get_time().as_secs().saturating_sub(last) > MEDIA_INACTIVITY_TIMEOUT_SECS
     }
+
+    pub fn to_streaming_session(&self) -> RsStreamingSession {
+        RsStreamingSession {
+            id: self.key.clone(),
+            kind: StreamingSessionKind::Media,
+            library: self.library_id.clone(),
+            media: Some(self.media_id.clone()),
+            channel: None,
+            user: self.stats.user.clone(),
+            mode: if self.encoder.is_some() {
+                StreamingMode::Transcode
+            } else {
+                StreamingMode::Copy
+            },
+            encoder: self.encoder.clone(),
+            started: self.stats.started * 1000,
+            last_active: self.last_active.load(Ordering::Relaxed) * 1000,
+            segments_served: self.stats.segments_served.load(Ordering::Relaxed),
+            bytes_sent: self.stats.bytes_sent.load(Ordering::Relaxed),
+        }
+    }
 }
 
 /// Spawn FFmpeg in copy mode (no transcoding) for HLS VOD output
@@ -80,14 +107,14 @@ fn build_copy_mode_command(input_uri: &str, output_dir: &Path, playlist_path: &P
 }
 
 /// Probe source codecs and spawn FFmpeg with H.264 + AAC compatibility for HLS.
-/// - Video: copy if already H.264, otherwise transcode to H.264
+/// - Video: copy if already H.264 SDR, otherwise transcode to H.264 (tone mapped if HDR)
 /// - Audio: copy if already AAC, otherwise transcode to AAC 128k
 /// - Subtitles: stripped (HLS .ts doesn't support most formats)
 async fn spawn_compatible_hls(
     input_uri: &str,
     output_dir: &Path,
     playlist_path: &Path,
-) -> crate::error::RsResult<tokio::process::Child> {
+) -> crate::error::RsResult<(tokio::process::Child, Option<String>)> {
     let probe = probe_video(input_uri).await?;
 
     let video_codec = probe.video_stream().and_then(|s| s.codec_name.as_deref());
@@ -98,7 +125,8 @@ async fn spawn_compatible_hls(
     });
     let audio_is_aac = audio_codec.map_or(true, |c| c.eq_ignore_ascii_case("aac"));
 
-    let video_needs_transcode = !video_is_h264;
+    let hdr = probe.hdr_format();
+    let video_needs_transcode = !video_is_h264 || hdr.is_some();
     let audio_needs_transcode = !audio_is_aac;
 
     // Skip CUDA/hardware detection when everything can be copied
@@ -109,6 +137,9 @@ async fn spawn_compatible_hls(
     };
 
     if video_needs_transcode {
+        if hdr.is_some() {
+            builder.add_tonemap().await;
+        }
         builder.add_out_option("-c:v");
         if builder.has_cuda_support() {
             builder.add_out_option("h264_nvenc");
@@ -150,13 +181,15 @@ async fn spawn_compatible_hls(
 
     builder.add_out_option("-sn");
 
+    let encoder = builder.video_encoder();
     let cmd = builder.build_command_for_hls(output_dir, playlist_path, MEDIA_HLS_SEGMENT_DURATION);
-    cmd.spawn().map_err(|e| {
+    let child = cmd.spawn().map_err(|e| {
         crate::error::RsError::Error(format!(
             "Failed to spawn FFmpeg for HLS compatible mode: {}",
             e
         ))
-    })
+    })?;
+    Ok((child, encoder))
 }
 
 /// Supervisor loop for media HLS: runs FFmpeg once (no restart logic for VOD)
@@ -232,11 +265,11 @@ async fn media_supervisor_loop(
 }
 
 /// Build the FFmpeg command for a media HLS session.
-/// Returns a spawned child process and the output directory + playlist path.
+/// Returns a spawned child process, the output directory + playlist path and the video encoder.
 async fn build_and_spawn_media_hls(
     input_uri: &str,
     convert_request: Option<rs_plugin_common_interfaces::video::VideoConvertRequest>,
-) -> crate::error::RsResult<(tokio::process::Child, PathBuf, PathBuf)> {
+) -> crate::error::RsResult<(tokio::process::Child, PathBuf, PathBuf, Option<String>)> {
     let dir_name = format!("hls_{}", nanoid::nanoid!());
     let output_dir = get_server_folder_path_array(vec![".cache", &dir_name]).await?;
     let playlist_path = output_dir.join("playlist.m3u8");
@@ -244,9 +277,10 @@ async fn build_and_spawn_media_hls(
     let spawn_result = if let Some(request) = convert_request {
         let mut builder = VideoCommandBuilder::new(input_uri.to_string()).await;
         builder.set_request(request).await?;
+        let encoder = builder.video_encoder();
         let cmd =
             builder.build_command_for_hls(&output_dir, &playlist_path, MEDIA_HLS_SEGMENT_DURATION);
-        cmd.spawn().map_err(|e| {
+        cmd.spawn().map(|child| (child, encoder)).map_err(|e| {
             crate::error::RsError::Error(format!("Failed to spawn FFmpeg for HLS transcode: {}", e))
         })
     } else {
@@ -254,7 +288,7 @@ async fn build_and_spawn_media_hls(
     };
 
     match spawn_result {
-        Ok(child) => Ok((child, output_dir, playlist_path)),
+        Ok((child, encoder)) => Ok((child, output_dir, playlist_path, encoder)),
         Err(e) => {
             // Clean up the output directory on spawn failure
             let _ = tokio::fs::remove_dir_all(&output_dir).await;
@@ -265,17 +299,103 @@ async fn build_and_spawn_media_hls(
 
 /// Start a media HLS session: builds FFmpeg command, spawns it, creates session.
 /// The caller must ensure no session with the same key already exists.
+/// Returns the new session, `None` if a concurrent request created it first.
 pub async fn start_media_hls_session(
     key: String,
     library_id: String,
     media_id: String,
+    user: Option<String>,
     input_uri: &str,
     convert_request: Option<rs_plugin_common_interfaces::video::VideoConvertRequest>,
     media_hls_sessions: Arc<RwLock<HashMap<String, MediaHlsSession>>>,
-) -> crate::error::RsResult<()> {
-    let (child, output_dir, playlist_path) =
+) -> crate::error::RsResult<Option<RsStreamingSession>> {
+    let (child, output_dir, playlist_path, encoder) =
         build_and_spawn_media_hls(input_uri, convert_request).await?;
 
+    Ok(register_media_hls_session(
+        key,
+        library_id,
+        media_id,
+        user,
+        child,
+        output_dir,
+        playlist_path,
+        encoder,
+        media_hls_sessions,
+    )
+    .await)
+}
+
+/// Start an audio only HLS session playing the parts of an audiobook as one stream.
+/// Parts are joined with FFmpeg's concat demuxer and transcoded to AAC 128k.
+/// The session `media_id` is the book id
+pub async fn start_audiobook_hls_session(
+    key: String,
+    library_id: String,
+    book_id: String,
+    user: Option<String>,
+    inputs: &[String],
+    media_hls_sessions: Arc<RwLock<HashMap<String, MediaHlsSession>>>,
+) -> crate::error::RsResult<Option<RsStreamingSession>> {
+    let dir_name = format!("hls_{}", nanoid::nanoid!());
+    let output_dir = get_server_folder_path_array(vec![".cache", &dir_name]).await?;
+    let playlist_path = output_dir.join("playlist.m3u8");
+    let list_path = output_dir.join("parts.ffconcat");
+    if let Err(e) = tokio::fs::write(&list_path, ffconcat_list(inputs)).await {
+        let _ = tokio::fs::remove_dir_all(&output_dir).await;
+        return Err(e.into());
+    }
+
+    let mut builder = VideoCommandBuilder::new_copy_only(list_path.to_string_lossy().to_string());
+    builder
+        .add_input_option("-f")
+        .add_input_option("concat")
+        .add_input_option("-safe")
+        .add_input_option("0")
+        .add_input_option("-protocol_whitelist")
+        .add_input_option("file,http,https,tcp,tls");
+    builder.add_out_option("-vn");
+    builder.set_audio_codec_aac("128k");
+    let cmd =
+        builder.build_command_for_hls(&output_dir, &playlist_path, MEDIA_HLS_SEGMENT_DURATION);
+    let child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            let _ = tokio::fs::remove_dir_all(&output_dir).await;
+            return Err(crate::error::RsError::Error(format!(
+                "Failed to spawn FFmpeg for audiobook HLS: {}",
+                e
+            )));
+        }
+    };
+
+    Ok(register_media_hls_session(
+        key,
+        library_id,
+        book_id,
+        user,
+        child,
+        output_dir,
+        playlist_path,
+        None,
+        media_hls_sessions,
+    )
+    .await)
+}
+
+/// Supervise a spawned FFmpeg and add its session.
+/// Returns `None` if a concurrent request created a session with the same key first.
+async fn register_media_hls_session(
+    key: String,
+    library_id: String,
+    media_id: String,
+    user: Option<String>,
+    child: tokio::process::Child,
+    output_dir: PathBuf,
+    playlist_path: PathBuf,
+    encoder: Option<String>,
+    media_hls_sessions: Arc<RwLock<HashMap<String, MediaHlsSession>>>,
+) -> Option<RsStreamingSession> {
     let cancel_token = CancellationToken::new();
     let last_active = Arc::new(AtomicU64::new(get_time().as_secs()));
     let finished = Arc::new(AtomicBool::new(false));
@@ -298,8 +418,11 @@ pub async fn start_media_hls_session(
         cancel_token,
         last_active,
         finished,
+        stats: Arc::new(HlsSessionStats::new(user)),
+        encoder,
         _supervisor_handle: supervisor_handle,
     };
+    let snapshot = session.to_streaming_session();
 
     {
         let mut sessions = media_hls_sessions.write().await;
@@ -314,32 +437,35 @@ pub async fn start_media_hls_session(
                 ),
             );
             session.cancel_token.cancel();
-            return Ok(());
+            return None;
         }
         sessions.insert(key, session);
     }
 
-    Ok(())
+    Some(snapshot)
 }
 
-/// Stop a session by key
+/// Stop a session by key, returning its last state
 pub async fn stop_session(
     key: &str,
     media_hls_sessions: &Arc<RwLock<HashMap<String, MediaHlsSession>>>,
-) {
+) -> Option<RsStreamingSession> {
     let session = {
         let sessions = media_hls_sessions.read().await;
-        sessions.get(key).map(|s| s.cancel_token.clone())
+        sessions
+            .get(key)
+            .map(|s| (s.cancel_token.clone(), s.to_streaming_session()))
     };
-    if let Some(token) = session {
+    session.map(|(token, snapshot)| {
         token.cancel();
-    }
+        snapshot
+    })
 }
 
-/// Clean up stale media HLS sessions (called periodically)
+/// Clean up stale media HLS sessions (called periodically), returning the removed sessions
 pub async fn cleanup_stale_sessions(
     media_hls_sessions: &Arc<RwLock<HashMap<String, MediaHlsSession>>>,
-) {
+) -> Vec<RsStreamingSession> {
     let stale_keys: Vec<String> = {
         let sessions = media_hls_sessions.read().await;
         sessions
@@ -349,12 +475,15 @@ pub async fn cleanup_stale_sessions(
             .collect()
     };
 
+    let mut removed = Vec::new();
     for key in &stale_keys {
         log_info(
             LogServiceType::Other,
             format!("Media HLS [{}]: Cleaning up stale session", key),
         );
-        stop_session(key, media_hls_sessions).await;
+        if let Some(session) = stop_session(key, media_hls_sessions).await {
+            removed.push(session);
+        }
         // After cancel, the supervisor loop will clean up the directory
         // But we also remove from the map in case supervisor already exited
         let output_dir = {
@@ -365,6 +494,7 @@ pub async fn cleanup_stale_sessions(
             let _ = tokio::fs::remove_dir_all(&dir).await;
         }
     }
+    removed
 }
 
 #[cfg(test)]
@@ -383,6 +513,8 @@ mod tests {
             cancel_token: CancellationToken::new(),
             last_active: last_active.clone(),
             finished: Arc::new(AtomicBool::new(false)),
+            stats: Arc::new(HlsSessionStats::new(None)),
+            encoder: None,
             _supervisor_handle: tokio::spawn(async {}),
         };
 
@@ -406,6 +538,8 @@ mod tests {
             cancel_token: CancellationToken::new(),
             last_active: last_active.clone(),
             finished: Arc::new(AtomicBool::new(false)),
+            stats: Arc::new(HlsSessionStats::new(None)),
+            encoder: None,
             _supervisor_handle: tokio::spawn(async {}),
         };
 
@@ -414,6 +548,33 @@ mod tests {
         assert!(last_active.load(Ordering::Relaxed) > 0);
     }
 
+    #[tokio::test]
+    async fn test_streaming_session_snapshot() {
+        let session = MediaHlsSession {
+            key: "lib1:media1:default".to_string(),
+            library_id: "lib1".to_string(),
+            media_id: "media1".to_string(),
+            output_dir: PathBuf::from("/tmp/test"),
+            playlist_path: PathBuf::from("/tmp/test/playlist.m3u8"),
+            cancel_token: CancellationToken::new(),
+            last_active: Arc::new(AtomicU64::new(10)),
+            finished: Arc::new(AtomicBool::new(false)),
+            stats: Arc::new(HlsSessionStats::new(Some("user1".to_string()))),
+            encoder: Some("libx264".to_string()),
+            _supervisor_handle: tokio::spawn(async {}),
+        };
+        session.stats.record_segment(1000);
+        session.stats.record_segment(500);
+
+        let snapshot = session.to_streaming_session();
+        assert_eq!(snapshot.id, "lib1:media1:default");
+        assert_eq!(snapshot.mode, StreamingMode::Transcode);
+        assert_eq!(snapshot.user.as_deref(), Some("user1"));
+        assert_eq!(snapshot.last_active, 10_000);
+        assert_eq!(snapshot.segments_served, 2);
+        assert_eq!(snapshot.bytes_sent, 1500);
+    }
+
     #[test]
     fn test_constants() {
         assert_eq!(MEDIA_HLS_SEGMENT_DURATION, 6);
@@ -439,7 +600,7 @@ mod tests {
         let sessions: Arc<RwLock<HashMap<String, MediaHlsSession>>> =
             Arc::new(RwLock::new(HashMap::new()));
         // Should not panic on nonexistent session
-        stop_session("nonexistent", &sessions).await;
+        assert!(stop_session("nonexistent", &sessions).await.is_none());
     }
 
     #[tokio::test]
@@ -447,6 +608,6 @@ mod tests {
         let sessions: Arc<RwLock<HashMap<String, MediaHlsSession>>> =
             Arc::new(RwLock::new(HashMap::new()));
         // Should not panic on empty sessions
-        cleanup_stale_sessions(&sessions).await;
+        assert!(cleanup_stale_sessions(&sessions).await.is_empty());
     }
 }
@@ -1,9 +1,13 @@
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub mod array_tools;
+pub mod audio_fingerprint;
+pub mod audiobook;
 pub mod auth;
+pub mod clip;
 pub mod convert;
 pub mod encryption;
+pub mod face_index;
 pub mod file_tools;
 pub mod http_tools;
 pub mod image_tools;
@@ -12,6 +16,7 @@ pub mod prediction;
 pub mod recognition;
 pub mod scheduler;
 pub mod serialization;
+pub mod serie_gaps;
 pub mod video_tools;
 
 pub mod text_tools;
@@ -19,12 +24,27 @@ pub mod text_tools;
 pub mod serialization_tools;
 
 pub mod clock;
+pub mod comic_archive;
+pub mod comicinfo_parser;
 pub mod compression;
 pub mod download_external_libs;
+pub mod dvr;
+pub mod hdhomerun;
 pub mod hls_session;
+pub mod iptv_export;
+pub mod iptv_health;
+pub mod kosync;
 pub mod m3u_parser;
 pub mod media_hls_session;
+pub mod ocr;
+pub mod opds;
+pub mod opf_parser;
+pub mod pdf_tools;
 pub mod test_sample;
+pub mod trickplay;
+pub mod xml_tools;
+pub mod xmltv_parser;
+pub mod xtream;
 pub mod zip_range;
 
 pub fn get_time() -> Duration {
@@ -0,0 +1,100 @@
+use std::{process::Stdio, sync::OnceLock};
+
+use tokio::{io::AsyncWriteExt, process::Command};
+use which::which;
+
+use crate::error::{RsError, RsResult};
+
+static OCR_ENGINE: OnceLock<Option<OcrEngine>> = OnceLock::new();
+
+/// External program used to recognize the text of images
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OcrEngine {
+    /// `tesseract` command line
+    Tesseract,
+}
+
+impl OcrEngine {
+    /// Engine found on the first call, kept for the lifetime of the server
+    pub fn detect() -> Option<Self> {
+        *OCR_ENGINE.get_or_init(|| {
+            if which("tesseract").is_ok() {
+                Some(OcrEngine::Tesseract)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+pub fn has_ocr_engine() -> bool {
+    OcrEngine::detect().is_some()
+}
+
+/// Text of an image, `None` when nothing readable was found
+pub async fn recognize_text(image: Vec<u8>, languages: Option<&str>) -> RsResult<Option<String>> {
+    let engine = OcrEngine::detect().ok_or(RsError::Error(
+        "No OCR engine found (install tesseract-ocr)".to_string(),
+    ))?;
+    let mut cmd = match engine {
+        OcrEngine::Tesseract => {
+            let mut cmd = Command::new("tesseract");
+            cmd.arg("stdin").arg("stdout");
+            if let Some(languages) = languages {
+                cmd.arg("-l").arg(languages);
+            }
+            cmd
+        }
+    };
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or(RsError::Error("Unable to write to OCR engine".to_string()))?;
+    let writing = tokio::spawn(async move {
+        let _ = stdin.write_all(&image).await;
+    });
+    let output = child.wait_with_output().await?;
+    let _ = writing.await;
+    if !output.status.success() {
+        return Err(RsError::Error(format!(
+            "OCR engine failed ({})",
+            output.status
+        )));
+    }
+    Ok(clean_ocr_text(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Join recognized lines with single spaces, dropping the lines without any letter or
+/// digit that engines output for noise and borders
+pub fn clean_ocr_text(raw: &str) -> Option<String> {
+    let text = raw
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| line.chars().any(|c| c.is_alphanumeric()))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_recognized_text() {
+        assert_eq!(
+            clean_ocr_text("TOTAL   12.50 EUR\n\n |  ~ \nThank  you\n\u{c}"),
+            Some("TOTAL 12.50 EUR Thank you".to_string())
+        );
+        assert_eq!(clean_ocr_text(" \n -- \n\u{c}"), None);
+    }
+}
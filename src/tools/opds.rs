@@ -0,0 +1,519 @@
+use chrono::DateTime;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::text_tools::escape_xml;
+
+pub const OPDS_PAGE_SIZE: usize = 50;
+pub const ATOM_NAVIGATION_TYPE: &str = "application/atom+xml;profile=opds-catalog;kind=navigation";
+pub const ATOM_ACQUISITION_TYPE: &str =
+    "application/atom+xml;profile=opds-catalog;kind=acquisition";
+pub const OPDS2_TYPE: &str = "application/opds+json";
+pub const OPENSEARCH_TYPE: &str = "application/opensearchdescription+xml";
+pub const ACQUISITION_REL: &str = "http://opds-spec.org/acquisition";
+const IMAGE_REL: &str = "http://opds-spec.org/image";
+const THUMBNAIL_REL: &str = "http://opds-spec.org/image/thumbnail";
+
+/// OPDS 1.2 (Atom) or OPDS 2.0 (JSON) catalog
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OpdsVersion {
+    V1,
+    V2,
+}
+
+impl OpdsVersion {
+    pub fn path(&self) -> &'static str {
+        match self {
+            OpdsVersion::V1 => "v1",
+            OpdsVersion::V2 => "v2",
+        }
+    }
+
+    fn feed_type(&self, acquisition: bool) -> &'static str {
+        match (self, acquisition) {
+            (OpdsVersion::V1, false) => ATOM_NAVIGATION_TYPE,
+            (OpdsVersion::V1, true) => ATOM_ACQUISITION_TYPE,
+            (OpdsVersion::V2, _) => OPDS2_TYPE,
+        }
+    }
+}
+
+/// Builds the urls of a library catalog, carrying the share token of the request if any
+#[derive(Debug, Clone)]
+pub struct OpdsUrls {
+    pub base_url: String,
+    pub library_id: String,
+    pub version: OpdsVersion,
+    pub sharetoken: Option<String>,
+}
+
+impl OpdsUrls {
+    /// Url of a library path such as `books/{id}/image`
+    pub fn library(&self, path: &str) -> String {
+        self.with_token(format!(
+            "{}/libraries/{}/{}",
+            self.base_url, self.library_id, path
+        ))
+    }
+
+    /// Url of a catalog feed such as `recent`, empty for the root
+    pub fn feed(&self, path: &str) -> String {
+        let path = if path.is_empty() {
+            self.version.path().to_string()
+        } else {
+            format!("{}/{}", self.version.path(), path)
+        };
+        self.library(&format!("opds/{}", path))
+    }
+
+    /// Search link of the feeds: the OpenSearch description for OPDS 1.2,
+    /// a url template for OPDS 2.0
+    pub fn search(&self) -> String {
+        match self.version {
+            OpdsVersion::V1 => self.feed("opensearch.xml"),
+            OpdsVersion::V2 => self.search_template("{?query}", "{&query}"),
+        }
+    }
+
+    /// Search url template of the OpenSearch description
+    pub fn opensearch_template(&self) -> String {
+        self.search_template("?query={searchTerms}", "&query={searchTerms}")
+    }
+
+    fn search_template(&self, first: &str, next: &str) -> String {
+        let url = self.feed("search");
+        let template = if url.contains('?') { next } else { first };
+        format!("{}{}", url, template)
+    }
+
+    fn with_token(&self, url: String) -> String {
+        match &self.sharetoken {
+            Some(token) => {
+                let separator = if url.contains('?') { '&' } else { '?' };
+                format!(
+                    "{}{}sharetoken={}",
+                    url,
+                    separator,
+                    urlencoding::encode(token)
+                )
+            }
+            None => url,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpdsLink {
+    pub rel: String,
+    pub href: String,
+    pub kind: String,
+    pub title: Option<String>,
+    pub length: Option<u64>,
+}
+
+impl OpdsLink {
+    pub fn new(rel: &str, href: String, kind: &str) -> Self {
+        OpdsLink {
+            rel: rel.to_string(),
+            href,
+            kind: kind.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Entry of a navigation feed leading to another feed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpdsNavigation {
+    pub id: String,
+    pub title: String,
+    pub href: String,
+    pub content: Option<String>,
+    /// The target lists publications
+    pub acquisition: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpdsPublication {
+    pub id: String,
+    pub title: String,
+    /// Unix milliseconds
+    pub updated: i64,
+    pub summary: Option<String>,
+    pub language: Option<String>,
+    pub issued: Option<String>,
+    pub isbn: Option<String>,
+    pub authors: Vec<String>,
+    pub series: Option<String>,
+    pub position: Option<f64>,
+    pub cover: Option<String>,
+    pub thumbnail: Option<String>,
+    pub acquisitions: Vec<OpdsLink>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpdsFeed {
+    pub id: String,
+    pub title: String,
+    /// Unix milliseconds
+    pub updated: i64,
+    pub self_href: String,
+    pub start_href: String,
+    pub search_href: Option<String>,
+    pub next_href: Option<String>,
+    pub previous_href: Option<String>,
+    pub navigation: Vec<OpdsNavigation>,
+    pub publications: Vec<OpdsPublication>,
+}
+
+impl OpdsFeed {
+    fn is_acquisition(&self) -> bool {
+        self.navigation.is_empty()
+    }
+
+    /// Render as an OPDS 1.2 Atom feed
+    pub fn to_atom(&self) -> String {
+        let version = OpdsVersion::V1;
+        let mut feed = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\" xmlns:dc=\"http://purl.org/dc/terms/\" xmlns:opds=\"http://opds-spec.org/2010/catalog\">\n");
+        feed.push_str(&format!(
+            "  <id>{}</id>\n  <title>{}</title>\n  <updated>{}</updated>\n",
+            escape_xml(&self.id),
+            escape_xml(&self.title),
+            format_atom_time(self.updated)
+        ));
+        for link in self.links(version) {
+            feed.push_str(&format!("  {}\n", atom_link(&link)));
+        }
+        for navigation in &self.navigation {
+            feed.push_str(&format!(
+                "  <entry>\n    <title>{}</title>\n    <id>{}</id>\n    <updated>{}</updated>\n",
+                escape_xml(&navigation.title),
+                escape_xml(&navigation.id),
+                format_atom_time(self.updated)
+            ));
+            if let Some(content) = &navigation.content {
+                feed.push_str(&format!(
+                    "    <content type=\"text\">{}</content>\n",
+                    escape_xml(content)
+                ));
+            }
+            let link = OpdsLink::new(
+                "subsection",
+                navigation.href.clone(),
+                version.feed_type(navigation.acquisition),
+            );
+            feed.push_str(&format!("    {}\n  </entry>\n", atom_link(&link)));
+        }
+        for publication in &self.publications {
+            feed.push_str(&format!(
+                "  <entry>\n    <title>{}</title>\n    <id>{}</id>\n    <updated>{}</updated>\n",
+                escape_xml(&publication.title),
+                escape_xml(&publication.id),
+                format_atom_time(publication.updated)
+            ));
+            for author in &publication.authors {
+                feed.push_str(&format!(
+                    "    <author><name>{}</name></author>\n",
+                    escape_xml(author)
+                ));
+            }
+            if let Some(language) = &publication.language {
+                feed.push_str(&format!(
+                    "    <dc:language>{}</dc:language>\n",
+                    escape_xml(language)
+                ));
+            }
+            if let Some(issued) = &publication.issued {
+                feed.push_str(&format!(
+                    "    <dc:issued>{}</dc:issued>\n",
+                    escape_xml(issued)
+                ));
+            }
+            if let Some(isbn) = &publication.isbn {
+                feed.push_str(&format!(
+                    "    <dc:identifier>urn:isbn:{}</dc:identifier>\n",
+                    escape_xml(isbn)
+                ));
+            }
+            if let Some(series) = &publication.series {
+                let position = publication
+                    .position
+                    .map(|p| format!(" #{}", p))
+                    .unwrap_or_default();
+                feed.push_str(&format!(
+                    "    <category term=\"{0}\" label=\"{0}{1}\" />\n",
+                    escape_xml(series),
+                    position
+                ));
+            }
+            if let Some(summary) = &publication.summary {
+                feed.push_str(&format!(
+                    "    <summary type=\"text\">{}</summary>\n",
+                    escape_xml(summary)
+                ));
+            }
+            for link in publication_links(publication) {
+                feed.push_str(&format!("    {}\n", atom_link(&link)));
+            }
+            feed.push_str("  </entry>\n");
+        }
+        feed.push_str("</feed>\n");
+        feed
+    }
+
+    /// Render as an OPDS 2.0 JSON feed
+    pub fn to_json(&self) -> Value {
+        let version = OpdsVersion::V2;
+        let links: Vec<Value> = self.links(version).iter().map(json_link).collect();
+        let mut feed = json!({
+            "metadata": {
+                "title": self.title,
+                "modified": format_atom_time(self.updated),
+            },
+            "links": links,
+        });
+        if !self.navigation.is_empty() {
+            feed["navigation"] = self
+                .navigation
+                .iter()
+                .map(|navigation| {
+                    json!({
+                        "href": navigation.href,
+                        "title": navigation.title,
+                        "type": OPDS2_TYPE,
+                        "rel": "subsection",
+                    })
+                })
+                .collect();
+        }
+        if self.is_acquisition() {
+            feed["publications"] = self.publications.iter().map(json_publication).collect();
+        }
+        feed
+    }
+
+    fn links(&self, version: OpdsVersion) -> Vec<OpdsLink> {
+        let feed_type = version.feed_type(self.is_acquisition());
+        let mut links = vec![
+            OpdsLink::new("self", self.self_href.clone(), feed_type),
+            OpdsLink::new("start", self.start_href.clone(), version.feed_type(false)),
+        ];
+        if let Some(search) = &self.search_href {
+            links.push(match version {
+                OpdsVersion::V1 => OpdsLink::new("search", search.clone(), OPENSEARCH_TYPE),
+                OpdsVersion::V2 => OpdsLink::new("search", search.clone(), OPDS2_TYPE),
+            });
+        }
+        if let Some(next) = &self.next_href {
+            links.push(OpdsLink::new("next", next.clone(), feed_type));
+        }
+        if let Some(previous) = &self.previous_href {
+            links.push(OpdsLink::new("previous", previous.clone(), feed_type));
+        }
+        links
+    }
+}
+
+fn publication_links(publication: &OpdsPublication) -> Vec<OpdsLink> {
+    let mut links = vec![];
+    if let Some(cover) = &publication.cover {
+        links.push(OpdsLink::new(IMAGE_REL, cover.clone(), "image/jpeg"));
+    }
+    if let Some(thumbnail) = &publication.thumbnail {
+        links.push(OpdsLink::new(
+            THUMBNAIL_REL,
+            thumbnail.clone(),
+            "image/jpeg",
+        ));
+    }
+    links.extend(publication.acquisitions.iter().cloned());
+    links
+}
+
+fn atom_link(link: &OpdsLink) -> String {
+    let mut attributes = format!(
+        "rel=\"{}\" href=\"{}\" type=\"{}\"",
+        escape_xml(&link.rel),
+        escape_xml(&link.href),
+        escape_xml(&link.kind)
+    );
+    if let Some(title) = &link.title {
+        attributes.push_str(&format!(" title=\"{}\"", escape_xml(title)));
+    }
+    if let Some(length) = link.length {
+        attributes.push_str(&format!(" length=\"{}\"", length));
+    }
+    format!("<link {} />", attributes)
+}
+
+fn json_link(link: &OpdsLink) -> Value {
+    let mut value = json!({
+        "rel": link.rel,
+        "href": link.href,
+        "type": link.kind,
+    });
+    if link.href.contains('{') {
+        value["templated"] = json!(true);
+    }
+    if let Some(title) = &link.title {
+        value["title"] = json!(title);
+    }
+    if let Some(length) = link.length {
+        value["properties"] = json!({ "length": length });
+    }
+    value
+}
+
+fn json_publication(publication: &OpdsPublication) -> Value {
+    let mut metadata = json!({
+        "@type": "http://schema.org/Book",
+        "title": publication.title,
+        "identifier": publication
+            .isbn
+            .as_ref()
+            .map(|isbn| format!("urn:isbn:{}", isbn))
+            .unwrap_or_else(|| publication.id.clone()),
+        "modified": format_atom_time(publication.updated),
+    });
+    if !publication.authors.is_empty() {
+        metadata["author"] = json!(publication.authors);
+    }
+    if let Some(language) = &publication.language {
+        metadata["language"] = json!(language);
+    }
+    if let Some(issued) = &publication.issued {
+        metadata["published"] = json!(issued);
+    }
+    if let Some(summary) = &publication.summary {
+        metadata["description"] = json!(summary);
+    }
+    if let Some(series) = &publication.series {
+        let mut serie = json!({ "name": series });
+        if let Some(position) = publication.position {
+            serie["position"] = json!(position);
+        }
+        metadata["belongsTo"] = json!({ "series": [serie] });
+    }
+    let images: Vec<Value> = publication
+        .cover
+        .iter()
+        .chain(publication.thumbnail.iter())
+        .map(|href| json!({ "href": href, "type": "image/jpeg" }))
+        .collect();
+    json!({
+        "metadata": metadata,
+        "links": publication.acquisitions.iter().map(json_link).collect::<Vec<Value>>(),
+        "images": images,
+    })
+}
+
+/// OpenSearch description pointing to `template`, where `{searchTerms}` is the query
+pub fn opensearch_description(title: &str, template: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<OpenSearchDescription xmlns=\"http://a9.com/-/spec/opensearch/1.1/\">\n  <ShortName>{}</ShortName>\n  <Description>Search {}</Description>\n  <InputEncoding>UTF-8</InputEncoding>\n  <OutputEncoding>UTF-8</OutputEncoding>\n  <Url type=\"{}\" template=\"{}\" />\n</OpenSearchDescription>\n",
+        escape_xml(title),
+        escape_xml(title),
+        ATOM_ACQUISITION_TYPE,
+        escape_xml(template)
+    )
+}
+
+/// RFC 3339 time of a timestamp in milliseconds
+pub fn format_atom_time(millis: i64) -> String {
+    DateTime::from_timestamp_millis(millis)
+        .unwrap_or_default()
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed() -> OpdsFeed {
+        let urls = OpdsUrls {
+            base_url: "http://host".to_string(),
+            library_id: "lib".to_string(),
+            version: OpdsVersion::V1,
+            sharetoken: Some("t".to_string()),
+        };
+        OpdsFeed {
+            id: "urn:redseat:lib:recent".to_string(),
+            title: "Recently added".to_string(),
+            updated: 1_706_733_000_000,
+            self_href: urls.feed("recent?page=1"),
+            start_href: urls.feed(""),
+            search_href: Some(urls.feed("opensearch.xml")),
+            next_href: Some(urls.feed("recent?page=2")),
+            publications: vec![OpdsPublication {
+                id: "urn:redseat:book:b1".to_string(),
+                title: "Dune & co".to_string(),
+                updated: 1_706_733_000_000,
+                authors: vec!["Frank Herbert".to_string()],
+                series: Some("Dune".to_string()),
+                position: Some(1.0),
+                cover: Some(urls.library("books/b1/image")),
+                acquisitions: vec![OpdsLink {
+                    length: Some(1024),
+                    ..OpdsLink::new(
+                        ACQUISITION_REL,
+                        urls.library("medias/m1"),
+                        "application/epub+zip",
+                    )
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn urls_carry_the_share_token() {
+        let feed = feed();
+        assert_eq!(
+            feed.self_href,
+            "http://host/libraries/lib/opds/v1/recent?page=1&sharetoken=t"
+        );
+        assert_eq!(
+            feed.start_href,
+            "http://host/libraries/lib/opds/v1?sharetoken=t"
+        );
+        let urls = OpdsUrls {
+            base_url: "http://host".to_string(),
+            library_id: "lib".to_string(),
+            version: OpdsVersion::V2,
+            sharetoken: None,
+        };
+        assert_eq!(
+            urls.search(),
+            "http://host/libraries/lib/opds/v2/search{?query}"
+        );
+    }
+
+    #[test]
+    fn atom_acquisition_feed() {
+        let atom = feed().to_atom();
+        assert!(atom.contains("<title>Dune &amp; co</title>"));
+        assert!(atom.contains("<updated>2024-01-31T20:30:00Z</updated>"));
+        assert!(atom.contains(&format!(
+            "<link rel=\"next\" href=\"http://host/libraries/lib/opds/v1/recent?page=2&amp;sharetoken=t\" type=\"{}\" />",
+            ATOM_ACQUISITION_TYPE
+        )));
+        assert!(atom.contains("<link rel=\"http://opds-spec.org/acquisition\" href=\"http://host/libraries/lib/medias/m1?sharetoken=t\" type=\"application/epub+zip\" length=\"1024\" />"));
+    }
+
+    #[test]
+    fn json_acquisition_feed() {
+        let json = feed().to_json();
+        let publication = &json["publications"][0];
+        assert_eq!(publication["metadata"]["title"], "Dune & co");
+        assert_eq!(
+            publication["metadata"]["belongsTo"]["series"][0]["position"],
+            1.0
+        );
+        assert_eq!(publication["links"][0]["type"], "application/epub+zip");
+        assert_eq!(publication["links"][0]["properties"]["length"], 1024);
+        assert!(json.get("navigation").is_none());
+    }
+}
@@ -0,0 +1,244 @@
+use std::io::{Read, Seek};
+
+use crate::error::{RsError, RsResult};
+
+use super::xml_tools::{attribute, elements, first_text};
+
+pub const EPUB_MIME: &str = "application/epub+zip";
+const CONTAINER_PATH: &str = "META-INF/container.xml";
+
+/// Metadata of an EPUB package document (OPF)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpfMetadata {
+    pub title: Option<String>,
+    /// Creators with an author role, or without role
+    pub authors: Vec<String>,
+    pub isbn13: Option<String>,
+    pub language: Option<String>,
+    pub publisher: Option<String>,
+    pub description: Option<String>,
+    pub year: Option<u16>,
+    pub series: Option<String>,
+    pub series_index: Option<f64>,
+    /// Issue number, for comics
+    pub chapter: Option<f64>,
+}
+
+/// Read the OPF metadata of an EPUB archive
+pub fn read_epub_metadata<R: Read + Seek>(reader: R) -> RsResult<OpfMetadata> {
+    let mut archive = zip::ZipArchive::new(reader)
+        .map_err(|_| RsError::Error("Unable to open epub as zip".to_string()))?;
+    let container = read_entry(&mut archive, CONTAINER_PATH)?;
+    let opf_path = rootfile_path(&container)
+        .ok_or(RsError::Error("No package document in epub".to_string()))?;
+    let opf = read_entry(&mut archive, &opf_path)?;
+    Ok(parse_opf(&opf))
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut zip::ZipArchive<R>, path: &str) -> RsResult<String> {
+    let mut entry = archive
+        .by_name(path)
+        .map_err(|_| RsError::Error(format!("Unable to find {} in epub", path)))?;
+    let mut content = String::new();
+    entry.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// Path of the package document declared in `META-INF/container.xml`
+pub fn rootfile_path(container: &str) -> Option<String> {
+    elements(container, "rootfile")
+        .into_iter()
+        .find(|(tag, _)| {
+            attribute(tag, "media-type")
+                .map(|t| t == "application/oebps-package+xml")
+                .unwrap_or(true)
+        })
+        .and_then(|(tag, _)| attribute(tag, "full-path"))
+}
+
+pub fn parse_opf(content: &str) -> OpfMetadata {
+    let metadata = content
+        .find("<metadata")
+        .map(|start| &content[start..])
+        .unwrap_or(content);
+    let metas = elements(metadata, "meta");
+    // EPUB 3 refinements: `<meta refines="#id" property="role">aut</meta>`
+    let refinement = |id: Option<String>, property: &str| -> Option<String> {
+        let id = format!("#{}", id?);
+        metas
+            .iter()
+            .find(|(tag, _)| {
+                attribute(tag, "refines").as_deref() == Some(id.as_str())
+                    && attribute(tag, "property").as_deref() == Some(property)
+            })
+            .map(|(_, text)| text.clone())
+    };
+    // EPUB 2 and calibre: `<meta name="calibre:series" content="..." />`
+    let named = |name: &str| -> Option<String> {
+        metas
+            .iter()
+            .find(|(tag, _)| attribute(tag, "name").as_deref() == Some(name))
+            .and_then(|(tag, _)| attribute(tag, "content"))
+            .filter(|content| !content.trim().is_empty())
+    };
+
+    let authors = elements(metadata, "dc:creator")
+        .into_iter()
+        .filter(|(tag, _)| {
+            let role = attribute(tag, "opf:role")
+                .or_else(|| attribute(tag, "role"))
+                .or_else(|| refinement(attribute(tag, "id"), "role"));
+            role.map(|r| r.trim() == "aut").unwrap_or(true)
+        })
+        .map(|(_, name)| name)
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    let isbn13 = elements(metadata, "dc:identifier")
+        .into_iter()
+        .filter_map(|(_, identifier)| parse_isbn13(&identifier))
+        .next();
+
+    let collection = metas.iter().find(|(tag, _)| {
+        attribute(tag, "property").as_deref() == Some("belongs-to-collection")
+            && refinement(attribute(tag, "id"), "collection-type")
+                .map(|kind| kind == "series")
+                .unwrap_or(true)
+    });
+    let (series, series_index) = match collection {
+        Some((tag, name)) if !name.is_empty() => (
+            Some(name.clone()),
+            refinement(attribute(tag, "id"), "group-position"),
+        ),
+        _ => (named("calibre:series"), named("calibre:series_index")),
+    };
+
+    OpfMetadata {
+        title: first_text(metadata, "dc:title"),
+        authors,
+        isbn13,
+        language: first_text(metadata, "dc:language"),
+        publisher: first_text(metadata, "dc:publisher"),
+        description: first_text(metadata, "dc:description")
+            .map(|d| strip_html(&d))
+            .filter(|d| !d.is_empty()),
+        year: first_text(metadata, "dc:date").and_then(|date| date.get(..4)?.parse().ok()),
+        series,
+        series_index: series_index.and_then(|index| index.trim().parse().ok()),
+        chapter: None,
+    }
+}
+
+/// ISBN-13 of an identifier like `urn:isbn:978-2-07-036822-8`, converting ISBN-10
+pub fn parse_isbn13(identifier: &str) -> Option<String> {
+    let value = identifier.trim();
+    let value = value
+        .get(..9)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("urn:isbn:"))
+        .map(|_| &value[9..])
+        .unwrap_or(value);
+    let digits: String = value.chars().filter(|c| !matches!(c, '-' | ' ')).collect();
+    if !digits.is_ascii() {
+        return None;
+    }
+    match digits.len() {
+        13 if digits.chars().all(|c| c.is_ascii_digit())
+            && (digits.starts_with("978") || digits.starts_with("979")) =>
+        {
+            Some(digits)
+        }
+        10 if digits[..9].chars().all(|c| c.is_ascii_digit())
+            && matches!(digits.chars().last(), Some('0'..='9' | 'X' | 'x')) =>
+        {
+            let base = format!("978{}", &digits[..9]);
+            let sum: u32 = base
+                .chars()
+                .filter_map(|c| c.to_digit(10))
+                .enumerate()
+                .map(|(i, d)| if i % 2 == 0 { d } else { d * 3 })
+                .sum();
+            Some(format!("{}{}", base, (10 - sum % 10) % 10))
+        }
+        _ => None,
+    }
+}
+
+/// Descriptions are often escaped HTML
+fn strip_html(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut in_tag = false;
+    for c in value.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_epub3_and_calibre_metadata() {
+        let container = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+</container>"#;
+        assert_eq!(
+            rootfile_path(container).as_deref(),
+            Some("OEBPS/content.opf")
+        );
+
+        let opf = r##"<package version="3.0" xmlns="http://www.idpf.org/2007/opf">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>L&apos;Étranger</dc:title>
+    <dc:creator id="c1">Albert Camus</dc:creator>
+    <meta refines="#c1" property="role" scheme="marc:relators">aut</meta>
+    <dc:creator id="c2">Jean Dupont</dc:creator>
+    <meta refines="#c2" property="role" scheme="marc:relators">trl</meta>
+    <dc:identifier id="uid">urn:uuid:1234</dc:identifier>
+    <dc:identifier>urn:isbn:2-07-036002-4</dc:identifier>
+    <dc:language>fr</dc:language>
+    <dc:publisher>Gallimard</dc:publisher>
+    <dc:date>1942-05-19</dc:date>
+    <dc:description>&lt;p&gt;Aujourd'hui, maman est morte.&lt;/p&gt;</dc:description>
+    <meta property="belongs-to-collection" id="s1">Cycle de l'absurde</meta>
+    <meta refines="#s1" property="collection-type">series</meta>
+    <meta refines="#s1" property="group-position">1</meta>
+  </metadata>
+</package>"##;
+        let metadata = parse_opf(opf);
+        assert_eq!(metadata.title.as_deref(), Some("L'Étranger"));
+        assert_eq!(metadata.authors, vec!["Albert Camus".to_string()]);
+        assert_eq!(metadata.isbn13.as_deref(), Some("9782070360024"));
+        assert_eq!(metadata.language.as_deref(), Some("fr"));
+        assert_eq!(metadata.publisher.as_deref(), Some("Gallimard"));
+        assert_eq!(metadata.year, Some(1942));
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("Aujourd'hui, maman est morte.")
+        );
+        assert_eq!(metadata.series.as_deref(), Some("Cycle de l'absurde"));
+        assert_eq!(metadata.series_index, Some(1.0));
+
+        let calibre = r#"<package version="2.0"><metadata>
+    <dc:title>Dune</dc:title>
+    <dc:creator opf:role="aut" opf:file-as="Herbert, Frank">Frank Herbert</dc:creator>
+    <dc:identifier opf:scheme="ISBN">9780441013593</dc:identifier>
+    <meta name="calibre:series" content="Dune"/>
+    <meta name="calibre:series_index" content="1.0"/>
+</metadata></package>"#;
+        let metadata = parse_opf(calibre);
+        assert_eq!(metadata.authors, vec!["Frank Herbert".to_string()]);
+        assert_eq!(metadata.isbn13.as_deref(), Some("9780441013593"));
+        assert_eq!(metadata.series.as_deref(), Some("Dune"));
+        assert_eq!(metadata.series_index, Some(1.0));
+    }
+}
@@ -0,0 +1,135 @@
+use std::{path::Path, process::Stdio, sync::OnceLock};
+
+use tokio::process::Command;
+use which::which;
+
+use crate::error::{RsError, RsResult};
+
+pub const PDF_MIME: &str = "application/pdf";
+
+static PDF_RENDERER: OnceLock<Option<PdfRenderer>> = OnceLock::new();
+
+/// External program used to read and render PDF documents
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PdfRenderer {
+    /// `pdfinfo` and `pdftoppm` from poppler-utils
+    Poppler,
+    /// `mutool` from MuPDF
+    Mupdf,
+}
+
+impl PdfRenderer {
+    /// Renderer found on the first call, kept for the lifetime of the server
+    pub fn detect() -> Option<Self> {
+        *PDF_RENDERER.get_or_init(|| {
+            if which("pdftoppm").is_ok() && which("pdfinfo").is_ok() {
+                Some(PdfRenderer::Poppler)
+            } else if which("mutool").is_ok() {
+                Some(PdfRenderer::Mupdf)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+pub fn has_pdf_renderer() -> bool {
+    PdfRenderer::detect().is_some()
+}
+
+fn renderer() -> RsResult<PdfRenderer> {
+    PdfRenderer::detect().ok_or(RsError::Error(
+        "No PDF renderer found (install poppler-utils or mupdf-tools)".to_string(),
+    ))
+}
+
+async fn run(mut cmd: Command) -> RsResult<Vec<u8>> {
+    let output = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .await?;
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(RsError::Error(format!(
+            "PDF renderer failed ({})",
+            output.status
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Number of pages of the PDF stored at `path`
+pub async fn pdf_page_count(path: &Path) -> RsResult<usize> {
+    let cmd = match renderer()? {
+        PdfRenderer::Poppler => {
+            let mut cmd = Command::new("pdfinfo");
+            cmd.arg(path);
+            cmd
+        }
+        PdfRenderer::Mupdf => {
+            let mut cmd = Command::new("mutool");
+            cmd.arg("info").arg(path);
+            cmd
+        }
+    };
+    let info = run(cmd).await?;
+    parse_page_count(&String::from_utf8_lossy(&info))
+        .ok_or(RsError::Error("Unable to read PDF page count".to_string()))
+}
+
+/// Page count from the `Pages: N` line printed by `pdfinfo` and `mutool info`
+pub fn parse_page_count(info: &str) -> Option<usize> {
+    info.lines()
+        .find_map(|line| line.trim().strip_prefix("Pages:")?.trim().parse().ok())
+}
+
+/// Render a page (starting at 1) of the PDF stored at `path` as a PNG of `width` pixels
+pub async fn render_pdf_page(path: &Path, page: usize, width: u32) -> RsResult<Vec<u8>> {
+    let page = page.max(1).to_string();
+    let cmd = match renderer()? {
+        PdfRenderer::Poppler => {
+            let mut cmd = Command::new("pdftoppm");
+            cmd.arg("-f")
+                .arg(&page)
+                .arg("-l")
+                .arg(&page)
+                .arg("-singlefile")
+                .arg("-png")
+                .arg("-scale-to-x")
+                .arg(width.to_string())
+                .arg("-scale-to-y")
+                .arg("-1")
+                .arg(path);
+            cmd
+        }
+        PdfRenderer::Mupdf => {
+            let mut cmd = Command::new("mutool");
+            cmd.arg("draw")
+                .arg("-q")
+                .arg("-F")
+                .arg("png")
+                .arg("-o")
+                .arg("-")
+                .arg("-w")
+                .arg(width.to_string())
+                .arg(path)
+                .arg(&page);
+            cmd
+        }
+    };
+    run(cmd).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_page_count() {
+        let pdfinfo = "Title:          Dune\nProducer:       calibre\nPages:          412\nEncrypted:      no\n";
+        assert_eq!(parse_page_count(pdfinfo), Some(412));
+        let mutool = "PDF-1.4\nInfo object (12 0 R):\n<</Producer(calibre)>>\nPages: 3\n";
+        assert_eq!(parse_page_count(mutool), Some(3));
+        assert_eq!(parse_page_count("Encrypted: no"), None);
+    }
+}
@@ -6,7 +6,13 @@ use std::{
     str::FromStr,
 };
 
-use crate::{Error, Result};
+use crate::{
+    domain::tagging_model::{
+        RsLabelFormat, RsPixelNormalization, RsTaggingModelSettings, RsTaggingOutput,
+        RsTensorLayout,
+    },
+    Error, Result,
+};
 use image::{
     imageops::{self, FilterType},
     DynamicImage, GenericImageView, ImageBuffer, ImageFormat, Rgb, Rgba,
@@ -334,6 +340,139 @@ impl PredictionTag {
     }
 }
 
+/// Labels of a registered tagging model, in the order of the model outputs
+pub fn parse_labels(content: &str, format: RsLabelFormat, model_id: &str) -> Vec<PredictionTag> {
+    match format {
+        RsLabelFormat::Wd => content
+            .lines()
+            .skip_while(|line| line.starts_with("tag_id"))
+            .enumerate()
+            .map(|(index, line)| {
+                let mut tag = PredictionTag::from_csv((index, line));
+                tag.id = format!(
+                    "{}:{}",
+                    model_id,
+                    line.split(',').next().unwrap_or_default()
+                );
+                tag
+            })
+            .collect(),
+        RsLabelFormat::Names => content
+            .lines()
+            .enumerate()
+            .map(|(index, line)| {
+                let mut names = line.split('|').map(|name| name.trim().to_string());
+                PredictionTag {
+                    index,
+                    id: format!("{}:{}", model_id, index),
+                    name: names.next().unwrap_or_default(),
+                    alts: names.collect(),
+                    kind: PredictionTagKind::Tag,
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Input tensor of a registered tagging model from a decoded image
+pub fn prepare_tagging_input(
+    image: &DynamicImage,
+    settings: &RsTaggingModelSettings,
+) -> Array4<f32> {
+    let size = settings.input_size;
+    let rgb = resize_center_crop(image, size, size).to_rgb8();
+    let size = size as usize;
+    let shape = match settings.layout {
+        RsTensorLayout::Nhwc => (1, size, size, 3),
+        RsTensorLayout::Nchw => (1, 3, size, size),
+    };
+    let mut input = Array4::<f32>::zeros(shape);
+    for (x, y, pixel) in rgb.enumerate_pixels() {
+        for channel in 0..3 {
+            let source = if settings.bgr { 2 - channel } else { channel };
+            let value = pixel[source] as f32;
+            let value = match settings.normalization {
+                RsPixelNormalization::None => value,
+                RsPixelNormalization::Unit => value / 255.0,
+                RsPixelNormalization::Centered => (value - 127.5) / 127.5,
+                RsPixelNormalization::ImageNet => {
+                    (value / 255.0 - IMAGENET_MEAN[channel]) / IMAGENET_STD[channel]
+                }
+            };
+            let (x, y) = (x as usize, y as usize);
+            match settings.layout {
+                RsTensorLayout::Nhwc => input[[0, y, x, channel]] = value,
+                RsTensorLayout::Nchw => input[[0, channel, y, x]] = value,
+            }
+        }
+    }
+    input
+}
+
+const IMAGENET_MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const IMAGENET_STD: [f32; 3] = [0.229, 0.224, 0.225];
+
+/// Probabilities from the model scores. Models exported without their final
+/// activation output logits, which are converted here
+pub fn activate_scores(scores: &[f32], output: RsTaggingOutput) -> Vec<f32> {
+    let probabilities = scores.iter().all(|s| (0.0..=1.0).contains(s));
+    match output {
+        RsTaggingOutput::Sigmoid if probabilities => scores.to_vec(),
+        RsTaggingOutput::Sigmoid => scores.iter().map(|s| 1.0 / (1.0 + (-s).exp())).collect(),
+        RsTaggingOutput::Softmax
+            if probabilities && (scores.iter().sum::<f32>() - 1.0).abs() < 0.01 =>
+        {
+            scores.to_vec()
+        }
+        RsTaggingOutput::Softmax => {
+            let max = scores.iter().copied().fold(f32::MIN, f32::max);
+            let exps: Vec<f32> = scores.iter().map(|s| (s - max).exp()).collect();
+            let sum: f32 = exps.iter().sum();
+            exps.iter().map(|e| e / sum).collect()
+        }
+    }
+}
+
+/// Labels with a probability over the threshold, best first, probability in percent
+pub fn select_tags(
+    probabilities: &[f32],
+    labels: &[PredictionTag],
+    threshold: f32,
+) -> Vec<PredictionTagResult> {
+    let mut tags: Vec<PredictionTagResult> = probabilities
+        .iter()
+        .zip(labels)
+        .filter(|(probability, _)| **probability >= threshold)
+        .map(|(probability, tag)| PredictionTagResult {
+            probability: probability * 100.0,
+            tag: tag.clone(),
+        })
+        .collect();
+    tags.sort_by(|a, b| b.probability.total_cmp(&a.probability));
+    tags
+}
+
+/// Tags of an image with a registered tagging model
+pub fn predict_tagging_model(
+    session: &mut Session,
+    settings: &RsTaggingModelSettings,
+    labels: &[PredictionTag],
+    buffer_image: &[u8],
+) -> Result<Vec<PredictionTagResult>> {
+    let image = image::load_from_memory(buffer_image)?;
+    let input = prepare_tagging_input(&image, settings);
+    let input_name = session
+        .inputs
+        .first()
+        .ok_or(Error::Error("Tagging model does not have inputs".into()))?
+        .name
+        .clone();
+    let outputs: SessionOutputs = session.run(inputs![input_name => Tensor::from_array(input)?])?;
+    let (_, scores) = outputs[0].try_extract_tensor::<f32>()?;
+    let probabilities = activate_scores(scores, settings.output);
+    Ok(select_tags(&probabilities, labels, settings.threshold))
+}
+
 fn mapping(path: PathBuf) -> Result<Vec<PredictionTag>> {
     if path.exists() {
         let tags: Vec<PredictionTag> = read_to_string(path)?
@@ -357,3 +496,68 @@ fn calculate_similarity(embedding1: &[f32], embedding2: &[f32]) -> f32 {
     let norm2: f32 = embedding2.iter().map(|a| a * a).sum::<f32>().sqrt();
     dot / (norm1 * norm2)
 }
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    #[test]
+    fn parse_label_formats() {
+        let wd = parse_labels(
+            "tag_id,name,category,count\n9999,long_hair,0,100\n1,hatsune_miku,4,50\n",
+            RsLabelFormat::Wd,
+            "wd",
+        );
+        assert_eq!(wd.len(), 2);
+        assert_eq!(wd[0].name, "long hair");
+        assert_eq!(wd[0].id, "wd:9999");
+        assert_eq!(wd[1].kind, PredictionTagKind::Character);
+
+        let names = parse_labels("cat | kitten\ndog", RsLabelFormat::Names, "pets");
+        assert_eq!(names[0].name, "cat");
+        assert_eq!(names[0].alts, vec!["kitten".to_string()]);
+        assert_eq!(names[1].id, "pets:1");
+    }
+
+    #[test]
+    fn prepare_input_layouts() {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(8, 8, Rgb([255, 0, 0])));
+        let mut settings = RsTaggingModelSettings {
+            input_size: 4,
+            bgr: true,
+            ..Default::default()
+        };
+        let input = prepare_tagging_input(&image, &settings);
+        assert_eq!(input.shape(), &[1, 4, 4, 3]);
+        assert_eq!(input[[0, 1, 1, 2]], 255.0);
+
+        settings.bgr = false;
+        settings.layout = RsTensorLayout::Nchw;
+        settings.normalization = RsPixelNormalization::Centered;
+        let input = prepare_tagging_input(&image, &settings);
+        assert_eq!(input.shape(), &[1, 3, 4, 4]);
+        assert_eq!(input[[0, 0, 1, 1]], 1.0);
+        assert_eq!(input[[0, 1, 1, 1]], -1.0);
+    }
+
+    #[test]
+    fn activate_and_select() {
+        let labels = parse_labels("a\nb\nc", RsLabelFormat::Names, "m");
+        let sigmoid = activate_scores(&[3.0, -3.0, 0.0], RsTaggingOutput::Sigmoid);
+        let tags = select_tags(&sigmoid, &labels, 0.5);
+        let names: Vec<&str> = tags.iter().map(|t| t.tag.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "c"]);
+
+        let softmax = activate_scores(&[1.0, 3.0, 1.0], RsTaggingOutput::Softmax);
+        assert!((softmax.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        let tags = select_tags(&softmax, &labels, 0.5);
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].tag.name, "b");
+        assert_eq!(
+            activate_scores(&[0.2, 0.7], RsTaggingOutput::Sigmoid),
+            vec![0.2, 0.7]
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::domain::people::FaceModel;
 use crate::error::{RsError, RsResult};
 use crate::tools::log::{log_info, LogServiceType};
 use futures::StreamExt;
@@ -17,6 +18,18 @@ use std::sync::{Arc, Mutex};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 
+/// Name and version stored with every face embedding. Bump the version when one of the
+/// `MODELS` changes so existing faces get re-embedded instead of mixed with the new ones
+pub const FACE_MODEL_NAME: &str = "buffalo_l";
+pub const FACE_MODEL_VERSION: u32 = 1;
+
+pub fn current_face_model() -> FaceModel {
+    FaceModel {
+        name: FACE_MODEL_NAME.to_string(),
+        version: FACE_MODEL_VERSION,
+    }
+}
+
 const MODELS: &[(&str, &str)] = &[
     (
         "det_10g.onnx", 
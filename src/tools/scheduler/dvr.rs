@@ -0,0 +1,34 @@
+use axum::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::library::LibraryType,
+    error::RsResult,
+    model::{users::ConnectedUser, ModelController},
+    tools::log::{log_error, LogServiceType},
+};
+
+use super::RsSchedulerTask;
+
+/// Plan, start and finalize DVR recordings of every IPTV library
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DvrTask {}
+
+#[async_trait]
+impl RsSchedulerTask for DvrTask {
+    async fn execute(&self, mc: ModelController) -> RsResult<()> {
+        let libraries = mc.get_libraries(&ConnectedUser::ServerAdmin).await?;
+        for library in libraries
+            .into_iter()
+            .filter(|l| l.kind == LibraryType::Iptv)
+        {
+            if let Err(error) = mc.dvr_tick(&library.id).await {
+                log_error(
+                    LogServiceType::Scheduler,
+                    format!("DVR tick failed for {}: {:#}", library.name, error),
+                );
+            }
+        }
+        Ok(())
+    }
+}
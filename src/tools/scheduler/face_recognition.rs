@@ -214,6 +214,16 @@ impl RsSchedulerTask for FaceRecognitionTask {
                 }
             }
 
+            if let Err(e) = mc.save_face_index(&library.id).await {
+                log_error(
+                    crate::tools::log::LogServiceType::Scheduler,
+                    format!(
+                        "Error saving face index for library {}: {:#}",
+                        library.name, e
+                    ),
+                );
+            }
+
             // Send completion message
             if total_count > 0 {
                 let message = format!(
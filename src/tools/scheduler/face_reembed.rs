@@ -0,0 +1,55 @@
+use axum::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::library::LibraryType,
+    error::RsResult,
+    model::{users::ConnectedUser, ModelController},
+    tools::log::{log_error, log_info, LogServiceType},
+};
+
+use super::RsSchedulerTask;
+
+/// Detect and embed again the faces produced by an older face model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FaceReembedTask {
+    pub specific_library: Option<String>,
+}
+
+#[async_trait]
+impl RsSchedulerTask for FaceReembedTask {
+    async fn execute(&self, mc: ModelController) -> RsResult<()> {
+        let user = ConnectedUser::ServerAdmin;
+        let libraries = mc.get_libraries(&user).await?;
+
+        let libraries: Vec<_> = libraries
+            .into_iter()
+            .filter(|l| l.kind != LibraryType::Iptv)
+            .filter(|l| {
+                self.specific_library
+                    .as_ref()
+                    .map(|id| l.id == *id)
+                    .unwrap_or(true)
+            })
+            .collect();
+
+        for library in libraries {
+            match mc.reembed_outdated_faces(&library.id, &user).await {
+                Ok(0) => {}
+                Ok(count) => log_info(
+                    LogServiceType::Scheduler,
+                    format!("Re-embedded {} faces in library {}", count, library.name),
+                ),
+                Err(e) => log_error(
+                    LogServiceType::Scheduler,
+                    format!(
+                        "Error re-embedding faces of library {}: {:#}",
+                        library.name, e
+                    ),
+                ),
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use axum::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::library::LibraryType,
+    error::RsResult,
+    model::{users::ConnectedUser, ModelController},
+    tools::log::{log_error, LogServiceType},
+};
+
+use super::RsSchedulerTask;
+
+/// Probe the channel variants of IPTV libraries so dead streams are skipped
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IptvHealthTask {
+    pub specific_library: Option<String>,
+}
+
+#[async_trait]
+impl RsSchedulerTask for IptvHealthTask {
+    async fn execute(&self, mc: ModelController) -> RsResult<()> {
+        let user = ConnectedUser::ServerAdmin;
+        let libraries = mc.get_libraries(&user).await?;
+        for library in libraries
+            .into_iter()
+            .filter(|l| l.kind == LibraryType::Iptv)
+            .filter(|l| {
+                self.specific_library
+                    .as_ref()
+                    .map(|id| l.id == *id)
+                    .unwrap_or(true)
+            })
+        {
+            if let Err(error) = mc.check_channel_variants(&library.id, &user).await {
+                log_error(
+                    LogServiceType::Scheduler,
+                    format!("IPTV health check failed for {}: {:#}", library.name, error),
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -33,13 +33,13 @@ impl RsSchedulerTask for IptvRefreshTask {
             .collect();
 
         for library in iptv_libraries {
-            // The M3U URL is stored in library.root
-            let has_m3u_url = library
+            // The M3U URL or the Xtream server is stored in library.root
+            let has_source_url = library
                 .root
                 .as_ref()
                 .map(|s| !s.is_empty())
                 .unwrap_or(false);
-            if !has_m3u_url {
+            if !has_source_url {
                 continue;
             }
 
@@ -48,8 +48,10 @@ impl RsSchedulerTask for IptvRefreshTask {
                 format!("Refreshing IPTV library: {} ({})", library.name, library.id),
             );
 
-            match mc.import_m3u(&library.id, None, &user).await {
+            let mut epg_fallback = None;
+            match mc.import_iptv(&library.id, &user).await {
                 Ok(result) => {
+                    epg_fallback = result.epg_url.clone();
                     log_info(
                         LogServiceType::Scheduler,
                         format!(
@@ -69,6 +71,21 @@ impl RsSchedulerTask for IptvRefreshTask {
                     );
                 }
             }
+
+            let has_epg_url = library
+                .settings
+                .as_ref()
+                .and_then(|s| s.epg_url.as_ref())
+                .map(|s| !s.is_empty())
+                .unwrap_or(false);
+            if has_epg_url || epg_fallback.is_some() {
+                if let Err(e) = mc.import_epg(&library.id, epg_fallback, &user).await {
+                    log_error(
+                        LogServiceType::Scheduler,
+                        format!("EPG refresh failed for {}: {:#}", library.name, e),
+                    );
+                }
+            }
         }
 
         Ok(())
@@ -0,0 +1,71 @@
+use axum::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::library::LibraryType,
+    error::RsResult,
+    model::{users::ConnectedUser, ModelController},
+    tools::log::{log_error, log_info, LogServiceType},
+};
+
+use super::RsSchedulerTask;
+
+/// Backfill KOReader document hashes of books and comics
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KosyncHashTask {
+    pub specific_library: Option<String>,
+}
+
+#[async_trait]
+impl RsSchedulerTask for KosyncHashTask {
+    async fn execute(&self, mc: ModelController) -> RsResult<()> {
+        let user = ConnectedUser::ServerAdmin;
+        let libraries = mc.get_libraries(&user).await?;
+
+        let libraries: Vec<_> = libraries
+            .into_iter()
+            .filter(|l| l.kind != LibraryType::Iptv)
+            .filter(|l| !l.crypt.unwrap_or(false))
+            .filter(|l| {
+                self.specific_library
+                    .as_ref()
+                    .map(|id| l.id == *id)
+                    .unwrap_or(true)
+            })
+            .collect();
+
+        for library in libraries {
+            let media_ids = match mc.get_medias_without_kohash(&library.id).await {
+                Ok(ids) => ids,
+                Err(e) => {
+                    log_error(
+                        LogServiceType::Scheduler,
+                        format!("Unable to list books of {}: {:#}", library.name, e),
+                    );
+                    continue;
+                }
+            };
+            if media_ids.is_empty() {
+                continue;
+            }
+            log_info(
+                LogServiceType::Scheduler,
+                format!(
+                    "Computing KOReader hashes for {} medias in library {}",
+                    media_ids.len(),
+                    library.name
+                ),
+            );
+            for media_id in media_ids {
+                if let Err(e) = mc.update_media_kohash(&library.id, &media_id, &user).await {
+                    log_error(
+                        LogServiceType::Scheduler,
+                        format!("Unable to compute KOReader hash of {}: {:#}", media_id, e),
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
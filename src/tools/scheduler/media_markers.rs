@@ -0,0 +1,94 @@
+use axum::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::library::{LibraryStatusMessage, LibraryType},
+    error::RsResult,
+    model::{users::ConnectedUser, ModelController},
+    tools::log::{log_error, log_info, LogServiceType},
+};
+
+use super::RsSchedulerTask;
+
+/// Detect intro and credits markers, season by season
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MediaMarkersTask {
+    pub specific_library: Option<String>,
+    /// Only analyze this season (serie id, season number), even if already analyzed
+    pub specific_season: Option<(String, u32)>,
+}
+
+#[async_trait]
+impl RsSchedulerTask for MediaMarkersTask {
+    async fn execute(&self, mc: ModelController) -> RsResult<()> {
+        let user = ConnectedUser::ServerAdmin;
+        let libraries = mc.get_libraries(&user).await?;
+
+        let libraries: Vec<_> = libraries
+            .into_iter()
+            .filter(|l| l.kind == LibraryType::Shows)
+            .filter(|l| !l.crypt.unwrap_or(false))
+            .filter(|l| {
+                self.specific_library
+                    .as_ref()
+                    .map(|id| l.id == *id)
+                    .unwrap_or(true)
+            })
+            .collect();
+
+        for library in libraries {
+            if mc.get_library_encryption_key(&library.id).await.is_some() {
+                continue;
+            }
+            let seasons = if let Some(season) = &self.specific_season {
+                vec![season.clone()]
+            } else {
+                match mc.get_seasons_without_markers(&library.id).await {
+                    Ok(seasons) => seasons,
+                    Err(e) => {
+                        log_error(
+                            LogServiceType::Scheduler,
+                            format!("Unable to list seasons of {}: {:#}", library.name, e),
+                        );
+                        continue;
+                    }
+                }
+            };
+            let total = seasons.len();
+            for (index, (serie_id, season)) in seasons.into_iter().enumerate() {
+                mc.send_library_status(LibraryStatusMessage {
+                    message: format!(
+                        "Detecting intros and credits... ({}/{}) - {}%",
+                        index + 1,
+                        total,
+                        index * 100 / total
+                    ),
+                    library: library.id.clone(),
+                });
+                match mc
+                    .detect_season_markers(&library.id, &serie_id, season, &user)
+                    .await
+                {
+                    Ok(markers) => log_info(
+                        LogServiceType::Scheduler,
+                        format!(
+                            "Analyzed {} medias of {} season {}",
+                            markers.len(),
+                            serie_id,
+                            season
+                        ),
+                    ),
+                    Err(e) => log_error(
+                        LogServiceType::Scheduler,
+                        format!(
+                            "Unable to detect markers of {} season {}: {:#}",
+                            serie_id, season, e
+                        ),
+                    ),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -10,9 +10,12 @@ use tokio::sync::{Mutex, RwLock};
 use tokio_util::sync::CancellationToken;
 
 use self::{
-    encrypt_library::EncryptLibraryTask, face_recognition::FaceRecognitionTask, ip::RefreshIpTask,
-    iptv_refresh::IptvRefreshTask, refresh::RefreshTask, request_progress::RequestProgressTask,
-    series::SerieTask,
+    dvr::DvrTask, encrypt_library::EncryptLibraryTask, face_recognition::FaceRecognitionTask,
+    face_reembed::FaceReembedTask, hdr::HdrProbeTask, ip::RefreshIpTask,
+    iptv_health::IptvHealthTask, iptv_refresh::IptvRefreshTask, kosync::KosyncHashTask,
+    media_markers::MediaMarkersTask, ocr::OcrTask, refresh::RefreshTask,
+    request_progress::RequestProgressTask, semantic::SemanticEmbeddingTask, series::SerieTask,
+    tagging::TaggingTask, trickplay::TrickplayTask,
 };
 
 use super::{
@@ -21,13 +24,23 @@ use super::{
 };
 
 pub mod backup;
+pub mod dvr;
 pub mod encrypt_library;
 pub mod face_recognition;
+pub mod face_reembed;
+pub mod hdr;
 pub mod ip;
+pub mod iptv_health;
 pub mod iptv_refresh;
+pub mod kosync;
+pub mod media_markers;
+pub mod ocr;
 pub mod refresh;
 pub mod request_progress;
+pub mod semantic;
 pub mod series;
+pub mod tagging;
+pub mod trickplay;
 
 #[derive(Debug, Clone)]
 pub struct RsScheduler {
@@ -194,6 +207,16 @@ pub enum RsTaskType {
     RequestProgress,
     EncryptLibrary,
     IptvRefresh,
+    Trickplay,
+    MediaMarkers,
+    Dvr,
+    IptvHealth,
+    KosyncHash,
+    SemanticEmbedding,
+    Ocr,
+    Tagging,
+    FaceReembed,
+    HdrProbe,
 }
 
 #[derive(Debug)]
@@ -229,6 +252,46 @@ impl RsSchedulerItem {
                 let deserialized: IptvRefreshTask = serde_json::from_str(&self.task)?;
                 Ok(Box::pin(deserialized))
             }
+            RsTaskType::Trickplay => {
+                let deserialized: TrickplayTask = serde_json::from_str(&self.task)?;
+                Ok(Box::pin(deserialized))
+            }
+            RsTaskType::MediaMarkers => {
+                let deserialized: MediaMarkersTask = serde_json::from_str(&self.task)?;
+                Ok(Box::pin(deserialized))
+            }
+            RsTaskType::Dvr => {
+                let deserialized: DvrTask = serde_json::from_str(&self.task)?;
+                Ok(Box::pin(deserialized))
+            }
+            RsTaskType::IptvHealth => {
+                let deserialized: IptvHealthTask = serde_json::from_str(&self.task)?;
+                Ok(Box::pin(deserialized))
+            }
+            RsTaskType::KosyncHash => {
+                let deserialized: KosyncHashTask = serde_json::from_str(&self.task)?;
+                Ok(Box::pin(deserialized))
+            }
+            RsTaskType::SemanticEmbedding => {
+                let deserialized: SemanticEmbeddingTask = serde_json::from_str(&self.task)?;
+                Ok(Box::pin(deserialized))
+            }
+            RsTaskType::Ocr => {
+                let deserialized: OcrTask = serde_json::from_str(&self.task)?;
+                Ok(Box::pin(deserialized))
+            }
+            RsTaskType::Tagging => {
+                let deserialized: TaggingTask = serde_json::from_str(&self.task)?;
+                Ok(Box::pin(deserialized))
+            }
+            RsTaskType::FaceReembed => {
+                let deserialized: FaceReembedTask = serde_json::from_str(&self.task)?;
+                Ok(Box::pin(deserialized))
+            }
+            RsTaskType::HdrProbe => {
+                let deserialized: HdrProbeTask = serde_json::from_str(&self.task)?;
+                Ok(Box::pin(deserialized))
+            }
         }
     }
 
@@ -0,0 +1,90 @@
+use axum::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::library::{LibraryStatusMessage, LibraryType},
+    error::RsResult,
+    model::{users::ConnectedUser, ModelController},
+    tools::{
+        log::{log_error, log_info, LogServiceType},
+        ocr::has_ocr_engine,
+    },
+};
+
+use super::RsSchedulerTask;
+
+/// Backfill recognized text for photos of the libraries with OCR enabled
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OcrTask {
+    pub specific_library: Option<String>,
+}
+
+#[async_trait]
+impl RsSchedulerTask for OcrTask {
+    async fn execute(&self, mc: ModelController) -> RsResult<()> {
+        if !has_ocr_engine() {
+            return Ok(());
+        }
+        let user = ConnectedUser::ServerAdmin;
+        let libraries = mc.get_libraries(&user).await?;
+
+        let libraries: Vec<_> = libraries
+            .into_iter()
+            .filter(|l| l.kind != LibraryType::Iptv)
+            .filter(|l| !l.crypt.unwrap_or(false))
+            .filter(|l| {
+                self.specific_library
+                    .as_ref()
+                    .map(|id| l.id == *id)
+                    .unwrap_or(true)
+            })
+            .collect();
+
+        for library in libraries {
+            if !mc.is_ocr_enabled(&library.id).await {
+                continue;
+            }
+            let media_ids = match mc.get_medias_without_ocr(&library.id).await {
+                Ok(ids) => ids,
+                Err(e) => {
+                    log_error(
+                        LogServiceType::Scheduler,
+                        format!("Unable to list photos of {}: {:#}", library.name, e),
+                    );
+                    continue;
+                }
+            };
+            if media_ids.is_empty() {
+                continue;
+            }
+            let total = media_ids.len();
+            log_info(
+                LogServiceType::Scheduler,
+                format!(
+                    "Recognizing text of {} photos in library {}",
+                    total, library.name
+                ),
+            );
+
+            for (index, media_id) in media_ids.iter().enumerate() {
+                if let Err(e) = mc.update_media_ocr(&library.id, media_id, &user).await {
+                    log_error(
+                        LogServiceType::Scheduler,
+                        format!("Unable to recognize text of {}: {:#}", media_id, e),
+                    );
+                }
+                mc.send_library_status(LibraryStatusMessage {
+                    message: format!(
+                        "Recognizing text... ({}/{}) - {}%",
+                        index + 1,
+                        total,
+                        (index + 1) * 100 / total
+                    ),
+                    library: library.id.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
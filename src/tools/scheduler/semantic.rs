@@ -0,0 +1,89 @@
+use axum::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::library::{LibraryStatusMessage, LibraryType},
+    error::{RsError, RsResult},
+    model::{users::ConnectedUser, ModelController},
+    tools::log::{log_error, log_info, LogServiceType},
+};
+
+use super::RsSchedulerTask;
+
+/// Backfill semantic search embeddings for photos and videos that don't have them yet
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SemanticEmbeddingTask {
+    pub specific_library: Option<String>,
+}
+
+#[async_trait]
+impl RsSchedulerTask for SemanticEmbeddingTask {
+    async fn execute(&self, mc: ModelController) -> RsResult<()> {
+        match mc.get_clip_service().await {
+            Ok(_) => {}
+            Err(RsError::NoModelFound) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+        let user = ConnectedUser::ServerAdmin;
+        let libraries = mc.get_libraries(&user).await?;
+
+        let libraries: Vec<_> = libraries
+            .into_iter()
+            .filter(|l| l.kind != LibraryType::Iptv)
+            .filter(|l| !l.crypt.unwrap_or(false))
+            .filter(|l| {
+                self.specific_library
+                    .as_ref()
+                    .map(|id| l.id == *id)
+                    .unwrap_or(true)
+            })
+            .collect();
+
+        for library in libraries {
+            let media_ids = match mc.get_medias_without_embedding(&library.id).await {
+                Ok(ids) => ids,
+                Err(e) => {
+                    log_error(
+                        LogServiceType::Scheduler,
+                        format!("Unable to list medias of {}: {:#}", library.name, e),
+                    );
+                    continue;
+                }
+            };
+            if media_ids.is_empty() {
+                continue;
+            }
+            let total = media_ids.len();
+            log_info(
+                LogServiceType::Scheduler,
+                format!(
+                    "Computing search embeddings for {} medias in library {}",
+                    total, library.name
+                ),
+            );
+
+            for (index, media_id) in media_ids.iter().enumerate() {
+                if let Err(e) = mc
+                    .update_media_embedding(&library.id, media_id, &user)
+                    .await
+                {
+                    log_error(
+                        LogServiceType::Scheduler,
+                        format!("Unable to compute embedding for {}: {:#}", media_id, e),
+                    );
+                }
+                mc.send_library_status(LibraryStatusMessage {
+                    message: format!(
+                        "Indexing images for search... ({}/{}) - {}%",
+                        index + 1,
+                        total,
+                        (index + 1) * 100 / total
+                    ),
+                    library: library.id.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
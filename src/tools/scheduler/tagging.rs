@@ -0,0 +1,92 @@
+use axum::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::library::{LibraryStatusMessage, LibraryType},
+    error::{RsError, RsResult},
+    model::{users::ConnectedUser, ModelController},
+    tools::log::{log_error, log_info, LogServiceType},
+};
+
+use super::RsSchedulerTask;
+
+/// Tag again all photos and videos of the libraries, replacing previously generated AI tags
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaggingTask {
+    pub specific_library: Option<String>,
+    /// Registered tagging model to use instead of the library settings
+    pub model: Option<String>,
+}
+
+#[async_trait]
+impl RsSchedulerTask for TaggingTask {
+    async fn execute(&self, mc: ModelController) -> RsResult<()> {
+        let user = ConnectedUser::ServerAdmin;
+        let libraries = mc.get_libraries(&user).await?;
+
+        let libraries: Vec<_> = libraries
+            .into_iter()
+            .filter(|l| l.kind != LibraryType::Iptv)
+            .filter(|l| !l.crypt.unwrap_or(false))
+            .filter(|l| {
+                self.specific_library
+                    .as_ref()
+                    .map(|id| l.id == *id)
+                    .unwrap_or(true)
+            })
+            .collect();
+
+        for library in libraries {
+            let media_ids = match mc.get_medias_to_tag(&library.id).await {
+                Ok(ids) => ids,
+                Err(e) => {
+                    log_error(
+                        LogServiceType::Scheduler,
+                        format!("Unable to list medias of {}: {:#}", library.name, e),
+                    );
+                    continue;
+                }
+            };
+            if media_ids.is_empty() {
+                continue;
+            }
+            let total = media_ids.len();
+            log_info(
+                LogServiceType::Scheduler,
+                format!("Tagging {} medias in library {}", total, library.name),
+            );
+
+            for (index, media_id) in media_ids.iter().enumerate() {
+                match mc
+                    .prediction(
+                        &library.id,
+                        media_id,
+                        self.model.clone(),
+                        true,
+                        &user,
+                        false,
+                    )
+                    .await
+                {
+                    Err(RsError::NoModelFound) => break,
+                    Err(e) => log_error(
+                        LogServiceType::Scheduler,
+                        format!("Unable to tag {}: {:#}", media_id, e),
+                    ),
+                    Ok(_) => {}
+                }
+                mc.send_library_status(LibraryStatusMessage {
+                    message: format!(
+                        "Tagging medias... ({}/{}) - {}%",
+                        index + 1,
+                        total,
+                        (index + 1) * 100 / total
+                    ),
+                    library: library.id.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
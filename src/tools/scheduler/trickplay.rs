@@ -0,0 +1,87 @@
+use axum::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::library::{LibraryStatusMessage, LibraryType},
+    error::RsResult,
+    model::{users::ConnectedUser, ModelController},
+    tools::log::{log_error, log_info, LogServiceType},
+};
+
+use super::RsSchedulerTask;
+
+/// Backfill trickplay sprites for videos that don't have them yet
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrickplayTask {
+    pub specific_library: Option<String>,
+}
+
+#[async_trait]
+impl RsSchedulerTask for TrickplayTask {
+    async fn execute(&self, mc: ModelController) -> RsResult<()> {
+        let user = ConnectedUser::ServerAdmin;
+        let libraries = mc.get_libraries(&user).await?;
+
+        let libraries: Vec<_> = libraries
+            .into_iter()
+            .filter(|l| l.kind != LibraryType::Iptv)
+            .filter(|l| !l.crypt.unwrap_or(false))
+            .filter(|l| {
+                self.specific_library
+                    .as_ref()
+                    .map(|id| l.id == *id)
+                    .unwrap_or(true)
+            })
+            .collect();
+
+        for library in libraries {
+            if mc.get_library_encryption_key(&library.id).await.is_some() {
+                continue;
+            }
+            let media_ids = match mc.get_medias_without_trickplay(&library.id).await {
+                Ok(ids) => ids,
+                Err(e) => {
+                    log_error(
+                        LogServiceType::Scheduler,
+                        format!("Unable to list videos of {}: {:#}", library.name, e),
+                    );
+                    continue;
+                }
+            };
+            if media_ids.is_empty() {
+                continue;
+            }
+            let total = media_ids.len();
+            log_info(
+                LogServiceType::Scheduler,
+                format!(
+                    "Generating trickplay for {} videos in library {}",
+                    total, library.name
+                ),
+            );
+
+            for (index, media_id) in media_ids.iter().enumerate() {
+                if let Err(e) = mc
+                    .generate_media_trickplay(&library.id, media_id, false, &user)
+                    .await
+                {
+                    log_error(
+                        LogServiceType::Scheduler,
+                        format!("Unable to generate trickplay for {}: {:#}", media_id, e),
+                    );
+                }
+                mc.send_library_status(LibraryStatusMessage {
+                    message: format!(
+                        "Generating video previews... ({}/{}) - {}%",
+                        index + 1,
+                        total,
+                        (index + 1) * 100 / total
+                    ),
+                    library: library.id.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::domain::serie_gaps::{RsGapDuplicate, RsGapNumbering, RsSerieGapReport};
+
+lazy_static! {
+    static ref RE_VOLUME: Regex =
+        Regex::new(r"(?i)\b(?:volume|vol|tome|t|v)\.?\s*0*(\d+(?:\.\d+)?)\b").unwrap();
+    static ref RE_CHAPTER: Regex =
+        Regex::new(r"(?i)(?:\b(?:chapter|chap|ch|c)\.?\s*|#\s*)0*(\d+(?:\.\d+)?)\b").unwrap();
+}
+
+/// Owned book of a serie as compared by the gap report
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GapBook {
+    pub id: String,
+    pub number: f64,
+    pub airdate: Option<i64>,
+    pub year: Option<i64>,
+}
+
+/// Volumes can be fractional (`1.5` for a special), compare them on three decimals
+fn number_key(number: f64) -> i64 {
+    (number * 1000.0).round() as i64
+}
+
+/// Release date comparison, on the air date when both have one or else on the year
+fn released_after(a: &GapBook, b: &GapBook) -> bool {
+    match (a.airdate, b.airdate) {
+        (Some(a), Some(b)) => a > b,
+        _ => matches!((a.year, b.year), (Some(a), Some(b)) if a > b),
+    }
+}
+
+/// Whether a release title (`Serie Vol. 03`, `Serie T3`, `Serie Ch. 12`...) names the volume or
+/// chapter `number`
+pub fn title_has_number(title: &str, numbering: RsGapNumbering, number: f64) -> bool {
+    let re = match numbering {
+        RsGapNumbering::Volume => &*RE_VOLUME,
+        RsGapNumbering::Chapter => &*RE_CHAPTER,
+    };
+    re.captures_iter(title)
+        .filter_map(|c| c[1].parse::<f64>().ok())
+        .any(|found| number_key(found) == number_key(number))
+}
+
+/// Compare the owned books of a serie with the numbers listed by metadata providers
+pub fn serie_gaps(
+    serie: &str,
+    numbering: RsGapNumbering,
+    expected: Vec<f64>,
+    books: Vec<GapBook>,
+) -> RsSerieGapReport {
+    let expected: BTreeMap<i64, f64> = expected
+        .into_iter()
+        .map(|number| (number_key(number), number))
+        .collect();
+    let mut owned: BTreeMap<i64, Vec<GapBook>> = BTreeMap::new();
+    for book in books {
+        owned.entry(number_key(book.number)).or_default().push(book);
+    }
+
+    let missing = if expected.is_empty() {
+        let last = owned
+            .values()
+            .last()
+            .map(|books| books[0].number.floor() as i64)
+            .unwrap_or(0);
+        (1..=last)
+            .filter(|number| !owned.contains_key(&number_key(*number as f64)))
+            .map(|number| number as f64)
+            .collect()
+    } else {
+        expected
+            .iter()
+            .filter(|(key, _)| !owned.contains_key(key))
+            .map(|(_, number)| *number)
+            .collect()
+    };
+    let unknown = if expected.is_empty() {
+        vec![]
+    } else {
+        owned
+            .iter()
+            .filter(|(key, _)| !expected.contains_key(key))
+            .map(|(_, books)| books[0].number)
+            .collect()
+    };
+    let duplicated = owned
+        .values()
+        .filter(|books| books.len() > 1)
+        .map(|books| RsGapDuplicate {
+            number: books[0].number,
+            books: books.iter().map(|book| book.id.clone()).collect(),
+        })
+        .collect();
+
+    let ordered: Vec<&GapBook> = owned.values().map(|books| &books[0]).collect();
+    let mut out_of_order: Vec<String> = vec![];
+    for pair in ordered.windows(2) {
+        if released_after(pair[0], pair[1]) {
+            for book in pair {
+                if !out_of_order.contains(&book.id) {
+                    out_of_order.push(book.id.clone());
+                }
+            }
+        }
+    }
+
+    RsSerieGapReport {
+        serie: serie.to_string(),
+        numbering,
+        expected: expected.into_values().collect(),
+        owned: owned.values().map(|books| books[0].number).collect(),
+        missing,
+        duplicated,
+        out_of_order,
+        unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(id: &str, number: f64, year: Option<i64>) -> GapBook {
+        GapBook {
+            id: id.to_string(),
+            number,
+            year,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn gaps_against_provider_list() {
+        let report = serie_gaps(
+            "serie",
+            RsGapNumbering::Volume,
+            vec![1.0, 2.0, 3.0, 4.0, 5.0],
+            vec![
+                book("a", 1.0, Some(2001)),
+                book("b", 2.0, Some(2003)),
+                book("c", 3.0, Some(2002)),
+                book("d", 3.0, None),
+                book("e", 7.0, None),
+            ],
+        );
+        assert_eq!(report.owned, vec![1.0, 2.0, 3.0, 7.0]);
+        assert_eq!(report.missing, vec![4.0, 5.0]);
+        assert_eq!(report.unknown, vec![7.0]);
+        assert_eq!(
+            report.duplicated,
+            vec![RsGapDuplicate {
+                number: 3.0,
+                books: vec!["c".to_string(), "d".to_string()]
+            }]
+        );
+        assert_eq!(report.out_of_order, vec!["b".to_string(), "c".to_string()]);
+    }
+
+    #[test]
+    fn numbers_in_titles() {
+        let volume = RsGapNumbering::Volume;
+        assert!(title_has_number("One Piece Vol. 03 (2003)", volume, 3.0));
+        assert!(title_has_number("One.Piece.T03.FRENCH.CBZ", volume, 3.0));
+        assert!(title_has_number("One Piece Volume 10.5", volume, 10.5));
+        assert!(!title_has_number("One Piece Vol. 13", volume, 3.0));
+        assert!(!title_has_number("Dune 3", volume, 3.0));
+        let chapter = RsGapNumbering::Chapter;
+        assert!(title_has_number("One Piece Ch. 1001", chapter, 1001.0));
+        assert!(title_has_number("One Piece #12", chapter, 12.0));
+        assert!(!title_has_number("One Piece Vol. 12", chapter, 12.0));
+    }
+
+    #[test]
+    fn gaps_without_provider_list() {
+        let report = serie_gaps(
+            "serie",
+            RsGapNumbering::Chapter,
+            vec![],
+            vec![
+                book("a", 1.0, None),
+                book("b", 1.5, None),
+                book("c", 4.0, None),
+            ],
+        );
+        assert_eq!(report.missing, vec![2.0, 3.0]);
+        assert!(report.unknown.is_empty());
+        assert!(report.expected.is_empty());
+    }
+}
@@ -16,6 +16,16 @@ pub fn extract_people(text: &str) -> Vec<String> {
         .collect()
 }
 
+/// Escape text for XML content and attribute values
+pub fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
 pub trait Printable {
     fn printable(&self) -> String;
 }
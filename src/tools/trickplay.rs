@@ -0,0 +1,220 @@
+use std::{path::Path, process::Stdio};
+
+use tokio::process::Command;
+
+use crate::error::{RsError, RsResult};
+
+use super::{
+    log::{log_error, LogServiceType},
+    text_tools::Printable,
+    video_tools::VideoCommandBuilder,
+};
+
+/// Seconds between two trickplay thumbnails
+pub const TRICKPLAY_INTERVAL: u32 = 10;
+/// Width of a single thumbnail inside a sprite sheet
+pub const TRICKPLAY_THUMB_WIDTH: u32 = 320;
+pub const TRICKPLAY_COLUMNS: u32 = 10;
+pub const TRICKPLAY_ROWS: u32 = 10;
+pub const TRICKPLAY_VTT_NAME: &str = "thumbnails.vtt";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrickplayLayout {
+    pub interval: u32,
+    pub width: u32,
+    pub height: u32,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl TrickplayLayout {
+    /// Default layout for a video of the given size, keeping its aspect ratio
+    pub fn for_video(video_width: Option<u32>, video_height: Option<u32>) -> Self {
+        let height = match (video_width, video_height) {
+            (Some(w), Some(h)) if w > 0 && h > 0 => {
+                let height = (TRICKPLAY_THUMB_WIDTH as f64 * h as f64 / w as f64).round() as u32;
+                // ffmpeg scalers require even dimensions
+                (height + 1) & !1
+            }
+            _ => TRICKPLAY_THUMB_WIDTH * 9 / 16,
+        };
+        TrickplayLayout {
+            interval: TRICKPLAY_INTERVAL,
+            width: TRICKPLAY_THUMB_WIDTH,
+            height,
+            columns: TRICKPLAY_COLUMNS,
+            rows: TRICKPLAY_ROWS,
+        }
+    }
+
+    pub fn thumbs_per_sprite(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    pub fn video_filter(&self) -> String {
+        format!(
+            "fps=1/{},scale={}:{},tile={}x{}",
+            self.interval, self.width, self.height, self.columns, self.rows
+        )
+    }
+}
+
+/// Sprite file name as produced by ffmpeg (`sprite_%03d.jpg` starts at 1)
+pub fn sprite_name(index: u32) -> String {
+    format!("sprite_{:03}.jpg", index + 1)
+}
+
+/// Only accept names generated by `sprite_name` to prevent path traversal
+pub fn is_valid_sprite_name(name: &str) -> bool {
+    name.len() >= 14
+        && name.starts_with("sprite_")
+        && name.ends_with(".jpg")
+        && name[7..name.len() - 4].bytes().all(|b| b.is_ascii_digit())
+}
+
+fn vtt_timestamp(seconds: f64) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+    let hours = millis / 3_600_000;
+    let minutes = (millis % 3_600_000) / 60_000;
+    let secs = (millis % 60_000) / 1000;
+    let ms = millis % 1000;
+    format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, secs, ms)
+}
+
+/// Build the WebVTT thumbnails track pointing to sprite regions with `#xywh=`
+pub fn build_vtt(duration: f64, layout: &TrickplayLayout) -> String {
+    let mut vtt = String::from("WEBVTT\n");
+    if duration <= 0.0 || layout.interval == 0 {
+        return vtt;
+    }
+    let count = (duration / layout.interval as f64).ceil() as u32;
+    let per_sprite = layout.thumbs_per_sprite();
+    for i in 0..count {
+        let start = (i * layout.interval) as f64;
+        let end = (((i + 1) * layout.interval) as f64).min(duration);
+        let position = i % per_sprite;
+        let x = (position % layout.columns) * layout.width;
+        let y = (position / layout.columns) * layout.height;
+        vtt.push_str(&format!(
+            "\n{} --> {}\n{}#xywh={},{},{},{}\n",
+            vtt_timestamp(start),
+            vtt_timestamp(end),
+            sprite_name(i / per_sprite),
+            x,
+            y,
+            layout.width,
+            layout.height
+        ));
+    }
+    vtt
+}
+
+/// Rewrite sprite references of a stored VTT with the given url builder
+pub fn rewrite_vtt<F: Fn(&str) -> String>(vtt: &str, url: F) -> String {
+    vtt.lines()
+        .map(|line| match line.split_once("#xywh=") {
+            Some((name, region)) if is_valid_sprite_name(name) => {
+                format!("{}#xywh={}", url(name), region)
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Extract sprite sheets of the video into `output_dir` and write the VTT track
+pub async fn generate_trickplay(
+    uri: &str,
+    output_dir: &Path,
+    duration: f64,
+    layout: &TrickplayLayout,
+) -> RsResult<()> {
+    tokio::fs::create_dir_all(output_dir).await?;
+
+    let mut cmd = Command::new(VideoCommandBuilder::get_ffmpeg_path());
+    cmd.arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        // Only decode keyframes: much faster and precise enough for scrubbing
+        .arg("-skip_frame")
+        .arg("nokey")
+        .arg("-i")
+        .arg(uri)
+        .arg("-an")
+        .arg("-sn")
+        .arg("-vf")
+        .arg(layout.video_filter())
+        .arg("-q:v")
+        .arg("5")
+        .arg("-y")
+        .arg(output_dir.join("sprite_%03d.jpg"))
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+
+    let output = cmd.output().await?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr).to_string();
+        log_error(
+            LogServiceType::Other,
+            format!(
+                "Trickplay generation failed ({}): {}",
+                cmd.printable(),
+                error
+            ),
+        );
+        return Err(RsError::Error(format!(
+            "Unable to generate trickplay sprites: {}",
+            error
+        )));
+    }
+
+    let vtt = build_vtt(duration, layout);
+    tokio::fs::write(output_dir.join(TRICKPLAY_VTT_NAME), vtt).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_keeps_aspect_ratio() {
+        let layout = TrickplayLayout::for_video(Some(1920), Some(1080));
+        assert_eq!(layout.height, 180);
+        let layout = TrickplayLayout::for_video(Some(1920), Some(800));
+        assert_eq!(layout.height, 134);
+        let layout = TrickplayLayout::for_video(None, None);
+        assert_eq!(layout.height, 180);
+    }
+
+    #[test]
+    fn vtt_cues_point_to_sprite_regions() {
+        let layout = TrickplayLayout {
+            interval: 10,
+            width: 320,
+            height: 180,
+            columns: 2,
+            rows: 2,
+        };
+        let vtt = build_vtt(45.0, &layout);
+        let expected = "WEBVTT\n\
+            \n00:00:00.000 --> 00:00:10.000\nsprite_001.jpg#xywh=0,0,320,180\n\
+            \n00:00:10.000 --> 00:00:20.000\nsprite_001.jpg#xywh=320,0,320,180\n\
+            \n00:00:20.000 --> 00:00:30.000\nsprite_001.jpg#xywh=0,180,320,180\n\
+            \n00:00:30.000 --> 00:00:40.000\nsprite_001.jpg#xywh=320,180,320,180\n\
+            \n00:00:40.000 --> 00:00:45.000\nsprite_002.jpg#xywh=0,0,320,180\n";
+        assert_eq!(vtt, expected);
+    }
+
+    #[test]
+    fn rewrite_only_touches_sprites() {
+        let vtt = "WEBVTT\n\n00:00:00.000 --> 00:00:10.000\nsprite_001.jpg#xywh=0,0,320,180\n";
+        let rewritten = rewrite_vtt(vtt, |name| format!("/base/{}?t=1", name));
+        assert_eq!(
+            rewritten,
+            "WEBVTT\n\n00:00:00.000 --> 00:00:10.000\n/base/sprite_001.jpg?t=1#xywh=0,0,320,180"
+        );
+        assert!(!is_valid_sprite_name("../sprite_001.jpg"));
+        assert!(!is_valid_sprite_name("sprite_.jpg"));
+    }
+}
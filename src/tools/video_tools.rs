@@ -1,6 +1,6 @@
 use crate::domain::progress;
 use crate::error::{RsError, RsResult};
-use crate::{domain::ffmpeg::FfprobeResult, Error};
+use crate::{domain::ffmpeg::FfprobeResult, domain::media_chapters::RsMediaChapter, Error};
 use crate::{server::get_server_temp_file_path, tools};
 use nanoid::nanoid;
 use regex::Regex;
@@ -10,6 +10,7 @@ use rs_plugin_common_interfaces::video::{
 use rs_plugin_common_interfaces::{RsVideoCodec, RsVideoFormat};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
+use std::collections::HashSet;
 use std::fmt::Alignment;
 use std::io::{BufRead, Read};
 use std::path::PathBuf;
@@ -34,7 +35,7 @@ use tokio_util::io::ReaderStream;
 use tools::text_tools::{Printable, ToHms};
 
 use super::file_tools::executable_dir;
-use super::log::{log_error, LogServiceType};
+use super::log::{log_error, log_info, LogServiceType};
 
 pub mod ytdl;
 use lazy_static::lazy_static;
@@ -49,6 +50,14 @@ lazy_static! {
     static ref FFPROBE_LOCK: Arc<RwLock<()>> = Arc::new(RwLock::new(()));
 }
 
+static FFMPEG_FILTERS: OnceCell<HashSet<String>> = OnceCell::const_new();
+
+/// CPU tone mapping of HDR (PQ/HLG) frames to SDR BT.709, requires libzimg (zscale)
+const TONEMAP_CPU: &str = "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,tonemap=tonemap=hable:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p";
+/// GPU tone mapping, frames are downloaded back so following filters still apply
+const TONEMAP_CUDA: &str =
+    "hwupload_cuda,tonemap_cuda=tonemap=hable,hwdownload,format=nv12|p010le,format=yuv420p";
+
 #[serde_as]
 #[derive(Debug, Serialize, strum_macros::AsRefStr)]
 pub enum VideoError {
@@ -312,14 +321,14 @@ impl VideoCommandBuilder {
     }
 
     #[cfg(target_os = "windows")]
-    fn get_ffprobe_path() -> PathBuf {
+    pub(crate) fn get_ffprobe_path() -> PathBuf {
         let mut exec_dir = executable_dir().unwrap_or(PathBuf::from("./"));
         exec_dir.push("ffprobe.exe");
         return exec_dir;
     }
 
     #[cfg(not(target_os = "windows"))]
-    fn get_ffprobe_path() -> PathBuf {
+    pub(crate) fn get_ffprobe_path() -> PathBuf {
         let mut exec_dir = executable_dir().unwrap_or(PathBuf::from("./"));
         exec_dir.push("ffprobe");
         return exec_dir;
@@ -468,6 +477,16 @@ impl VideoCommandBuilder {
         self
     }
 
+    /// Video encoder selected with `-c:v`, `None` when the stream is copied
+    pub fn video_encoder(&self) -> Option<String> {
+        self.output_options
+            .windows(2)
+            .filter(|w| w[0] == "-c:v")
+            .last()
+            .map(|w| w[1].clone())
+            .filter(|e| e != "copy")
+    }
+
     pub fn has_cuda_support(&self) -> bool {
         self.cuda_support
     }
@@ -726,6 +745,8 @@ impl VideoCommandBuilder {
                 }
             }
             Some(RsVideoCodec::H264) => {
+                // H.264 targets are SDR: HDR sources would look washed out
+                self.tonemap_if_hdr().await;
                 let supported_hw = video_hardware().await.unwrap_or_default();
                 self.add_out_option("-c:v");
                 if self.cuda_support {
@@ -798,6 +819,38 @@ impl VideoCommandBuilder {
         self
     }
 
+    /// Convert HDR (PQ/HLG) frames to SDR BT.709 with the filters available in this ffmpeg
+    /// build. Tone mapping is skipped with a warning when the needed filters are missing
+    pub async fn add_tonemap(&mut self) -> &mut Self {
+        match tonemap_filter(ffmpeg_filters().await, self.cuda_support) {
+            Some(filter) => self.add_video_effect(filter),
+            None => {
+                log_error(
+                    LogServiceType::Other,
+                    "Unable to tone map HDR video, ffmpeg lacks tonemap_cuda and zscale (libzimg)"
+                        .to_string(),
+                );
+                self
+            }
+        }
+    }
+
+    async fn tonemap_if_hdr(&mut self) -> &mut Self {
+        let hdr = self
+            .get_probe_result()
+            .await
+            .ok()
+            .and_then(|p| p.hdr_format());
+        if let Some(hdr) = hdr {
+            log_info(
+                LogServiceType::Other,
+                format!("Tone mapping {} source to SDR", hdr),
+            );
+            self.add_tonemap().await;
+        }
+        self
+    }
+
     pub fn set_aspect_ratio(
         &mut self,
         aspect_ratio: String,
@@ -977,6 +1030,24 @@ impl VideoCommandBuilder {
         }
     }
 
+    /// Write chapters into the output container, shifted to the selected interval
+    pub async fn set_chapters(&mut self, chapters: &[RsMediaChapter]) -> RsResult<&mut Self> {
+        let metadata = chapters_ffmetadata(chapters, self.expected_start, self.expected_duration);
+        let path = get_server_temp_file_path().await?;
+        tokio::fs::write(&path, metadata).await?;
+        let path = path
+            .to_str()
+            .ok_or(RsError::Error(
+                "Unable to get temp path for chapters file".to_string(),
+            ))?
+            .to_string();
+        self.add_input(path.clone());
+        let input = self.current_input.to_string();
+        self.add_out_option("-map_chapters").add_out_option(input);
+        self.cleanup_files.push(path);
+        Ok(self)
+    }
+
     pub async fn clean(&mut self) -> RsResult<()> {
         let cleaning = self.cleanup_files.clone();
         self.cleanup_files.clear();
@@ -1182,6 +1253,53 @@ impl VideoCommandBuilder {
     }
 }
 
+/// Names of the filters in a `ffmpeg -filters` listing
+fn parse_ffmpeg_filters(listing: &str) -> HashSet<String> {
+    listing
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let (_flags, name, kind) = (parts.next()?, parts.next()?, parts.next()?);
+            kind.contains("->").then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Tone mapping chain for the available filters (matched on their exact names).
+/// tonemap_cuda only ships in some builds (e.g. jellyfin-ffmpeg), CUDA hosts fall back to the
+/// CPU chain otherwise: frames are decoded in system memory so it applies as is
+fn tonemap_filter(filters: &HashSet<String>, cuda_support: bool) -> Option<&'static str> {
+    if cuda_support && filters.contains("tonemap_cuda") {
+        Some(TONEMAP_CUDA)
+    } else if filters.contains("zscale") && filters.contains("tonemap") {
+        Some(TONEMAP_CPU)
+    } else {
+        None
+    }
+}
+
+/// Filters available in the ffmpeg build, probed once
+pub async fn ffmpeg_filters() -> &'static HashSet<String> {
+    FFMPEG_FILTERS
+        .get_or_init(|| async {
+            let output = Command::new(VideoCommandBuilder::get_ffmpeg_path())
+                .args(["-hide_banner", "-filters"])
+                .output()
+                .await;
+            match output {
+                Ok(output) => parse_ffmpeg_filters(&String::from_utf8_lossy(&output.stdout)),
+                Err(e) => {
+                    log_error(
+                        LogServiceType::Other,
+                        format!("Unable to list ffmpeg filters: {:?}", e),
+                    );
+                    HashSet::new()
+                }
+            }
+        })
+        .await
+}
+
 pub async fn video_hardware() -> Result<Vec<String>, Error> {
     let _lock = FFMPEG_LOCK.read().await;
     let mut child = Command::new(VideoCommandBuilder::get_ffmpeg_path())
@@ -1201,12 +1319,51 @@ pub async fn video_hardware() -> Result<Vec<String>, Error> {
     Ok(results)
 }
 
+fn escape_ffmetadata(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// FFMETADATA file with the chapters overlapping the `start`/`duration` window, rebased on it
+pub fn chapters_ffmetadata(
+    chapters: &[RsMediaChapter],
+    start: Option<f64>,
+    duration: Option<f64>,
+) -> String {
+    let offset = start.unwrap_or(0.0);
+    let limit = duration.map(|d| offset + d).unwrap_or(f64::MAX);
+    let mut metadata = String::from(";FFMETADATA1\n");
+    for chapter in chapters {
+        let chapter_start = chapter.start.max(offset);
+        let chapter_end = chapter.end.min(limit);
+        if chapter_end <= chapter_start {
+            continue;
+        }
+        metadata.push_str(&format!(
+            "[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\n",
+            ((chapter_start - offset) * 1000.0).round() as u64,
+            ((chapter_end - offset) * 1000.0).round() as u64
+        ));
+        if let Some(title) = &chapter.title {
+            metadata.push_str(&format!("title={}\n", escape_ffmetadata(title)));
+        }
+    }
+    metadata
+}
+
 pub async fn probe_video(uri: &str) -> Result<FfprobeResult, Error> {
     let _lock = FFPROBE_LOCK.read().await;
     let output = Command::new(VideoCommandBuilder::get_ffprobe_path())
         .arg("-v")
         .arg("error")
         .arg("-show_streams")
+        .arg("-show_chapters")
         .arg("-show_entries")
         .arg("format")
         .arg("-of")
@@ -1414,4 +1571,60 @@ mod tests {
     async fn convert() {
         //convert_to_pipe("C:/Users/arnau/Downloads/IMG_5020.mov", None).await;
     }
+
+    #[test]
+    fn parses_ffmpeg_filters() {
+        let listing = "Filters:
+  T.. = Timeline support
+  ------
+ TSC zscale            V->V       Apply resizing, colorspace and bit depth conversion.
+ ... tonemap           V->V       Conversion to/from different dynamic ranges.
+ ... abuffer           |->A       Buffer audio frames.";
+        let filters = parse_ffmpeg_filters(listing);
+        assert!(filters.contains("zscale"));
+        assert!(filters.contains("tonemap"));
+        assert!(filters.contains("abuffer"));
+        assert!(!filters.contains("Timeline"));
+        assert_eq!(filters.len(), 3);
+    }
+
+    #[test]
+    fn tonemap_filter_falls_back_to_cpu() {
+        let filters = |names: &[&str]| -> HashSet<String> {
+            names.iter().map(|n| n.to_string()).collect()
+        };
+        let cpu = filters(&["zscale", "tonemap"]);
+        assert_eq!(tonemap_filter(&cpu, true), Some(TONEMAP_CPU));
+        assert_eq!(tonemap_filter(&cpu, false), Some(TONEMAP_CPU));
+        let cuda = filters(&["zscale", "tonemap", "tonemap_cuda"]);
+        assert_eq!(tonemap_filter(&cuda, true), Some(TONEMAP_CUDA));
+        assert_eq!(tonemap_filter(&cuda, false), Some(TONEMAP_CPU));
+        let other = filters(&["zscale", "tonemap_opencl", "tonemap_vaapi"]);
+        assert_eq!(tonemap_filter(&other, true), None);
+        assert_eq!(tonemap_filter(&other, false), None);
+    }
+
+    #[test]
+    fn chapters_are_rebased_on_interval() {
+        let chapters = vec![
+            RsMediaChapter {
+                start: 0.0,
+                end: 60.0,
+                title: Some("Intro; part=1".to_string()),
+            },
+            RsMediaChapter {
+                start: 60.0,
+                end: 600.0,
+                title: None,
+            },
+        ];
+        assert_eq!(
+            chapters_ffmetadata(&chapters, None, None),
+            ";FFMETADATA1\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=60000\ntitle=Intro\\; part\\=1\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=60000\nEND=600000\n"
+        );
+        assert_eq!(
+            chapters_ffmetadata(&chapters, Some(90.0), Some(30.0)),
+            ";FFMETADATA1\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=30000\n"
+        );
+    }
 }
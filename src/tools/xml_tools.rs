@@ -0,0 +1,123 @@
+/// Decode XML entities and character references
+pub fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(index) = rest.find('&') {
+        result.push_str(&rest[..index]);
+        rest = &rest[index..];
+        let Some(end) = rest.find(';').filter(|e| *e <= 10) else {
+            result.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Value of an attribute inside an opening tag
+pub fn attribute(tag: &str, name: &str) -> Option<String> {
+    let mut search = tag;
+    while let Some(index) = search.find(name) {
+        let before = search[..index].chars().last();
+        let after = search[index + name.len()..].trim_start();
+        search = &search[index + name.len()..];
+        if !before.map(|c| c.is_whitespace()).unwrap_or(false) {
+            continue;
+        }
+        let Some(after) = after.strip_prefix('=') else {
+            continue;
+        };
+        let after = after.trim_start();
+        let quote = after.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            continue;
+        }
+        let value = &after[1..];
+        let end = value.find(quote)?;
+        return Some(unescape(&value[..end]));
+    }
+    None
+}
+
+/// Opening tags and raw inner content of every `name` element, empty when self closing
+pub fn elements_raw<'a>(content: &'a str, name: &str) -> Vec<(&'a str, &'a str)> {
+    let open = format!("<{}", name);
+    let close = format!("</{}>", name);
+    let mut found = vec![];
+    let mut rest = content;
+    while let Some(index) = rest.find(&open) {
+        rest = &rest[index..];
+        let boundary = rest[open.len()..].chars().next();
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        if !matches!(boundary, Some(c) if c.is_whitespace() || c == '>' || c == '/') {
+            rest = &rest[open.len()..];
+            continue;
+        }
+        let tag = &rest[..tag_end];
+        if tag.ends_with('/') {
+            found.push((tag, ""));
+            rest = &rest[tag_end + 1..];
+            continue;
+        }
+        let inner = &rest[tag_end + 1..];
+        let Some(end) = inner.find(&close) else {
+            break;
+        };
+        found.push((tag, &inner[..end]));
+        rest = &inner[end + close.len()..];
+    }
+    found
+}
+
+/// Opening tags and text content of every `name` child element
+pub fn elements<'a>(body: &'a str, name: &str) -> Vec<(&'a str, String)> {
+    elements_raw(body, name)
+        .into_iter()
+        .map(|(tag, inner)| {
+            let inner = inner.trim();
+            let text = match inner
+                .strip_prefix("<![CDATA[")
+                .and_then(|t| t.strip_suffix("]]>"))
+            {
+                Some(cdata) => cdata.trim().to_string(),
+                None => unescape(inner),
+            };
+            (tag, text)
+        })
+        .collect()
+}
+
+/// Text of the first non empty `name` child element
+pub fn first_text(body: &str, name: &str) -> Option<String> {
+    elements(body, name)
+        .into_iter()
+        .map(|(_, text)| text)
+        .find(|text| !text.is_empty())
+}
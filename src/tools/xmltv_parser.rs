@@ -0,0 +1,235 @@
+use std::io::Read;
+
+use chrono::{DateTime, NaiveDateTime};
+use flate2::read::GzDecoder;
+
+use super::xml_tools::{attribute, elements, elements_raw, first_text};
+use crate::{
+    domain::epg::EpgProgramme,
+    error::{RsError, RsResult},
+};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Decode a downloaded XMLTV file, gunzipping it when needed
+pub fn decode_xmltv(bytes: &[u8]) -> RsResult<String> {
+    if bytes.starts_with(&GZIP_MAGIC) {
+        let mut content = String::new();
+        GzDecoder::new(bytes)
+            .read_to_string(&mut content)
+            .map_err(|e| RsError::Error(format!("Unable to decompress XMLTV: {}", e)))?;
+        Ok(content)
+    } else {
+        Ok(String::from_utf8_lossy(bytes).to_string())
+    }
+}
+
+/// Parse XMLTV times like `20240101203000 +0100` into milliseconds. Missing offset is UTC
+pub fn parse_xmltv_time(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let digits: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+    // Offset follows the digits, separated by a space or not
+    let offset = value[digits.len()..].trim();
+    let digits = match digits.len() {
+        14 => digits,
+        12 => format!("{}00", digits),
+        _ => return None,
+    };
+    if !offset.is_empty() {
+        if let Ok(date) =
+            DateTime::parse_from_str(&format!("{} {}", digits, offset), "%Y%m%d%H%M%S %z")
+        {
+            return Some(date.timestamp_millis());
+        }
+    }
+    NaiveDateTime::parse_from_str(&digits, "%Y%m%d%H%M%S")
+        .ok()
+        .map(|date| date.and_utc().timestamp_millis())
+}
+
+/// `xmltv_ns` numbering is zero based: `0.4.0/1` is S01E05
+fn xmltv_ns_episode(value: &str) -> Option<String> {
+    let mut parts = value.split('.');
+    let part = |p: Option<&str>| -> Option<u32> {
+        p.and_then(|p| p.split('/').next())
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .and_then(|p| p.parse::<u32>().ok())
+            .map(|n| n + 1)
+    };
+    let season = part(parts.next());
+    let episode = part(parts.next());
+    match (season, episode) {
+        (Some(season), Some(episode)) => Some(format!("S{:02}E{:02}", season, episode)),
+        (None, Some(episode)) => Some(format!("E{:02}", episode)),
+        (Some(season), None) => Some(format!("S{:02}", season)),
+        (None, None) => None,
+    }
+}
+
+/// Prefer the `onscreen` numbering, fallback to converted `xmltv_ns`
+fn episode_num(body: &str) -> Option<String> {
+    let numbers = elements(body, "episode-num");
+    let system = |tag: &str| attribute(tag, "system").unwrap_or_default();
+    numbers
+        .iter()
+        .find(|(tag, text)| system(tag) == "onscreen" && !text.is_empty())
+        .map(|(_, text)| text.clone())
+        .or_else(|| {
+            numbers
+                .iter()
+                .filter(|(tag, _)| system(tag) == "xmltv_ns")
+                .find_map(|(_, text)| xmltv_ns_episode(text))
+        })
+        .or_else(|| {
+            numbers
+                .iter()
+                .find(|(_, text)| !text.is_empty())
+                .map(|(_, text)| text.clone())
+        })
+}
+
+/// Parse the `<programme>` entries of an XMLTV document.
+/// Programmes without stop time end when the next one of the same channel starts
+pub fn parse_xmltv(content: &str) -> Vec<EpgProgramme> {
+    let mut parsed: Vec<(EpgProgramme, bool)> = vec![];
+    for (tag, body) in elements_raw(content, "programme") {
+        let Some(channel) = attribute(tag, "channel").filter(|c| !c.is_empty()) else {
+            continue;
+        };
+        let Some(start) = attribute(tag, "start").and_then(|s| parse_xmltv_time(&s)) else {
+            continue;
+        };
+        let stop = attribute(tag, "stop").and_then(|s| parse_xmltv_time(&s));
+        let Some(title) = first_text(body, "title") else {
+            continue;
+        };
+        parsed.push((
+            EpgProgramme {
+                channel,
+                start,
+                stop: stop.unwrap_or(start),
+                title,
+                description: first_text(body, "desc"),
+                category: first_text(body, "category"),
+                episode_num: episode_num(body),
+                icon: elements(body, "icon")
+                    .into_iter()
+                    .find_map(|(tag, _)| attribute(tag, "src")),
+            },
+            stop.is_some(),
+        ));
+    }
+
+    parsed.sort_by(|a, b| {
+        a.0.channel
+            .cmp(&b.0.channel)
+            .then(a.0.start.cmp(&b.0.start))
+    });
+    let mut programmes: Vec<EpgProgramme> = Vec::with_capacity(parsed.len());
+    for index in 0..parsed.len() {
+        let (mut programme, has_stop) = parsed[index].clone();
+        if !has_stop {
+            match parsed
+                .get(index + 1)
+                .filter(|(next, _)| next.channel == programme.channel)
+            {
+                Some((next, _)) => programme.stop = next.start,
+                None => continue,
+            }
+        }
+        if programme.stop <= programme.start {
+            continue;
+        }
+        programmes.push(programme);
+    }
+    programmes.dedup_by(|b, a| a.channel == b.channel && a.start == b.start);
+    programmes
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE tv SYSTEM "xmltv.dtd">
+<tv generator-info-name="test">
+  <channel id="TF1.fr"><display-name>TF1</display-name></channel>
+  <programme start="20240101200000 +0100" stop="20240101210000 +0100" channel="TF1.fr">
+    <title lang="fr">Journal &amp; M&#233;t&#xE9;o</title>
+    <desc lang="fr"><![CDATA[Les <b>infos</b> du soir]]></desc>
+    <category lang="fr">News</category>
+    <category lang="en">Information</category>
+    <episode-num system="xmltv_ns">0.4.0/1</episode-num>
+    <icon src="http://img/tf1.png" />
+  </programme>
+  <programme start="20240101210000 +0100" channel="TF1.fr">
+    <title>Film</title>
+    <episode-num system="onscreen">S2 E3</episode-num>
+    <episode-num system="xmltv_ns">1.2.</episode-num>
+  </programme>
+  <programme start="20240101220000 +0100" stop="20240101233000 +0100" channel="TF1.fr">
+    <title>Late show</title>
+  </programme>
+  <programme start="20240101200000" stop="20240101203000" channel='M6.fr'>
+    <title>Meteo</title>
+  </programme>
+  <programme start="bad" channel="M6.fr"><title>Invalid</title></programme>
+</tv>"#;
+
+    #[test]
+    fn parse_times() {
+        assert_eq!(
+            parse_xmltv_time("20240101200000 +0100"),
+            Some(1704135600000)
+        );
+        assert_eq!(parse_xmltv_time("20240101190000"), Some(1704135600000));
+        assert_eq!(parse_xmltv_time("202401011900 +0000"), Some(1704135600000));
+        assert_eq!(
+            parse_xmltv_time("20240101150000 -0400"),
+            Some(1704135600000)
+        );
+        assert_eq!(parse_xmltv_time("202401011500 -0400"), Some(1704135600000));
+        assert_eq!(parse_xmltv_time("202401011500-0400"), Some(1704135600000));
+        assert_eq!(parse_xmltv_time("2024"), None);
+    }
+
+    #[test]
+    fn parse_programmes() {
+        let programmes = parse_xmltv(SAMPLE);
+        assert_eq!(programmes.len(), 4);
+
+        let m6 = &programmes[0];
+        assert_eq!(m6.channel, "M6.fr");
+        assert_eq!(m6.stop - m6.start, 30 * 60 * 1000);
+
+        let news = &programmes[1];
+        assert_eq!(news.title, "Journal & Météo");
+        assert_eq!(
+            news.description.as_deref(),
+            Some("Les <b>infos</b> du soir")
+        );
+        assert_eq!(news.category.as_deref(), Some("News"));
+        assert_eq!(news.episode_num.as_deref(), Some("S01E05"));
+        assert_eq!(news.icon.as_deref(), Some("http://img/tf1.png"));
+
+        // Missing stop ends at the next programme of the channel
+        let film = &programmes[2];
+        assert_eq!(film.start, news.stop);
+        assert_eq!(film.stop, programmes[3].start);
+        assert_eq!(film.episode_num.as_deref(), Some("S2 E3"));
+    }
+
+    #[test]
+    fn decode_gzipped() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(SAMPLE.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(decode_xmltv(&compressed).unwrap(), SAMPLE);
+        assert_eq!(decode_xmltv(SAMPLE.as_bytes()).unwrap(), SAMPLE);
+    }
+}
@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use serde_json::Value;
+
+use crate::error::{RsError, RsResult};
+
+use super::m3u_parser::M3uEntry;
+
+/// Xtream panels return ids and numbers either as json numbers or strings
+fn string_or_number<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    })
+}
+
+/// `episodes` is an empty array instead of an object when a series has none
+fn episodes_map<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, Vec<XtreamEpisode>>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Object(map) => map
+            .into_iter()
+            .filter_map(|(season, episodes)| Some((season, serde_json::from_value(episodes).ok()?)))
+            .collect(),
+        _ => HashMap::new(),
+    })
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct XtreamCategory {
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub category_id: Option<String>,
+    #[serde(default)]
+    pub category_name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct XtreamLiveStream {
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub stream_id: Option<String>,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub stream_icon: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub epg_channel_id: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub category_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct XtreamVodStream {
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub stream_id: Option<String>,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub stream_icon: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub category_id: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub container_extension: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub year: Option<String>,
+}
+
+/// `info` is an empty array instead of an object when the panel has no details
+fn vod_details<'de, D: Deserializer<'de>>(deserializer: D) -> Result<XtreamVodDetails, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        value @ Value::Object(_) => serde_json::from_value(value).unwrap_or_default(),
+        _ => XtreamVodDetails::default(),
+    })
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct XtreamVodDetails {
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub releasedate: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub duration_secs: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub movie_image: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct XtreamVodInfo {
+    #[serde(default, deserialize_with = "vod_details")]
+    pub info: XtreamVodDetails,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct XtreamSeries {
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub series_id: Option<String>,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub cover: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub category_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct XtreamEpisode {
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub id: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub episode_num: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub season: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub title: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub container_extension: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct XtreamSeriesInfo {
+    #[serde(default, deserialize_with = "episodes_map")]
+    pub episodes: HashMap<String, Vec<XtreamEpisode>>,
+}
+
+/// Client of the Xtream Codes `player_api.php` API
+#[derive(Debug, Clone)]
+pub struct XtreamClient {
+    pub server: String,
+    pub username: String,
+    pub password: String,
+}
+
+impl XtreamClient {
+    pub fn new(server: &str, username: &str, password: &str) -> Self {
+        Self {
+            server: server
+                .trim()
+                .trim_end_matches('/')
+                .trim_end_matches("/player_api.php")
+                .to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    fn api_url(&self, action: Option<&str>, extra: &[(&str, &str)]) -> String {
+        let mut url = format!(
+            "{}/player_api.php?username={}&password={}",
+            self.server,
+            urlencoding::encode(&self.username),
+            urlencoding::encode(&self.password)
+        );
+        if let Some(action) = action {
+            url.push_str(&format!("&action={}", action));
+        }
+        for (key, value) in extra {
+            url.push_str(&format!("&{}={}", key, urlencoding::encode(value)));
+        }
+        url
+    }
+
+    async fn get<T: DeserializeOwned>(&self, action: &str, extra: &[(&str, &str)]) -> RsResult<T> {
+        let response = reqwest::get(self.api_url(Some(action), extra))
+            .await
+            .map_err(|e| {
+                RsError::Error(format!("Xtream {} failed: {}", action, e.without_url()))
+            })?;
+        if !response.status().is_success() {
+            return Err(RsError::Error(format!(
+                "Xtream {} failed: {}",
+                action,
+                response.status()
+            )));
+        }
+        let content = response.text().await.map_err(|e| {
+            RsError::Error(format!("Xtream {} failed: {}", action, e.without_url()))
+        })?;
+        let value: Value = serde_json::from_str(&content)
+            .map_err(|e| RsError::Error(format!("Invalid Xtream {} response: {}", action, e)))?;
+        // Panels answer an empty list with `null` or `{}` on some actions
+        let value = match value {
+            Value::Null => Value::Array(vec![]),
+            Value::Object(map) if map.is_empty() => Value::Array(vec![]),
+            value => value,
+        };
+        serde_json::from_value(value)
+            .map_err(|e| RsError::Error(format!("Invalid Xtream {} response: {}", action, e)))
+    }
+
+    /// Check the credentials, the panel answers `user_info.auth = 0` when they are wrong
+    pub async fn authenticate(&self) -> RsResult<()> {
+        let response = reqwest::get(self.api_url(None, &[]))
+            .await
+            .map_err(|e| RsError::Error(format!("Xtream login failed: {}", e.without_url())))?;
+        let content = response
+            .text()
+            .await
+            .map_err(|e| RsError::Error(format!("Xtream login failed: {}", e.without_url())))?;
+        let value: Value = serde_json::from_str(&content)
+            .map_err(|e| RsError::Error(format!("Invalid Xtream login response: {}", e)))?;
+        let auth = value
+            .get("user_info")
+            .and_then(|u| u.get("auth"))
+            .and_then(|a| {
+                a.as_i64()
+                    .or_else(|| a.as_str().and_then(|s| s.parse().ok()))
+            });
+        if auth == Some(1) {
+            Ok(())
+        } else {
+            Err(RsError::Error("Xtream authentication failed".to_string()))
+        }
+    }
+
+    pub async fn live_categories(&self) -> RsResult<Vec<XtreamCategory>> {
+        self.get("get_live_categories", &[]).await
+    }
+
+    pub async fn live_streams(&self) -> RsResult<Vec<XtreamLiveStream>> {
+        self.get("get_live_streams", &[]).await
+    }
+
+    pub async fn vod_categories(&self) -> RsResult<Vec<XtreamCategory>> {
+        self.get("get_vod_categories", &[]).await
+    }
+
+    pub async fn vod_streams(&self) -> RsResult<Vec<XtreamVodStream>> {
+        self.get("get_vod_streams", &[]).await
+    }
+
+    pub async fn vod_info(&self, vod_id: &str) -> RsResult<XtreamVodInfo> {
+        self.get("get_vod_info", &[("vod_id", vod_id)]).await
+    }
+
+    pub async fn series_categories(&self) -> RsResult<Vec<XtreamCategory>> {
+        self.get("get_series_categories", &[]).await
+    }
+
+    pub async fn series(&self) -> RsResult<Vec<XtreamSeries>> {
+        self.get("get_series", &[]).await
+    }
+
+    pub async fn series_info(&self, series_id: &str) -> RsResult<XtreamSeriesInfo> {
+        self.get("get_series_info", &[("series_id", series_id)])
+            .await
+    }
+
+    fn stream_url(&self, kind: &str, id: &str, extension: &str) -> String {
+        format!(
+            "{}/{}/{}/{}/{}.{}",
+            self.server,
+            kind,
+            urlencoding::encode(&self.username),
+            urlencoding::encode(&self.password),
+            id,
+            extension
+        )
+    }
+
+    pub fn xmltv_url(&self) -> String {
+        format!(
+            "{}/xmltv.php?username={}&password={}",
+            self.server,
+            urlencoding::encode(&self.username),
+            urlencoding::encode(&self.password)
+        )
+    }
+
+    /// Live stream as a playlist entry, grouped on its EPG id (or its name when it has none)
+    pub fn live_entry(
+        &self,
+        stream: &XtreamLiveStream,
+        categories: &HashMap<String, String>,
+    ) -> Option<M3uEntry> {
+        let id = stream.stream_id.as_ref()?;
+        let mut entry = M3uEntry {
+            tvg_id: stream.epg_channel_id.clone(),
+            tvg_name: Some(stream.name.clone()),
+            tvg_logo: stream.stream_icon.clone(),
+            group_title: category_name(&stream.category_id, categories),
+            display_name: stream.name.clone(),
+            url: self.stream_url("live", id, "ts"),
+            duration: -1,
+        };
+        if entry.tvg_id.is_none() {
+            entry.tvg_id = Some(entry.channel_key()).filter(|k| !k.is_empty());
+        }
+        Some(entry)
+    }
+
+    /// VOD stream as a playlist entry, completed with its `get_vod_info` details when fetched
+    pub fn vod_entry(
+        &self,
+        stream: &XtreamVodStream,
+        info: Option<&XtreamVodInfo>,
+        categories: &HashMap<String, String>,
+    ) -> Option<M3uEntry> {
+        let id = stream.stream_id.as_ref()?;
+        let extension = stream.container_extension.as_deref().unwrap_or("mp4");
+        let details = info.map(|i| &i.info);
+        let year = stream.year.clone().or_else(|| {
+            details
+                .and_then(|d| d.releasedate.as_deref())
+                .and_then(|date| date.get(..4))
+                .filter(|year| year.chars().all(|c| c.is_ascii_digit()))
+                .map(|year| year.to_string())
+        });
+        // The year is used to match movies, keep it in the title when the panel gives it apart
+        let name = match &year {
+            Some(year) if !stream.name.contains(year.as_str()) => {
+                format!("{} {}", stream.name, year)
+            }
+            _ => stream.name.clone(),
+        };
+        Some(M3uEntry {
+            tvg_id: None,
+            tvg_name: Some(name.clone()),
+            tvg_logo: stream
+                .stream_icon
+                .clone()
+                .or_else(|| details.and_then(|d| d.movie_image.clone())),
+            group_title: category_name(&stream.category_id, categories),
+            display_name: name,
+            url: self.stream_url("movie", id, extension),
+            duration: details
+                .and_then(|d| d.duration_secs.as_deref())
+                .and_then(|d| d.parse().ok())
+                .unwrap_or(-1),
+        })
+    }
+
+    /// Episodes of a series, named `Series S01 E02` like M3U series entries
+    pub fn episode_entries(
+        &self,
+        series: &XtreamSeries,
+        info: &XtreamSeriesInfo,
+        categories: &HashMap<String, String>,
+    ) -> Vec<M3uEntry> {
+        let mut entries = vec![];
+        for (season_key, episodes) in &info.episodes {
+            for episode in episodes {
+                let Some(id) = episode.id.as_ref() else {
+                    continue;
+                };
+                let season = episode
+                    .season
+                    .as_deref()
+                    .unwrap_or(season_key)
+                    .parse::<u32>();
+                let number = episode.episode_num.as_deref().map(|n| n.parse::<u32>());
+                let (Ok(season), Some(Ok(number))) = (season, number) else {
+                    continue;
+                };
+                let name = format!("{} S{:02} E{:02}", series.name, season, number);
+                let extension = episode.container_extension.as_deref().unwrap_or("mp4");
+                entries.push(M3uEntry {
+                    tvg_id: None,
+                    tvg_name: Some(name.clone()),
+                    tvg_logo: series.cover.clone(),
+                    group_title: category_name(&series.category_id, categories),
+                    display_name: name,
+                    url: self.stream_url("series", id, extension),
+                    duration: -1,
+                });
+            }
+        }
+        entries
+    }
+}
+
+fn category_name(id: &Option<String>, categories: &HashMap<String, String>) -> Option<String> {
+    id.as_ref().and_then(|id| categories.get(id)).cloned()
+}
+
+/// Category id to name lookup
+pub fn category_names(categories: Vec<XtreamCategory>) -> HashMap<String, String> {
+    categories
+        .into_iter()
+        .filter_map(|c| Some((c.category_id?, c.category_name)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tools::m3u_parser::M3uContentType;
+
+    use super::*;
+
+    #[test]
+    fn entries_from_panel_json() {
+        let client = XtreamClient::new("http://panel.tv:8080/player_api.php", "user", "p@ss");
+        let categories = category_names(
+            serde_json::from_str(
+                r#"[{"category_id":"3","category_name":"FR| Sport"},{"category_id":4,"category_name":"Movies"}]"#,
+            )
+            .unwrap(),
+        );
+        let live: Vec<XtreamLiveStream> = serde_json::from_str(
+            r#"[{"stream_id":12,"name":"beIN Sports 1 FHD","stream_icon":"","epg_channel_id":null,"category_id":"3"},
+                {"stream_id":"13","name":"TF1 HD","epg_channel_id":"tf1.fr","category_id":"9"}]"#,
+        )
+        .unwrap();
+        let entry = client.live_entry(&live[0], &categories).unwrap();
+        assert_eq!(entry.url, "http://panel.tv:8080/live/user/p%40ss/12.ts");
+        assert_eq!(entry.tvg_id.as_deref(), Some("beIN Sports 1"));
+        assert_eq!(entry.tvg_logo, None);
+        assert_eq!(entry.group_title.as_deref(), Some("FR| Sport"));
+        assert_eq!(entry.content_type(), M3uContentType::Live);
+        let entry = client.live_entry(&live[1], &categories).unwrap();
+        assert_eq!(entry.tvg_id.as_deref(), Some("tf1.fr"));
+        assert_eq!(entry.group_title, None);
+
+        let vod: Vec<XtreamVodStream> = serde_json::from_str(
+            r#"[{"stream_id":7,"name":"Dune","category_id":4,"container_extension":"mkv","year":"2021"}]"#,
+        )
+        .unwrap();
+        let entry = client.vod_entry(&vod[0], None, &categories).unwrap();
+        assert_eq!(entry.content_type(), M3uContentType::Vod);
+        assert_eq!(entry.url, "http://panel.tv:8080/movie/user/p%40ss/7.mkv");
+        assert_eq!(
+            entry.parse_name_and_year(),
+            ("Dune".to_string(), Some(2021))
+        );
+
+        let vod: Vec<XtreamVodStream> =
+            serde_json::from_str(r#"[{"stream_id":8,"name":"Arrival","category_id":4}]"#).unwrap();
+        let info: XtreamVodInfo = serde_json::from_str(
+            r#"{"info":{"releasedate":"2016-11-10","duration_secs":6960,"movie_image":"http://c/a.jpg"},"movie_data":{}}"#,
+        )
+        .unwrap();
+        let entry = client.vod_entry(&vod[0], Some(&info), &categories).unwrap();
+        assert_eq!(
+            entry.parse_name_and_year(),
+            ("Arrival".to_string(), Some(2016))
+        );
+        assert_eq!(entry.duration, 6960);
+        assert_eq!(entry.tvg_logo.as_deref(), Some("http://c/a.jpg"));
+        let empty: XtreamVodInfo = serde_json::from_str(r#"{"info":[]}"#).unwrap();
+        assert!(empty.info.releasedate.is_none());
+
+        let series: XtreamSeries =
+            serde_json::from_str(r#"{"series_id":5,"name":"Dark","cover":"http://c/d.jpg"}"#)
+                .unwrap();
+        let info: XtreamSeriesInfo = serde_json::from_str(
+            r#"{"episodes":{"2":[{"id":"501","episode_num":3,"season":2,"container_extension":"mp4"}]}}"#,
+        )
+        .unwrap();
+        let entries = client.episode_entries(&series, &info, &categories);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].content_type(), M3uContentType::Series);
+        assert_eq!(entries[0].parse_season_episode(), Some((2, 3)));
+        assert_eq!(entries[0].parse_series_name(), "Dark");
+
+        let empty: XtreamSeriesInfo = serde_json::from_str(r#"{"episodes":[]}"#).unwrap();
+        assert!(empty.episodes.is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+#!/usr/bin/env python3
+"""Generate the tiny CLIP fixtures used by the `tools::clip` tests.
+
+The models are not real CLIP weights: they only keep the input and output
+names of an exported CLIP so `ClipService` can load them.
+
+- visual.onnx: `pixel_values` [1, 3, 4, 4] is averaged per channel, so the
+  image embedding is the mean (normalized) red, green and blue values.
+- textual.onnx: `input_ids` [1, 3] look up a one hot embedding per word
+  (red, green, blue) that is summed into `text_embeds`.
+- tokenizer.json: word level tokenizer matching those ids.
+
+A text query "red" is therefore closest to a red image. The protobuf is
+written by hand so the script has no dependency; run it from anywhere with
+`python3 test_data/clip/generate_fixtures.py`.
+"""
+
+import json
+import os
+import struct
+
+FOLDER = os.path.dirname(os.path.abspath(__file__))
+
+# onnx enums
+FLOAT = 1
+INT64 = 7
+ATTRIBUTE_INT = 2
+ATTRIBUTE_INTS = 7
+
+
+def varint(value):
+    out = bytearray()
+    while True:
+        byte = value & 0x7F
+        value >>= 7
+        if value:
+            out.append(byte | 0x80)
+        else:
+            out.append(byte)
+            return bytes(out)
+
+
+def field_varint(number, value):
+    return varint(number << 3) + varint(value)
+
+
+def field_bytes(number, value):
+    if isinstance(value, str):
+        value = value.encode()
+    return varint(number << 3 | 2) + varint(len(value)) + value
+
+
+def attribute_int(name, value):
+    return field_bytes(1, name) + field_varint(3, value) + field_varint(20, ATTRIBUTE_INT)
+
+
+def attribute_ints(name, values):
+    ints = b"".join(field_varint(8, v) for v in values)
+    return field_bytes(1, name) + ints + field_varint(20, ATTRIBUTE_INTS)
+
+
+def node(inputs, outputs, name, op_type, attributes=()):
+    data = b"".join(field_bytes(1, i) for i in inputs)
+    data += b"".join(field_bytes(2, o) for o in outputs)
+    data += field_bytes(3, name) + field_bytes(4, op_type)
+    data += b"".join(field_bytes(5, a) for a in attributes)
+    return data
+
+
+def value_info(name, elem_type, dims):
+    shape = b"".join(field_bytes(1, field_varint(1, d)) for d in dims)
+    tensor = field_varint(1, elem_type) + field_bytes(2, shape)
+    return field_bytes(1, name) + field_bytes(2, field_bytes(1, tensor))
+
+
+def float_tensor(name, dims, values):
+    data = b"".join(field_varint(1, d) for d in dims)
+    data += field_varint(2, FLOAT)
+    data += field_bytes(4, struct.pack("<%df" % len(values), *values))
+    return data + field_bytes(8, name)
+
+
+def model(graph):
+    opset = field_bytes(1, "") + field_varint(2, 11)
+    return (
+        field_varint(1, 7)
+        + field_bytes(2, "redseat-fixture")
+        + field_bytes(7, graph)
+        + field_bytes(8, opset)
+    )
+
+
+def visual():
+    graph = field_bytes(1, node(["pixel_values"], ["pooled"], "pool", "GlobalAveragePool"))
+    graph += field_bytes(
+        1,
+        node(["pooled"], ["image_embeds"], "flatten", "Flatten", [attribute_int("axis", 1)]),
+    )
+    graph += field_bytes(2, "visual")
+    graph += field_bytes(11, value_info("pixel_values", FLOAT, [1, 3, 4, 4]))
+    graph += field_bytes(12, value_info("image_embeds", FLOAT, [1, 3]))
+    return model(graph)
+
+
+def textual():
+    graph = field_bytes(
+        1,
+        node(
+            ["embedding", "input_ids"],
+            ["tokens"],
+            "gather",
+            "Gather",
+            [attribute_int("axis", 0)],
+        ),
+    )
+    graph += field_bytes(
+        1,
+        node(
+            ["tokens"],
+            ["text_embeds"],
+            "sum",
+            "ReduceSum",
+            [attribute_ints("axes", [1]), attribute_int("keepdims", 0)],
+        ),
+    )
+    graph += field_bytes(2, "textual")
+    # Row 0 is the unknown token, then one axis per color
+    embedding = [0.0] * 3 + [1.0, 0.0, 0.0] + [0.0, 1.0, 0.0] + [0.0, 0.0, 1.0]
+    graph += field_bytes(5, float_tensor("embedding", [4, 3], embedding))
+    graph += field_bytes(11, value_info("input_ids", INT64, [1, 3]))
+    graph += field_bytes(12, value_info("text_embeds", FLOAT, [1, 3]))
+    return model(graph)
+
+
+def tokenizer():
+    return {
+        "version": "1.0",
+        "truncation": None,
+        "padding": None,
+        "added_tokens": [],
+        "normalizer": {"type": "Lowercase"},
+        "pre_tokenizer": {"type": "Whitespace"},
+        "post_processor": None,
+        "decoder": None,
+        "model": {
+            "type": "WordLevel",
+            "vocab": {"[UNK]": 0, "red": 1, "green": 2, "blue": 3},
+            "unk_token": "[UNK]",
+        },
+    }
+
+
+def main():
+    with open(os.path.join(FOLDER, "visual.onnx"), "wb") as f:
+        f.write(visual())
+    with open(os.path.join(FOLDER, "textual.onnx"), "wb") as f:
+        f.write(textual())
+    with open(os.path.join(FOLDER, "tokenizer.json"), "w") as f:
+        json.dump(tokenizer(), f, indent=2)
+
+
+if __name__ == "__main__":
+    main()